@@ -0,0 +1,150 @@
+//! Headless recipe tooling for authors and editor integrations.
+//!
+//! ```text
+//! clawpal-recipes lint <recipes.json>... [--format json|text]
+//! clawpal-recipes test <recipes.json> <fixtures-dir> [--update] [--format json|text]
+//! clawpal-recipes schema [recipes|model-profiles|snapshot-index|ssh-hosts]
+//! ```
+//!
+//! JSON goes to stdout. Exit status is 0 when clean, 1 when there are lint
+//! errors or failing fixtures and 2 on usage errors.
+
+use std::{fs, path::Path, process::ExitCode};
+
+use clawpal::recipe_lint::{lint_document, load_checked, run_fixtures, Diagnostic};
+use clawpal::schemas::schema_for;
+use serde_json::json;
+
+const USAGE: &str = "usage:
+  clawpal-recipes lint <recipes.json>... [--format json|text]
+  clawpal-recipes test <recipes.json> <fixtures-dir> [--update] [--format json|text]
+  clawpal-recipes schema [recipes|model-profiles|snapshot-index|ssh-hosts]";
+
+struct Args {
+    command: String,
+    files: Vec<String>,
+    update: bool,
+    text: bool,
+}
+
+fn parse_args() -> Result<Args, String> {
+    let mut raw = std::env::args().skip(1);
+    let command = raw.next().ok_or("missing command")?;
+    let mut args = Args { command, files: Vec::new(), update: false, text: false };
+    while let Some(arg) = raw.next() {
+        match arg.as_str() {
+            "--update" => args.update = true,
+            "--format" => match raw.next().as_deref() {
+                Some("json") => args.text = false,
+                Some("text") => args.text = true,
+                other => return Err(format!("unknown format: {}", other.unwrap_or(""))),
+            },
+            flag if flag.starts_with("--") => return Err(format!("unknown option: {flag}")),
+            _ => args.files.push(arg),
+        }
+    }
+    Ok(args)
+}
+
+fn lint(args: &Args) -> ExitCode {
+    if args.files.is_empty() {
+        eprintln!("{USAGE}");
+        return ExitCode::from(2);
+    }
+    let mut reports = Vec::new();
+    let mut failed = false;
+    for file in &args.files {
+        let diagnostics = match fs::read_to_string(file) {
+            Ok(text) => lint_document(&text),
+            Err(e) => vec![Diagnostic::new("error", "io", None, "", format!("cannot read file: {e}"))],
+        };
+        failed |= diagnostics.iter().any(Diagnostic::is_error);
+        if args.text {
+            for d in &diagnostics {
+                println!("{file}: {}[{}] {}: {}", d.severity, d.code, d.path, d.message);
+            }
+        }
+        reports.push(json!({ "file": file, "diagnostics": diagnostics }));
+    }
+    if !args.text {
+        println!("{}", json!({ "ok": !failed, "files": reports }));
+    }
+    if failed { ExitCode::from(1) } else { ExitCode::SUCCESS }
+}
+
+fn test(args: &Args) -> ExitCode {
+    let [recipes_file, fixtures_dir] = args.files.as_slice() else {
+        eprintln!("{USAGE}");
+        return ExitCode::from(2);
+    };
+    let outcome = fs::read_to_string(recipes_file)
+        .map_err(|e| format!("cannot read {recipes_file}: {e}"))
+        .and_then(|text| load_checked(&text))
+        .and_then(|recipes| run_fixtures(&recipes, Path::new(fixtures_dir), args.update));
+    let results = match outcome {
+        Ok(results) => results,
+        Err(e) => {
+            if args.text {
+                eprintln!("error: {e}");
+            } else {
+                println!("{}", json!({ "ok": false, "error": e }));
+            }
+            return ExitCode::from(1);
+        }
+    };
+    let failed = results.iter().any(|r| r.status == "fail" || r.status == "error");
+    if args.text {
+        for r in &results {
+            println!("{} {} ({})", r.status, r.case, r.recipe_id);
+            if let Some(message) = &r.message {
+                println!("  {message}");
+            }
+            if let Some(diff) = &r.diff {
+                print!("{diff}");
+            }
+        }
+    } else {
+        println!("{}", json!({ "ok": !failed, "cases": results }));
+    }
+    if failed { ExitCode::from(1) } else { ExitCode::SUCCESS }
+}
+
+fn schema(args: &Args) -> ExitCode {
+    let kind = match args.files.as_slice() {
+        [] => "recipes",
+        [kind] => kind.as_str(),
+        _ => {
+            eprintln!("{USAGE}");
+            return ExitCode::from(2);
+        }
+    };
+    match schema_for(kind) {
+        Ok(schema) => {
+            println!("{}", serde_json::to_string_pretty(&schema).unwrap_or_default());
+            ExitCode::SUCCESS
+        }
+        Err(e) => {
+            eprintln!("{e}\n{USAGE}");
+            ExitCode::from(2)
+        }
+    }
+}
+
+fn main() -> ExitCode {
+    let args = match parse_args() {
+        Ok(args) => args,
+        Err(e) => {
+            eprintln!("{e}\n{USAGE}");
+            return ExitCode::from(2);
+        }
+    };
+    match args.command.as_str() {
+        "lint" => lint(&args),
+        "test" => test(&args),
+        "schema" => schema(&args),
+        other => {
+            eprintln!("unknown command: {other}\n{USAGE}");
+            ExitCode::from(2)
+        }
+    }
+}
@@ -7,6 +7,7 @@ use serde_json::Value;
 use uuid::Uuid;
 
 use crate::models::resolve_paths;
+use crate::snapshot_store::{NewSnapshot, SnapshotStore, SshSnapshotStore};
 use crate::ssh::SshConnectionPool;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -25,9 +26,17 @@ pub fn run_openclaw_with_env(
     args: &[&str],
     env: Option<&HashMap<String, String>>,
 ) -> Result<CliOutput, String> {
-    let mut cmd = Command::new(crate::commands::resolve_openclaw_bin());
-    cmd.args(args)
-        .stdout(std::process::Stdio::piped())
+    // A sandboxed run picks its own state dir; anything else targets the
+    // selected local instance.
+    let mut cmd = match env {
+        Some(env_vars) if env_vars.contains_key("OPENCLAW_HOME") => {
+            let mut cmd = Command::new(crate::commands::resolve_openclaw_bin());
+            cmd.args(args);
+            cmd
+        }
+        _ => crate::instances::openclaw_command(args),
+    };
+    cmd.stdout(std::process::Stdio::piped())
         .stderr(std::process::Stdio::piped());
 
     if let Some(env_vars) = env {
@@ -257,9 +266,31 @@ pub struct PreviewQueueResult {
     pub commands: Vec<PendingCommand>,
     pub config_before: String,
     pub config_after: String,
+    pub changes: Vec<crate::recipe::ChangeItem>,
+    pub diff: String,
+    pub impact_level: String,
+    pub warnings: Vec<String>,
     pub errors: Vec<String>,
 }
 
+impl PreviewQueueResult {
+    fn new(commands: Vec<PendingCommand>, config_before: String, config_after: String, errors: Vec<String>) -> Self {
+        let parse = |text: &str| json5::from_str::<Value>(text).unwrap_or(Value::Null);
+        let mut changes = crate::recipe::collect_change_paths(&parse(&config_before), &parse(&config_after));
+        let assessment = crate::risk::assess(&mut changes);
+        Self {
+            changes,
+            diff: crate::json_diff::unified_diff(&config_before, &config_after, 3),
+            impact_level: assessment.impact_level,
+            warnings: assessment.warnings,
+            commands,
+            config_before,
+            config_after,
+            errors,
+        }
+    }
+}
+
 #[tauri::command]
 pub async fn preview_queued_commands(
     queue: tauri::State<'_, CommandQueue>,
@@ -363,7 +394,7 @@ pub async fn preview_queued_commands(
             }
         }
         let normalize_json = |s: &str| -> String {
-            match serde_json::from_str::<Value>(s) {
+            match json5::from_str::<Value>(s) {
                 Ok(v) => serde_json::to_string_pretty(&sort_value(&v))
                     .unwrap_or_else(|_| s.to_string()),
                 Err(_) => s.to_string(),
@@ -375,12 +406,7 @@ pub async fn preview_queued_commands(
         // Cleanup sandbox
         let _ = std::fs::remove_dir_all(paths.clawpal_dir.join("preview"));
 
-        Ok(PreviewQueueResult {
-            commands,
-            config_before,
-            config_after,
-            errors,
-        })
+        Ok(PreviewQueueResult::new(commands, config_before, config_after, errors))
     }).await.map_err(|e| e.to_string())?
 }
 
@@ -414,6 +440,7 @@ pub async fn apply_queued_commands(
     tauri::async_runtime::spawn_blocking(move || {
         let paths = resolve_paths();
         let total_count = commands.len();
+        let mut write = crate::config_watch::LocalWriteGuard::new(&paths);
 
         // Save snapshot before applying (for rollback)
         let config_before = crate::config_io::read_text(&paths.config_path)?;
@@ -448,7 +475,9 @@ pub async fn apply_queued_commands(
             if matches!(cmd.command.first().map(|s| s.as_str()), Some("__config_write__") | Some("__rollback__")) {
                 // Internal command: write config content directly
                 if let Some(content) = cmd.command.get(1) {
-                    if let Err(e) = crate::config_io::write_text(&paths.config_path, content) {
+                    let written = crate::config_cas::ConfigLock::acquire(&paths.config_path)
+                        .and_then(|_lock| crate::config_io::write_text(&paths.config_path, content));
+                    if let Err(e) = written {
                         let _ = crate::config_io::write_text(&paths.config_path, &config_before);
                         queue_handle.clear();
                         return Ok(ApplyQueueResult {
@@ -521,6 +550,10 @@ pub async fn apply_queued_commands(
         if let Err(e) = &gateway_result {
             eprintln!("Warning: gateway restart failed after apply: {e}");
         }
+        // What the commands left behind is ClawPal's own change.
+        if let Ok(text) = crate::config_io::read_text(&paths.config_path) {
+            write.written(&text);
+        }
 
         Ok(ApplyQueueResult {
             ok: true,
@@ -748,7 +781,7 @@ pub async fn remote_preview_queued_commands(
     // Normalize both configs to sorted-key pretty JSON so the diff only
     // shows semantic changes, not key reordering by the CLI.
     let normalize = |s: &str| -> String {
-        match serde_json::from_str::<Value>(s) {
+        match json5::from_str::<Value>(s) {
             Ok(v) => {
                 fn sort_value(v: &Value) -> Value {
                     match v {
@@ -771,12 +804,12 @@ pub async fn remote_preview_queued_commands(
         }
     };
 
-    Ok(PreviewQueueResult {
+    Ok(PreviewQueueResult::new(
         commands,
-        config_before: normalize(&config_before),
-        config_after: normalize(&config_after),
+        normalize(&config_before),
+        normalize(&config_after),
         errors,
-    })
+    ))
 }
 
 // ---------------------------------------------------------------------------
@@ -794,12 +827,12 @@ pub async fn remote_apply_queued_commands(
         return Err("No pending commands to apply".into());
     }
     let total_count = commands.len();
+    let mut write = crate::config_watch::RemoteWriteGuard::new(&host_id);
 
     // Save snapshot on remote
     let config_before = pool
         .sftp_read(&host_id, "~/.openclaw/openclaw.json")
         .await?;
-    let ts = chrono::Utc::now().timestamp();
     let mut summary: String = commands.iter()
         .map(|c| c.label.as_str())
         .collect::<Vec<_>>()
@@ -808,21 +841,17 @@ pub async fn remote_apply_queued_commands(
         summary.truncate(80);
         summary.push_str("...");
     }
-    // Sanitize summary for safe filename
-    let safe_summary: String = summary.chars().map(|c| match c {
-        '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' | ' ' => '-',
-        _ => c,
-    }).collect();
     let is_rollback = commands.iter()
         .any(|c| c.command.first().map(|s| s.as_str()) == Some("__rollback__"));
     let source = if is_rollback { "rollback" } else { "clawpal" };
-    let snapshot_filename = format!("{ts}-{source}-{safe_summary}.json");
-    let snapshot_path = format!("~/.clawpal/snapshots/{snapshot_filename}");
-    let _ = pool
-        .exec(&host_id, "mkdir -p ~/.clawpal/snapshots")
-        .await;
-    let _ = pool
-        .sftp_write(&host_id, &snapshot_path, &config_before)
+    let _ = SshSnapshotStore::new(&pool, &host_id)
+        .add(NewSnapshot {
+            recipe_id: Some(summary),
+            source: source.to_string(),
+            can_rollback: !is_rollback,
+            config: config_before.clone(),
+            rollback_of: None,
+        })
         .await;
 
     // Execute each command
@@ -897,6 +926,7 @@ pub async fn remote_apply_queued_commands(
         }
     }
 
+    write.written();
     queues.clear(&host_id);
     let _ = pool
         .exec_login(&host_id, "openclaw gateway restart")
@@ -7,10 +7,14 @@ use serde_json::{Map, Value};
 
 use tauri::{Manager, State};
 
-use crate::config_io::{ensure_dirs, read_openclaw_config, write_json, write_text};
+use crate::config_cas::{read_base as read_config_base, ConfigBase};
+use crate::config_io::{ensure_dirs, read_openclaw_config, write_text};
 use crate::doctor::{apply_auto_fixes, run_doctor, DoctorReport};
-use crate::history::{add_snapshot, list_snapshots, read_snapshot};
+use crate::history::{add_snapshot, list_snapshots};
+use crate::instances::LocalInstance;
+use crate::snapshot_store::{run_local, LocalSnapshotStore, NewSnapshot, SnapshotStore, SshSnapshotStore};
 use crate::models::resolve_paths;
+use crate::openclaw_config::{AgentEntry, ModelRef, OpenClawConfig};
 use crate::ssh::{SshConnectionPool, SshHostConfig, SshExecResult, SftpEntry};
 
 /// Escape a string for safe inclusion in a single-quoted shell argument.
@@ -80,11 +84,14 @@ fn find_in_path(bin: &str) -> bool {
         .unwrap_or(false)
 }
 
+use crate::recipe_bundle::{
+    read_trust_store, trusted_key, write_trust_store, TrustPolicy, TrustStore,
+};
+use crate::recipe_registry::{
+    load_registry, read_sources, recipes_dir, write_sources, RemoteSourceConfig, SourceStatus,
+};
 use crate::recipe::{
-    load_recipes_with_fallback,
-    collect_change_paths,
     build_candidate_config_from_template,
-    format_diff,
     ApplyResult,
     PreviewResult,
 };
@@ -230,6 +237,9 @@ pub struct ExtractModelProfilesResult {
     pub created: usize,
     pub reused: usize,
     pub skipped_invalid: usize,
+    /// Config sections that didn't fit the typed model and were skipped.
+    #[serde(default)]
+    pub warnings: Vec<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -341,7 +351,7 @@ pub struct SessionSummary {
     pub by_agent: Vec<AgentSessionSummary>,
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, schemars::JsonSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct ModelProfile {
     pub id: String,
@@ -422,12 +432,69 @@ pub struct HistoryItem {
     pub can_rollback: bool,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub rollback_of: Option<String>,
+    pub pinned: bool,
+    pub labels: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub note: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct HistoryPage {
     pub items: Vec<HistoryItem>,
+    /// Every pinned snapshot matching the query, whatever the page.
+    pub pinned: Vec<HistoryItem>,
+}
+
+impl HistoryPage {
+    /// Page through `items` (newest first), keeping those matching `query`.
+    fn new(items: Vec<crate::history::SnapshotMeta>, limit: usize, offset: usize, query: Option<&str>) -> Self {
+        let matching: Vec<_> = items.into_iter().filter(|s| s.matches(query.unwrap_or(""))).collect();
+        let pinned = matching.iter().filter(|s| s.pinned).cloned().map(HistoryItem::from).collect();
+        let items = matching.into_iter().skip(offset).take(limit).map(HistoryItem::from).collect();
+        HistoryPage { items, pinned }
+    }
+}
+
+impl From<crate::history::SnapshotMeta> for HistoryItem {
+    fn from(item: crate::history::SnapshotMeta) -> Self {
+        HistoryItem {
+            id: item.id,
+            recipe_id: item.recipe_id,
+            created_at: item.created_at,
+            source: item.source,
+            can_rollback: item.can_rollback,
+            rollback_of: item.rollback_of,
+            pinned: item.pinned,
+            labels: item.labels,
+            note: item.note,
+        }
+    }
+}
+
+/// Structural and textual diff between two snapshots' configs.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SnapshotDiff {
+    pub from: HistoryItem,
+    pub to: HistoryItem,
+    pub changes: Vec<crate::recipe::ChangeItem>,
+    pub diff: String,
+    pub config_before: String,
+    pub config_after: String,
+}
+
+impl SnapshotDiff {
+    fn new(from: crate::history::SnapshotMeta, before: &Value, to: crate::history::SnapshotMeta, after: &Value) -> Self {
+        SnapshotDiff {
+            from: from.into(),
+            to: to.into(),
+            changes: crate::json_diff::diff_values(before, after),
+            diff: crate::recipe::format_diff(before, after),
+            config_before: serde_json::to_string_pretty(before).unwrap_or_else(|_| "{}".into()),
+            config_after: serde_json::to_string_pretty(after).unwrap_or_else(|_| "{}".into()),
+        }
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -618,9 +685,9 @@ pub fn check_openclaw_update() -> Result<OpenclawUpdateCheck, String> {
 #[tauri::command]
 pub fn extract_model_profiles_from_config() -> Result<ExtractModelProfilesResult, String> {
     let paths = resolve_paths();
-    let cfg = read_openclaw_config(&paths)?;
+    let (config, warnings) = typed_config_for_read(&read_openclaw_config(&paths)?);
     let profiles = load_model_profiles(&paths);
-    let bindings = collect_model_bindings(&cfg, &profiles);
+    let bindings = collect_model_bindings(&config, &profiles);
     let mut created = 0usize;
     let mut reused = 0usize;
     let mut skipped_invalid = 0usize;
@@ -657,9 +724,10 @@ pub fn extract_model_profiles_from_config() -> Result<ExtractModelProfilesResult
             skipped_invalid += 1;
             continue;
         }
-        let auth_ref = resolve_auth_ref_for_provider(&cfg, provider)
+        let auth_ref = config
+            .auth_profile_for_provider(provider)
             .unwrap_or_else(|| format!("{provider}:default"));
-        let base_url = resolve_model_provider_base_url(&cfg, provider);
+        let base_url = config.provider_base_url(provider);
         let profile = ModelProfile {
             id: uuid::Uuid::new_v4().to_string(),
             name: format!("{scope_label} model profile"),
@@ -686,6 +754,7 @@ pub fn extract_model_profiles_from_config() -> Result<ExtractModelProfilesResult
         created,
         reused,
         skipped_invalid,
+        warnings,
     })
 }
 
@@ -701,10 +770,11 @@ pub fn upsert_model_profile(mut profile: ModelProfile) -> Result<ModelProfile, S
     if profile.auth_ref.trim().is_empty() && !has_api_key {
         // Auto-resolve auth ref from openclaw config or env vars
         let paths_tmp = resolve_paths();
-        if let Ok(cfg) = read_openclaw_config(&paths_tmp) {
-            if let Some(auth_ref) = resolve_auth_ref_for_provider(&cfg, &profile.provider) {
-                profile.auth_ref = auth_ref;
-            }
+        if let Some(auth_ref) = read_openclaw_config(&paths_tmp)
+            .ok()
+            .and_then(|cfg| typed_config_for_read(&cfg).0.auth_profile_for_provider(&profile.provider))
+        {
+            profile.auth_ref = auth_ref;
         }
         if profile.auth_ref.trim().is_empty() {
             // Try env var convention
@@ -756,10 +826,11 @@ pub fn resolve_provider_auth(provider: String) -> Result<ProviderAuthSuggestion,
         return Ok(ProviderAuthSuggestion { auth_ref: None, has_key: false, source: String::new() });
     }
     let paths = resolve_paths();
-    let cfg = read_openclaw_config(&paths)?;
+    let (config, warnings) = typed_config_for_read(&read_openclaw_config(&paths)?);
+    log_config_warnings(&warnings);
 
     // 1. Check openclaw config auth profiles
-    if let Some(auth_ref) = resolve_auth_ref_for_provider(&cfg, provider_trimmed) {
+    if let Some(auth_ref) = config.auth_profile_for_provider(provider_trimmed) {
         return Ok(ProviderAuthSuggestion {
             auth_ref: Some(auth_ref),
             has_key: true,
@@ -837,7 +908,7 @@ pub fn list_channels_minimal() -> Result<Vec<ChannelNode>, String> {
 #[tauri::command]
 pub fn list_discord_guild_channels() -> Result<Vec<DiscordGuildChannel>, String> {
     let paths = resolve_paths();
-    let cache_file = paths.clawpal_dir.join("discord-guild-channels.json");
+    let cache_file = paths.instance_dir().join("discord-guild-channels.json");
     if cache_file.exists() {
         let text = fs::read_to_string(&cache_file).map_err(|e| e.to_string())?;
         let entries: Vec<DiscordGuildChannel> = serde_json::from_str(&text).unwrap_or_default();
@@ -1075,7 +1146,7 @@ pub async fn refresh_discord_guild_channels() -> Result<Vec<DiscordGuildChannel>
         }
 
         // Persist to cache
-        let cache_file = paths.clawpal_dir.join("discord-guild-channels.json");
+        let cache_file = paths.instance_dir().join("discord-guild-channels.json");
         let json = serde_json::to_string_pretty(&entries).map_err(|e| e.to_string())?;
         write_text(&cache_file, &json)?;
 
@@ -1095,17 +1166,14 @@ pub fn update_channel_config(
         return Err("channel path is required".into());
     }
     let paths = resolve_paths();
-    let mut cfg = read_openclaw_config(&paths)?;
-    let current = serde_json::to_string_pretty(&cfg).map_err(|e| e.to_string())?;
-    set_nested_value(&mut cfg, &format!("{path}.type"), channel_type.map(Value::String))?;
-    set_nested_value(&mut cfg, &format!("{path}.mode"), mode.map(Value::String))?;
-    let allowlist_values = allowlist
-        .into_iter()
-        .map(Value::String)
-        .collect::<Vec<_>>();
-    set_nested_value(&mut cfg, &format!("{path}.allowlist"), Some(Value::Array(allowlist_values)))?;
-    set_nested_value(&mut cfg, &format!("{path}.model"), model.map(Value::String))?;
-    write_config_with_snapshot(&paths, &current, &cfg, "update-channel")?;
+    let (base, mut config) = read_typed_config(&paths)?;
+    config.update_channel(&path, |channel| {
+        channel.channel_type = channel_type;
+        channel.mode = mode;
+        channel.allowlist = Some(allowlist);
+        channel.model = model.map(ModelRef::Name);
+    })?;
+    write_typed_config_with_snapshot(&paths, &base, &config, "update-channel")?;
     Ok(true)
 }
 
@@ -1143,41 +1211,32 @@ pub fn delete_channel_node(path: String) -> Result<bool, String> {
         return Err("channel path is required".into());
     }
     let paths = resolve_paths();
-    let mut cfg = read_openclaw_config(&paths)?;
-    let current = serde_json::to_string_pretty(&cfg).map_err(|e| e.to_string())?;
-    let before = cfg.to_string();
-    set_nested_value(&mut cfg, &path, None)?;
-    if cfg.to_string() == before {
+    let (base, mut config) = read_typed_config(&paths)?;
+    if !config.remove_channel(&path)? {
         return Ok(false);
     }
-    write_config_with_snapshot(&paths, &current, &cfg, "delete-channel")?;
+    write_typed_config_with_snapshot(&paths, &base, &config, "delete-channel")?;
     Ok(true)
 }
 
 #[tauri::command]
 pub fn set_global_model(model_value: Option<String>) -> Result<bool, String> {
     let paths = resolve_paths();
-    let mut cfg = read_openclaw_config(&paths)?;
-    let current = serde_json::to_string_pretty(&cfg).map_err(|e| e.to_string())?;
+    let (base, mut config) = read_typed_config(&paths)?;
     let model = model_value.map(|v| v.trim().to_string()).filter(|v| !v.is_empty());
-    // If existing model is an object (has fallbacks etc.), only update "primary" inside it
-    if let Some(existing) = cfg.pointer_mut("/agents/defaults/model") {
-        if let Some(model_obj) = existing.as_object_mut() {
-            match model {
-                Some(v) => { model_obj.insert("primary".into(), Value::String(v)); }
-                None => { model_obj.remove("primary"); }
+    match model {
+        // If existing model is an object (has fallbacks etc.), only "primary" inside it changes
+        Some(v) => ModelRef::set_primary(&mut config.defaults_mut().model, v),
+        None => {
+            if let Some(defaults) = config.agents.as_mut().and_then(|a| a.defaults.as_mut()) {
+                match &mut defaults.model {
+                    Some(ModelRef::Detailed(spec)) => spec.primary = None,
+                    slot => *slot = None,
+                }
             }
-            write_config_with_snapshot(&paths, &current, &cfg, "set-global-model")?;
-            return Ok(true);
         }
     }
-    // Fallback: plain string or missing — set the whole value
-    set_nested_value(
-        &mut cfg,
-        "agents.defaults.model",
-        model.map(Value::String),
-    )?;
-    write_config_with_snapshot(&paths, &current, &cfg, "set-global-model")?;
+    write_typed_config_with_snapshot(&paths, &base, &config, "set-global-model")?;
     Ok(true)
 }
 
@@ -1187,11 +1246,16 @@ pub fn set_agent_model(agent_id: String, model_value: Option<String>) -> Result<
         return Err("agent id is required".into());
     }
     let paths = resolve_paths();
-    let mut cfg = read_openclaw_config(&paths)?;
-    let current = serde_json::to_string_pretty(&cfg).map_err(|e| e.to_string())?;
+    let (base, mut config) = read_typed_config(&paths)?;
     let value = model_value.map(|v| v.trim().to_string()).filter(|v| !v.is_empty());
-    set_agent_model_value(&mut cfg, &agent_id, value)?;
-    write_config_with_snapshot(&paths, &current, &cfg, "set-agent-model")?;
+    let agent = config
+        .agent_mut(&agent_id)
+        .ok_or_else(|| format!("agent not found: {agent_id}"))?;
+    match value {
+        Some(v) => ModelRef::set_primary(&mut agent.model, v),
+        None => agent.model = None,
+    }
+    write_typed_config_with_snapshot(&paths, &base, &config, "set-agent-model")?;
     Ok(true)
 }
 
@@ -1201,20 +1265,20 @@ pub fn set_channel_model(path: String, model_value: Option<String>) -> Result<bo
         return Err("channel path is required".into());
     }
     let paths = resolve_paths();
-    let mut cfg = read_openclaw_config(&paths)?;
-    let current = serde_json::to_string_pretty(&cfg).map_err(|e| e.to_string())?;
+    let (base, mut config) = read_typed_config(&paths)?;
     let value = model_value.map(|v| v.trim().to_string()).filter(|v| !v.is_empty());
-    set_nested_value(&mut cfg, &format!("{path}.model"), value.map(Value::String))?;
-    write_config_with_snapshot(&paths, &current, &cfg, "set-channel-model")?;
+    config.update_channel(&path, |channel| channel.model = value.map(ModelRef::Name))?;
+    write_typed_config_with_snapshot(&paths, &base, &config, "set-channel-model")?;
     Ok(true)
 }
 
 #[tauri::command]
 pub fn list_model_bindings() -> Result<Vec<ModelBinding>, String> {
     let paths = resolve_paths();
-    let cfg = read_openclaw_config(&paths)?;
+    let (config, warnings) = typed_config_for_read(&read_openclaw_config(&paths)?);
+    log_config_warnings(&warnings);
     let profiles = load_model_profiles(&paths);
-    Ok(collect_model_bindings(&cfg, &profiles))
+    Ok(collect_model_bindings(&config, &profiles))
 }
 
 #[tauri::command]
@@ -1302,10 +1366,9 @@ pub fn create_agent(
     }
 
     let paths = resolve_paths();
-    let mut cfg = read_openclaw_config(&paths)?;
-    let current = serde_json::to_string_pretty(&cfg).map_err(|e| e.to_string())?;
+    let (base, mut config) = read_typed_config(&paths)?;
 
-    let existing_ids = collect_agent_ids(&cfg);
+    let existing_ids = config.agent_ids();
     if existing_ids.iter().any(|id| id.eq_ignore_ascii_case(&agent_id)) {
         return Err(format!("Agent '{}' already exists", agent_id));
     }
@@ -1320,37 +1383,17 @@ pub fn create_agent(
         let ws_path = ws_dir.to_string_lossy().to_string();
         Some(ws_path)
     } else {
-        cfg.pointer("/agents/defaults/workspace")
-            .or_else(|| cfg.pointer("/agents/default/workspace"))
-            .and_then(Value::as_str)
-            .map(|s| s.to_string())
+        config.default_workspace()
     };
 
-    // Build agent entry
-    let mut agent_obj = serde_json::Map::new();
-    agent_obj.insert("id".into(), Value::String(agent_id.clone()));
-    if let Some(ref model_str) = model_display {
-        agent_obj.insert("model".into(), Value::String(model_str.clone()));
-    }
-    if let Some(ref ws) = workspace {
-        agent_obj.insert("workspace".into(), Value::String(ws.clone()));
-    }
-
-    let agents = cfg
-        .as_object_mut()
-        .ok_or("config is not an object")?
-        .entry("agents")
-        .or_insert_with(|| Value::Object(serde_json::Map::new()))
-        .as_object_mut()
-        .ok_or("agents is not an object")?;
-    let list = agents
-        .entry("list")
-        .or_insert_with(|| Value::Array(Vec::new()))
-        .as_array_mut()
-        .ok_or("agents.list is not an array")?;
-    list.push(Value::Object(agent_obj));
-
-    write_config_with_snapshot(&paths, &current, &cfg, "create-agent")?;
+    config.agent_list_mut().push(AgentEntry {
+        id: agent_id.clone(),
+        model: model_display.clone().map(ModelRef::Name),
+        workspace: workspace.clone(),
+        extra: Map::new(),
+    });
+
+    write_typed_config_with_snapshot(&paths, &base, &config, "create-agent")?;
     Ok(AgentOverview {
         id: agent_id,
         name: None,
@@ -1373,18 +1416,16 @@ pub fn delete_agent(agent_id: String) -> Result<bool, String> {
     }
 
     let paths = resolve_paths();
-    let mut cfg = read_openclaw_config(&paths)?;
-    let current = serde_json::to_string_pretty(&cfg).map_err(|e| e.to_string())?;
+    let (base, mut config) = read_typed_config(&paths)?;
 
-    let list = cfg
-        .pointer_mut("/agents/list")
-        .and_then(Value::as_array_mut)
+    let list = config
+        .agents
+        .as_mut()
+        .and_then(|agents| agents.list.as_mut())
         .ok_or("agents.list not found")?;
 
     let before = list.len();
-    list.retain(|agent| {
-        agent.get("id").and_then(Value::as_str) != Some(&agent_id)
-    });
+    list.retain(|agent| agent.id != agent_id);
 
     if list.len() == before {
         return Err(format!("Agent '{}' not found", agent_id));
@@ -1392,17 +1433,13 @@ pub fn delete_agent(agent_id: String) -> Result<bool, String> {
 
     // Reset any bindings that reference this agent back to "main" (default)
     // so the channel doesn't lose its binding entry entirely.
-    if let Some(bindings) = cfg.pointer_mut("/bindings").and_then(Value::as_array_mut) {
-        for b in bindings.iter_mut() {
-            if b.get("agentId").and_then(Value::as_str) == Some(&agent_id) {
-                if let Some(obj) = b.as_object_mut() {
-                    obj.insert("agentId".into(), Value::String("main".into()));
-                }
-            }
+    for binding in config.bindings.iter_mut().flatten() {
+        if binding.agent_id == agent_id {
+            binding.agent_id = "main".into();
         }
     }
 
-    write_config_with_snapshot(&paths, &current, &cfg, "delete-agent")?;
+    write_typed_config_with_snapshot(&paths, &base, &config, "delete-agent")?;
     Ok(true)
 }
 
@@ -1481,7 +1518,7 @@ pub async fn remote_setup_agent_identity(
 
     // Read remote config to find agent workspace
     let raw = pool.sftp_read(&host_id, "~/.openclaw/openclaw.json").await?;
-    let cfg: Value = serde_json::from_str(&raw).map_err(|e| format!("Failed to parse config: {e}"))?;
+    let cfg: Value = json5::from_str(&raw).map_err(|e| format!("Failed to parse config: {e}"))?;
 
     let agents_list = cfg.pointer("/agents/list")
         .and_then(Value::as_array)
@@ -1885,10 +1922,230 @@ fn preview_session_sync(agent_id: &str, session_id: &str) -> Result<Vec<Value>,
 }
 
 #[tauri::command]
-pub fn list_recipes(source: Option<String>) -> Result<Vec<crate::recipe::Recipe>, String> {
+pub async fn list_recipes(source: Option<String>) -> Result<Vec<crate::recipe::Recipe>, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let paths = resolve_paths();
+        Ok(load_registry(source, &recipes_dir(&paths.clawpal_dir)).recipes)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+#[tauri::command]
+pub async fn list_recipe_sources(source: Option<String>) -> Result<Vec<SourceStatus>, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let paths = resolve_paths();
+        Ok(load_registry(source, &recipes_dir(&paths.clawpal_dir)).sources)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+#[tauri::command]
+pub fn upsert_recipe_source(url: String, precedence: Option<i32>, enabled: Option<bool>) -> Result<bool, String> {
+    let url = url.trim().to_string();
+    if !crate::recipe::is_http_url(&url) {
+        return Err("recipe source must be an http(s) URL".into());
+    }
+    let dir = recipes_dir(&resolve_paths().clawpal_dir);
+    let mut file = read_sources(&dir);
+    let entry = RemoteSourceConfig { url: url.clone(), precedence, enabled: enabled.unwrap_or(true) };
+    match file.sources.iter_mut().find(|s| s.url == url) {
+        Some(existing) => *existing = entry,
+        None => file.sources.push(entry),
+    }
+    write_sources(&dir, &file)?;
+    Ok(true)
+}
+
+#[tauri::command]
+pub fn remove_recipe_source(url: String) -> Result<bool, String> {
+    let dir = recipes_dir(&resolve_paths().clawpal_dir);
+    let mut file = read_sources(&dir);
+    let before = file.sources.len();
+    file.sources.retain(|s| s.url != url.trim());
+    if file.sources.len() == before {
+        return Ok(false);
+    }
+    write_sources(&dir, &file)?;
+    Ok(true)
+}
+
+#[tauri::command]
+pub fn get_recipe_trust() -> Result<TrustStore, String> {
+    Ok(read_trust_store(&recipes_dir(&resolve_paths().clawpal_dir)))
+}
+
+#[tauri::command]
+pub fn add_trusted_recipe_key(name: String, public_key: String) -> Result<TrustStore, String> {
+    let dir = recipes_dir(&resolve_paths().clawpal_dir);
+    let mut store = read_trust_store(&dir);
+    let key = trusted_key(&name, &public_key)?;
+    store.keys.retain(|k| k.key_id != key.key_id);
+    store.keys.push(key);
+    write_trust_store(&dir, &store)?;
+    Ok(store)
+}
+
+#[tauri::command]
+pub fn remove_trusted_recipe_key(key_id: String) -> Result<TrustStore, String> {
+    let dir = recipes_dir(&resolve_paths().clawpal_dir);
+    let mut store = read_trust_store(&dir);
+    store.keys.retain(|k| k.key_id != key_id);
+    write_trust_store(&dir, &store)?;
+    Ok(store)
+}
+
+#[tauri::command]
+pub fn set_recipe_trust_policy(policy: TrustPolicy) -> Result<TrustStore, String> {
+    let dir = recipes_dir(&resolve_paths().clawpal_dir);
+    let mut store = read_trust_store(&dir);
+    store.policy = policy;
+    write_trust_store(&dir, &store)?;
+    Ok(store)
+}
+
+fn discord_channel_pairs(entries: &[DiscordGuildChannel]) -> Vec<(String, String)> {
+    entries
+        .iter()
+        .map(|e| (e.guild_id.clone(), e.channel_id.clone()))
+        .collect()
+}
+
+/// Model profiles and the Discord channel cache, for semantic param checks.
+fn recipe_param_context(paths: &crate::models::OpenClawPaths) -> crate::recipe::ParamContext {
+    let cache_file = paths.instance_dir().join("discord-guild-channels.json");
+    let discord_channels = fs::read_to_string(&cache_file)
+        .ok()
+        .and_then(|text| serde_json::from_str::<Vec<DiscordGuildChannel>>(&text).ok())
+        .map(|entries| discord_channel_pairs(&entries));
+    crate::recipe::ParamContext {
+        model_profile_ids: Some(load_model_profiles(paths).into_iter().map(|p| p.id).collect()),
+        discord_channels,
+    }
+}
+
+/// Model profile lookups and paths the recipe engine needs.
+fn recipe_env(paths: &crate::models::OpenClawPaths) -> crate::recipe::RecipeEnv {
+    crate::recipe::RecipeEnv {
+        base_dir: paths.base_dir.clone(),
+        model_values: load_model_profiles(paths)
+            .iter()
+            .map(|p| (p.id.clone(), profile_to_model_value(p)))
+            .collect(),
+        params: recipe_param_context(paths),
+    }
+}
+
+#[tauri::command]
+pub async fn validate_recipe_params(
+    recipe_id: String,
+    params: Map<String, Value>,
+    source: Option<String>,
+) -> Result<Vec<crate::recipe::ParamError>, String> {
+    let recipe = load_recipe_blocking(recipe_id, source).await?;
     let paths = resolve_paths();
-    let default_path = paths.clawpal_dir.join("recipes").join("recipes.json");
-    Ok(load_recipes_with_fallback(source, &default_path))
+    Ok(crate::recipe::validate_with(&recipe, &params, &recipe_param_context(&paths)))
+}
+
+/// Apply RFC 6902 operations to a document. The command queue uses this to
+/// turn a `json_patch` recipe step into `config set` commands.
+#[tauri::command]
+pub fn apply_json_patch(document: Value, operations: Value) -> Result<Value, String> {
+    let ops = crate::json_patch::parse_patch(&operations)?;
+    let mut document = document;
+    crate::json_patch::apply_patch(&mut document, &ops)?;
+    Ok(document)
+}
+
+/// JSON Schemas for recipes and the ClawPal data files. Also refreshes the
+/// copies under `~/.clawpal/schemas` so editors can point at them.
+#[tauri::command]
+pub fn get_json_schemas() -> Result<Vec<crate::schemas::SchemaDocument>, String> {
+    crate::schemas::write_schemas(&resolve_paths().clawpal_dir)?;
+    Ok(crate::schemas::all_schemas())
+}
+
+/// Steps as the engine will run them: `when` evaluated, `forEach` expanded
+/// and placeholders rendered.
+#[tauri::command]
+pub async fn resolve_recipe_steps(
+    recipe_id: String,
+    params: Map<String, Value>,
+    source: Option<String>,
+) -> Result<Vec<crate::recipe::ResolvedStep>, String> {
+    let recipe = load_recipe_blocking(recipe_id, source).await?;
+    let params = crate::recipe::coerce_params(&recipe, &params);
+    crate::recipe::resolve_steps(&recipe, &params)
+}
+
+#[tauri::command]
+pub async fn execute_recipe(
+    cache: State<'_, crate::cli_runner::CliCache>,
+    recipe_id: String,
+    params: Map<String, Value>,
+    source: Option<String>,
+    allow_untrusted: Option<bool>,
+) -> Result<crate::recipe::RecipeExecution, String> {
+    let recipe = load_recipe_blocking(recipe_id, source).await?;
+    crate::recipe::ensure_runnable(&recipe, allow_untrusted.unwrap_or(false))?;
+    let execution = tauri::async_runtime::spawn_blocking(move || {
+        let paths = resolve_paths();
+        crate::recipe::execute_recipe(&paths, &recipe, &params, &recipe_env(&paths))
+    })
+    .await
+    .map_err(|e| e.to_string())??;
+    if execution.ok {
+        cache.invalidate_all();
+    }
+    Ok(execution)
+}
+
+#[tauri::command]
+pub fn list_recipe_installs() -> Result<Vec<crate::recipe_install::RecipeInstall>, String> {
+    let paths = resolve_paths();
+    Ok(crate::recipe_install::read_installs(&crate::recipe_install::installs_path(&paths)).installs)
+}
+
+#[tauri::command]
+pub fn preview_recipe_uninstall(install_id: String) -> Result<crate::recipe_install::UninstallPreview, String> {
+    crate::recipe_install::preview_uninstall(&resolve_paths(), &install_id)
+}
+
+#[tauri::command]
+pub fn uninstall_recipe(
+    cache: State<'_, crate::cli_runner::CliCache>,
+    install_id: String,
+    keep_conflicts: Option<bool>,
+) -> Result<crate::recipe_install::UninstallResult, String> {
+    let result = crate::recipe_install::uninstall(&resolve_paths(), &install_id, keep_conflicts.unwrap_or(false))?;
+    if result.ok {
+        cache.invalidate_all();
+    }
+    Ok(result)
+}
+
+#[tauri::command]
+pub async fn prepare_recipe_install(link: String) -> Result<crate::deep_link::InstallPreview, String> {
+    let link = crate::deep_link::parse_install_link(&link)?;
+    tauri::async_runtime::spawn_blocking(move || {
+        let paths = resolve_paths();
+        let current = read_openclaw_config(&paths)?;
+        let config_path = paths.config_path.to_string_lossy().to_string();
+        let dir = recipes_dir(&paths.clawpal_dir);
+        crate::deep_link::prepare_install(
+            link,
+            |link| {
+                let registry = load_registry(None, &dir).recipes;
+                crate::deep_link::resolve_link_recipe(link, &registry, |url| crate::recipe_registry::load_source(url, &dir))
+            },
+            &current,
+            &recipe_env(&paths),
+            &config_path,
+        )
+    })
+    .await
+    .map_err(|e| e.to_string())?
 }
 
 #[tauri::command]
@@ -1898,19 +2155,19 @@ pub fn apply_config_patch(
 ) -> Result<ApplyResult, String> {
     let paths = resolve_paths();
     ensure_dirs(&paths)?;
-    let current = read_openclaw_config(&paths)?;
-    let current_text = serde_json::to_string_pretty(&current).map_err(|e| e.to_string())?;
-    let snapshot = add_snapshot(
-        &paths.history_dir,
-        &paths.metadata_path,
-        Some("config-patch".into()),
-        "apply",
-        true,
-        &current_text,
-        None,
-    )?;
-    let (candidate, _changes) = build_candidate_config_from_template(&current, &patch_template, &params)?;
-    write_json(&paths.config_path, &candidate)?;
+    let base = read_config_base(&paths)?;
+    let (candidate, _changes) = build_candidate_config_from_template(&base.value, &patch_template, &params)?;
+    let snapshot = crate::config_cas::commit(&paths, &base, &candidate, |current_text| {
+        add_snapshot(
+            &paths.history_dir,
+            &paths.metadata_path,
+            Some("config-patch".into()),
+            "apply",
+            true,
+            current_text,
+            None,
+        )
+    })?;
     Ok(ApplyResult {
         ok: true,
         snapshot_id: Some(snapshot.id),
@@ -2046,84 +2303,180 @@ pub async fn repair_primary_via_rescue(
     .map_err(|e| e.to_string())?
 }
 
+/// A local snapshot and its config: from the index or, with git history
+/// enabled, from the commits once retention has pruned it from the index.
+fn find_local_snapshot(
+    paths: &crate::models::OpenClawPaths,
+    snapshot_id: &str,
+) -> Result<(crate::history::SnapshotMeta, String), String> {
+    let store = LocalSnapshotStore::for_paths(paths);
+    match run_local(store.find(snapshot_id)) {
+        Ok(meta) => {
+            let text = run_local(store.content(&meta))?;
+            Ok((meta, text))
+        }
+        Err(e) if crate::history_git::enabled(paths) => {
+            crate::history_git::GitHistory::for_paths(paths).find(snapshot_id).map_err(|_| e)
+        }
+        Err(e) => Err(e),
+    }
+}
+
 #[tauri::command]
-pub fn list_history(limit: usize, offset: usize) -> Result<HistoryPage, String> {
+pub fn list_history(limit: usize, offset: usize, query: Option<String>) -> Result<HistoryPage, String> {
     let paths = resolve_paths();
-    let index = list_snapshots(&paths.metadata_path)?;
-    let items = index
-        .items
-        .into_iter()
-        .skip(offset)
-        .take(limit)
-        .map(|item| HistoryItem {
-            id: item.id,
-            recipe_id: item.recipe_id,
-            created_at: item.created_at,
-            source: item.source,
-            can_rollback: item.can_rollback,
-            rollback_of: item.rollback_of,
-        })
-        .collect();
-    Ok(HistoryPage { items })
+    let mut items = run_local(LocalSnapshotStore::for_paths(&paths).snapshots())?;
+    if crate::history_git::enabled(&paths) {
+        let committed = crate::history_git::GitHistory::for_paths(&paths).snapshots()?;
+        items = crate::history_git::merge_snapshots(committed, items);
+    }
+    Ok(HistoryPage::new(items, limit, offset, query.as_deref()))
+}
+
+/// Compare the configs captured by two snapshots, `from` → `to`.
+#[tauri::command]
+pub fn diff_snapshots(from_id: String, to_id: String) -> Result<SnapshotDiff, String> {
+    let paths = resolve_paths();
+    let parse = |(meta, text): (crate::history::SnapshotMeta, String)| {
+        json5::from_str::<Value>(&text)
+            .map(|config| (meta.clone(), config))
+            .map_err(|e| format!("Failed to parse snapshot {}: {e}", meta.id))
+    };
+    let (from, before) = parse(find_local_snapshot(&paths, &from_id)?)?;
+    let (to, after) = parse(find_local_snapshot(&paths, &to_id)?)?;
+    Ok(SnapshotDiff::new(from, &before, to, &after))
+}
+
+/// Every snapshot whose write changed the value at `json_path`, newest first.
+#[tauri::command]
+pub fn blame(json_path: String) -> Result<Vec<crate::history::BlameEntry>, String> {
+    let paths = resolve_paths();
+    let current = read_openclaw_config(&paths)?;
+    run_local(LocalSnapshotStore::for_paths(&paths).blame(&json_path, &current))
+}
+
+/// Pin a snapshot or edit its labels and note.
+#[tauri::command]
+pub fn update_snapshot(snapshot_id: String, update: crate::history::SnapshotUpdate) -> Result<HistoryItem, String> {
+    let store = LocalSnapshotStore::for_paths(&resolve_paths());
+    run_local(store.update(&snapshot_id, update)).map(HistoryItem::from)
+}
+
+#[tauri::command]
+pub fn get_history_retention() -> Result<crate::history::RetentionPolicy, String> {
+    let store = LocalSnapshotStore::for_paths(&resolve_paths());
+    run_local(async { Ok(store.retention().await) })
+}
+
+#[tauri::command]
+pub fn set_history_retention(policy: crate::history::RetentionPolicy) -> Result<crate::history::RetentionPolicy, String> {
+    run_local(LocalSnapshotStore::for_paths(&resolve_paths()).set_retention(&policy))?;
+    Ok(policy)
+}
+
+/// Apply the retention policy and delete snapshot blobs nothing references.
+#[tauri::command]
+pub fn gc_history() -> Result<crate::history::GcReport, String> {
+    let paths = resolve_paths();
+    ensure_dirs(&paths)?;
+    crate::history::gc_history(&paths.history_dir, &paths.metadata_path)
+}
+
+/// Pack the local history, model profiles and SSH hosts into one archive,
+/// under `~/.clawpal/exports` unless `path` is given.
+#[tauri::command]
+pub fn export_history_bundle(path: Option<String>, strip_secrets: bool) -> Result<crate::history_bundle::ExportResult, String> {
+    let paths = resolve_paths();
+    let dest = match path.as_deref().map(str::trim).filter(|p| !p.is_empty()) {
+        Some(p) => PathBuf::from(shellexpand::tilde(p).to_string()),
+        None => crate::history_bundle::default_export_path(&paths),
+    };
+    let manifest = crate::history_bundle::export_bundle(&paths, &dest, strip_secrets)?;
+    Ok(crate::history_bundle::ExportResult { path: dest.to_string_lossy().to_string(), manifest })
+}
+
+/// Merge an exported archive into the local history, model profiles and SSH hosts.
+#[tauri::command]
+pub fn import_history_bundle(path: String) -> Result<crate::history_bundle::ImportReport, String> {
+    let paths = resolve_paths();
+    ensure_dirs(&paths)?;
+    crate::history_bundle::import_bundle(&paths, Path::new(&shellexpand::tilde(path.trim()).to_string()))
+}
+
+#[tauri::command]
+pub fn get_git_history_settings() -> Result<crate::history_git::GitHistorySettings, String> {
+    Ok(crate::history_git::settings(&resolve_paths()))
+}
+
+/// Turn git history on or off, or change its remote. Turning it on commits
+/// the current config to start the repository.
+#[tauri::command]
+pub fn set_git_history_settings(
+    settings: crate::history_git::GitHistorySettings,
+) -> Result<crate::history_git::GitHistorySettings, String> {
+    let paths = resolve_paths();
+    crate::history_git::set_settings(&paths, &settings)?;
+    Ok(crate::history_git::settings(&paths))
+}
+
+/// Push git history to its configured remote now.
+#[tauri::command]
+pub async fn push_git_history() -> Result<bool, String> {
+    tauri::async_runtime::spawn_blocking(|| {
+        let paths = resolve_paths();
+        let settings = crate::history_git::settings(&paths);
+        let remote = settings.remote.filter(|_| settings.enabled).ok_or_else(|| "git history has no remote".to_string())?;
+        crate::history_git::GitHistory::for_paths(&paths).push(&remote)?;
+        Ok(true)
+    })
+    .await
+    .map_err(|e| e.to_string())?
 }
 
 #[tauri::command]
 pub fn preview_rollback(snapshot_id: String) -> Result<PreviewResult, String> {
     let paths = resolve_paths();
-    let index = list_snapshots(&paths.metadata_path)?;
-    let target = index
-        .items
-        .into_iter()
-        .find(|s| s.id == snapshot_id)
-        .ok_or_else(|| "snapshot not found".to_string())?;
-    if !target.can_rollback {
-        return Err("snapshot is not rollbackable".to_string());
-    }
+    let (target, target_text) = find_local_snapshot(&paths, &snapshot_id)?;
+    target.ensure_rollbackable()?;
 
     let current = read_openclaw_config(&paths)?;
-    let target_text = read_snapshot(&target.config_path)?;
     let target_json: Value = json5::from_str(&target_text).unwrap_or(Value::Object(Default::default()));
-    let before_text = serde_json::to_string_pretty(&current).unwrap_or_else(|_| "{}".into());
-    let after_text = serde_json::to_string_pretty(&target_json).unwrap_or_else(|_| "{}".into());
-    Ok(PreviewResult {
-        recipe_id: "rollback".into(),
-        diff: format_diff(&current, &target_json),
-        config_before: before_text,
-        config_after: after_text,
-        changes: collect_change_paths(&current, &target_json),
-        overwrites_existing: true,
-        can_rollback: true,
-        impact_level: "medium".into(),
-        warnings: vec!["Rollback will replace current configuration".into()],
-    })
+    Ok(crate::recipe::rollback_preview(&current, &target_json))
 }
 
 #[tauri::command]
 pub fn rollback(snapshot_id: String) -> Result<ApplyResult, String> {
     let paths = resolve_paths();
     ensure_dirs(&paths)?;
-    let index = list_snapshots(&paths.metadata_path)?;
-    let target = index
-        .items
-        .into_iter()
-        .find(|s| s.id == snapshot_id)
-        .ok_or_else(|| "snapshot not found".to_string())?;
-    if !target.can_rollback {
-        return Err("snapshot is not rollbackable".to_string());
-    }
-    let target_text = read_snapshot(&target.config_path)?;
-    let backup = read_openclaw_config(&paths)?;
-    let backup_text = serde_json::to_string_pretty(&backup).map_err(|e| e.to_string())?;
-    let _ = add_snapshot(
-        &paths.history_dir,
-        &paths.metadata_path,
-        target.recipe_id.clone(),
-        "rollback",
-        true,
-        &backup_text,
-        Some(target.id.clone()),
-    )?;
-    write_text(&paths.config_path, &target_text)?;
+    let (target, target_text) = find_local_snapshot(&paths, &snapshot_id)?;
+    target.ensure_rollbackable()?;
+    let base = read_config_base(&paths)?;
+    let snapshot_backup = |current_text: &str| {
+        add_snapshot(
+            &paths.history_dir,
+            &paths.metadata_path,
+            target.recipe_id.clone(),
+            "rollback",
+            true,
+            current_text,
+            Some(target.id.clone()),
+        )
+    };
+    match json5::from_str::<Value>(&target_text) {
+        Ok(target_config) => {
+            crate::config_cas::commit(&paths, &base, &target_config, snapshot_backup)?;
+        }
+        Err(_) => {
+            // Not mergeable, so it only goes ahead over an unchanged config.
+            let mut write = crate::config_watch::LocalWriteGuard::new(&paths);
+            let _lock = crate::config_cas::ConfigLock::acquire(&paths.config_path)?;
+            let current_text = crate::config_io::read_text(&paths.config_path)?;
+            crate::config_cas::check_unchanged(&base, &current_text)?;
+            snapshot_backup(&current_text)?;
+            write_text(&paths.config_path, &target_text)?;
+            write.written(&target_text);
+        }
+    }
     Ok(ApplyResult {
         ok: true,
         snapshot_id: Some(target.id),
@@ -3033,10 +3386,7 @@ async fn repair_primary_via_rescue_remote(
 }
 
 fn resolve_gateway_port_from_config(cfg: &Value) -> u16 {
-    cfg.pointer("/gateway/port")
-        .and_then(Value::as_u64)
-        .and_then(|port| u16::try_from(port).ok())
-        .unwrap_or(18789)
+    typed_config_for_read(cfg).0.gateway_port()
 }
 
 fn suggest_rescue_port(main_port: u16) -> u16 {
@@ -3380,8 +3730,7 @@ fn run_openclaw_raw(args: &[&str]) -> Result<OpenclawCommandOutput, String> {
 }
 
 fn run_openclaw_raw_timeout(args: &[&str], timeout_secs: Option<u64>) -> Result<OpenclawCommandOutput, String> {
-    let mut child = Command::new(resolve_openclaw_bin())
-        .args(args)
+    let mut child = crate::instances::openclaw_command(args)
         .stdout(std::process::Stdio::piped())
         .stderr(std::process::Stdio::piped())
         .spawn()
@@ -4485,8 +4834,8 @@ pub async fn test_model_profile(profile_id: String) -> Result<bool, String> {
             .or_else(|| {
                 fs::read_to_string(&paths.config_path)
                     .ok()
-                    .and_then(|raw| serde_json::from_str::<Value>(&raw).ok())
-                    .and_then(|cfg| resolve_model_provider_base_url(&cfg, &profile.provider))
+                    .and_then(|raw| json5::from_str::<Value>(&raw).ok())
+                    .and_then(|cfg| typed_config_for_read(&cfg).0.provider_base_url(&profile.provider))
             });
 
         run_provider_probe(profile.provider, profile.model, resolved_base_url, api_key)
@@ -4632,87 +4981,57 @@ fn save_model_profiles(paths: &crate::models::OpenClawPaths, profiles: &[ModelPr
     Ok(())
 }
 
+/// Snapshot the config being replaced, then write `next` over the config
+/// read as `base` (see [`crate::config_cas::commit`]).
 fn write_config_with_snapshot(
     paths: &crate::models::OpenClawPaths,
-    current_text: &str,
+    base: &ConfigBase,
     next: &Value,
     source: &str,
 ) -> Result<(), String> {
-    let _ = add_snapshot(
-        &paths.history_dir,
-        &paths.metadata_path,
-        Some(source.to_string()),
-        source,
-        true,
-        current_text,
-        None,
-    )?;
-    write_json(&paths.config_path, next)
-}
-
-fn set_nested_value(root: &mut Value, path: &str, value: Option<Value>) -> Result<(), String> {
-    let path = path.trim().trim_matches('.');
-    if path.is_empty() {
-        return Err("invalid path".into());
-    }
-    let mut cur = root;
-    let mut parts = path.split('.').peekable();
-    while let Some(part) = parts.next() {
-        let is_last = parts.peek().is_none();
-        let obj = cur
-            .as_object_mut()
-            .ok_or_else(|| "path must point to object".to_string())?;
-        if is_last {
-            if let Some(v) = value {
-                obj.insert(part.to_string(), v);
-            } else {
-                obj.remove(part);
-            }
-            return Ok(());
-        }
-        let child = obj
-            .entry(part.to_string())
-            .or_insert_with(|| Value::Object(Default::default()));
-        if !child.is_object() {
-            *child = Value::Object(Default::default());
-        }
-        cur = child;
+    crate::config_cas::commit(paths, base, next, |current_text| {
+        add_snapshot(
+            &paths.history_dir,
+            &paths.metadata_path,
+            Some(source.to_string()),
+            source,
+            true,
+            current_text,
+            None,
+        )
+    })?;
+    Ok(())
+}
+
+/// The config typed for a read-only command. Sections that don't fit the
+/// typed model are left out and described in the returned warnings instead
+/// of failing the command; updates go through [`read_typed_config`].
+fn typed_config_for_read(value: &Value) -> (OpenClawConfig, Vec<String>) {
+    let (config, errors) = OpenClawConfig::from_value_lenient(value);
+    (config, errors.iter().map(ToString::to_string).collect())
+}
+
+fn log_config_warnings(warnings: &[String]) {
+    for warning in warnings {
+        eprintln!("Warning: {warning}");
     }
-    unreachable!("path should have at least one segment");
 }
 
-fn set_agent_model_value(
-    root: &mut Value,
-    agent_id: &str,
-    model: Option<String>,
+/// The local config read for an update, as the base the write checks
+/// against and the typed model to edit.
+fn read_typed_config(paths: &crate::models::OpenClawPaths) -> Result<(ConfigBase, OpenClawConfig), String> {
+    let base = read_config_base(paths)?;
+    let config = OpenClawConfig::from_value(&base.value)?;
+    Ok((base, config))
+}
+
+fn write_typed_config_with_snapshot(
+    paths: &crate::models::OpenClawPaths,
+    base: &ConfigBase,
+    config: &OpenClawConfig,
+    source: &str,
 ) -> Result<(), String> {
-    if let Some(agents) = root.pointer_mut("/agents").and_then(Value::as_object_mut) {
-        if let Some(list) = agents.get_mut("list").and_then(Value::as_array_mut) {
-            for agent in list {
-                if agent.get("id").and_then(Value::as_str) == Some(agent_id) {
-                    if let Some(agent_obj) = agent.as_object_mut() {
-                        match model {
-                            Some(v) => {
-                                // If existing model is an object, update "primary" inside it
-                                if let Some(existing) = agent_obj.get_mut("model") {
-                                    if let Some(model_obj) = existing.as_object_mut() {
-                                        model_obj.insert("primary".into(), Value::String(v));
-                                        return Ok(());
-                                    }
-                                }
-                                agent_obj.insert("model".into(), Value::String(v));
-                            }
-                            None => {
-                                agent_obj.remove("model");
-                            }
-                        }
-                    }
-                    return Ok(());
-                }
-            }
-        }
-    }
-    Err(format!("agent not found: {agent_id}"))
+    write_config_with_snapshot(paths, base, &config.to_value()?, source)
 }
 
 fn load_model_catalog(
@@ -5355,7 +5674,7 @@ fn enrich_channel_display_names(
         }
     }
 
-    let cache_file = paths.clawpal_dir.join("channel-name-cache.json");
+    let cache_file = paths.instance_dir().join("channel-name-cache.json");
     if nodes.is_empty() {
         if cache_file.exists() {
             let _ = fs::remove_file(&cache_file);
@@ -5614,28 +5933,9 @@ fn collect_channel_allowlist(obj: &serde_json::Map<String, Value>) -> Vec<String
     out
 }
 
-fn collect_agent_ids(cfg: &Value) -> Vec<String> {
-    let mut ids = Vec::new();
-    if let Some(agents) = cfg.get("agents").and_then(|v| v.get("list")).and_then(Value::as_array) {
-        for agent in agents {
-            if let Some(id) = agent.get("id").and_then(Value::as_str) {
-                ids.push(id.to_string());
-            }
-        }
-    }
-    // Implicit "main" agent when no agents.list
-    if ids.is_empty() {
-        ids.push("main".into());
-    }
-    ids
-}
-
-fn collect_model_bindings(cfg: &Value, profiles: &[ModelProfile]) -> Vec<ModelBinding> {
+fn collect_model_bindings(config: &OpenClawConfig, profiles: &[ModelProfile]) -> Vec<ModelBinding> {
     let mut out = Vec::new();
-    let global = cfg
-        .pointer("/agents/defaults/model")
-        .or_else(|| cfg.pointer("/agents/default/model"))
-        .and_then(read_model_value);
+    let global = config.default_model();
     out.push(ModelBinding {
         scope: "global".into(),
         scope_id: "global".into(),
@@ -5644,18 +5944,15 @@ fn collect_model_bindings(cfg: &Value, profiles: &[ModelProfile]) -> Vec<ModelBi
         path: Some("agents.defaults.model".into()),
     });
 
-    if let Some(agents) = cfg.get("agents").and_then(|v| v.get("list")).and_then(Value::as_array) {
-        for agent in agents {
-            let id = agent.get("id").and_then(Value::as_str).unwrap_or("agent");
-            let model = agent.get("model").and_then(read_model_value);
-            out.push(ModelBinding {
-                scope: "agent".into(),
-                scope_id: id.to_string(),
-                model_profile_id: find_profile_by_model(profiles, model.as_deref()),
-                model_value: model,
-                path: Some(format!("agents.list.{id}.model")),
-            });
-        }
+    for agent in config.agent_list() {
+        let model = agent.model.as_ref().and_then(ModelRef::primary);
+        out.push(ModelBinding {
+            scope: "agent".into(),
+            scope_id: agent.id.clone(),
+            model_profile_id: find_profile_by_model(profiles, model.as_deref()),
+            model_value: model,
+            path: Some(format!("agents.list.{}.model", agent.id)),
+        });
     }
 
     fn walk_channel_binding(prefix: &str, node: &Value, out: &mut Vec<ModelBinding>, profiles: &[ModelProfile]) {
@@ -5677,8 +5974,8 @@ fn collect_model_bindings(cfg: &Value, profiles: &[ModelProfile]) -> Vec<ModelBi
         }
     }
 
-    if let Some(channels) = cfg.get("channels") {
-        walk_channel_binding("channels", channels, &mut out, profiles);
+    if let Some(channels) = config.channels.as_ref().and_then(|c| serde_json::to_value(c).ok()) {
+        walk_channel_binding("channels", &channels, &mut out, profiles);
     }
 
     out
@@ -5697,33 +5994,6 @@ fn find_profile_by_model(profiles: &[ModelProfile], value: Option<&str>) -> Opti
     None
 }
 
-fn resolve_auth_ref_for_provider(cfg: &Value, provider: &str) -> Option<String> {
-    let provider = provider.trim().to_lowercase();
-    if provider.is_empty() {
-        return None;
-    }
-    if let Some(auth_profiles) = cfg.pointer("/auth/profiles").and_then(Value::as_object) {
-        let mut fallback = None;
-        for (profile_id, profile) in auth_profiles {
-            let entry_provider = profile.get("provider").or_else(|| profile.get("name"));
-            if let Some(entry_provider) = entry_provider.and_then(Value::as_str) {
-                if entry_provider.trim().eq_ignore_ascii_case(&provider) {
-                    if profile_id.ends_with(":default") {
-                        return Some(profile_id.clone());
-                    }
-                    if fallback.is_none() {
-                        fallback = Some(profile_id.clone());
-                    }
-                }
-            }
-        }
-        if fallback.is_some() {
-            return fallback;
-        }
-    }
-    None
-}
-
 #[tauri::command]
 pub fn read_raw_config() -> Result<String, String> {
     let paths = resolve_paths();
@@ -5849,7 +6119,7 @@ pub struct BackupInfo {
 #[tauri::command]
 pub fn backup_before_upgrade() -> Result<BackupInfo, String> {
     let paths = resolve_paths();
-    let backups_dir = paths.clawpal_dir.join("backups");
+    let backups_dir = paths.instance_dir().join("backups");
     fs::create_dir_all(&backups_dir).map_err(|e| format!("Failed to create backups dir: {e}"))?;
 
     let now_secs = unix_timestamp_secs();
@@ -5913,7 +6183,7 @@ fn copy_dir_recursive(src: &Path, dst: &Path, skip_dirs: &HashSet<&str>, total:
 #[tauri::command]
 pub fn list_backups() -> Result<Vec<BackupInfo>, String> {
     let paths = resolve_paths();
-    let backups_dir = paths.clawpal_dir.join("backups");
+    let backups_dir = paths.instance_dir().join("backups");
     if !backups_dir.exists() {
         return Ok(Vec::new());
     }
@@ -5962,7 +6232,7 @@ fn dir_size(path: &Path) -> u64 {
 #[tauri::command]
 pub fn restore_from_backup(backup_name: String) -> Result<String, String> {
     let paths = resolve_paths();
-    let backup_dir = paths.clawpal_dir.join("backups").join(&backup_name);
+    let backup_dir = paths.instance_dir().join("backups").join(&backup_name);
     if !backup_dir.exists() {
         return Err(format!("Backup '{}' not found", backup_name));
     }
@@ -5970,8 +6240,13 @@ pub fn restore_from_backup(backup_name: String) -> Result<String, String> {
     // Restore config file
     let backup_config = backup_dir.join("openclaw.json");
     if backup_config.exists() {
-        fs::copy(&backup_config, &paths.config_path)
+        let mut write = crate::config_watch::LocalWriteGuard::new(&paths);
+        let _lock = crate::config_cas::ConfigLock::acquire(&paths.config_path)?;
+        let text = fs::read_to_string(&backup_config)
+            .map_err(|e| format!("Failed to read backup config: {e}"))?;
+        write_text(&paths.config_path, &text)
             .map_err(|e| format!("Failed to restore config: {e}"))?;
+        write.written(&text);
     }
 
     // Restore other directories (agents except sessions/archive, memory, etc.)
@@ -6011,7 +6286,7 @@ fn restore_dir_recursive(src: &Path, dst: &Path, skip_dirs: &HashSet<&str>) -> R
 #[tauri::command]
 pub fn delete_backup(backup_name: String) -> Result<bool, String> {
     let paths = resolve_paths();
-    let backup_dir = paths.clawpal_dir.join("backups").join(&backup_name);
+    let backup_dir = paths.instance_dir().join("backups").join(&backup_name);
     if !backup_dir.exists() {
         return Ok(false);
     }
@@ -6173,29 +6448,42 @@ pub async fn remote_delete_backup(
     Ok(result.stdout.trim() == "deleted")
 }
 
-fn resolve_model_provider_base_url(cfg: &Value, provider: &str) -> Option<String> {
-    let provider = provider.trim();
-    if provider.is_empty() {
-        return None;
+// ---------------------------------------------------------------------------
+// Local instances
+// ---------------------------------------------------------------------------
+
+#[tauri::command]
+pub fn list_local_instances() -> Result<Vec<LocalInstance>, String> {
+    Ok(crate::instances::list_instances())
+}
+
+/// Point local commands at another instance. Queued commands were previewed
+/// against the current one, so they have to be applied or discarded first.
+#[tauri::command]
+pub fn select_local_instance(
+    app_handle: tauri::AppHandle,
+    queue: State<'_, crate::cli_runner::CommandQueue>,
+    cache: State<'_, crate::cli_runner::CliCache>,
+    instance_id: String,
+) -> Result<LocalInstance, String> {
+    if crate::instances::active_id() != instance_id && !queue.is_empty() {
+        return Err("apply or discard pending changes before switching instances".into());
     }
-    cfg.pointer("/models/providers")
-        .and_then(Value::as_object)
-        .and_then(|providers| providers.get(provider))
-        .and_then(Value::as_object)
-        .and_then(|provider_cfg| {
-            provider_cfg
-                .get("baseUrl")
-                .or_else(|| provider_cfg.get("base_url"))
-                .and_then(Value::as_str)
-                .map(str::to_string)
-                .or_else(|| {
-                    provider_cfg
-                        .get("apiBase")
-                        .or_else(|| provider_cfg.get("api_base"))
-                        .and_then(Value::as_str)
-                        .map(str::to_string)
-                })
-        })
+    let instance = crate::instances::select_instance(&instance_id)?;
+    cache.invalidate_all();
+    crate::config_watch::watch_local(&app_handle, &instance.id, resolve_paths());
+    Ok(instance)
+}
+
+#[tauri::command]
+pub fn add_local_instance(path: String, label: Option<String>) -> Result<LocalInstance, String> {
+    crate::instances::add_instance(&path, label)
+}
+
+#[tauri::command]
+pub fn remove_local_instance(instance_id: String) -> Result<bool, String> {
+    crate::instances::remove_instance(&instance_id)?;
+    Ok(true)
 }
 
 // ---------------------------------------------------------------------------
@@ -6557,34 +6845,39 @@ pub fn delete_ssh_host(host_id: String) -> Result<bool, String> {
 // ---------------------------------------------------------------------------
 
 #[tauri::command]
-pub async fn ssh_connect(pool: State<'_, SshConnectionPool>, host_id: String) -> Result<bool, String> {
+pub async fn ssh_connect(
+    app_handle: tauri::AppHandle,
+    pool: State<'_, SshConnectionPool>,
+    host_id: String,
+) -> Result<bool, String> {
     // If already connected and handle is alive, reuse
-    if pool.is_connected(&host_id).await {
-        return Ok(true);
+    if !pool.is_connected(&host_id).await {
+        let hosts = read_hosts_from_disk()?;
+        let host = hosts.into_iter().find(|h| h.id == host_id)
+            .ok_or_else(|| format!("No SSH host config with id: {host_id}"))?;
+        pool.connect(&host).await?;
     }
-    let hosts = read_hosts_from_disk()?;
-    let host = hosts.into_iter().find(|h| h.id == host_id)
-        .ok_or_else(|| format!("No SSH host config with id: {host_id}"))?;
-    pool.connect(&host).await?;
+    crate::config_watch::watch_remote(&app_handle, &host_id);
     Ok(true)
 }
 
 #[tauri::command]
 pub async fn ssh_connect_with_passphrase(
+    app_handle: tauri::AppHandle,
     pool: State<'_, SshConnectionPool>,
     host_id: String,
     passphrase: String,
 ) -> Result<bool, String> {
-    if pool.is_connected(&host_id).await {
-        return Ok(true);
+    if !pool.is_connected(&host_id).await {
+        let hosts = read_hosts_from_disk()?;
+        let host = hosts
+            .into_iter()
+            .find(|h| h.id == host_id)
+            .ok_or_else(|| format!("No SSH host config with id: {host_id}"))?;
+        pool.connect_with_passphrase(&host, Some(passphrase.as_str()))
+            .await?;
     }
-    let hosts = read_hosts_from_disk()?;
-    let host = hosts
-        .into_iter()
-        .find(|h| h.id == host_id)
-        .ok_or_else(|| format!("No SSH host config with id: {host_id}"))?;
-    pool.connect_with_passphrase(&host, Some(passphrase.as_str()))
-        .await?;
+    crate::config_watch::watch_remote(&app_handle, &host_id);
     Ok(true)
 }
 
@@ -6839,25 +7132,48 @@ pub async fn remote_list_bindings(pool: State<'_, SshConnectionPool>, host_id: S
 // ---------------------------------------------------------------------------
 
 /// Private helper: snapshot current config then write new config on remote.
+/// `base_text` is the config as read when `next` was derived from it.
 async fn remote_write_config_with_snapshot(
     pool: &SshConnectionPool,
     host_id: &str,
-    current_text: &str,
+    base_text: &str,
     next: &Value,
     source: &str,
 ) -> Result<(), String> {
-    // Create snapshot dir
-    pool.exec(host_id, "mkdir -p ~/.clawpal/snapshots").await?;
-    // Write snapshot (use chrono-free timestamp from SystemTime)
-    let ts = std::time::SystemTime::now()
-        .duration_since(std::time::UNIX_EPOCH)
-        .unwrap_or_default()
-        .as_secs();
-    let snapshot_path = format!("~/.clawpal/snapshots/{ts}-{source}.json");
-    pool.sftp_write(host_id, &snapshot_path, current_text).await?;
-    // Write new config
-    let new_text = serde_json::to_string_pretty(next).map_err(|e| e.to_string())?;
+    remote_write_config_after_snapshot(pool, host_id, base_text, next, source, None).await
+}
+
+/// Like [`remote_write_config_with_snapshot`]; `rollback_of` links the
+/// snapshot to the one being restored.
+///
+/// The config is re-read first and outside changes since `base_text` are
+/// merged as for local writes (see [`crate::config_cas::resolve`]). There is
+/// no lock on the remote side, so this narrows the race rather than closing it.
+async fn remote_write_config_after_snapshot(
+    pool: &SshConnectionPool,
+    host_id: &str,
+    base_text: &str,
+    next: &Value,
+    source: &str,
+    rollback_of: Option<String>,
+) -> Result<(), String> {
+    let mut write = crate::config_watch::RemoteWriteGuard::new(host_id);
+    let base = ConfigBase::from_text(base_text.to_string());
+    let current_text = pool.sftp_read(host_id, "~/.openclaw/openclaw.json").await?;
+    let next = crate::config_cas::resolve(&base, &current_text, next)?;
+    // Edit the text in place, as local writes do, to keep comments and formatting.
+    let new_text = crate::json5_edit::update_text(&current_text, &next);
+    SshSnapshotStore::new(pool, host_id)
+        .add(NewSnapshot {
+            recipe_id: Some(source.to_string()),
+            source: source.to_string(),
+            can_rollback: true,
+            config: current_text,
+            rollback_of,
+        })
+        .await?;
     pool.sftp_write(host_id, "~/.openclaw/openclaw.json", &new_text).await?;
+    write.written();
     Ok(())
 }
 
@@ -7076,11 +7392,10 @@ pub async fn remote_apply_config_patch(
 ) -> Result<ApplyResult, String> {
     let raw = pool.sftp_read(&host_id, "~/.openclaw/openclaw.json").await?;
     let current: Value =
-        serde_json::from_str(&raw).map_err(|e| format!("Failed to parse remote config: {e}"))?;
-    let current_text = serde_json::to_string_pretty(&current).map_err(|e| e.to_string())?;
+        json5::from_str(&raw).map_err(|e| format!("Failed to parse remote config: {e}"))?;
     let (candidate, _changes) =
         build_candidate_config_from_template(&current, &patch_template, &params)?;
-    remote_write_config_with_snapshot(&pool, &host_id, &current_text, &candidate, "config-patch")
+    remote_write_config_with_snapshot(&pool, &host_id, &raw, &candidate, "config-patch")
         .await?;
     Ok(ApplyResult {
         ok: true,
@@ -7092,6 +7407,81 @@ pub async fn remote_apply_config_patch(
     })
 }
 
+/// Same checks as [`validate_recipe_params`], against the remote host's
+/// model profiles and Discord channel cache.
+#[tauri::command]
+pub async fn remote_validate_recipe_params(
+    pool: State<'_, SshConnectionPool>,
+    host_id: String,
+    recipe_id: String,
+    params: Map<String, Value>,
+    source: Option<String>,
+) -> Result<Vec<crate::recipe::ParamError>, String> {
+    let recipe = load_recipe_blocking(recipe_id, source).await?;
+    let env = remote_recipe_env(&pool, &host_id).await;
+    Ok(crate::recipe::validate_with(&recipe, &params, &env.params))
+}
+
+/// Recipe lookup may fetch over blocking HTTP, so keep it off the async runtime.
+async fn load_recipe_blocking(recipe_id: String, source: Option<String>) -> Result<crate::recipe::Recipe, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        crate::recipe::find_recipe_with_source(&recipe_id, source)
+            .ok_or_else(|| format!("recipe not found: {recipe_id}"))
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+/// Remote counterpart of `recipe_env`.
+async fn remote_recipe_env(pool: &SshConnectionPool, host_id: &str) -> crate::recipe::RecipeEnv {
+    let profiles = read_remote_model_profiles(pool, host_id).await;
+    let discord_channels = pool
+        .sftp_read(host_id, "~/.clawpal/discord-guild-channels.json")
+        .await
+        .ok()
+        .and_then(|text| serde_json::from_str::<Vec<DiscordGuildChannel>>(&text).ok())
+        .map(|entries| discord_channel_pairs(&entries));
+    crate::recipe::RecipeEnv {
+        base_dir: PathBuf::from("~/.openclaw"),
+        model_values: profiles
+            .iter()
+            .map(|p| (p.id.clone(), profile_to_model_value(p)))
+            .collect(),
+        params: crate::recipe::ParamContext {
+            model_profile_ids: Some(profiles.into_iter().map(|p| p.id).collect()),
+            discord_channels,
+        },
+    }
+}
+
+/// Dry-run a recipe against the local config (`target` empty or "local") or an SSH host.
+#[tauri::command]
+pub async fn preview_recipe(
+    pool: State<'_, SshConnectionPool>,
+    recipe_id: String,
+    params: Map<String, Value>,
+    target: Option<String>,
+    source: Option<String>,
+) -> Result<PreviewResult, String> {
+    let recipe = load_recipe_blocking(recipe_id, source).await?;
+    match target.filter(|t| !t.is_empty() && t != "local") {
+        None => {
+            let paths = resolve_paths();
+            let current = read_openclaw_config(&paths)?;
+            let config_path = paths.config_path.to_string_lossy().to_string();
+            crate::recipe::preview_recipe(&current, &recipe, &params, &recipe_env(&paths), &config_path)
+        }
+        Some(host_id) => {
+            let config_path = "~/.openclaw/openclaw.json";
+            let text = pool.sftp_read(&host_id, config_path).await?;
+            let current: Value = json5::from_str(&text)
+                .map_err(|e| format!("Failed to parse config: {e}"))?;
+            let env = remote_recipe_env(&pool, &host_id).await;
+            crate::recipe::preview_recipe(&current, &recipe, &params, &env, config_path)
+        }
+    }
+}
+
 #[tauri::command]
 pub async fn remote_run_doctor(
     pool: State<'_, SshConnectionPool>,
@@ -7120,42 +7510,78 @@ pub async fn remote_run_doctor(
 pub async fn remote_list_history(
     pool: State<'_, SshConnectionPool>,
     host_id: String,
-) -> Result<Value, String> {
-    // Ensure dir exists
-    pool.exec(&host_id, "mkdir -p ~/.clawpal/snapshots").await?;
-    let entries = pool.sftp_list(&host_id, "~/.clawpal/snapshots").await?;
-    let mut items: Vec<Value> = Vec::new();
-    for entry in entries {
-        if entry.name.starts_with('.') || entry.is_dir {
-            continue;
-        }
-        // Parse filename: {unix_ts}-{source}-{summary}.json
-        let stem = entry.name.trim_end_matches(".json");
-        let parts: Vec<&str> = stem.splitn(3, '-').collect();
-        let ts_str = parts.first().unwrap_or(&"0");
-        let source = parts.get(1).unwrap_or(&"unknown");
-        let recipe_id = parts.get(2).map(|s| s.to_string());
-        let created_at = ts_str.parse::<i64>().unwrap_or(0);
-        // Convert Unix timestamp to ISO 8601 format for frontend compatibility
-        let created_at_iso = chrono::DateTime::from_timestamp(created_at, 0)
-            .map(|dt| dt.format("%Y-%m-%dT%H:%M:%SZ").to_string())
-            .unwrap_or_else(|| created_at.to_string());
-        let is_rollback = *source == "rollback";
-        items.push(serde_json::json!({
-            "id": entry.name,
-            "recipeId": recipe_id,
-            "createdAt": created_at_iso,
-            "source": source,
-            "canRollback": !is_rollback,
-        }));
-    }
-    // Sort newest first
-    items.sort_by(|a, b| {
-        let ta = a["createdAt"].as_str().unwrap_or("");
-        let tb = b["createdAt"].as_str().unwrap_or("");
-        tb.cmp(ta)
-    });
-    Ok(serde_json::json!({ "items": items }))
+    limit: Option<usize>,
+    offset: Option<usize>,
+    query: Option<String>,
+) -> Result<HistoryPage, String> {
+    let store = SshSnapshotStore::new(&pool, &host_id);
+    store.import_legacy().await?;
+    let items = store.snapshots().await?;
+    Ok(HistoryPage::new(items, limit.unwrap_or(usize::MAX), offset.unwrap_or(0), query.as_deref()))
+}
+
+#[tauri::command]
+pub async fn remote_diff_snapshots(
+    pool: State<'_, SshConnectionPool>,
+    host_id: String,
+    from_id: String,
+    to_id: String,
+) -> Result<SnapshotDiff, String> {
+    let store = SshSnapshotStore::new(&pool, &host_id);
+    let (from, to) = (store.find(&from_id).await?, store.find(&to_id).await?);
+    let (before, after) = (store.config(&from).await?, store.config(&to).await?);
+    Ok(SnapshotDiff::new(from, &before, to, &after))
+}
+
+#[tauri::command]
+pub async fn remote_blame(
+    pool: State<'_, SshConnectionPool>,
+    host_id: String,
+    json_path: String,
+) -> Result<Vec<crate::history::BlameEntry>, String> {
+    let current_text = pool.sftp_read(&host_id, "~/.openclaw/openclaw.json").await?;
+    let current: Value = json5::from_str(&current_text)
+        .map_err(|e| format!("Failed to parse config: {e}"))?;
+    SshSnapshotStore::new(&pool, &host_id).blame(&json_path, &current).await
+}
+
+#[tauri::command]
+pub async fn remote_update_snapshot(
+    pool: State<'_, SshConnectionPool>,
+    host_id: String,
+    snapshot_id: String,
+    update: crate::history::SnapshotUpdate,
+) -> Result<HistoryItem, String> {
+    let item = SshSnapshotStore::new(&pool, &host_id).update(&snapshot_id, update).await?;
+    Ok(HistoryItem::from(item))
+}
+
+#[tauri::command]
+pub async fn remote_get_history_retention(
+    pool: State<'_, SshConnectionPool>,
+    host_id: String,
+) -> Result<crate::history::RetentionPolicy, String> {
+    Ok(SshSnapshotStore::new(&pool, &host_id).retention().await)
+}
+
+#[tauri::command]
+pub async fn remote_set_history_retention(
+    pool: State<'_, SshConnectionPool>,
+    host_id: String,
+    policy: crate::history::RetentionPolicy,
+) -> Result<crate::history::RetentionPolicy, String> {
+    SshSnapshotStore::new(&pool, &host_id).set_retention(&policy).await?;
+    Ok(policy)
+}
+
+#[tauri::command]
+pub async fn remote_gc_history(
+    pool: State<'_, SshConnectionPool>,
+    host_id: String,
+) -> Result<crate::history::GcReport, String> {
+    let store = SshSnapshotStore::new(&pool, &host_id);
+    store.import_legacy().await?;
+    store.gc().await
 }
 
 #[tauri::command]
@@ -7164,28 +7590,18 @@ pub async fn remote_preview_rollback(
     host_id: String,
     snapshot_id: String,
 ) -> Result<PreviewResult, String> {
-    let snapshot_path = format!("~/.clawpal/snapshots/{snapshot_id}");
-    let snapshot_text = pool.sftp_read(&host_id, &snapshot_path).await?;
-    let target: Value = serde_json::from_str(&snapshot_text)
+    let store = SshSnapshotStore::new(&pool, &host_id);
+    let snapshot = store.find(&snapshot_id).await?;
+    snapshot.ensure_rollbackable()?;
+    let snapshot_text = store.content(&snapshot).await?;
+    let target: Value = json5::from_str(&snapshot_text)
         .map_err(|e| format!("Failed to parse snapshot: {e}"))?;
 
     let current_text = pool.sftp_read(&host_id, "~/.openclaw/openclaw.json").await?;
-    let current: Value = serde_json::from_str(&current_text)
+    let current: Value = json5::from_str(&current_text)
         .map_err(|e| format!("Failed to parse config: {e}"))?;
 
-    let before = serde_json::to_string_pretty(&current).unwrap_or_else(|_| "{}".into());
-    let after = serde_json::to_string_pretty(&target).unwrap_or_else(|_| "{}".into());
-    Ok(PreviewResult {
-        recipe_id: "rollback".into(),
-        diff: format_diff(&current, &target),
-        config_before: before,
-        config_after: after,
-        changes: collect_change_paths(&current, &target),
-        overwrites_existing: true,
-        can_rollback: true,
-        impact_level: "medium".into(),
-        warnings: vec!["Rollback will replace current configuration".into()],
-    })
+    Ok(crate::recipe::rollback_preview(&current, &target))
 }
 
 #[tauri::command]
@@ -7194,13 +7610,16 @@ pub async fn remote_rollback(
     host_id: String,
     snapshot_id: String,
 ) -> Result<ApplyResult, String> {
-    let snapshot_path = format!("~/.clawpal/snapshots/{snapshot_id}");
-    let target_text = pool.sftp_read(&host_id, &snapshot_path).await?;
-    let target: Value = serde_json::from_str(&target_text)
+    let store = SshSnapshotStore::new(&pool, &host_id);
+    let snapshot = store.find(&snapshot_id).await?;
+    snapshot.ensure_rollbackable()?;
+    let target_text = store.content(&snapshot).await?;
+    let target: Value = json5::from_str(&target_text)
         .map_err(|e| format!("Failed to parse snapshot: {e}"))?;
 
     let current_text = pool.sftp_read(&host_id, "~/.openclaw/openclaw.json").await?;
-    remote_write_config_with_snapshot(&pool, &host_id, &current_text, &target, "rollback").await?;
+    remote_write_config_after_snapshot(&pool, &host_id, &current_text, &target, "rollback", Some(snapshot.id.clone()))
+        .await?;
 
     Ok(ApplyResult {
         ok: true,
@@ -7476,10 +7895,7 @@ pub async fn remote_write_raw_config(
     let next: Value =
         serde_json::from_str(&content).map_err(|e| format!("Invalid JSON: {e}"))?;
     // Read current for snapshot
-    let current = pool
-        .sftp_read(&host_id, "~/.openclaw/openclaw.json")
-        .await
-        .unwrap_or_default();
+    let current = pool.sftp_read(&host_id, "~/.openclaw/openclaw.json").await?;
     remote_write_config_with_snapshot(&pool, &host_id, &current, &next, "raw-edit").await?;
     Ok(true)
 }
@@ -7789,7 +8205,11 @@ pub async fn remote_list_model_profiles(
     pool: State<'_, SshConnectionPool>,
     host_id: String,
 ) -> Result<Vec<ModelProfile>, String> {
-    let content = pool.sftp_read(&host_id, "~/.clawpal/model-profiles.json").await
+    Ok(read_remote_model_profiles(&pool, &host_id).await)
+}
+
+async fn read_remote_model_profiles(pool: &SshConnectionPool, host_id: &str) -> Vec<ModelProfile> {
+    let content = pool.sftp_read(host_id, "~/.clawpal/model-profiles.json").await
         .unwrap_or_else(|_| r#"{"profiles":[]}"#.to_string());
     #[derive(serde::Deserialize)]
     struct Storage {
@@ -7797,7 +8217,7 @@ pub async fn remote_list_model_profiles(
         profiles: Vec<ModelProfile>,
     }
     let parsed: Storage = serde_json::from_str(&content).unwrap_or(Storage { profiles: Vec::new() });
-    Ok(parsed.profiles)
+    parsed.profiles
 }
 
 #[tauri::command]
@@ -8021,9 +8441,11 @@ async fn resolve_remote_profile_base_url(
         Err(e) if is_remote_missing_path_error(&e) => return Ok(None),
         Err(e) => return Err(format!("Failed to read remote config for base URL resolution: {e}")),
     };
-    let cfg: Value = serde_json::from_str(&raw)
+    let cfg: Value = json5::from_str(&raw)
         .map_err(|e| format!("Failed to parse remote config for base URL resolution: {e}"))?;
-    Ok(resolve_model_provider_base_url(&cfg, &profile.provider))
+    let (config, warnings) = typed_config_for_read(&cfg);
+    log_config_warnings(&warnings);
+    Ok(config.provider_base_url(&profile.provider))
 }
 
 async fn resolve_remote_profile_api_key(
@@ -8125,7 +8547,8 @@ pub async fn remote_extract_model_profiles_from_config(
     host_id: String,
 ) -> Result<ExtractModelProfilesResult, String> {
     let raw = pool.sftp_read(&host_id, "~/.openclaw/openclaw.json").await?;
-    let cfg: Value = serde_json::from_str(&raw).map_err(|e| format!("Failed to parse remote config: {e}"))?;
+    let cfg: Value = json5::from_str(&raw).map_err(|e| format!("Failed to parse remote config: {e}"))?;
+    let (config, warnings) = typed_config_for_read(&cfg);
 
     let profiles_raw = pool.sftp_read(&host_id, "~/.clawpal/model-profiles.json").await
         .unwrap_or_else(|_| r#"{"profiles":[]}"#.to_string());
@@ -8137,7 +8560,7 @@ pub async fn remote_extract_model_profiles_from_config(
     let existing: StorageIn = serde_json::from_str(&profiles_raw).unwrap_or(StorageIn { profiles: Vec::new() });
     let profiles = existing.profiles;
 
-    let bindings = collect_model_bindings(&cfg, &profiles);
+    let bindings = collect_model_bindings(&config, &profiles);
     let mut created = 0usize;
     let mut reused = 0usize;
     let mut skipped_invalid = 0usize;
@@ -8170,9 +8593,10 @@ pub async fn remote_extract_model_profiles_from_config(
             skipped_invalid += 1;
             continue;
         }
-        let auth_ref = resolve_auth_ref_for_provider(&cfg, provider)
+        let auth_ref = config
+            .auth_profile_for_provider(provider)
             .unwrap_or_else(|| format!("{provider}:default"));
-        let base_url = resolve_model_provider_base_url(&cfg, provider);
+        let base_url = config.provider_base_url(provider);
         let new_profile = ModelProfile {
             id: uuid::Uuid::new_v4().to_string(),
             name: format!("{scope_label} model profile"),
@@ -8203,7 +8627,7 @@ pub async fn remote_extract_model_profiles_from_config(
         pool.sftp_write(&host_id, "~/.clawpal/model-profiles.json", &text).await?;
     }
 
-    Ok(ExtractModelProfilesResult { created, reused, skipped_invalid })
+    Ok(ExtractModelProfilesResult { created, reused, skipped_invalid, warnings })
 }
 
 #[tauri::command]
@@ -8400,8 +8824,7 @@ pub fn get_cron_runs(job_id: String, limit: Option<usize>) -> Result<Vec<Value>,
 #[tauri::command]
 pub async fn trigger_cron_job(job_id: String) -> Result<String, String> {
     tauri::async_runtime::spawn_blocking(move || {
-        let output = std::process::Command::new(resolve_openclaw_bin())
-            .args(["cron", "run", &job_id])
+        let output = crate::instances::openclaw_command(&["cron", "run", &job_id])
             .output()
             .map_err(|e| format!("Failed to run openclaw: {e}"))?;
         let stdout = String::from_utf8_lossy(&output.stdout).to_string();
@@ -8418,8 +8841,7 @@ pub async fn trigger_cron_job(job_id: String) -> Result<String, String> {
 
 #[tauri::command]
 pub fn delete_cron_job(job_id: String) -> Result<String, String> {
-    let output = std::process::Command::new(resolve_openclaw_bin())
-        .args(["cron", "remove", &job_id])
+    let output = crate::instances::openclaw_command(&["cron", "remove", &job_id])
         .output()
         .map_err(|e| format!("Failed to run openclaw: {e}"))?;
     let stdout = String::from_utf8_lossy(&output.stdout).to_string();
@@ -0,0 +1,332 @@
+//! Compare-and-swap writes of the local config.
+//!
+//! A writer reads the config as a [`ConfigBase`], which records the content
+//! hash at read time, and later commits its edited value with [`commit`].
+//! The commit takes `openclaw.json.lock` next to the config, re-reads the
+//! file and compares hashes. If the gateway or CLI wrote in between, their
+//! change and ours are merged three ways against the base; overlapping
+//! edits fail with [`ConfigWriteError::Conflict`] instead of one side being
+//! lost.
+//!
+//! The lock is advisory: a file created exclusively, holding the owner's
+//! pid, that other tools can check for before writing. Locks older than
+//! [`STALE_LOCK`] are assumed abandoned and taken over.
+//!
+//! Errors that the UI should react to are returned as a JSON
+//! [`ConfigWriteError`] inside the usual `String` error.
+
+use std::collections::BTreeSet;
+use std::fs::{self, OpenOptions};
+use std::io::{ErrorKind, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime};
+
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+use crate::history::content_hash;
+use crate::json_diff::child_path;
+use crate::models::OpenClawPaths;
+
+/// How long a writer waits for another holder to release the lock.
+const LOCK_WAIT: Duration = Duration::from_secs(2);
+const LOCK_POLL: Duration = Duration::from_millis(25);
+/// Locks older than this are treated as left behind by a crashed writer.
+pub const STALE_LOCK: Duration = Duration::from_secs(30);
+
+/// A config write that could not go ahead.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "camelCase", rename_all_fields = "camelCase")]
+pub enum ConfigWriteError {
+    /// The config changed since it was read and the changes overlap.
+    Conflict {
+        expected_hash: String,
+        actual_hash: String,
+        /// Paths edited on both sides. Empty when the config on disk could
+        /// not be parsed at all.
+        paths: Vec<String>,
+    },
+    /// Another writer held the lock for longer than we were willing to wait.
+    Locked {
+        lock_path: String,
+        holder: Option<LockOwner>,
+    },
+}
+
+impl std::fmt::Display for ConfigWriteError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&serde_json::to_string(self).map_err(|_| std::fmt::Error)?)
+    }
+}
+
+impl From<ConfigWriteError> for String {
+    fn from(error: ConfigWriteError) -> Self {
+        error.to_string()
+    }
+}
+
+/// Contents of the lock file.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LockOwner {
+    pub pid: u32,
+    pub tool: String,
+    pub acquired_at: String,
+}
+
+pub fn lock_path(config_path: &Path) -> PathBuf {
+    let mut name = config_path.file_name().unwrap_or_default().to_os_string();
+    name.push(".lock");
+    config_path.with_file_name(name)
+}
+
+fn read_owner(path: &Path) -> Option<LockOwner> {
+    serde_json::from_slice(&fs::read(path).ok()?).ok()
+}
+
+fn is_stale(path: &Path) -> bool {
+    fs::metadata(path)
+        .and_then(|m| m.modified())
+        .ok()
+        .and_then(|modified| SystemTime::now().duration_since(modified).ok())
+        .is_some_and(|age| age > STALE_LOCK)
+}
+
+/// Exclusive hold on the config lock file, released on drop.
+pub struct ConfigLock {
+    path: PathBuf,
+}
+
+impl ConfigLock {
+    pub fn acquire(config_path: &Path) -> Result<Self, String> {
+        let path = lock_path(config_path);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+        }
+        let deadline = Instant::now() + LOCK_WAIT;
+        loop {
+            match OpenOptions::new().write(true).create_new(true).open(&path) {
+                Ok(mut file) => {
+                    let owner = LockOwner {
+                        pid: std::process::id(),
+                        tool: "clawpal".into(),
+                        acquired_at: chrono::Utc::now().to_rfc3339(),
+                    };
+                    let text = serde_json::to_string(&owner).map_err(|e| e.to_string())?;
+                    file.write_all(text.as_bytes()).map_err(|e| e.to_string())?;
+                    return Ok(Self { path });
+                }
+                Err(e) if e.kind() == ErrorKind::AlreadyExists => {
+                    if is_stale(&path) {
+                        let _ = fs::remove_file(&path);
+                        continue;
+                    }
+                    if Instant::now() >= deadline {
+                        return Err(ConfigWriteError::Locked {
+                            lock_path: path.to_string_lossy().to_string(),
+                            holder: read_owner(&path),
+                        }
+                        .into());
+                    }
+                    std::thread::sleep(LOCK_POLL);
+                }
+                Err(e) => return Err(format!("failed to lock config: {e}")),
+            }
+        }
+    }
+}
+
+impl Drop for ConfigLock {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+/// Wait, up to the usual lock timeout, for another writer to finish. Used by
+/// readers that found the config unparseable mid-write.
+pub fn wait_unlocked(config_path: &Path) {
+    let path = lock_path(config_path);
+    let deadline = Instant::now() + LOCK_WAIT;
+    while path.exists() && !is_stale(&path) && Instant::now() < deadline {
+        std::thread::sleep(LOCK_POLL);
+    }
+}
+
+/// The config as a writer read it.
+#[derive(Debug, Clone)]
+pub struct ConfigBase {
+    pub text: String,
+    pub hash: String,
+    /// Parsed `text`, or an empty object when it does not parse.
+    pub value: Value,
+}
+
+impl ConfigBase {
+    pub fn from_text(text: String) -> Self {
+        let value = json5::from_str(&text).unwrap_or_else(|_| Value::Object(Default::default()));
+        Self { hash: content_hash(&text), text, value }
+    }
+}
+
+/// Read the local config, recording its hash for a later [`commit`].
+pub fn read_base(paths: &OpenClawPaths) -> Result<ConfigBase, String> {
+    crate::config_io::ensure_dirs(paths)?;
+    let text = crate::config_io::read_text(&paths.config_path)?;
+    if json5::from_str::<Value>(&text).is_ok() {
+        return Ok(ConfigBase::from_text(text));
+    }
+    // Config may be mid-write by another process
+    wait_unlocked(&paths.config_path);
+    std::thread::sleep(Duration::from_millis(50));
+    Ok(ConfigBase::from_text(crate::config_io::read_text(&paths.config_path)?))
+}
+
+/// Three-way merge of `ours` and `theirs`, both edited from `base`. Objects
+/// merge key by key; anything else, arrays included, conflicts when both
+/// sides changed it differently. `None` stands for an absent value.
+fn merge_value(
+    base: Option<&Value>,
+    ours: Option<&Value>,
+    theirs: Option<&Value>,
+    path: &str,
+    conflicts: &mut Vec<String>,
+) -> Option<Value> {
+    if ours == theirs || theirs == base {
+        return ours.cloned();
+    }
+    if ours == base {
+        return theirs.cloned();
+    }
+    if let (Some(Value::Object(o)), Some(Value::Object(t))) = (ours, theirs) {
+        let b = base.and_then(Value::as_object);
+        let keys: BTreeSet<&String> = o.keys().chain(t.keys()).chain(b.into_iter().flat_map(Map::keys)).collect();
+        let mut merged = Map::new();
+        for key in keys {
+            let child = merge_value(b.and_then(|b| b.get(key)), o.get(key), t.get(key), &child_path(path, key), conflicts);
+            if let Some(child) = child {
+                merged.insert(key.clone(), child);
+            }
+        }
+        return Some(Value::Object(merged));
+    }
+    conflicts.push(if path.is_empty() { "$".to_string() } else { path.to_string() });
+    ours.cloned()
+}
+
+/// Fail unless the config on disk is still the one read as `base`, for
+/// writes that cannot be merged.
+pub fn check_unchanged(base: &ConfigBase, current_text: &str) -> Result<(), ConfigWriteError> {
+    let actual_hash = content_hash(current_text);
+    if actual_hash == base.hash {
+        return Ok(());
+    }
+    Err(ConfigWriteError::Conflict {
+        expected_hash: base.hash.clone(),
+        actual_hash,
+        paths: Vec::new(),
+    })
+}
+
+/// What to write given the config on disk: `next` when nothing changed
+/// since `base` was read, otherwise `next` merged with the outside change.
+pub fn resolve(base: &ConfigBase, current_text: &str, next: &Value) -> Result<Value, ConfigWriteError> {
+    let actual_hash = content_hash(current_text);
+    if actual_hash == base.hash {
+        return Ok(next.clone());
+    }
+    let conflict = |paths| ConfigWriteError::Conflict {
+        expected_hash: base.hash.clone(),
+        actual_hash: actual_hash.clone(),
+        paths,
+    };
+    let Ok(theirs) = json5::from_str::<Value>(current_text) else {
+        return Err(conflict(Vec::new()));
+    };
+    let mut conflicts = Vec::new();
+    let merged = merge_value(Some(&base.value), Some(next), Some(&theirs), "", &mut conflicts);
+    if !conflicts.is_empty() {
+        return Err(conflict(conflicts));
+    }
+    Ok(merged.unwrap_or_else(|| Value::Object(Default::default())))
+}
+
+/// Write `next` over the config read as `base`, under the config lock and a
+/// [`LocalWriteGuard`](crate::config_watch::LocalWriteGuard). `before_write`
+/// gets the text about to be replaced, to snapshot it, and its result is
+/// returned once the write is done.
+pub fn commit<T>(
+    paths: &OpenClawPaths,
+    base: &ConfigBase,
+    next: &Value,
+    before_write: impl FnOnce(&str) -> Result<T, String>,
+) -> Result<T, String> {
+    let mut write = crate::config_watch::LocalWriteGuard::new(paths);
+    let _lock = ConfigLock::acquire(&paths.config_path)?;
+    let current_text = crate::config_io::read_text(&paths.config_path)?;
+    let value = resolve(base, &current_text, next)?;
+    let result = before_write(&current_text)?;
+    let text = crate::config_io::write_config(&paths.config_path, &value)?;
+    write.written(&text);
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn base(value: &Value) -> ConfigBase {
+        ConfigBase::from_text(serde_json::to_string_pretty(value).unwrap())
+    }
+
+    #[test]
+    fn merges_disjoint_outside_changes_and_reports_overlapping_ones() {
+        let read = base(&json!({"gateway": {"port": 18789}, "agents": {"list": [{"id": "main"}]}}));
+        let ours = json!({"gateway": {"port": 18790}, "agents": {"list": [{"id": "main"}]}});
+
+        // Unchanged on disk: ours as is.
+        assert_eq!(resolve(&read, &read.text, &ours).unwrap(), ours);
+        // Formatting-only change on disk: still ours.
+        let compact = serde_json::to_string(&read.value).unwrap();
+        assert_eq!(resolve(&read, &compact, &ours).unwrap(), ours);
+
+        let theirs = json!({"gateway": {"port": 18789, "bind": "lan"}, "agents": {"list": [{"id": "main"}]}});
+        assert_eq!(
+            resolve(&read, &theirs.to_string(), &ours).unwrap(),
+            json!({"gateway": {"port": 18790, "bind": "lan"}, "agents": {"list": [{"id": "main"}]}})
+        );
+
+        let theirs = json!({"gateway": {"port": 9000}, "agents": {"list": [{"id": "main"}, {"id": "ops"}]}});
+        let ours = json!({"gateway": {"port": 18790}, "agents": {"list": [{"id": "main"}, {"id": "dev"}]}});
+        match resolve(&read, &theirs.to_string(), &ours).unwrap_err() {
+            ConfigWriteError::Conflict { paths, expected_hash, .. } => {
+                assert_eq!(paths, ["agents.list", "gateway.port"]);
+                assert_eq!(expected_hash, read.hash);
+            }
+            other => panic!("unexpected {other:?}"),
+        }
+        assert!(matches!(
+            resolve(&read, "{ not json", &ours),
+            Err(ConfigWriteError::Conflict { paths, .. }) if paths.is_empty()
+        ));
+    }
+
+    #[test]
+    fn lock_is_exclusive_and_reported_as_a_typed_error() {
+        let dir = std::env::temp_dir().join(format!("clawpal-cas-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let config = dir.join("openclaw.json");
+        let lock = ConfigLock::acquire(&config).unwrap();
+        assert_eq!(read_owner(&lock_path(&config)).unwrap().pid, std::process::id());
+
+        let error = ConfigLock::acquire(&config).err().unwrap();
+        let parsed: ConfigWriteError = serde_json::from_str(&error).unwrap();
+        assert!(matches!(parsed, ConfigWriteError::Locked { holder: Some(_), .. }));
+        assert!(error.contains("\"kind\":\"locked\""));
+
+        drop(lock);
+        assert!(!lock_path(&config).exists());
+        drop(ConfigLock::acquire(&config).unwrap());
+        fs::remove_dir_all(&dir).ok();
+    }
+}
@@ -58,15 +58,18 @@ where
     write_text(path, &pretty)
 }
 
+/// Write a config, editing the text already there so comments and
+/// formatting outside the changed entries survive (see
+/// [`crate::json5_edit`]). Returns the text written.
+pub fn write_config(path: &Path, value: &Value) -> Result<String, String> {
+    let text = match fs::read_to_string(path) {
+        Ok(current) => crate::json5_edit::update_text(&current, value),
+        Err(_) => serde_json::to_string_pretty(value).map_err(|e| e.to_string())?,
+    };
+    write_text(path, &text)?;
+    Ok(text)
+}
+
 pub fn read_openclaw_config(paths: &OpenClawPaths) -> Result<Value, String> {
-    ensure_dirs(paths)?;
-    match read_json::<Value>(&paths.config_path) {
-        Ok(v) => Ok(v),
-        Err(_) => {
-            // Config may be mid-write by another process — retry once after short delay
-            std::thread::sleep(std::time::Duration::from_millis(50));
-            read_json::<Value>(&paths.config_path)
-                .or_else(|_| Ok(Value::Object(Default::default())))
-        }
-    }
+    Ok(crate::config_cas::read_base(paths)?.value)
 }
@@ -0,0 +1,284 @@
+//! Notice config edits made outside ClawPal.
+//!
+//! ClawPal snapshots the config before each of its own writes, so edits by
+//! the `openclaw` CLI, a text editor or the gateway would otherwise leave no
+//! trace. The snapshot store keeps a head: the config as ClawPal last wrote
+//! or saw it. A file watcher on the config of each local instance that has
+//! been selected, and a poll of the file's mtime on connected SSH instances,
+//! compare the live config with the head; when it differs,
+//! [`SnapshotStore::observe`] records the state that was replaced as an
+//! `external` snapshot and [`EXTERNAL_CHANGE_EVENT`] is emitted.
+//!
+//! ClawPal's own writes hold a [`LocalWriteGuard`] or [`RemoteWriteGuard`]
+//! so they aren't mistaken for outside edits: checks are skipped while one is
+//! held. Once the write succeeds the guard is told so, and what it wrote
+//! becomes the new head; a guard dropped without a write checks the config
+//! like the watcher would.
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{mpsc, Mutex};
+use std::time::{Duration, Instant};
+
+use notify::{RecursiveMode, Watcher};
+use serde::Serialize;
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::history::SnapshotMeta;
+use crate::models::OpenClawPaths;
+use crate::snapshot_store::{run_local, LocalSnapshotStore, SnapshotStore, SshSnapshotStore};
+use crate::ssh::SshConnectionPool;
+
+pub const EXTERNAL_CHANGE_EVENT: &str = "history:external-change";
+
+const REMOTE_CONFIG_PATH: &str = "~/.openclaw/openclaw.json";
+/// Editors often save in several steps; events this close together are
+/// handled as one change.
+const DEBOUNCE: Duration = Duration::from_millis(500);
+const REMOTE_POLL_INTERVAL: Duration = Duration::from_secs(15);
+
+static LOCAL_WRITES: AtomicUsize = AtomicUsize::new(0);
+/// Host ids with a ClawPal write in flight, once per guard.
+static REMOTE_WRITES: Mutex<Vec<String>> = Mutex::new(Vec::new());
+/// Host ids whose last change was ClawPal's own, to be taken as the head by
+/// the next poll.
+static REMOTE_OWN_CHANGES: Mutex<Vec<String>> = Mutex::new(Vec::new());
+static REMOTE_WATCHED: Mutex<Vec<String>> = Mutex::new(Vec::new());
+static LOCAL_WATCHED: Mutex<Vec<String>> = Mutex::new(Vec::new());
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExternalChange {
+    /// `None` for local instances.
+    pub host_id: Option<String>,
+    /// The local instance id, or the host id.
+    pub instance_id: String,
+    pub snapshot_id: String,
+    pub created_at: String,
+}
+
+/// Held while ClawPal writes the local config. On drop, the text passed to
+/// [`LocalWriteGuard::written`] becomes the head, and is committed to git
+/// history when that is enabled.
+pub struct LocalWriteGuard {
+    paths: OpenClawPaths,
+    /// Newest snapshot before the write, when git history needs to tell
+    /// which snapshot the write recorded.
+    newest: Option<Option<String>>,
+    written: Option<String>,
+}
+
+fn newest_snapshot(paths: &OpenClawPaths) -> Option<SnapshotMeta> {
+    run_local(LocalSnapshotStore::for_paths(paths).snapshots()).ok()?.into_iter().next()
+}
+
+impl LocalWriteGuard {
+    pub fn new(paths: &OpenClawPaths) -> Self {
+        LOCAL_WRITES.fetch_add(1, Ordering::SeqCst);
+        let newest = crate::history_git::enabled(paths).then(|| newest_snapshot(paths).map(|s| s.id));
+        Self { paths: paths.clone(), newest, written: None }
+    }
+
+    /// The write went through and left `text` on disk.
+    pub fn written(&mut self, text: &str) {
+        self.written = Some(text.to_string());
+    }
+}
+
+impl Drop for LocalWriteGuard {
+    fn drop(&mut self) {
+        let Some(text) = self.written.take() else {
+            // Nothing written: whatever changed on disk wasn't ClawPal.
+            LOCAL_WRITES.fetch_sub(1, Ordering::SeqCst);
+            if let Err(e) = check_local(&self.paths) {
+                eprintln!("Failed to check config for outside edits: {e}");
+            }
+            return;
+        };
+        let _ = run_local(LocalSnapshotStore::for_paths(&self.paths).set_head(&text));
+        if let Some(before) = &self.newest {
+            let snapshot = newest_snapshot(&self.paths).filter(|s| Some(&s.id) != before.as_ref());
+            crate::history_git::record_change(&self.paths, &text, snapshot.as_ref());
+        }
+        LOCAL_WRITES.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// Held while ClawPal writes a remote config. If the write went through,
+/// the next poll takes the config as it finds it for the head; otherwise it
+/// checks it for outside edits as usual.
+pub struct RemoteWriteGuard {
+    host_id: String,
+    written: bool,
+}
+
+impl RemoteWriteGuard {
+    pub fn new(host_id: &str) -> Self {
+        REMOTE_WRITES.lock().unwrap().push(host_id.to_string());
+        Self { host_id: host_id.to_string(), written: false }
+    }
+
+    /// The write went through.
+    pub fn written(&mut self) {
+        self.written = true;
+    }
+}
+
+impl Drop for RemoteWriteGuard {
+    fn drop(&mut self) {
+        let mut writes = REMOTE_WRITES.lock().unwrap();
+        if let Some(i) = writes.iter().position(|h| *h == self.host_id) {
+            writes.remove(i);
+        }
+        if !self.written {
+            return;
+        }
+        let mut own = REMOTE_OWN_CHANGES.lock().unwrap();
+        if !own.contains(&self.host_id) {
+            own.push(self.host_id.clone());
+        }
+    }
+}
+
+fn remote_write_in_flight(host_id: &str) -> bool {
+    REMOTE_WRITES.lock().unwrap().iter().any(|h| h == host_id)
+}
+
+fn emit(app: &AppHandle, host_id: Option<&str>, instance_id: &str, snapshot: &SnapshotMeta) {
+    let _ = app.emit(
+        EXTERNAL_CHANGE_EVENT,
+        ExternalChange {
+            host_id: host_id.map(str::to_string),
+            instance_id: instance_id.to_string(),
+            snapshot_id: snapshot.id.clone(),
+            created_at: snapshot.created_at.clone(),
+        },
+    );
+}
+
+/// Record an `external` snapshot if the local config changed since ClawPal
+/// last wrote or saw it.
+pub fn check_local(paths: &OpenClawPaths) -> Result<Option<SnapshotMeta>, String> {
+    if LOCAL_WRITES.load(Ordering::SeqCst) > 0 || !paths.config_path.exists() {
+        return Ok(None);
+    }
+    let text = std::fs::read_to_string(&paths.config_path).map_err(|e| e.to_string())?;
+    let recorded = run_local(LocalSnapshotStore::for_paths(paths).observe(&text))?;
+    if let Some(snapshot) = &recorded {
+        crate::history_git::record_change(paths, &text, Some(snapshot));
+    }
+    Ok(recorded)
+}
+
+/// Watch a local instance's config for the lifetime of the app. Calling
+/// this again for the same instance does nothing.
+pub fn watch_local(app: &AppHandle, instance_id: &str, paths: OpenClawPaths) {
+    {
+        let mut watched = LOCAL_WATCHED.lock().unwrap();
+        if watched.iter().any(|id| id == instance_id) {
+            return;
+        }
+        watched.push(instance_id.to_string());
+    }
+    let app = app.clone();
+    let instance_id = instance_id.to_string();
+    std::thread::spawn(move || {
+        let check = || match check_local(&paths) {
+            Ok(Some(snapshot)) => emit(&app, None, &instance_id, &snapshot),
+            Ok(None) => {}
+            Err(e) => eprintln!("Failed to check config for outside edits: {e}"),
+        };
+        // Edits made while ClawPal wasn't running.
+        check();
+
+        let (tx, rx) = mpsc::channel::<notify::Result<notify::Event>>();
+        let mut watcher = match notify::recommended_watcher(tx) {
+            Ok(watcher) => watcher,
+            Err(e) => {
+                eprintln!("Failed to watch config: {e}");
+                return;
+            }
+        };
+        // Watch the directory: editors, and ClawPal itself, replace the file
+        // by renaming another one over it.
+        let Some(dir) = paths.config_path.parent() else {
+            return;
+        };
+        if let Err(e) = watcher.watch(dir, RecursiveMode::NonRecursive) {
+            eprintln!("Failed to watch {}: {e}", dir.display());
+            return;
+        }
+        let name = paths.config_path.file_name();
+        while let Ok(event) = rx.recv() {
+            let Ok(event) = event else {
+                continue;
+            };
+            if !event.paths.iter().any(|p| p.file_name() == name) {
+                continue;
+            }
+            let settled = Instant::now() + DEBOUNCE;
+            while rx.recv_timeout(settled.saturating_duration_since(Instant::now())).is_ok() {}
+            check();
+        }
+    });
+}
+
+/// Check a remote config if its mtime or size moved since `last_seen`.
+async fn poll_remote(
+    pool: &SshConnectionPool,
+    host_id: &str,
+    last_seen: &mut Option<String>,
+) -> Result<Option<SnapshotMeta>, String> {
+    let resolved = pool.resolve_path(host_id, REMOTE_CONFIG_PATH).await?;
+    let quoted = crate::ssh::shell_quote(&resolved);
+    // GNU stat, then BSD stat.
+    let cmd = format!("stat -c '%Y %s' {quoted} 2>/dev/null || stat -f '%m %z' {quoted}");
+    let result = pool.exec(host_id, &cmd).await?;
+    let stat = result.stdout.trim().to_string();
+    if result.exit_code != 0 || last_seen.as_deref() == Some(stat.as_str()) {
+        return Ok(None);
+    }
+    let text = pool.sftp_read(host_id, REMOTE_CONFIG_PATH).await?;
+    if remote_write_in_flight(host_id) {
+        return Ok(None);
+    }
+    *last_seen = Some(stat);
+    let store = SshSnapshotStore::new(pool, host_id);
+    let own_change = {
+        let mut own = REMOTE_OWN_CHANGES.lock().unwrap();
+        own.iter().position(|h| h == host_id).map(|i| own.remove(i)).is_some()
+    };
+    if own_change {
+        store.set_head(&text).await?;
+        return Ok(None);
+    }
+    store.observe(&text).await
+}
+
+/// Poll a connected SSH instance's config until it disconnects. Calling this
+/// again while a poll is running does nothing.
+pub fn watch_remote(app: &AppHandle, host_id: &str) {
+    {
+        let mut watched = REMOTE_WATCHED.lock().unwrap();
+        if watched.iter().any(|h| h == host_id) {
+            return;
+        }
+        watched.push(host_id.to_string());
+    }
+    let app = app.clone();
+    let host_id = host_id.to_string();
+    tauri::async_runtime::spawn(async move {
+        let pool = app.state::<SshConnectionPool>();
+        let mut last_seen = None;
+        while pool.is_connected(&host_id).await {
+            if !remote_write_in_flight(&host_id) {
+                match poll_remote(&pool, &host_id, &mut last_seen).await {
+                    Ok(Some(snapshot)) => emit(&app, Some(&host_id), &host_id, &snapshot),
+                    Ok(None) => {}
+                    Err(e) => eprintln!("Failed to check {host_id} config for outside edits: {e}"),
+                }
+            }
+            tokio::time::sleep(REMOTE_POLL_INTERVAL).await;
+        }
+        REMOTE_WATCHED.lock().unwrap().retain(|h| *h != host_id);
+    });
+}
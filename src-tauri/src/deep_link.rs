@@ -0,0 +1,318 @@
+//! `clawpal://install/<recipe-id>` links from the website.
+//!
+//! ```text
+//! clawpal://install/dedicated-channel-agent?source=https://example.com/recipes.json&param.agent_id=ops
+//! ```
+//!
+//! `source` is either an `https` recipe source or a short origin label such as
+//! `web`. A source URL only supplies the linked recipe: unless it is signed by
+//! a trusted key it can't stand in for a recipe ClawPal already has, so a
+//! site can't ship its own `dedicated-channel-agent`. `version` is optional
+//! and only checked against the resolved recipe.
+//! Every other query key must be `param.<id>` naming a param of the recipe.
+//! Anything else — other schemes, credentials, ports, fragments, duplicate or
+//! oversized values — is rejected before any recipe is loaded.
+
+use serde::Serialize;
+use serde_json::{Map, Value};
+
+use crate::recipe::{
+    coerce_params, plan_preview, plan_recipe, validate_with, ParamError, PreviewResult, Recipe, RecipeEnv,
+};
+use crate::recipe_registry::{is_untrusted, RecipeTrust};
+
+const MAX_LINK_LEN: usize = 4096;
+const MAX_PARAMS: usize = 64;
+const MAX_VALUE_LEN: usize = 2048;
+const MAX_ID_LEN: usize = 64;
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InstallLink {
+    pub recipe_id: String,
+    /// Recipe source URL to load the recipe from.
+    pub source: Option<String>,
+    /// Origin label like "web" when `source` is not a URL.
+    pub origin: Option<String>,
+    pub version: Option<String>,
+    pub params: Map<String, Value>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InstallPreview {
+    pub link: InstallLink,
+    pub recipe: Recipe,
+    /// Recipe defaults overlaid with the params from the link.
+    pub params: Map<String, Value>,
+    pub param_errors: Vec<ParamError>,
+    /// Present once the params are valid.
+    pub preview: Option<PreviewResult>,
+    pub warnings: Vec<String>,
+}
+
+fn is_safe_id(id: &str) -> bool {
+    !id.is_empty()
+        && id.len() <= MAX_ID_LEN
+        && id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_' || c == '.')
+        && !id.starts_with('.')
+}
+
+fn is_safe_value(value: &str) -> bool {
+    value.len() <= MAX_VALUE_LEN && !value.chars().any(|c| c.is_control() && c != '\n' && c != '\t')
+}
+
+pub fn parse_install_link(raw: &str) -> Result<InstallLink, String> {
+    let raw = raw.trim();
+    if raw.len() > MAX_LINK_LEN {
+        return Err("link is too long".into());
+    }
+    let url = url::Url::parse(raw).map_err(|e| format!("malformed link: {e}"))?;
+    if url.scheme() != "clawpal" {
+        return Err(format!("unsupported link scheme: {}", url.scheme()));
+    }
+    if url.host_str() != Some("install") {
+        return Err("only clawpal://install links are supported".into());
+    }
+    if !url.username().is_empty() || url.password().is_some() || url.port().is_some() || url.fragment().is_some() {
+        return Err("link must not carry credentials, a port or a fragment".into());
+    }
+
+    let segments: Vec<&str> = url.path_segments().map(|s| s.collect()).unwrap_or_default();
+    let recipe_id = match segments.as_slice() {
+        [id] | [id, ""] => percent_encoding::percent_decode_str(id)
+            .decode_utf8()
+            .map_err(|_| "recipe id is not valid UTF-8")?
+            .into_owned(),
+        _ => return Err("link must name exactly one recipe".into()),
+    };
+    if !is_safe_id(&recipe_id) {
+        return Err(format!("invalid recipe id: {recipe_id:?}"));
+    }
+
+    let mut link = InstallLink {
+        recipe_id,
+        source: None,
+        origin: None,
+        version: None,
+        params: Map::new(),
+    };
+    for (key, value) in url.query_pairs() {
+        if !is_safe_value(&value) {
+            return Err(format!("value of {key:?} is too long or contains control characters"));
+        }
+        let value = value.into_owned();
+        let slot = match key.as_ref() {
+            "source" => {
+                if value.starts_with("https://") {
+                    url::Url::parse(&value).map_err(|e| format!("invalid source URL: {e}"))?;
+                    &mut link.source
+                } else if is_safe_id(&value) {
+                    &mut link.origin
+                } else {
+                    return Err("source must be an https URL or an origin label".into());
+                }
+            }
+            "version" => {
+                if !is_safe_id(&value) {
+                    return Err(format!("invalid version: {value:?}"));
+                }
+                &mut link.version
+            }
+            other => {
+                let Some(id) = other.strip_prefix("param.") else {
+                    return Err(format!("unknown link parameter: {other:?}"));
+                };
+                if !is_safe_id(id) {
+                    return Err(format!("invalid param id: {id:?}"));
+                }
+                if link.params.len() >= MAX_PARAMS {
+                    return Err("link carries too many params".into());
+                }
+                if link.params.insert(id.to_string(), Value::String(value)).is_some() {
+                    return Err(format!("duplicate param: {id}"));
+                }
+                continue;
+            }
+        };
+        if slot.replace(value).is_some() {
+            return Err(format!("duplicate link parameter: {key}"));
+        }
+    }
+    Ok(link)
+}
+
+/// Find the recipe a link names: in `registry` when the link has no source
+/// URL, otherwise in what `load_source` loads from that URL alone.
+pub fn resolve_link_recipe(
+    link: &InstallLink,
+    registry: &[Recipe],
+    load_source: impl FnOnce(&str) -> Result<Vec<Recipe>, String>,
+) -> Result<Recipe, String> {
+    let id = &link.recipe_id;
+    let Some(source) = &link.source else {
+        return registry
+            .iter()
+            .find(|r| &r.id == id)
+            .cloned()
+            .ok_or_else(|| format!("recipe not found: {id}"));
+    };
+    let recipe = load_source(source)?
+        .into_iter()
+        .find(|r| &r.id == id)
+        .ok_or_else(|| format!("recipe {id} not found in {source}"))?;
+    if is_untrusted(&recipe) && registry.iter().any(|r| &r.id == id) {
+        return Err(format!(
+            "{source} is not a trusted source and can't replace the existing recipe {id}"
+        ));
+    }
+    Ok(recipe)
+}
+
+/// Resolve, pre-fill, validate and preview the recipe behind a link.
+///
+/// `load` finds the linked recipe, normally through [`resolve_link_recipe`];
+/// the caller decides where recipes come from, which keeps this testable
+/// without a window or network.
+pub fn prepare_install(
+    link: InstallLink,
+    load: impl FnOnce(&InstallLink) -> Result<Recipe, String>,
+    current: &Value,
+    env: &RecipeEnv,
+    config_path: &str,
+) -> Result<InstallPreview, String> {
+    let recipe = load(&link)?;
+
+    for id in link.params.keys() {
+        if !recipe.params.iter().any(|p| &p.id == id) {
+            return Err(format!("recipe {} has no param {id:?}", recipe.id));
+        }
+    }
+
+    let mut warnings = Vec::new();
+    if let Some(version) = &link.version {
+        if version != &recipe.version {
+            warnings.push(format!(
+                "link asked for version {version}, installing {}",
+                recipe.version
+            ));
+        }
+    }
+    if let Some(source) = &recipe.source {
+        if source.trust == RecipeTrust::Untrusted {
+            warnings.push(format!("recipe comes from an unverified source: {}", source.location));
+        }
+    }
+
+    let mut params = Map::new();
+    for param in &recipe.params {
+        if let Some(default) = &param.default_value {
+            params.insert(param.id.clone(), Value::String(default.clone()));
+        }
+    }
+    params.extend(link.params.clone());
+
+    let param_errors = validate_with(&recipe, &params, &env.params);
+    let preview = if param_errors.is_empty() {
+        let coerced = coerce_params(&recipe, &params);
+        let plan = plan_recipe(current, &recipe, &coerced, env, config_path)?;
+        Some(plan_preview(&recipe.id, current, &plan)?)
+    } else {
+        None
+    };
+
+    Ok(InstallPreview { link, recipe, params, param_errors, preview, warnings })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn parses_install_links() {
+        let link = parse_install_link(
+            "clawpal://install/dedicated-channel-agent?source=web&version=1.0.0&param.agent_id=ops%20bot",
+        )
+        .unwrap();
+        assert_eq!(link.recipe_id, "dedicated-channel-agent");
+        assert_eq!(link.origin.as_deref(), Some("web"));
+        assert_eq!(link.source, None);
+        assert_eq!(link.version.as_deref(), Some("1.0.0"));
+        assert_eq!(link.params.get("agent_id"), Some(&json!("ops bot")));
+    }
+
+    #[test]
+    fn rejects_hostile_links() {
+        for raw in [
+            "https://install/x",
+            "clawpal://uninstall/x",
+            "clawpal://install/",
+            "clawpal://install/a/b",
+            "clawpal://install/..%2Fetc",
+            "clawpal://user:pw@install/x",
+            "clawpal://install/x#frag",
+            "clawpal://install/x?source=file:///etc/passwd",
+            "clawpal://install/x?source=http://example.com/r.json",
+            "clawpal://install/x?cmd=rm",
+            "clawpal://install/x?param.a=1&param.a=2",
+            "clawpal://install/x?param.a=%00",
+        ] {
+            assert!(parse_install_link(raw).is_err(), "accepted {raw}");
+        }
+        let long = format!("clawpal://install/x?param.a={}", "a".repeat(MAX_VALUE_LEN + 1));
+        assert!(parse_install_link(&long).is_err());
+    }
+
+    #[test]
+    fn prepares_a_preview_with_prefilled_params() {
+        let recipe = crate::recipe::builtin_recipes()
+            .into_iter()
+            .find(|r| r.id == "dedicated-channel-agent")
+            .expect("builtin recipe");
+        let mut raw = format!("clawpal://install/{}?source=web", recipe.id);
+        for param in recipe.params.iter().filter(|p| p.required) {
+            raw.push_str(&format!("&param.{}=x{}", param.id, param.id.len()));
+        }
+        let link = parse_install_link(&raw).unwrap();
+        let current = json!({ "agents": { "list": [] } });
+        let env = RecipeEnv::default();
+        let install = prepare_install(link, |_| Ok(recipe.clone()), &current, &env, "openclaw.json").unwrap();
+        assert!(install.param_errors.is_empty(), "{:?}", install.param_errors);
+        assert!(install.preview.is_some_and(|p| !p.changes.is_empty()));
+
+        let unknown = parse_install_link("clawpal://install/dedicated-channel-agent?param.nope=1").unwrap();
+        assert!(prepare_install(unknown, |_| Ok(recipe.clone()), &current, &env, "openclaw.json").is_err());
+    }
+
+    #[test]
+    fn link_sources_cannot_shadow_existing_recipes() {
+        let builtin = crate::recipe::builtin_recipes();
+        let id = builtin[0].id.clone();
+        let from_site = |id: &str, trust: RecipeTrust| {
+            let mut recipe = builtin[0].clone();
+            recipe.id = id.into();
+            recipe.source = Some(crate::recipe_registry::RecipeSource {
+                kind: "explicit".into(),
+                location: "https://example.com/r.json".into(),
+                precedence: crate::recipe_registry::EXPLICIT_PRECEDENCE,
+                trust,
+                signed_by: None,
+            });
+            recipe
+        };
+        let shadowing = parse_install_link(&format!("clawpal://install/{id}?source=https://example.com/r.json")).unwrap();
+        let err = resolve_link_recipe(&shadowing, &builtin, |_| Ok(vec![from_site(&id, RecipeTrust::Untrusted)]));
+        assert!(err.unwrap_err().contains("not a trusted source"));
+        let signed = resolve_link_recipe(&shadowing, &builtin, |_| Ok(vec![from_site(&id, RecipeTrust::Trusted)]));
+        assert!(signed.unwrap().source.is_some_and(|s| s.kind == "explicit"));
+
+        let new_id = parse_install_link("clawpal://install/site-only?source=https://example.com/r.json").unwrap();
+        let recipe = resolve_link_recipe(&new_id, &builtin, |_| Ok(vec![from_site("site-only", RecipeTrust::Untrusted)]));
+        assert_eq!(recipe.unwrap().id, "site-only");
+
+        let from_registry = parse_install_link(&format!("clawpal://install/{id}?source=web")).unwrap();
+        let recipe = resolve_link_recipe(&from_registry, &builtin, |_| panic!("no source URL to load"));
+        assert_eq!(recipe.unwrap().id, builtin[0].id);
+    }
+}
@@ -3,7 +3,6 @@ use serde_json::Value;
 
 use crate::config_io::read_openclaw_config;
 use crate::models::OpenClawPaths;
-use regex::Regex;
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct DoctorIssue {
@@ -24,10 +23,8 @@ pub struct DoctorReport {
 
 pub fn apply_auto_fixes(paths: &OpenClawPaths, issue_ids: &[String]) -> Vec<String> {
     let text = std::fs::read_to_string(&paths.config_path).unwrap_or_else(|_| "{}".into());
-    let mut current = match json5::from_str::<Value>(&text) {
-        Ok(v) => v,
-        Err(_) => Value::Object(Default::default()),
-    };
+    let base = crate::config_cas::ConfigBase::from_text(text);
+    let mut current = base.value.clone();
     let mut fixed = Vec::new();
 
     if issue_ids.iter().any(|id| id == "field.agents") && current.get("agents").is_none() {
@@ -63,19 +60,13 @@ pub fn apply_auto_fixes(paths: &OpenClawPaths, issue_ids: &[String]) -> Vec<Stri
         fixed.push("field.port".into());
     }
 
-    let maybe_json = serde_json::to_string_pretty(&current).unwrap_or_else(|_| "{}".into());
-    if !fixed.is_empty() {
-        let _ = clean_and_write_json(paths, &maybe_json);
+    // Lost to a concurrent edit or lock holder: nothing was fixed.
+    if !fixed.is_empty() && crate::config_cas::commit(paths, &base, &current, |_| Ok(())).is_err() {
+        fixed.clear();
     }
     fixed
 }
 
-fn clean_and_write_json(paths: &OpenClawPaths, text: &str) -> Result<(), String> {
-    let trailing = Regex::new(r",(\s*[}\]])").map_err(|e| e.to_string())?;
-    let normalized = trailing.replace_all(text, "$1");
-    crate::config_io::write_text(&paths.config_path, normalized.as_ref())
-}
-
 pub fn run_doctor(paths: &OpenClawPaths) -> DoctorReport {
     let mut issues = Vec::new();
     let mut score: i32 = 100;
@@ -1,11 +1,44 @@
+//! Config snapshot history.
+//!
+//! `metadata.json` indexes the snapshots; their content lives in a
+//! content-addressed store under the history directory:
+//!
+//! ```text
+//! ~/.clawpal/metadata.json                      SnapshotIndex
+//! ~/.clawpal/history/objects/ab/cdef….json.gz   gzip'd config, named by SHA-256
+//! ~/.clawpal/history/retention.json             RetentionPolicy
+//! ~/.clawpal/history/head.json                  ConfigHead
+//! ~/.clawpal/history/git.json                   GitHistorySettings (local only)
+//! ~/.clawpal/history/<timestamp>-<recipe>.json  legacy snapshot, migrated by gc
+//! ```
+//!
+//! Identical configs share one blob. Retention runs on every new snapshot;
+//! [`gc_history`] additionally migrates legacy files and sweeps blobs that
+//! nothing in the index references. Remote instances use the same layout
+//! under their own `~/.clawpal`; see [`crate::snapshot_store`]. The local
+//! history can also be committed to git; see [`crate::history_git`].
+
+use std::collections::HashSet;
 use std::fs::{self, File};
 use std::io::{Read, Write};
-use std::path::PathBuf;
+use std::path::Path;
 
-use chrono::Utc;
+use chrono::NaiveDateTime;
+use flate2::{read::GzDecoder, write::GzEncoder, Compression};
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+
+use crate::snapshot_store::{run_local, LocalSnapshotStore, NewSnapshot, SnapshotStore};
+
+pub(crate) const TIMESTAMP_FORMAT: &str = "%Y-%m-%dT%H-%M-%S";
+pub(crate) const RETENTION_FILE: &str = "retention.json";
+pub(crate) const HEAD_FILE: &str = "head.json";
+/// Source of snapshots recorded for edits made outside ClawPal.
+pub const EXTERNAL_SOURCE: &str = "external";
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, JsonSchema)]
 pub struct SnapshotMeta {
     pub id: String,
     pub recipe_id: Option<String>,
@@ -15,9 +48,79 @@ pub struct SnapshotMeta {
     pub can_rollback: bool,
     #[serde(skip_serializing_if = "Option::is_none", default)]
     pub rollback_of: Option<String>,
+    /// SHA-256 of the config text. Absent for legacy, uncompressed snapshots.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub hash: Option<String>,
+    /// Pinned snapshots are never removed by retention or GC.
+    #[serde(default)]
+    pub pinned: bool,
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub labels: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub note: Option<String>,
 }
 
-#[derive(Debug, Serialize, Deserialize, Default)]
+/// Edits to a snapshot's annotations; absent fields are left as they are.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct SnapshotUpdate {
+    pub pinned: Option<bool>,
+    pub labels: Option<Vec<String>>,
+    /// An empty note clears it.
+    pub note: Option<String>,
+}
+
+impl SnapshotMeta {
+    /// Rollback and its preview refuse snapshots recorded as not
+    /// rollbackable, such as those imported with their secrets stripped.
+    pub fn ensure_rollbackable(&self) -> Result<(), String> {
+        if self.can_rollback {
+            Ok(())
+        } else {
+            Err("snapshot is not rollbackable".to_string())
+        }
+    }
+
+    pub fn apply_update(&mut self, update: SnapshotUpdate) {
+        if let Some(pinned) = update.pinned {
+            self.pinned = pinned;
+        }
+        if let Some(labels) = update.labels {
+            self.labels.clear();
+            for label in labels.iter().map(|l| l.trim()).filter(|l| !l.is_empty()) {
+                if !self.labels.iter().any(|l| l.eq_ignore_ascii_case(label)) {
+                    self.labels.push(label.to_string());
+                }
+            }
+        }
+        if let Some(note) = update.note {
+            let note = note.trim();
+            self.note = (!note.is_empty()).then(|| note.to_string());
+        }
+    }
+
+    /// Whether every whitespace-separated term of `query` matches, ignoring
+    /// case. `is:pinned` and `label:<name>` match exactly; other terms match
+    /// anywhere in the id, recipe, source, labels or note.
+    pub fn matches(&self, query: &str) -> bool {
+        query.split_whitespace().all(|term| {
+            let term = term.to_lowercase();
+            if term == "is:pinned" {
+                return self.pinned;
+            }
+            if let Some(label) = term.strip_prefix("label:") {
+                return self.labels.iter().any(|l| l.to_lowercase() == label);
+            }
+            [Some(&self.id), self.recipe_id.as_ref(), Some(&self.source), self.note.as_ref()]
+                .into_iter()
+                .flatten()
+                .chain(&self.labels)
+                .any(|field| field.to_lowercase().contains(&term))
+        })
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Default, JsonSchema)]
 pub struct SnapshotIndex {
     pub items: Vec<SnapshotMeta>,
 }
@@ -50,63 +153,306 @@ pub fn write_snapshots(path: &std::path::Path, index: &SnapshotIndex) -> Result<
 }
 
 pub fn add_snapshot(
-    paths: &PathBuf,
-    metadata_path: &PathBuf,
+    paths: &Path,
+    metadata_path: &Path,
     recipe_id: Option<String>,
     source: &str,
     rollbackable: bool,
     current_config: &str,
     rollback_of: Option<String>,
 ) -> Result<SnapshotMeta, String> {
-    fs::create_dir_all(paths).map_err(|e| e.to_string())?;
-
-    let index = list_snapshots(metadata_path).unwrap_or_default();
-    let ts = Utc::now().format("%Y-%m-%dT%H-%M-%S").to_string();
-    let snapshot_recipe_id = recipe_id.clone().unwrap_or_else(|| "manual".into());
-    let id = format!("{}-{}", ts, snapshot_recipe_id);
-    // Sanitize for safe filename: replace path separators and other problematic chars
-    let safe_id: String = id.chars().map(|c| match c {
-        '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '-',
-        _ => c,
-    }).collect();
-    let snapshot_path = paths.join(format!("{}.json", safe_id));
-    fs::write(&snapshot_path, current_config).map_err(|e| e.to_string())?;
-
-    let mut next = index;
-    next.items.push(SnapshotMeta {
-        id: id.clone(),
+    let store = LocalSnapshotStore::new(paths, metadata_path);
+    let snapshot = NewSnapshot {
         recipe_id,
-        created_at: ts.clone(),
-        config_path: snapshot_path.to_string_lossy().to_string(),
         source: source.to_string(),
         can_rollback: rollbackable,
-        rollback_of: rollback_of.clone(),
-    });
-    next.items.sort_by(|a, b| b.created_at.cmp(&a.created_at));
-    if next.items.len() > 200 {
-        next.items.truncate(200);
+        config: current_config.to_string(),
+        rollback_of,
+    };
+    let mut meta = run_local(store.add(snapshot))?;
+    meta.recipe_id = Some(meta.recipe_id.unwrap_or_else(|| "manual".into()));
+    Ok(meta)
+}
+
+pub fn content_hash(text: &str) -> String {
+    Sha256::digest(text.as_bytes()).iter().map(|b| format!("{b:02x}")).collect()
+}
+
+/// Hash of what a config says rather than how it is formatted, so that
+/// ClawPal re-serializing a config doesn't count as a change.
+pub fn config_fingerprint(text: &str) -> String {
+    match json5::from_str::<Value>(text) {
+        Ok(value) => content_hash(&value.to_string()),
+        Err(_) => content_hash(text),
     }
-    write_snapshots(metadata_path, &next)?;
+}
 
-    let returned = Some(snapshot_recipe_id.clone());
+/// The config as ClawPal last wrote or observed it; its content is kept as
+/// a blob so the state an outside edit replaced can still be recorded.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConfigHead {
+    pub fingerprint: String,
+    pub hash: String,
+}
 
-    Ok(SnapshotMeta {
-        id,
-        recipe_id: returned,
-        created_at: ts,
-        config_path: snapshot_path.to_string_lossy().to_string(),
-        source: source.to_string(),
-        can_rollback: rollbackable,
-        rollback_of,
-    })
+/// Key of a blob, relative to the history directory.
+pub fn blob_key(hash: &str) -> String {
+    let (prefix, rest) = hash.split_at(2.min(hash.len()));
+    format!("objects/{prefix}/{rest}.json.gz")
+}
+
+pub(crate) fn encode_blob(text: &str) -> Result<Vec<u8>, String> {
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(text.as_bytes()).map_err(|e| e.to_string())?;
+    encoder.finish().map_err(|e| e.to_string())
+}
+
+/// Decode a stored snapshot: gzip'd when `name` ends in `.gz`, plain text
+/// for legacy files.
+pub(crate) fn decode_blob(name: &str, bytes: &[u8]) -> Result<String, String> {
+    if name.ends_with(".gz") {
+        let mut text = String::new();
+        GzDecoder::new(bytes).read_to_string(&mut text).map_err(|e| e.to_string())?;
+        Ok(text)
+    } else {
+        String::from_utf8(bytes.to_vec()).map_err(|e| e.to_string())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct RetentionPolicy {
+    /// Always keep the newest N snapshots.
+    pub keep_last: usize,
+    /// Also keep the newest snapshot of each day for this many days.
+    pub keep_daily_days: u32,
+}
+
+impl Default for RetentionPolicy {
+    fn default() -> Self {
+        Self { keep_last: 50, keep_daily_days: 30 }
+    }
+}
+
+/// Drop the snapshots `policy` doesn't keep from `items` (newest first) and
+/// return them. Pinned snapshots are always kept.
+pub fn apply_retention(items: &mut Vec<SnapshotMeta>, policy: &RetentionPolicy, now: NaiveDateTime) -> Vec<SnapshotMeta> {
+    let mut days_seen = HashSet::new();
+    let mut kept = Vec::new();
+    let mut dropped = Vec::new();
+    for (i, item) in std::mem::take(items).into_iter().enumerate() {
+        let daily = NaiveDateTime::parse_from_str(&item.created_at, TIMESTAMP_FORMAT)
+            .ok()
+            .filter(|at| (now - *at).num_days() < i64::from(policy.keep_daily_days))
+            .is_some_and(|at| days_seen.insert(at.date()));
+        if i < policy.keep_last || item.pinned || daily {
+            kept.push(item);
+        } else {
+            dropped.push(item);
+        }
+    }
+    *items = kept;
+    dropped
+}
+
+/// A snapshot whose write changed the blamed path.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BlameEntry {
+    pub snapshot_id: String,
+    pub created_at: String,
+    pub source: String,
+    pub recipe_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rollback_of: Option<String>,
+    /// Value at the path before the write; `None` when it didn't exist.
+    pub before: Option<Value>,
+    pub after: Option<Value>,
+}
+
+/// Attribute each change of a path's value to the snapshot taken just
+/// before it. `states` pairs snapshots, oldest first, with the value they
+/// captured; `current` is the live value. A snapshot records the config
+/// before its write, so the write's effect shows in the next snapshot, or in
+/// the live config for the newest one. Snapshots removed by retention merge
+/// their writes into the previous one. Returns entries newest first.
+pub fn blame(states: &[(SnapshotMeta, Option<Value>)], current: Option<&Value>) -> Vec<BlameEntry> {
+    let mut entries = Vec::new();
+    for (i, (meta, before)) in states.iter().enumerate() {
+        let after = states.get(i + 1).map_or(current, |(_, next)| next.as_ref());
+        if before.as_ref() != after {
+            entries.push(BlameEntry {
+                snapshot_id: meta.id.clone(),
+                created_at: meta.created_at.clone(),
+                source: meta.source.clone(),
+                recipe_id: meta.recipe_id.clone(),
+                rollback_of: meta.rollback_of.clone(),
+                before: before.clone(),
+                after: after.cloned(),
+            });
+        }
+    }
+    entries.reverse();
+    entries
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GcReport {
+    pub kept_snapshots: usize,
+    pub removed_snapshots: usize,
+    /// Legacy uncompressed snapshots moved into the object store.
+    pub migrated_snapshots: usize,
+    pub removed_files: usize,
+    pub reclaimed_bytes: u64,
+    /// Size of everything still stored, after the sweep.
+    pub stored_bytes: u64,
+}
+
+/// Apply the retention policy, migrate legacy snapshots into the object
+/// store and delete every blob or legacy file the index no longer references.
+pub fn gc_history(history_dir: &Path, metadata_path: &Path) -> Result<GcReport, String> {
+    run_local(LocalSnapshotStore::new(history_dir, metadata_path).gc())
 }
 
-pub fn read_snapshot(path: &str) -> Result<String, String> {
-    let canonical = std::fs::canonicalize(path).map_err(|e| e.to_string())?;
-    let home = dirs::home_dir().ok_or("Cannot determine home directory")?;
-    let allowed_base = home.join(".clawpal").join("history");
-    if !canonical.starts_with(&allowed_base) {
-        return Err("Path outside allowed directory".into());
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn read_blob(path: &Path) -> Result<String, String> {
+        decode_blob(&path.to_string_lossy(), &fs::read(path).map_err(|e| e.to_string())?)
+    }
+
+    fn temp_history() -> (PathBuf, PathBuf) {
+        let root = std::env::temp_dir().join(format!("clawpal-history-{}", uuid::Uuid::new_v4()));
+        (root.join("history"), root.join("metadata.json"))
+    }
+
+    fn meta(created_at: &str, pinned: bool) -> SnapshotMeta {
+        SnapshotMeta {
+            id: created_at.into(),
+            recipe_id: None,
+            created_at: created_at.into(),
+            config_path: String::new(),
+            source: "test".into(),
+            can_rollback: true,
+            rollback_of: None,
+            hash: None,
+            pinned,
+            labels: Vec::new(),
+            note: None,
+        }
+    }
+
+    #[test]
+    fn identical_configs_share_one_compressed_blob() {
+        let (history, metadata) = temp_history();
+        let config = format!("{{\"agents\":{{\"list\":[{}]}}}}", vec!["{\"id\":\"main\"}"; 200].join(","));
+        let a = add_snapshot(&history, &metadata, None, "test", true, &config, None).unwrap();
+        let b = add_snapshot(&history, &metadata, Some("r".into()), "test", true, &config, None).unwrap();
+        assert_eq!(a.hash, b.hash);
+        assert_eq!(a.config_path, b.config_path);
+        assert!(a.config_path.ends_with(".json.gz"));
+        assert!(fs::metadata(&a.config_path).unwrap().len() < config.len() as u64 / 4);
+        assert_eq!(read_blob(Path::new(&a.config_path)).unwrap(), config);
+        assert_eq!(list_snapshots(&metadata).unwrap().items.len(), 2);
+        fs::remove_dir_all(history.parent().unwrap()).ok();
+    }
+
+    #[test]
+    fn retention_keeps_last_daily_and_pinned() {
+        let now = NaiveDateTime::parse_from_str("2025-03-31T12-00-00", TIMESTAMP_FORMAT).unwrap();
+        let mut items = vec![
+            meta("2025-03-31T11-00-00", false),
+            meta("2025-03-31T10-00-00", false),
+            meta("2025-03-31T09-00-00", false),
+            meta("2025-03-30T18-00-00", false),
+            meta("2025-03-30T08-00-00", false),
+            meta("2025-01-02T08-00-00", true),
+            meta("2025-01-01T08-00-00", false),
+        ];
+        let policy = RetentionPolicy { keep_last: 2, keep_daily_days: 30 };
+        let dropped = apply_retention(&mut items, &policy, now);
+        let ids = |items: &[SnapshotMeta]| items.iter().map(|i| i.id.clone()).collect::<Vec<_>>();
+        assert_eq!(
+            ids(&items),
+            vec!["2025-03-31T11-00-00", "2025-03-31T10-00-00", "2025-03-30T18-00-00", "2025-01-02T08-00-00"]
+        );
+        assert_eq!(ids(&dropped), vec!["2025-03-31T09-00-00", "2025-03-30T08-00-00", "2025-01-01T08-00-00"]);
+    }
+
+    #[test]
+    fn updates_and_searches_annotations() {
+        let mut item = meta("2025-03-31T11-00-00", false);
+        item.recipe_id = Some("discord-channel".into());
+        item.apply_update(SnapshotUpdate {
+            pinned: Some(true),
+            labels: Some(vec![" Known-Good ".into(), "known-good".into(), "".into(), "prod".into()]),
+            note: Some("Before switching models".into()),
+        });
+        assert_eq!(item.labels, vec!["Known-Good", "prod"]);
+        assert!(item.matches("is:pinned label:known-good"));
+        assert!(item.matches("DISCORD switching"));
+        assert!(!item.matches("label:known"));
+        assert!(!item.matches("discord missing"));
+        item.apply_update(SnapshotUpdate { note: Some("  ".into()), ..Default::default() });
+        assert_eq!((item.pinned, item.note.clone()), (true, None));
+        assert!(item.matches(""));
+    }
+
+    #[test]
+    fn blame_attributes_changes_to_the_writing_snapshot() {
+        let model = |m: &str| Some(Value::String(m.into()));
+        let mut states = Vec::new();
+        for (at, recipe, value) in [
+            ("2025-01-01T00-00-00", "setup", None),
+            ("2025-01-02T00-00-00", "set-model", model("a")),
+            ("2025-01-03T00-00-00", "add-channel", model("b")),
+            ("2025-01-04T00-00-00", "set-model", model("b")),
+        ] {
+            let mut item = meta(at, false);
+            item.recipe_id = Some(recipe.into());
+            states.push((item, value));
+        }
+        let entries = blame(&states, Some(&Value::String("c".into())));
+        let summary: Vec<_> = entries
+            .iter()
+            .map(|e| (e.recipe_id.as_deref().unwrap(), e.before.clone(), e.after.clone()))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![("set-model", model("b"), model("c")), ("set-model", model("a"), model("b")), ("setup", None, model("a"))]
+        );
+        assert_eq!(entries[0].snapshot_id, "2025-01-04T00-00-00");
+        assert!(blame(&states[3..], model("b").as_ref()).is_empty());
+    }
+
+    #[test]
+    fn gc_migrates_legacy_files_and_reclaims_orphans() {
+        let (history, metadata) = temp_history();
+        fs::create_dir_all(&history).unwrap();
+        let legacy = history.join("2025-01-01T00-00-00-manual.json");
+        fs::write(&legacy, "{\"a\":1}").unwrap();
+        let orphan = history.join("2024-12-31T00-00-00-manual.json");
+        fs::write(&orphan, "{\"a\":0}").unwrap();
+        let mut item = meta("2025-01-01T00-00-00", true);
+        item.config_path = legacy.to_string_lossy().to_string();
+        write_snapshots(&metadata, &SnapshotIndex { items: vec![item] }).unwrap();
+        let store = LocalSnapshotStore::new(&history, &metadata);
+        let policy = RetentionPolicy { keep_last: 0, keep_daily_days: 0 };
+        run_local(store.set_retention(&policy)).unwrap();
+        let (_, stale) = run_local(store.put_blob("{\"stale\":true}")).unwrap();
+        let stale = history.join(stale);
+
+        let report = gc_history(&history, &metadata).unwrap();
+        assert_eq!((report.kept_snapshots, report.migrated_snapshots, report.removed_files), (1, 1, 3));
+        assert!(report.reclaimed_bytes > 0 && report.stored_bytes > 0);
+        assert!(!legacy.exists() && !orphan.exists() && !stale.exists());
+        assert!(history.join(RETENTION_FILE).exists());
+        let index = list_snapshots(&metadata).unwrap();
+        assert_eq!(read_blob(Path::new(&index.items[0].config_path)).unwrap(), "{\"a\":1}");
+        fs::remove_dir_all(history.parent().unwrap()).ok();
     }
-    std::fs::read_to_string(&canonical).map_err(|e| e.to_string())
 }
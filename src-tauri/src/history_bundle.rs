@@ -0,0 +1,482 @@
+//! Portable bundles of the local history, model profiles and SSH hosts, for
+//! moving a ClawPal install to another machine.
+//!
+//! A bundle is a gzip'd tar archive:
+//!
+//! ```text
+//! manifest.json                     BundleManifest, with each file's SHA-256
+//! metadata.json                     SnapshotIndex, config paths relative to the bundle
+//! history/objects/ab/cdef….json.gz  snapshot blobs
+//! model-profiles.json
+//! remote-instances.json
+//! ```
+//!
+//! Exporting can strip secrets: API keys and SSH passwords are dropped and
+//! secret-looking values in snapshot configs are replaced by [`REDACTED`].
+//! Importing merges into what is already there: snapshots whose id is taken
+//! by different content get a new id, profiles and hosts are only added when
+//! their id is new, and snapshot config paths point into the local history.
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs::{self, File};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+use chrono::Utc;
+use flate2::{read::GzDecoder, write::GzEncoder, Compression};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::history::{blob_key, content_hash, decode_blob, encode_blob, SnapshotIndex, TIMESTAMP_FORMAT};
+use crate::models::OpenClawPaths;
+use crate::snapshot_store::{run_local, LocalSnapshotStore, SnapshotStore};
+
+pub const BUNDLE_FORMAT: &str = "clawpal-history-bundle/1";
+/// Stands in for secret values in stripped snapshot configs.
+pub const REDACTED: &str = "<redacted>";
+
+const MANIFEST_FILE: &str = "manifest.json";
+const METADATA_FILE: &str = "metadata.json";
+const HISTORY_PREFIX: &str = "history/";
+const MODEL_PROFILES_FILE: &str = "model-profiles.json";
+const SSH_HOSTS_FILE: &str = "remote-instances.json";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BundleFile {
+    pub path: String,
+    pub size: u64,
+    pub sha256: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BundleManifest {
+    pub format: String,
+    pub created_at: String,
+    pub clawpal_version: String,
+    pub secrets_stripped: bool,
+    pub snapshots: usize,
+    /// Every other file in the bundle.
+    pub files: Vec<BundleFile>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExportResult {
+    pub path: String,
+    pub manifest: BundleManifest,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportReport {
+    pub imported_snapshots: usize,
+    /// Snapshots already in the local history.
+    pub skipped_snapshots: usize,
+    /// Imported snapshots whose id was taken, with their new id.
+    pub renamed_snapshots: BTreeMap<String, String>,
+    pub model_profiles: usize,
+    pub ssh_hosts: usize,
+    pub secrets_stripped: bool,
+}
+
+/// Whether a value stored under `key` looks like a credential. Env-style
+/// names such as `OPENAI_API_KEY` count; `keyPath` and the like don't.
+fn is_secret_key(key: &str) -> bool {
+    let key = key.to_ascii_lowercase().replace(['_', '-'], "");
+    key == "access" || ["key", "token", "secret", "password"].iter().any(|suffix| key.ends_with(suffix))
+}
+
+/// Replace every string stored under a secret-looking key with [`REDACTED`].
+pub fn strip_config_secrets(value: &mut Value) {
+    match value {
+        Value::Object(map) => {
+            for (key, child) in map.iter_mut() {
+                if is_secret_key(key) && child.is_string() {
+                    *child = Value::String(REDACTED.into());
+                } else {
+                    strip_config_secrets(child);
+                }
+            }
+        }
+        Value::Array(items) => items.iter_mut().for_each(strip_config_secrets),
+        _ => {}
+    }
+}
+
+/// Drop `field` from each object of the list at `list` (or of the root, when
+/// `list` is `None` and the root is a list).
+fn drop_field(document: &mut Value, list: Option<&str>, field: &str) {
+    let items = match list {
+        Some(key) => document.get_mut(key),
+        None => Some(document),
+    };
+    if let Some(Value::Array(items)) = items {
+        for item in items.iter_mut().filter_map(Value::as_object_mut) {
+            item.remove(field);
+        }
+    }
+}
+
+/// Bundle paths and their content.
+type BundleFiles = Vec<(String, Vec<u8>)>;
+
+/// Every file of the bundle but the manifest, and the manifest describing them.
+fn collect(paths: &OpenClawPaths, strip_secrets: bool) -> Result<(BundleManifest, BundleFiles), String> {
+    let store = LocalSnapshotStore::for_paths(paths);
+    let mut files = BundleFiles::new();
+    let mut blobs = HashSet::new();
+    let mut index = SnapshotIndex::default();
+    for mut meta in run_local(store.snapshots())? {
+        // Snapshots whose content is gone can't be restored anywhere.
+        let Ok(mut text) = run_local(store.content(&meta)) else {
+            continue;
+        };
+        if strip_secrets {
+            // A snapshot that doesn't parse can't be redacted, so it stays out.
+            let Ok(mut config) = json5::from_str::<Value>(&text) else {
+                continue;
+            };
+            strip_config_secrets(&mut config);
+            text = serde_json::to_string_pretty(&config).map_err(|e| e.to_string())?;
+        }
+        let hash = content_hash(&text);
+        let key = blob_key(&hash);
+        if blobs.insert(key.clone()) {
+            files.push((format!("{HISTORY_PREFIX}{key}"), encode_blob(&text)?));
+        }
+        meta.config_path = format!("{HISTORY_PREFIX}{key}");
+        meta.hash = Some(hash);
+        index.items.push(meta);
+    }
+    let snapshots = index.items.len();
+    files.insert(0, (METADATA_FILE.into(), serde_json::to_vec_pretty(&index).map_err(|e| e.to_string())?));
+
+    for (name, list, secret) in [(MODEL_PROFILES_FILE, Some("profiles"), "apiKey"), (SSH_HOSTS_FILE, None, "password")] {
+        let Ok(bytes) = fs::read(paths.clawpal_dir.join(name)) else {
+            continue;
+        };
+        let bytes = if strip_secrets {
+            let mut document: Value = serde_json::from_slice(&bytes).map_err(|e| format!("Failed to parse {name}: {e}"))?;
+            drop_field(&mut document, list, secret);
+            serde_json::to_vec_pretty(&document).map_err(|e| e.to_string())?
+        } else {
+            bytes
+        };
+        files.push((name.into(), bytes));
+    }
+
+    let manifest = BundleManifest {
+        format: BUNDLE_FORMAT.into(),
+        created_at: Utc::now().to_rfc3339(),
+        clawpal_version: env!("CARGO_PKG_VERSION").into(),
+        secrets_stripped: strip_secrets,
+        snapshots,
+        files: files
+            .iter()
+            .map(|(path, bytes)| BundleFile { path: path.clone(), size: bytes.len() as u64, sha256: sha256(bytes) })
+            .collect(),
+    };
+    Ok((manifest, files))
+}
+
+fn sha256(bytes: &[u8]) -> String {
+    use sha2::{Digest, Sha256};
+    Sha256::digest(bytes).iter().map(|b| format!("{b:02x}")).collect()
+}
+
+/// Where an export goes when no path is given.
+pub fn default_export_path(paths: &OpenClawPaths) -> PathBuf {
+    let stamp = Utc::now().naive_utc().format(TIMESTAMP_FORMAT);
+    paths.clawpal_dir.join("exports").join(format!("clawpal-history-{stamp}.tar.gz"))
+}
+
+pub fn export_bundle(paths: &OpenClawPaths, dest: &Path, strip_secrets: bool) -> Result<BundleManifest, String> {
+    let (manifest, files) = collect(paths, strip_secrets)?;
+    if let Some(parent) = dest.parent() {
+        fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+    }
+    let file = File::create(dest).map_err(|e| format!("Failed to create {}: {e}", dest.display()))?;
+    let mut archive = tar::Builder::new(GzEncoder::new(file, Compression::default()));
+    let mtime = Utc::now().timestamp().max(0) as u64;
+    let manifest_bytes = serde_json::to_vec_pretty(&manifest).map_err(|e| e.to_string())?;
+    for (path, bytes) in std::iter::once((MANIFEST_FILE, &manifest_bytes)).chain(files.iter().map(|(p, b)| (p.as_str(), b))) {
+        let mut header = tar::Header::new_gnu();
+        header.set_size(bytes.len() as u64);
+        header.set_mode(0o600);
+        header.set_mtime(mtime);
+        archive.append_data(&mut header, path, bytes.as_slice()).map_err(|e| e.to_string())?;
+    }
+    archive.into_inner().and_then(|gz| gz.finish()).map_err(|e| e.to_string())?;
+    Ok(manifest)
+}
+
+/// Read a bundle and check it against its manifest.
+pub fn read_bundle(src: &Path) -> Result<(BundleManifest, HashMap<String, Vec<u8>>), String> {
+    let file = File::open(src).map_err(|e| format!("Failed to open {}: {e}", src.display()))?;
+    let mut archive = tar::Archive::new(GzDecoder::new(file));
+    let mut files = HashMap::new();
+    for entry in archive.entries().map_err(|e| format!("Not a history bundle: {e}"))? {
+        let mut entry = entry.map_err(|e| format!("Not a history bundle: {e}"))?;
+        let path = entry.path().map_err(|e| e.to_string())?.to_string_lossy().to_string();
+        let mut bytes = Vec::new();
+        entry.read_to_end(&mut bytes).map_err(|e| e.to_string())?;
+        files.insert(path, bytes);
+    }
+    let manifest: BundleManifest = files
+        .remove(MANIFEST_FILE)
+        .ok_or_else(|| "Not a history bundle: manifest.json is missing".to_string())
+        .and_then(|bytes| serde_json::from_slice(&bytes).map_err(|e| format!("Invalid manifest.json: {e}")))?;
+    if manifest.format != BUNDLE_FORMAT {
+        return Err(format!("Unsupported bundle format: {}", manifest.format));
+    }
+    for file in &manifest.files {
+        let bytes = files.get(&file.path).ok_or_else(|| format!("Bundle is missing {}", file.path))?;
+        if sha256(bytes) != file.sha256 {
+            return Err(format!("Checksum mismatch for {}", file.path));
+        }
+    }
+    Ok((manifest, files))
+}
+
+fn is_sha256(hash: &str) -> bool {
+    hash.len() == 64 && hash.bytes().all(|b| b.is_ascii_hexdigit())
+}
+
+/// A free id for an imported snapshot whose id is taken.
+fn unused_id(id: &str, taken: &HashSet<String>) -> String {
+    (1..)
+        .map(|n| if n == 1 { format!("{id}-imported") } else { format!("{id}-imported-{n}") })
+        .find(|candidate| !taken.contains(candidate))
+        .unwrap_or_default()
+}
+
+/// Add entries of the list `incoming` whose `id` isn't in `existing`.
+fn merge_by_id(existing: &mut Vec<Value>, incoming: Vec<Value>) -> usize {
+    let ids: HashSet<String> = existing.iter().filter_map(|v| v.get("id")?.as_str().map(str::to_string)).collect();
+    let before = existing.len();
+    existing.extend(incoming.into_iter().filter(|v| v.get("id").and_then(Value::as_str).is_some_and(|id| !ids.contains(id))));
+    existing.len() - before
+}
+
+fn write_private(path: &Path, document: &Value) -> Result<(), String> {
+    let text = serde_json::to_string_pretty(document).map_err(|e| e.to_string())?;
+    crate::config_io::write_text(path, &text)?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let _ = fs::set_permissions(path, fs::Permissions::from_mode(0o600));
+    }
+    Ok(())
+}
+
+/// A local JSON file, or `default` when there is none yet. A file that is
+/// there but doesn't parse is an error rather than something to overwrite.
+fn read_local<T: serde::de::DeserializeOwned>(path: &Path, default: T) -> Result<T, String> {
+    match fs::read(path) {
+        Ok(bytes) => serde_json::from_slice(&bytes).map_err(|e| format!("Failed to parse {}: {e}", path.display())),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(default),
+        Err(e) => Err(format!("Failed to read {}: {e}", path.display())),
+    }
+}
+
+pub fn import_bundle(paths: &OpenClawPaths, src: &Path) -> Result<ImportReport, String> {
+    let (manifest, files) = read_bundle(src)?;
+    // Read the local files the bundle merges into before importing anything.
+    let profiles_path = paths.clawpal_dir.join(MODEL_PROFILES_FILE);
+    let hosts_path = paths.clawpal_dir.join(SSH_HOSTS_FILE);
+    let local_profiles = files
+        .contains_key(MODEL_PROFILES_FILE)
+        .then(|| read_local(&profiles_path, serde_json::json!({ "profiles": [], "version": 1 })))
+        .transpose()?;
+    let local_hosts = files.contains_key(SSH_HOSTS_FILE).then(|| read_local(&hosts_path, Vec::<Value>::new())).transpose()?;
+    let mut report = ImportReport { secrets_stripped: manifest.secrets_stripped, ..Default::default() };
+
+    let incoming: SnapshotIndex = files
+        .get(METADATA_FILE)
+        .map(|bytes| serde_json::from_slice(bytes).map_err(|e| format!("Invalid metadata.json: {e}")))
+        .transpose()?
+        .unwrap_or_default();
+    let store = LocalSnapshotStore::for_paths(paths);
+    let mut index = run_local(store.read_index())?;
+    // A snapshot is the same one when it was taken at the same time with the
+    // same content, whatever id an earlier import gave it.
+    let local: HashMap<(String, String), String> = index
+        .items
+        .iter()
+        .filter_map(|s| Some(((s.created_at.clone(), s.hash.clone()?), s.id.clone())))
+        .collect();
+    let mut taken: HashSet<String> = index.items.iter().map(|s| s.id.clone()).collect();
+    // Bundle id → local id, for every snapshot whose id differs locally.
+    let mut local_ids = HashMap::new();
+    let mut added = Vec::new();
+    for mut meta in incoming.items {
+        let hash = meta.hash.clone().filter(|h| is_sha256(h)).ok_or_else(|| format!("Snapshot {} has no valid hash", meta.id))?;
+        if let Some(id) = local.get(&(meta.created_at.clone(), hash.clone())) {
+            if *id != meta.id {
+                local_ids.insert(meta.id.clone(), id.clone());
+            }
+            report.skipped_snapshots += 1;
+            continue;
+        }
+        let key = blob_key(&hash);
+        let bytes = files.get(&format!("{HISTORY_PREFIX}{key}")).ok_or_else(|| format!("Bundle is missing the content of snapshot {}", meta.id))?;
+        if content_hash(&decode_blob(&key, bytes)?) != hash {
+            return Err(format!("Content of snapshot {} does not match its hash", meta.id));
+        }
+        run_local(store.write(&key, bytes))?;
+        if taken.contains(&meta.id) {
+            let id = unused_id(&meta.id, &taken);
+            report.renamed_snapshots.insert(meta.id.clone(), id.clone());
+            local_ids.insert(meta.id.clone(), id.clone());
+            meta.id = id;
+        }
+        taken.insert(meta.id.clone());
+        meta.config_path = store.location(&key);
+        // Rolling back to a stripped snapshot would write placeholders over
+        // live credentials.
+        if manifest.secrets_stripped {
+            meta.can_rollback = false;
+        }
+        added.push(meta);
+    }
+    // Rollback chains follow renamed snapshots.
+    for meta in &mut added {
+        if let Some(id) = meta.rollback_of.as_ref().and_then(|id| local_ids.get(id)) {
+            meta.rollback_of = Some(id.clone());
+        }
+    }
+    report.imported_snapshots = added.len();
+    if !added.is_empty() {
+        index.items.extend(added);
+        index.items.sort_by(|a, b| b.created_at.cmp(&a.created_at));
+        run_local(store.write_index(&index))?;
+    }
+
+    if let (Some(bytes), Some(mut document)) = (files.get(MODEL_PROFILES_FILE), local_profiles) {
+        let incoming: Value = serde_json::from_slice(bytes).map_err(|e| format!("Invalid {MODEL_PROFILES_FILE}: {e}"))?;
+        let incoming = incoming.get("profiles").and_then(Value::as_array).cloned().unwrap_or_default();
+        if let Some(profiles) = document.get_mut("profiles").and_then(Value::as_array_mut) {
+            report.model_profiles = merge_by_id(profiles, incoming);
+        }
+        if report.model_profiles > 0 {
+            write_private(&profiles_path, &document)?;
+        }
+    }
+
+    if let (Some(bytes), Some(mut hosts)) = (files.get(SSH_HOSTS_FILE), local_hosts) {
+        let incoming: Vec<Value> = serde_json::from_slice(bytes).map_err(|e| format!("Invalid {SSH_HOSTS_FILE}: {e}"))?;
+        report.ssh_hosts = merge_by_id(&mut hosts, incoming);
+        if report.ssh_hosts > 0 {
+            write_private(&hosts_path, &Value::Array(hosts))?;
+        }
+    }
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::history::{add_snapshot, SnapshotMeta};
+    use serde_json::json;
+
+    fn snapshot(paths: &OpenClawPaths, recipe_id: &str, config: &str, rollback_of: Option<String>) -> SnapshotMeta {
+        add_snapshot(&paths.history_dir, &paths.metadata_path, Some(recipe_id.into()), "clawpal", true, config, rollback_of)
+            .unwrap()
+    }
+
+    #[test]
+    fn strips_secret_values_but_keeps_structure() {
+        let mut config = json!({
+            "gateway": { "auth": { "token": "t0k", "mode": "token" }, "port": 18789 },
+            "models": { "providers": { "x": { "apiKey": "sk-1", "baseUrl": "https://x" } } },
+            "channels": [{ "botToken": "b", "client_secret": "c", "keyPath": "~/.ssh/id" }],
+            "env": { "OPENAI_API_KEY": "sk-2", "ANTHROPIC_KEY": "sk-3", "HOME": "/home/me" },
+        });
+        strip_config_secrets(&mut config);
+        assert_eq!(config["gateway"]["auth"]["token"], REDACTED);
+        assert_eq!(config["gateway"]["auth"]["mode"], "token");
+        assert_eq!(config["gateway"]["port"], 18789);
+        assert_eq!(config["models"]["providers"]["x"]["apiKey"], REDACTED);
+        assert_eq!(config["channels"][0]["botToken"], REDACTED);
+        assert_eq!(config["channels"][0]["client_secret"], REDACTED);
+        assert_eq!(config["channels"][0]["keyPath"], "~/.ssh/id");
+        assert_eq!(config["env"]["OPENAI_API_KEY"], REDACTED);
+        assert_eq!(config["env"]["ANTHROPIC_KEY"], REDACTED);
+        assert_eq!(config["env"]["HOME"], "/home/me");
+    }
+
+    #[test]
+    fn exports_and_imports_history_into_another_install() {
+        let from = crate::models::test_paths("bundle-from");
+        let from_home = from.clawpal_dir.parent().unwrap();
+        let first = snapshot(&from, "discord", "{\"a\":1}", None);
+        let written = "{\"a\":2,\"apiKey\":\"sk-1\"}";
+        run_local(LocalSnapshotStore::for_paths(&from).set_head(written)).unwrap();
+        let second = snapshot(&from, "rollback", written, Some(first.id.clone()));
+        // Can't be parsed, so can't be redacted: left out of the bundle.
+        let broken = "{\"apiKey\": \"sk-1\"";
+        run_local(LocalSnapshotStore::for_paths(&from).set_head(broken)).unwrap();
+        snapshot(&from, "manual", broken, None);
+        fs::write(
+            from.clawpal_dir.join(MODEL_PROFILES_FILE),
+            json!({ "profiles": [{ "id": "p1", "apiKey": "sk-1" }], "version": 1 }).to_string(),
+        )
+        .unwrap();
+        fs::write(from.clawpal_dir.join(SSH_HOSTS_FILE), json!([{ "id": "h1", "password": "pw" }]).to_string()).unwrap();
+
+        let bundle = from_home.join("bundle.tar.gz");
+        let manifest = export_bundle(&from, &bundle, true).unwrap();
+        assert_eq!(manifest.snapshots, 2);
+        assert!(manifest.files.iter().any(|f| f.path == METADATA_FILE));
+
+        // The target already has a different snapshot under the first id.
+        let to = crate::models::test_paths("bundle-to");
+        fs::create_dir_all(&to.history_dir).unwrap();
+        let store = LocalSnapshotStore::for_paths(&to);
+        let (hash, key) = run_local(store.put_blob("{\"local\":true}")).unwrap();
+        let mut clash = first.clone();
+        clash.hash = Some(hash);
+        clash.config_path = store.location(&key);
+        run_local(store.write_index(&SnapshotIndex { items: vec![clash] })).unwrap();
+
+        let report = import_bundle(&to, &bundle).unwrap();
+        assert!(report.secrets_stripped);
+        assert_eq!(report.imported_snapshots, 2);
+        assert_eq!((report.model_profiles, report.ssh_hosts), (1, 1));
+        let renamed = report.renamed_snapshots.get(&first.id).unwrap();
+        assert_eq!(renamed, &format!("{}-imported", first.id));
+
+        let items = run_local(store.snapshots()).unwrap();
+        assert_eq!(items.len(), 3);
+        let imported_second = items.iter().find(|s| s.id == second.id).unwrap();
+        assert_eq!(imported_second.rollback_of.as_ref(), Some(renamed));
+        assert!(imported_second.config_path.starts_with(&*to.history_dir.to_string_lossy()));
+        // Stripped snapshots would put placeholders in the live config.
+        for imported in items.iter().filter(|s| s.id != first.id) {
+            assert!(imported.ensure_rollbackable().is_err(), "{}", imported.id);
+        }
+        let text = run_local(store.content(imported_second)).unwrap();
+        assert_eq!(serde_json::from_str::<Value>(&text).unwrap()["apiKey"], REDACTED);
+
+        let profiles: Value = serde_json::from_slice(&fs::read(to.clawpal_dir.join(MODEL_PROFILES_FILE)).unwrap()).unwrap();
+        assert_eq!(profiles["profiles"][0], json!({ "id": "p1" }));
+        let hosts: Value = serde_json::from_slice(&fs::read(to.clawpal_dir.join(SSH_HOSTS_FILE)).unwrap()).unwrap();
+        assert_eq!(hosts, json!([{ "id": "h1" }]));
+
+        // Importing again changes nothing.
+        let again = import_bundle(&to, &bundle).unwrap();
+        assert_eq!((again.imported_snapshots, again.skipped_snapshots), (0, 2));
+
+        // A local file that doesn't parse is reported, not replaced.
+        fs::write(to.clawpal_dir.join(SSH_HOSTS_FILE), b"[{ broken").unwrap();
+        assert!(import_bundle(&to, &bundle).unwrap_err().contains(SSH_HOSTS_FILE));
+        assert_eq!(fs::read(to.clawpal_dir.join(SSH_HOSTS_FILE)).unwrap(), b"[{ broken");
+
+        fs::write(&bundle, b"not a bundle").unwrap();
+        assert!(import_bundle(&to, &bundle).is_err());
+        fs::remove_dir_all(from_home).ok();
+        fs::remove_dir_all(to.clawpal_dir.parent().unwrap()).ok();
+    }
+}
@@ -0,0 +1,363 @@
+//! Opt-in git history for the local config.
+//!
+//! With it enabled, `~/.clawpal/history-git` (in the instance's own
+//! directory for instances other than the default, see
+//! [`crate::instances`]) is a git repository whose `openclaw.json` follows
+//! the live config: each change ClawPal makes, and each outside edit it
+//! notices, becomes a commit. Commit messages carry trailers naming the
+//! snapshot taken before the change, so a commit's parent holds that
+//! snapshot's config. The git log therefore lists the same history as the
+//! snapshot index, and keeps it after retention prunes the index. When a
+//! remote is configured, every new commit is pushed to it.
+//!
+//! Git runs as a subprocess with its own identity, so no git config is
+//! needed on the machine.
+
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use chrono::DateTime;
+use serde::{Deserialize, Serialize};
+
+use crate::history::{SnapshotMeta, TIMESTAMP_FORMAT};
+use crate::models::OpenClawPaths;
+
+/// Settings file, relative to the history directory.
+pub(crate) const GIT_SETTINGS_FILE: &str = "git.json";
+const REPO_DIR: &str = "history-git";
+const CONFIG_FILE: &str = "openclaw.json";
+const BRANCH: &str = "main";
+
+const SOURCE_TRAILER: &str = "ClawPal-Source";
+const RECIPE_TRAILER: &str = "ClawPal-Recipe";
+const SNAPSHOT_TRAILER: &str = "ClawPal-Snapshot";
+const ROLLBACK_OF_TRAILER: &str = "ClawPal-Rollback-Of";
+const ROLLBACKABLE_TRAILER: &str = "ClawPal-Rollbackable";
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct GitHistorySettings {
+    pub enabled: bool,
+    /// Remote name or URL that new commits are pushed to.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub remote: Option<String>,
+}
+
+/// One commit of the history repository.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GitCommit {
+    pub sha: String,
+    /// Unix seconds.
+    pub committed_at: i64,
+    pub trailers: Vec<(String, String)>,
+}
+
+impl GitCommit {
+    fn trailer(&self, key: &str) -> Option<&str> {
+        self.trailers.iter().find(|(k, _)| k == key).map(|(_, v)| v.as_str())
+    }
+
+    /// The snapshot recorded before this commit's change, if it names one.
+    pub fn snapshot(&self) -> Option<SnapshotMeta> {
+        let id = self.trailer(SNAPSHOT_TRAILER)?.to_string();
+        let source = self.trailer(SOURCE_TRAILER).unwrap_or("clawpal").to_string();
+        let created_at = DateTime::from_timestamp(self.committed_at, 0)?.naive_utc().format(TIMESTAMP_FORMAT).to_string();
+        Some(SnapshotMeta {
+            id,
+            recipe_id: self.trailer(RECIPE_TRAILER).map(str::to_string),
+            created_at,
+            config_path: format!("{}^:{CONFIG_FILE}", self.sha),
+            source,
+            can_rollback: self.trailer(ROLLBACKABLE_TRAILER) != Some("no"),
+            rollback_of: self.trailer(ROLLBACK_OF_TRAILER).map(str::to_string),
+            hash: None,
+            pinned: false,
+            labels: Vec::new(),
+            note: None,
+        })
+    }
+}
+
+/// Subject line plus trailers describing a change.
+fn commit_message(source: &str, snapshot: Option<&SnapshotMeta>) -> String {
+    let Some(snapshot) = snapshot else {
+        return format!("{source}\n\n{SOURCE_TRAILER}: {source}\n");
+    };
+    let recipe = snapshot.recipe_id.as_deref().unwrap_or("manual");
+    let mut message = format!("{source}: {recipe}\n\n{SOURCE_TRAILER}: {source}\n");
+    if let Some(recipe_id) = &snapshot.recipe_id {
+        message.push_str(&format!("{RECIPE_TRAILER}: {recipe_id}\n"));
+    }
+    message.push_str(&format!("{SNAPSHOT_TRAILER}: {}\n", snapshot.id));
+    if let Some(rollback_of) = &snapshot.rollback_of {
+        message.push_str(&format!("{ROLLBACK_OF_TRAILER}: {rollback_of}\n"));
+    }
+    if !snapshot.can_rollback {
+        message.push_str(&format!("{ROLLBACKABLE_TRAILER}: no\n"));
+    }
+    message
+}
+
+/// Parse `git log --format=%H%x1f%ct%x1f%B%x1e` output.
+fn parse_log(output: &str) -> Vec<GitCommit> {
+    output
+        .split('\x1e')
+        .filter_map(|record| {
+            let mut fields = record.trim_start_matches('\n').splitn(3, '\x1f');
+            let sha = fields.next()?.trim().to_string();
+            let committed_at = fields.next()?.trim().parse().ok()?;
+            let body = fields.next().unwrap_or("");
+            let trailers = body
+                .lines()
+                .filter_map(|line| {
+                    let (key, value) = line.split_once(": ")?;
+                    key.starts_with("ClawPal-").then(|| (key.to_string(), value.trim().to_string()))
+                })
+                .collect();
+            Some(GitCommit { sha, committed_at, trailers })
+        })
+        .collect()
+}
+
+pub struct GitHistory {
+    dir: PathBuf,
+}
+
+impl GitHistory {
+    pub fn new(dir: &Path) -> Self {
+        Self { dir: dir.to_path_buf() }
+    }
+
+    pub fn for_paths(paths: &OpenClawPaths) -> Self {
+        Self::new(&paths.instance_dir().join(REPO_DIR))
+    }
+
+    fn git(&self, args: &[&str]) -> Result<String, String> {
+        let output = Command::new("git")
+            .arg("-C")
+            .arg(&self.dir)
+            .args(["-c", "commit.gpgsign=false", "-c", "core.autocrlf=false"])
+            .args(args)
+            .env("GIT_AUTHOR_NAME", "ClawPal")
+            .env("GIT_AUTHOR_EMAIL", "clawpal@localhost")
+            .env("GIT_COMMITTER_NAME", "ClawPal")
+            .env("GIT_COMMITTER_EMAIL", "clawpal@localhost")
+            .env("GIT_TERMINAL_PROMPT", "0")
+            .output()
+            .map_err(|e| format!("failed to run git: {e}"))?;
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr).trim().to_string();
+            return Err(if stderr.is_empty() { format!("git {} failed", args.join(" ")) } else { stderr });
+        }
+        Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+    }
+
+    fn has_commits(&self) -> bool {
+        self.dir.join(".git").exists() && self.git(&["rev-parse", "--verify", "-q", "HEAD"]).is_ok()
+    }
+
+    pub fn init(&self) -> Result<(), String> {
+        if self.dir.join(".git").exists() {
+            return Ok(());
+        }
+        fs::create_dir_all(&self.dir).map_err(|e| e.to_string())?;
+        self.git(&["init", "-q"])?;
+        self.git(&["symbolic-ref", "HEAD", &format!("refs/heads/{BRANCH}")])?;
+        Ok(())
+    }
+
+    /// Commit `config` as the new state of the config, attributed to the
+    /// snapshot taken before the change. Returns the new commit, or `None`
+    /// when the config is unchanged.
+    pub fn commit(&self, config: &str, source: &str, snapshot: Option<&SnapshotMeta>) -> Result<Option<String>, String> {
+        self.init()?;
+        fs::write(self.dir.join(CONFIG_FILE), config).map_err(|e| e.to_string())?;
+        self.git(&["add", "--", CONFIG_FILE])?;
+        if self.git(&["status", "--porcelain", "--", CONFIG_FILE])?.trim().is_empty() {
+            return Ok(None);
+        }
+        self.git(&["commit", "-q", "-m", &commit_message(source, snapshot)])?;
+        Ok(Some(self.git(&["rev-parse", "HEAD"])?.trim().to_string()))
+    }
+
+    /// Commits, newest first.
+    pub fn log(&self) -> Result<Vec<GitCommit>, String> {
+        if !self.has_commits() {
+            return Ok(Vec::new());
+        }
+        Ok(parse_log(&self.git(&["log", "--format=%H%x1f%ct%x1f%B%x1e"])?))
+    }
+
+    /// Snapshots named by commits, newest first.
+    pub fn snapshots(&self) -> Result<Vec<SnapshotMeta>, String> {
+        Ok(self.log()?.iter().filter_map(GitCommit::snapshot).collect())
+    }
+
+    /// The snapshot `id` and the config it captured: the state before the
+    /// commit that names it.
+    pub fn find(&self, id: &str) -> Result<(SnapshotMeta, String), String> {
+        let commit = self
+            .log()?
+            .into_iter()
+            .find(|c| c.trailer(SNAPSHOT_TRAILER) == Some(id))
+            .ok_or_else(|| "snapshot not found".to_string())?;
+        let meta = commit.snapshot().ok_or_else(|| "snapshot not found".to_string())?;
+        let content = self.git(&["show", &format!("{}^:{CONFIG_FILE}", commit.sha)])?;
+        Ok((meta, content))
+    }
+
+    pub fn push(&self, remote: &str) -> Result<(), String> {
+        if !self.has_commits() {
+            return Ok(());
+        }
+        self.git(&["push", "-q", remote, &format!("HEAD:refs/heads/{BRANCH}")])?;
+        Ok(())
+    }
+}
+
+pub fn settings(paths: &OpenClawPaths) -> GitHistorySettings {
+    fs::read(paths.history_dir.join(GIT_SETTINGS_FILE))
+        .ok()
+        .and_then(|bytes| serde_json::from_slice(&bytes).ok())
+        .unwrap_or_default()
+}
+
+pub fn enabled(paths: &OpenClawPaths) -> bool {
+    settings(paths).enabled
+}
+
+/// Save the settings. Enabling starts the repository from the current config.
+pub fn set_settings(paths: &OpenClawPaths, settings: &GitHistorySettings) -> Result<(), String> {
+    let mut settings = settings.clone();
+    settings.remote = settings.remote.map(|r| r.trim().to_string()).filter(|r| !r.is_empty());
+    fs::create_dir_all(&paths.history_dir).map_err(|e| e.to_string())?;
+    let text = serde_json::to_string_pretty(&settings).map_err(|e| e.to_string())?;
+    fs::write(paths.history_dir.join(GIT_SETTINGS_FILE), text).map_err(|e| e.to_string())?;
+    if settings.enabled {
+        let repo = GitHistory::for_paths(paths);
+        repo.init()?;
+        if !repo.has_commits() {
+            if let Ok(config) = fs::read_to_string(&paths.config_path) {
+                repo.commit(&config, "import", None)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Commit a change to the local config if git history is enabled, then push
+/// it in the background. Failures are logged, not returned: the config write
+/// itself has already happened.
+pub fn record_change(paths: &OpenClawPaths, config: &str, snapshot: Option<&SnapshotMeta>) {
+    let settings = settings(paths);
+    if !settings.enabled {
+        return;
+    }
+    let source = snapshot.map(|s| s.source.as_str()).unwrap_or("clawpal");
+    let repo = GitHistory::for_paths(paths);
+    match repo.commit(config, source, snapshot) {
+        Ok(Some(_)) => {
+            if let Some(remote) = settings.remote {
+                std::thread::spawn(move || {
+                    if let Err(e) = repo.push(&remote) {
+                        eprintln!("Failed to push config history to {remote}: {e}");
+                    }
+                });
+            }
+        }
+        Ok(None) => {}
+        Err(e) => eprintln!("Failed to commit config history: {e}"),
+    }
+}
+
+/// Index entries and git-recorded snapshots together, newest first. Index
+/// entries win, so pins, labels and notes still show.
+pub fn merge_snapshots(git: Vec<SnapshotMeta>, index: Vec<SnapshotMeta>) -> Vec<SnapshotMeta> {
+    let indexed: HashSet<String> = index.iter().map(|s| s.id.clone()).collect();
+    let mut items = index;
+    items.extend(git.into_iter().filter(|s| !indexed.contains(&s.id)));
+    items.sort_by(|a, b| b.created_at.cmp(&a.created_at));
+    items
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("clawpal-git-{name}-{}", std::process::id()));
+        fs::remove_dir_all(&dir).ok();
+        dir
+    }
+
+    fn snapshot(id: &str, recipe_id: Option<&str>, source: &str) -> SnapshotMeta {
+        SnapshotMeta {
+            id: id.into(),
+            recipe_id: recipe_id.map(str::to_string),
+            created_at: "2025-03-31T10-00-00".into(),
+            config_path: String::new(),
+            source: source.into(),
+            can_rollback: true,
+            rollback_of: None,
+            hash: None,
+            pinned: false,
+            labels: Vec::new(),
+            note: None,
+        }
+    }
+
+    #[test]
+    fn commits_changes_with_snapshot_trailers_and_pushes_to_a_bare_remote() {
+        let root = temp_dir("push");
+        let repo = GitHistory::new(&root.join("repo"));
+        assert!(repo.commit("{\"a\":1}", "import", None).unwrap().is_some());
+        let first = snapshot("2025-03-31T10-00-00-discord", Some("discord"), "clawpal");
+        repo.commit("{\"a\":2}", "clawpal", Some(&first)).unwrap().unwrap();
+        // Unchanged configs make no commit.
+        assert_eq!(repo.commit("{\"a\":2}", "clawpal", Some(&first)).unwrap(), None);
+        let mut second = snapshot("2025-03-31T11-00-00-manual", None, "rollback");
+        second.rollback_of = Some(first.id.clone());
+        second.can_rollback = false;
+        repo.commit("{\"a\":1}", "rollback", Some(&second)).unwrap().unwrap();
+
+        let log = repo.log().unwrap();
+        assert_eq!(log.len(), 3);
+        let snapshots = repo.snapshots().unwrap();
+        assert_eq!(snapshots.iter().map(|s| s.id.as_str()).collect::<Vec<_>>(), [second.id.as_str(), first.id.as_str()]);
+        assert_eq!(snapshots[0].source, "rollback");
+        assert_eq!(snapshots[0].rollback_of.as_deref(), Some(first.id.as_str()));
+        assert!(!snapshots[0].can_rollback);
+        assert_eq!(snapshots[1].recipe_id.as_deref(), Some("discord"));
+
+        // A snapshot's config is the state before the commit naming it.
+        assert_eq!(repo.find(&first.id).unwrap().1, "{\"a\":1}");
+        assert_eq!(repo.find(&second.id).unwrap().1, "{\"a\":2}");
+        assert!(repo.find("missing").is_err());
+
+        let bare = root.join("remote.git");
+        let init = Command::new("git").args(["init", "-q", "--bare"]).arg(&bare).status().unwrap();
+        assert!(init.success());
+        repo.push(bare.to_str().unwrap()).unwrap();
+        let pushed = Command::new("git")
+            .arg("--git-dir")
+            .arg(&bare)
+            .args(["rev-parse", "refs/heads/main"])
+            .output()
+            .unwrap();
+        assert_eq!(String::from_utf8_lossy(&pushed.stdout).trim(), log[0].sha);
+        fs::remove_dir_all(&root).ok();
+    }
+
+    #[test]
+    fn merges_index_entries_over_git_snapshots() {
+        let mut pinned = snapshot("2025-03-31T10-00-00-manual", None, "clawpal");
+        pinned.pinned = true;
+        let mut pruned = snapshot("2025-03-30T10-00-00-manual", None, "clawpal");
+        pruned.created_at = "2025-03-30T10-00-00".into();
+        let merged = merge_snapshots(vec![snapshot(&pinned.id, None, "clawpal"), pruned.clone()], vec![pinned.clone()]);
+        assert_eq!(merged.iter().map(|s| s.id.as_str()).collect::<Vec<_>>(), [pinned.id.as_str(), pruned.id.as_str()]);
+        assert!(merged[0].pinned);
+    }
+}
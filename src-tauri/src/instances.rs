@@ -0,0 +1,394 @@
+//! Local OpenClaw instances.
+//!
+//! One machine can run several OpenClaw state dirs side by side: the default
+//! `~/.openclaw`, one `~/.openclaw-<name>` per `openclaw --profile <name>`
+//! (the rescue bot is one), and dirs pointed at by `OPENCLAW_STATE_DIR` or
+//! added by hand. Each is listed as an instance, and the selected one is
+//! what [`crate::models::resolve_paths`] returns and what local `openclaw`
+//! runs target, so status, config, history, sessions and doctor commands
+//! all follow the selection.
+//!
+//! Instances other than the default keep their snapshot history under
+//! `~/.clawpal/instances/<slug>`; the default keeps `~/.clawpal/history`.
+//! Model profiles, recipes and SSH hosts stay shared.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::Mutex;
+
+use dirs::home_dir;
+use serde::{Deserialize, Serialize};
+
+use crate::history::content_hash;
+use crate::models::{resolve_default_paths, OpenClawPaths};
+
+/// Id of the instance [`resolve_default_paths`] finds. The frontend uses the
+/// same ids, so local ids never clash with SSH host ids.
+pub const DEFAULT_INSTANCE_ID: &str = "local";
+const ID_PREFIX: &str = "local:";
+const STATE_FILE: &str = "local-instances.json";
+const INSTANCES_DIR: &str = "instances";
+const PROFILE_DIR_PREFIX: &str = ".openclaw-";
+const CONFIG_FILE: &str = "openclaw.json";
+/// Env vars that may point at a state dir, or at the parent of `.openclaw`.
+const STATE_DIR_ENV: [&str; 3] = ["CLAWPAL_OPENCLAW_DIR", "OPENCLAW_HOME", "OPENCLAW_STATE_DIR"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum LocalInstanceKind {
+    Default,
+    /// `~/.openclaw-<name>`, run with `openclaw --profile <name>`.
+    Profile,
+    /// Found through an env var or added by hand.
+    Custom,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LocalInstance {
+    pub id: String,
+    pub label: String,
+    pub kind: LocalInstanceKind,
+    pub profile: Option<String>,
+    pub openclaw_dir: String,
+    pub has_config: bool,
+    /// Added by hand, so it can be removed again.
+    pub removable: bool,
+    pub active: bool,
+}
+
+/// What local commands target while an instance other than the default is
+/// selected.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Target {
+    id: String,
+    openclaw_dir: PathBuf,
+    profile: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CustomDir {
+    label: Option<String>,
+    path: PathBuf,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+struct State {
+    active: Option<Target>,
+    custom: Vec<CustomDir>,
+}
+
+/// Loaded on first use, so [`crate::models::resolve_paths`] doesn't read
+/// the state file on every call.
+static STATE: Mutex<Option<State>> = Mutex::new(None);
+
+fn state_path(clawpal_dir: &Path) -> PathBuf {
+    clawpal_dir.join(STATE_FILE)
+}
+
+fn with_state<T>(clawpal_dir: &Path, f: impl FnOnce(&mut State) -> T) -> T {
+    let mut guard = STATE.lock().unwrap();
+    let state = guard.get_or_insert_with(|| {
+        fs::read(state_path(clawpal_dir))
+            .ok()
+            .and_then(|bytes| serde_json::from_slice(&bytes).ok())
+            .unwrap_or_default()
+    });
+    f(state)
+}
+
+fn save_state(clawpal_dir: &Path, state: &State) -> Result<(), String> {
+    fs::create_dir_all(clawpal_dir).map_err(|e| e.to_string())?;
+    let text = serde_json::to_string_pretty(state).map_err(|e| e.to_string())?;
+    fs::write(state_path(clawpal_dir), text).map_err(|e| e.to_string())
+}
+
+/// `path` itself when it holds a config, else its `.openclaw` child when
+/// that does: `OPENCLAW_HOME` is used both ways.
+fn state_dir(path: &Path) -> PathBuf {
+    let nested = path.join(".openclaw");
+    if !path.join(CONFIG_FILE).exists() && nested.join(CONFIG_FILE).exists() {
+        nested
+    } else {
+        path.to_path_buf()
+    }
+}
+
+fn same_dir(a: &Path, b: &Path) -> bool {
+    a == b || matches!((a.canonicalize(), b.canonicalize()), (Ok(a), Ok(b)) if a == b)
+}
+
+fn custom_id(path: &Path) -> String {
+    format!("{ID_PREFIX}dir-{}", &content_hash(&path.to_string_lossy())[..8])
+}
+
+/// Directory name under `~/.clawpal/instances` for an instance id.
+fn slug(id: &str) -> String {
+    id.strip_prefix(ID_PREFIX)
+        .unwrap_or(id)
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '_' { c } else { '_' })
+        .collect()
+}
+
+/// Paths for an instance, given the default instance's paths.
+pub fn instance_paths(default: &OpenClawPaths, id: &str, openclaw_dir: &Path) -> OpenClawPaths {
+    if id == DEFAULT_INSTANCE_ID {
+        return default.clone();
+    }
+    let data_dir = default.clawpal_dir.join(INSTANCES_DIR).join(slug(id));
+    OpenClawPaths {
+        openclaw_dir: openclaw_dir.to_path_buf(),
+        config_path: openclaw_dir.join(CONFIG_FILE),
+        base_dir: openclaw_dir.to_path_buf(),
+        clawpal_dir: default.clawpal_dir.clone(),
+        history_dir: data_dir.join("history"),
+        metadata_path: data_dir.join("metadata.json"),
+    }
+}
+
+/// The selected instance's paths. Falls back to the default when the
+/// selected dir has gone away.
+pub fn apply_active(default: OpenClawPaths) -> OpenClawPaths {
+    match with_state(&default.clawpal_dir, |state| state.active.clone()) {
+        Some(target) if target.openclaw_dir.is_dir() => instance_paths(&default, &target.id, &target.openclaw_dir),
+        _ => default,
+    }
+}
+
+/// Every local instance: the default, then profiles, then custom dirs.
+fn discover(default: &OpenClawPaths, home: &Path, custom: &[CustomDir]) -> Vec<LocalInstance> {
+    let mut found = vec![LocalInstance {
+        id: DEFAULT_INSTANCE_ID.into(),
+        label: "default".into(),
+        kind: LocalInstanceKind::Default,
+        profile: None,
+        openclaw_dir: default.openclaw_dir.to_string_lossy().to_string(),
+        has_config: default.config_path.exists(),
+        removable: false,
+        active: false,
+    }];
+    let mut dirs = vec![default.openclaw_dir.clone()];
+    let mut push = |found: &mut Vec<LocalInstance>, instance: LocalInstance, dir: PathBuf| {
+        if dirs.iter().any(|d| same_dir(d, &dir)) {
+            return;
+        }
+        dirs.push(dir);
+        found.push(instance);
+    };
+
+    let mut profiles: Vec<(String, PathBuf)> = fs::read_dir(home)
+        .into_iter()
+        .flatten()
+        .flatten()
+        .filter(|entry| entry.path().is_dir())
+        .filter_map(|entry| {
+            let name = entry.file_name().to_string_lossy().strip_prefix(PROFILE_DIR_PREFIX)?.to_string();
+            (!name.is_empty()).then(|| (name, entry.path()))
+        })
+        .collect();
+    profiles.sort();
+    for (name, dir) in profiles {
+        let instance = LocalInstance {
+            id: format!("{ID_PREFIX}{name}"),
+            label: name.clone(),
+            kind: LocalInstanceKind::Profile,
+            profile: Some(name),
+            openclaw_dir: dir.to_string_lossy().to_string(),
+            has_config: dir.join(CONFIG_FILE).exists(),
+            removable: false,
+            active: false,
+        };
+        push(&mut found, instance, dir);
+    }
+
+    let from_env = STATE_DIR_ENV.iter().filter_map(|name| {
+        let dir = state_dir(&crate::models::env_path(name)?);
+        Some((name.to_string(), dir, false))
+    });
+    let added = custom.iter().map(|c| {
+        let label = c.label.clone().unwrap_or_else(|| c.path.to_string_lossy().to_string());
+        (label, c.path.clone(), true)
+    });
+    for (label, dir, removable) in from_env.chain(added) {
+        let instance = LocalInstance {
+            id: custom_id(&dir),
+            label,
+            kind: LocalInstanceKind::Custom,
+            profile: None,
+            openclaw_dir: dir.to_string_lossy().to_string(),
+            has_config: dir.join(CONFIG_FILE).exists(),
+            removable,
+            active: false,
+        };
+        push(&mut found, instance, dir);
+    }
+    found
+}
+
+pub fn list_instances() -> Vec<LocalInstance> {
+    let default = resolve_default_paths();
+    let home = home_dir().unwrap_or_else(|| PathBuf::from("."));
+    let (active, custom) = with_state(&default.clawpal_dir, |state| (state.active.clone(), state.custom.clone()));
+    let active_id = active
+        .filter(|target| target.openclaw_dir.is_dir())
+        .map(|target| target.id)
+        .unwrap_or_else(|| DEFAULT_INSTANCE_ID.into());
+    let mut instances = discover(&default, &home, &custom);
+    for instance in &mut instances {
+        instance.active = instance.id == active_id;
+    }
+    instances
+}
+
+/// Make `id` the instance local commands target.
+pub fn select_instance(id: &str) -> Result<LocalInstance, String> {
+    let default = resolve_default_paths();
+    let mut instance = list_instances()
+        .into_iter()
+        .find(|instance| instance.id == id)
+        .ok_or_else(|| format!("unknown local instance: {id}"))?;
+    let target = (instance.kind != LocalInstanceKind::Default).then(|| Target {
+        id: instance.id.clone(),
+        openclaw_dir: PathBuf::from(&instance.openclaw_dir),
+        profile: instance.profile.clone(),
+    });
+    with_state(&default.clawpal_dir, |state| {
+        state.active = target;
+        save_state(&default.clawpal_dir, state)
+    })?;
+    instance.active = true;
+    Ok(instance)
+}
+
+/// Add a state dir that discovery doesn't find.
+pub fn add_instance(path: &str, label: Option<String>) -> Result<LocalInstance, String> {
+    let path = path.trim();
+    if path.is_empty() {
+        return Err("instance path is required".into());
+    }
+    let dir = state_dir(&crate::models::expand_user_path(path));
+    if !dir.is_dir() {
+        return Err(format!("not a directory: {}", dir.display()));
+    }
+    let default = resolve_default_paths();
+    let label = label.map(|l| l.trim().to_string()).filter(|l| !l.is_empty());
+    with_state(&default.clawpal_dir, |state| {
+        if !state.custom.iter().any(|c| same_dir(&c.path, &dir)) {
+            state.custom.push(CustomDir { label, path: dir.clone() });
+        }
+        save_state(&default.clawpal_dir, state)
+    })?;
+    list_instances()
+        .into_iter()
+        .find(|instance| same_dir(Path::new(&instance.openclaw_dir), &dir))
+        .ok_or_else(|| format!("{} is already listed", dir.display()))
+}
+
+/// Forget a hand-added instance. Its history is kept, and the default is
+/// selected if it was the selected one.
+pub fn remove_instance(id: &str) -> Result<(), String> {
+    let default = resolve_default_paths();
+    with_state(&default.clawpal_dir, |state| {
+        let before = state.custom.len();
+        state.custom.retain(|c| custom_id(&c.path) != id);
+        if state.custom.len() == before {
+            return Err(format!("not a removable local instance: {id}"));
+        }
+        if state.active.as_ref().is_some_and(|target| target.id == id) {
+            state.active = None;
+        }
+        save_state(&default.clawpal_dir, state)
+    })
+}
+
+/// Id of the selected instance.
+pub fn active_id() -> String {
+    let default = resolve_default_paths();
+    with_state(&default.clawpal_dir, |state| state.active.clone())
+        .filter(|target| target.openclaw_dir.is_dir())
+        .map(|target| target.id)
+        .unwrap_or_else(|| DEFAULT_INSTANCE_ID.into())
+}
+
+/// An `openclaw` command aimed at the selected instance: `--profile` for a
+/// profile, the state dir env vars for a custom dir. Commands that already
+/// pick a profile run as given.
+pub fn openclaw_command(args: &[&str]) -> Command {
+    let mut cmd = Command::new(crate::commands::resolve_openclaw_bin());
+    let default = resolve_default_paths();
+    let target = with_state(&default.clawpal_dir, |state| state.active.clone())
+        .filter(|target| target.openclaw_dir.is_dir());
+    match target {
+        Some(target) if !args.contains(&"--profile") => match &target.profile {
+            Some(profile) => {
+                cmd.args(["--profile", profile]);
+            }
+            None => {
+                cmd.env("OPENCLAW_STATE_DIR", &target.openclaw_dir)
+                    .env("OPENCLAW_CONFIG_PATH", target.openclaw_dir.join(CONFIG_FILE));
+            }
+        },
+        _ => {}
+    }
+    cmd.args(args);
+    cmd
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::models::test_paths;
+
+    fn home_of(paths: &OpenClawPaths) -> PathBuf {
+        paths.clawpal_dir.parent().unwrap().to_path_buf()
+    }
+
+    #[test]
+    fn discovers_profiles_and_custom_dirs_once_each() {
+        let default = test_paths("instances-discover");
+        let home = home_of(&default);
+        for dir in [".openclaw", ".openclaw-rescue", ".openclaw-work", "elsewhere/.openclaw"] {
+            fs::create_dir_all(home.join(dir)).unwrap();
+        }
+        fs::write(home.join(".openclaw-rescue").join(CONFIG_FILE), "{}").unwrap();
+        fs::write(home.join("elsewhere/.openclaw").join(CONFIG_FILE), "{}").unwrap();
+        let custom = vec![
+            CustomDir { label: Some("Lab".into()), path: state_dir(&home.join("elsewhere")) },
+            CustomDir { label: None, path: home.join(".openclaw-work") },
+        ];
+
+        let found = discover(&default, &home, &custom);
+        let ids: Vec<&str> = found.iter().map(|i| i.id.as_str()).collect();
+        let lab = custom_id(&home.join("elsewhere/.openclaw"));
+        assert!(ids.starts_with(&["local", "local:rescue", "local:work"]));
+        assert!(ids.contains(&lab.as_str()));
+        assert_eq!(ids.iter().filter(|id| id.ends_with("work")).count(), 1);
+        let rescue = &found[1];
+        assert_eq!(rescue.profile.as_deref(), Some("rescue"));
+        assert!(rescue.has_config);
+        assert!(!found[2].has_config);
+        fs::remove_dir_all(&home).ok();
+    }
+
+    #[test]
+    fn instances_other_than_the_default_get_their_own_history() {
+        let default = test_paths("instances-paths");
+        let home = home_of(&default);
+        let rescue_dir = home.join(".openclaw-rescue");
+        let rescue = instance_paths(&default, "local:rescue", &rescue_dir);
+
+        assert_eq!(instance_paths(&default, DEFAULT_INSTANCE_ID, &rescue_dir).history_dir, default.history_dir);
+        assert_eq!(rescue.config_path, rescue_dir.join(CONFIG_FILE));
+        assert_eq!(rescue.history_dir, home.join(".clawpal/instances/rescue/history"));
+        assert_eq!(rescue.metadata_path, home.join(".clawpal/instances/rescue/metadata.json"));
+        assert_eq!(rescue.clawpal_dir, default.clawpal_dir);
+        assert_eq!(slug("local:dir-ab/../x"), "dir-ab____x");
+        fs::remove_dir_all(&home).ok();
+    }
+}
@@ -0,0 +1,659 @@
+//! Format-preserving edits to JSON5 text.
+//!
+//! `openclaw.json` is often written by hand: comments, trailing commas,
+//! unquoted keys and a key order the user chose. [`Json5Document`] parses the
+//! text into a tree of byte spans and applies path-level edits — set, unset,
+//! insert into an array — to the text itself, so everything outside the
+//! edited entries stays byte-identical. [`update_text`] turns a whole new
+//! config into the fewest such edits; config writers go through it via
+//! [`crate::config_io::write_config`].
+//!
+//! New entries follow the surrounding style: the indentation of their
+//! siblings, unquoted keys when the object already uses them, and a trailing
+//! comma when the last entry had one.
+
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::json_diff::{align, parse_path, PathSegment};
+
+#[derive(Debug)]
+struct Key {
+    name: String,
+    start: usize,
+    quoted: bool,
+}
+
+#[derive(Debug)]
+struct Entry {
+    /// `None` for array elements.
+    key: Option<Key>,
+    value: Node,
+    /// Offset of the comma following the entry, if any.
+    comma: Option<usize>,
+}
+
+impl Entry {
+    fn start(&self) -> usize {
+        self.key.as_ref().map_or(self.value.start(), |key| key.start)
+    }
+
+    fn end(&self) -> usize {
+        self.value.end()
+    }
+}
+
+#[derive(Debug)]
+enum Node {
+    /// `start` is the opening bracket, `end` is just past the closing one.
+    Object { start: usize, end: usize, entries: Vec<Entry> },
+    Array { start: usize, end: usize, entries: Vec<Entry> },
+    Scalar { start: usize, end: usize },
+}
+
+impl Node {
+    fn start(&self) -> usize {
+        match self {
+            Node::Object { start, .. } | Node::Array { start, .. } | Node::Scalar { start, .. } => *start,
+        }
+    }
+
+    fn end(&self) -> usize {
+        match self {
+            Node::Object { end, .. } | Node::Array { end, .. } | Node::Scalar { end, .. } => *end,
+        }
+    }
+
+    fn entries(&self) -> Option<&[Entry]> {
+        match self {
+            Node::Object { entries, .. } | Node::Array { entries, .. } => Some(entries),
+            Node::Scalar { .. } => None,
+        }
+    }
+
+    /// Index of the entry at `segment`. Later duplicate keys win, as when
+    /// parsing.
+    fn find(&self, segment: &PathSegment) -> Option<usize> {
+        match (self, segment) {
+            (Node::Object { entries, .. }, PathSegment::Key(name)) => {
+                entries.iter().rposition(|e| e.key.as_ref().is_some_and(|k| k.name == *name))
+            }
+            (Node::Object { entries, .. }, PathSegment::Index(index)) => {
+                let name = index.to_string();
+                entries.iter().rposition(|e| e.key.as_ref().is_some_and(|k| k.name == name))
+            }
+            (Node::Array { entries, .. }, PathSegment::Index(index)) => (*index < entries.len()).then_some(*index),
+            (Node::Array { entries, .. }, PathSegment::Key(key)) => {
+                key.parse::<usize>().ok().filter(|index| *index < entries.len())
+            }
+            (Node::Scalar { .. }, _) => None,
+        }
+    }
+}
+
+struct Parser<'a> {
+    text: &'a str,
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn new(text: &'a str) -> Self {
+        Self { text, bytes: text.as_bytes(), pos: 0 }
+    }
+
+    fn error(&self, message: &str) -> String {
+        format!("{message} at offset {}", self.pos)
+    }
+
+    fn parse(mut self) -> Result<Node, String> {
+        self.skip_trivia()?;
+        let root = self.value()?;
+        self.skip_trivia()?;
+        if self.pos != self.bytes.len() {
+            return Err(self.error("unexpected content after the document"));
+        }
+        Ok(root)
+    }
+
+    /// Skip whitespace and comments.
+    fn skip_trivia(&mut self) -> Result<(), String> {
+        loop {
+            let rest = &self.text[self.pos..];
+            if let Some(comment) = rest.strip_prefix("//") {
+                self.pos += 2 + comment.find('\n').unwrap_or(comment.len());
+            } else if let Some(comment) = rest.strip_prefix("/*") {
+                let end = comment.find("*/").ok_or_else(|| self.error("unterminated comment"))?;
+                self.pos += 2 + end + 2;
+            } else {
+                match rest.chars().next() {
+                    Some(c) if c.is_whitespace() || c == '\u{feff}' => self.pos += c.len_utf8(),
+                    _ => return Ok(()),
+                }
+            }
+        }
+    }
+
+    fn expect(&mut self, byte: u8) -> Result<(), String> {
+        if self.bytes.get(self.pos) != Some(&byte) {
+            return Err(self.error(&format!("expected '{}'", byte as char)));
+        }
+        self.pos += 1;
+        Ok(())
+    }
+
+    fn value(&mut self) -> Result<Node, String> {
+        match self.bytes.get(self.pos) {
+            Some(b'{') => self.container(b'}', true),
+            Some(b'[') => self.container(b']', false),
+            Some(b'"' | b'\'') => {
+                let start = self.pos;
+                self.string()?;
+                Ok(Node::Scalar { start, end: self.pos })
+            }
+            Some(_) => {
+                let start = self.pos;
+                self.atom()?;
+                Ok(Node::Scalar { start, end: self.pos })
+            }
+            None => Err(self.error("unexpected end of document")),
+        }
+    }
+
+    fn string(&mut self) -> Result<(), String> {
+        let quote = self.bytes[self.pos];
+        self.pos += 1;
+        while let Some(&byte) = self.bytes.get(self.pos) {
+            self.pos += if byte == b'\\' { 2 } else { 1 };
+            if byte == quote {
+                return Ok(());
+            }
+        }
+        Err(self.error("unterminated string"))
+    }
+
+    /// A number, literal or unquoted key.
+    fn atom(&mut self) -> Result<(), String> {
+        let start = self.pos;
+        while let Some(&byte) = self.bytes.get(self.pos) {
+            if byte.is_ascii_whitespace() || matches!(byte, b',' | b':' | b']' | b'}' | b'/' | b'[' | b'{') {
+                break;
+            }
+            self.pos += 1;
+        }
+        if self.pos == start {
+            return Err(self.error("unexpected character"));
+        }
+        Ok(())
+    }
+
+    fn key(&mut self) -> Result<Key, String> {
+        let start = self.pos;
+        let quoted = matches!(self.bytes.get(self.pos), Some(b'"' | b'\''));
+        let name = if quoted {
+            self.string()?;
+            json5::from_str::<String>(&self.text[start..self.pos]).map_err(|e| self.error(&e.to_string()))?
+        } else {
+            self.atom()?;
+            self.text[start..self.pos].to_string()
+        };
+        Ok(Key { name, start, quoted })
+    }
+
+    fn container(&mut self, close: u8, is_object: bool) -> Result<Node, String> {
+        let start = self.pos;
+        self.pos += 1;
+        let mut entries = Vec::new();
+        loop {
+            self.skip_trivia()?;
+            if self.bytes.get(self.pos) == Some(&close) {
+                break;
+            }
+            let key = if is_object {
+                let key = self.key()?;
+                self.skip_trivia()?;
+                self.expect(b':')?;
+                self.skip_trivia()?;
+                Some(key)
+            } else {
+                None
+            };
+            let value = self.value()?;
+            self.skip_trivia()?;
+            let comma = (self.bytes.get(self.pos) == Some(&b',')).then_some(self.pos);
+            entries.push(Entry { key, value, comma });
+            if comma.is_none() {
+                break;
+            }
+            self.pos += 1;
+        }
+        self.expect(close)?;
+        let end = self.pos;
+        Ok(if is_object { Node::Object { start, end, entries } } else { Node::Array { start, end, entries } })
+    }
+}
+
+fn is_identifier(key: &str) -> bool {
+    let mut chars = key.chars();
+    chars.next().is_some_and(|c| c.is_ascii_alphabetic() || c == '_' || c == '$')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '$')
+}
+
+/// JSON5 text with path-level edits that leave the rest of it untouched.
+/// Paths use the [`parse_path`] notation.
+#[derive(Debug, Clone)]
+pub struct Json5Document {
+    text: String,
+}
+
+impl Json5Document {
+    pub fn parse(text: &str) -> Result<Self, String> {
+        json5::from_str::<Value>(text).map_err(|e| e.to_string())?;
+        Parser::new(text).parse()?;
+        Ok(Self { text: text.to_string() })
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    pub fn into_text(self) -> String {
+        self.text
+    }
+
+    pub fn value(&self) -> Result<Value, String> {
+        json5::from_str(&self.text).map_err(|e| e.to_string())
+    }
+
+    /// Set the value at `path`, adding the key (or appending to the array)
+    /// when it isn't there yet. Its parent must exist.
+    pub fn set(&mut self, path: &str, value: &Value) -> Result<(), String> {
+        self.set_at(&parse_path(path)?, value)
+    }
+
+    /// Remove the entry at `path`. Returns whether there was one.
+    pub fn unset(&mut self, path: &str) -> Result<bool, String> {
+        self.unset_at(&parse_path(path)?)
+    }
+
+    /// Insert `value` into the array at `path`, before the element at `index`.
+    pub fn insert(&mut self, path: &str, index: usize, value: &Value) -> Result<(), String> {
+        self.insert_at(&parse_path(path)?, index, value)
+    }
+
+    fn root(&self) -> Result<Node, String> {
+        Parser::new(&self.text).parse()
+    }
+
+    fn locate<'n>(node: &'n Node, segments: &[PathSegment]) -> Option<&'n Node> {
+        segments.iter().try_fold(node, |node, segment| {
+            let index = node.find(segment)?;
+            Some(&node.entries()?[index].value)
+        })
+    }
+
+    fn parent<'n>(root: &'n Node, segments: &[PathSegment]) -> Result<&'n Node, String> {
+        let parent = &segments[..segments.len().saturating_sub(1)];
+        match Self::locate(root, parent) {
+            Some(node @ (Node::Object { .. } | Node::Array { .. })) => Ok(node),
+            _ => Err(format!("no object or array at {}", display_path(parent))),
+        }
+    }
+
+    fn set_at(&mut self, segments: &[PathSegment], value: &Value) -> Result<(), String> {
+        let root = self.root()?;
+        let Some(last) = segments.last() else {
+            let text = self.render(value, "");
+            self.splice(root.start(), root.end(), &text);
+            return Ok(());
+        };
+        let parent = Self::parent(&root, segments)?;
+        if let Some(index) = parent.find(last) {
+            let target = &parent.entries().unwrap_or_default()[index].value;
+            let text = self.render(value, self.line_indent(target.start()));
+            self.splice(target.start(), target.end(), &text);
+            return Ok(());
+        }
+        match (parent, last) {
+            (Node::Object { .. }, PathSegment::Key(name)) => self.insert_entry(parent, Some(name), usize::MAX, value),
+            (Node::Object { .. }, PathSegment::Index(index)) => {
+                self.insert_entry(parent, Some(&index.to_string()), usize::MAX, value)
+            }
+            (Node::Array { entries, .. }, PathSegment::Index(index)) if *index == entries.len() => {
+                self.insert_entry(parent, None, *index, value)
+            }
+            _ => return Err(format!("index out of range at {}", display_path(segments))),
+        }
+        Ok(())
+    }
+
+    fn unset_at(&mut self, segments: &[PathSegment]) -> Result<bool, String> {
+        let Some(last) = segments.last() else {
+            return Err("cannot remove the whole document".into());
+        };
+        let root = self.root()?;
+        let parent = Self::parent(&root, segments)?;
+        let Some(index) = parent.find(last) else {
+            return Ok(false);
+        };
+        self.remove_entry(parent, index);
+        Ok(true)
+    }
+
+    fn insert_at(&mut self, segments: &[PathSegment], index: usize, value: &Value) -> Result<(), String> {
+        let root = self.root()?;
+        match Self::locate(&root, segments) {
+            Some(node @ Node::Array { entries, .. }) if index <= entries.len() => {
+                self.insert_entry(node, None, index, value);
+                Ok(())
+            }
+            Some(Node::Array { .. }) => Err(format!("index {index} out of range at {}", display_path(segments))),
+            _ => Err(format!("no array at {}", display_path(segments))),
+        }
+    }
+
+    fn splice(&mut self, start: usize, end: usize, with: &str) {
+        self.text.replace_range(start..end, with);
+    }
+
+    fn newline(&self) -> &'static str {
+        if self.text.contains("\r\n") {
+            "\r\n"
+        } else {
+            "\n"
+        }
+    }
+
+    fn line_start(&self, pos: usize) -> usize {
+        self.text[..pos].rfind('\n').map_or(0, |i| i + 1)
+    }
+
+    /// Leading whitespace of the line containing `pos`.
+    fn line_indent(&self, pos: usize) -> &str {
+        let start = self.line_start(pos);
+        let line = &self.text[start..];
+        &line[..line.len() - line.trim_start_matches([' ', '\t']).len()]
+    }
+
+    /// One level of indentation: that of the first indented line.
+    fn indent_unit(&self) -> String {
+        self.text
+            .lines()
+            .map(|line| &line[..line.len() - line.trim_start_matches([' ', '\t']).len()])
+            .find(|indent| !indent.is_empty())
+            .unwrap_or("  ")
+            .to_string()
+    }
+
+    /// `value` pretty-printed for a line indented by `indent`.
+    fn render(&self, value: &Value, indent: &str) -> String {
+        let unit = self.indent_unit();
+        let mut out = Vec::new();
+        let formatter = serde_json::ser::PrettyFormatter::with_indent(unit.as_bytes());
+        let mut serializer = serde_json::Serializer::with_formatter(&mut out, formatter);
+        // Serializing a Value into memory can't fail.
+        let _ = value.serialize(&mut serializer);
+        String::from_utf8_lossy(&out).replace('\n', &format!("{}{indent}", self.newline()))
+    }
+
+    fn remove_entry(&mut self, container: &Node, index: usize) {
+        let entries = container.entries().unwrap_or_default();
+        let entry = &entries[index];
+        if entries.len() == 1 {
+            // Drop everything inside the brackets, comments included.
+            self.splice(container.start() + 1, container.end() - 1, "");
+            return;
+        }
+        if let Some(comma) = entry.comma {
+            // The entry, its comma and the rest of its line.
+            let rest = &self.text[comma + 1..];
+            let mut end = comma + 1 + (rest.len() - rest.trim_start_matches([' ', '\t']).len());
+            if self.text[end..].starts_with("//") {
+                end += self.text[end..].find('\n').unwrap_or(self.text.len() - end);
+            }
+            let mut start = entry.start();
+            if let Some(newline) = ["\r\n", "\n"].into_iter().find(|nl| self.text[end..].starts_with(nl)) {
+                let line_start = self.line_start(start);
+                if self.text[line_start..start].trim().is_empty() {
+                    // Whole lines: take the indentation and the line break too.
+                    start = line_start;
+                    end += newline.len();
+                }
+            }
+            self.splice(start, end, "");
+        } else {
+            // The last entry without a trailing comma: drop the comma before it.
+            let before = entries[index - 1].comma.unwrap_or(entries[index - 1].end());
+            self.splice(before, entry.end(), "");
+        }
+    }
+
+    fn insert_entry(&mut self, container: &Node, key: Option<&str>, index: usize, value: &Value) {
+        let entries = container.entries().unwrap_or_default();
+        let nl = self.newline();
+        let unquoted = entries.iter().any(|e| e.key.as_ref().is_some_and(|k| !k.quoted));
+        let entry_text = |doc: &Self, indent: &str| {
+            let value = doc.render(value, indent);
+            match key {
+                Some(key) if unquoted && is_identifier(key) => format!("{key}: {value}"),
+                Some(key) => format!("{}: {value}", Value::String(key.to_string())),
+                None => value,
+            }
+        };
+
+        let (open, close) = (container.start(), container.end() - 1);
+        let Some(first) = entries.first() else {
+            let outer = self.line_indent(open).to_string();
+            let inner = format!("{outer}{}", self.indent_unit());
+            let text = format!("{nl}{inner}{}{nl}{outer}", entry_text(self, &inner));
+            self.splice(open + 1, close, &text);
+            return;
+        };
+
+        let multiline = self.text[open..first.start()].contains('\n');
+        let indent = if multiline { self.line_indent(first.start()) } else { self.line_indent(open) }.to_string();
+        let text = entry_text(self, &indent);
+        let separator = if multiline { format!("{nl}{indent}") } else { " ".to_string() };
+        if index < entries.len() {
+            let at = entries[index].start();
+            self.splice(at, at, &format!("{text},{separator}"));
+            return;
+        }
+        let last = &entries[entries.len() - 1];
+        if let Some(comma) = last.comma {
+            self.splice(comma + 1, comma + 1, &format!("{separator}{text},"));
+            return;
+        }
+        // Keep a comment trailing the last entry on its line.
+        let rest = &self.text[last.end()..];
+        let gap = rest.len() - rest.trim_start_matches([' ', '\t']).len();
+        let at = if multiline && rest[gap..].starts_with("//") {
+            let line = &rest[gap..];
+            last.end() + gap + line.find(['\r', '\n']).unwrap_or(line.len())
+        } else {
+            last.end()
+        };
+        self.splice(at, at, &format!("{separator}{text}"));
+        self.splice(last.end(), last.end(), ",");
+    }
+}
+
+fn display_path(segments: &[PathSegment]) -> String {
+    if segments.is_empty() {
+        return "root".into();
+    }
+    segments.iter().fold(String::new(), |path, segment| match segment {
+        PathSegment::Key(key) => crate::json_diff::child_path(&path, key),
+        PathSegment::Index(index) => crate::json_diff::index_path(&path, *index),
+    })
+}
+
+#[derive(Debug)]
+enum Edit {
+    Set(Vec<PathSegment>, Value),
+    Unset(Vec<PathSegment>),
+    Insert(Vec<PathSegment>, usize, Value),
+}
+
+fn with(path: &[PathSegment], segment: PathSegment) -> Vec<PathSegment> {
+    let mut path = path.to_vec();
+    path.push(segment);
+    path
+}
+
+/// Edits that turn `before` into `after`, in an order where each one's path
+/// is still valid once the previous ones are applied.
+fn diff_edits(before: &Value, after: &Value, path: &[PathSegment], edits: &mut Vec<Edit>) {
+    if before == after {
+        return;
+    }
+    match (before, after) {
+        (Value::Object(a), Value::Object(b)) => {
+            for (key, old) in a {
+                match b.get(key) {
+                    Some(new) => diff_edits(old, new, &with(path, PathSegment::Key(key.clone())), edits),
+                    None => edits.push(Edit::Unset(with(path, PathSegment::Key(key.clone())))),
+                }
+            }
+            for (key, new) in b.iter().filter(|(key, _)| !a.contains_key(*key)) {
+                edits.push(Edit::Set(with(path, PathSegment::Key(key.clone())), new.clone()));
+            }
+        }
+        (Value::Array(a), Value::Array(b)) => {
+            // Runs of unmatched elements between the aligned ones.
+            let mut gaps = Vec::new();
+            let mut previous = (0, 0);
+            for (i, j) in align(a, b).into_iter().chain([(a.len(), b.len())]) {
+                gaps.push((previous.0..i, previous.1..j));
+                previous = (i + 1, j + 1);
+            }
+            // Last gap first, so indices of earlier ones don't move.
+            for (old, new) in gaps.into_iter().rev() {
+                let common = old.len().min(new.len());
+                for index in (old.start + common..old.end).rev() {
+                    edits.push(Edit::Unset(with(path, PathSegment::Index(index))));
+                }
+                for offset in common..new.len() {
+                    edits.push(Edit::Insert(path.to_vec(), old.start + offset, b[new.start + offset].clone()));
+                }
+                for offset in (0..common).rev() {
+                    let index = old.start + offset;
+                    diff_edits(&a[index], &b[new.start + offset], &with(path, PathSegment::Index(index)), edits);
+                }
+            }
+        }
+        _ => edits.push(Edit::Set(path.to_vec(), after.clone())),
+    }
+}
+
+/// Rewrite `text` so that it parses to `next`, editing only what changed.
+/// Text that can't be parsed is replaced by `next` pretty-printed.
+pub fn update_text(text: &str, next: &Value) -> String {
+    let pretty = || serde_json::to_string_pretty(next).unwrap_or_else(|_| "{}".into());
+    let Ok(mut doc) = Json5Document::parse(text) else {
+        return pretty();
+    };
+    let Ok(current) = doc.value() else {
+        return pretty();
+    };
+    let mut edits = Vec::new();
+    diff_edits(&current, next, &[], &mut edits);
+    for edit in edits {
+        let applied = match edit {
+            Edit::Set(path, value) => doc.set_at(&path, &value),
+            Edit::Unset(path) => doc.unset_at(&path).map(|_| ()),
+            Edit::Insert(path, index, value) => doc.insert_at(&path, index, &value),
+        };
+        if applied.is_err() {
+            return pretty();
+        }
+    }
+    // Duplicate keys and other oddities can defeat the edits; never write
+    // something that doesn't say what was asked.
+    match doc.value() {
+        Ok(value) if value == *next => doc.into_text(),
+        _ => pretty(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    const CONFIG: &str = r#"// OpenClaw config, edited by hand
+{
+  gateway: {
+    port: 18789, // keep in sync with the proxy
+    bind: "loopback",
+  },
+  /* agents */
+  agents: {
+    list: [
+      { id: "main", model: "anthropic/claude-sonnet-4-5" },
+      { id: "ops" },
+    ],
+  },
+  "channels": {},
+}
+"#;
+
+    #[test]
+    fn edits_leave_the_rest_of_the_text_alone() {
+        let mut doc = Json5Document::parse(CONFIG).unwrap();
+        doc.set("gateway.port", &json!(18800)).unwrap();
+        assert_eq!(doc.text(), CONFIG.replace("port: 18789,", "port: 18800,"));
+
+        doc.set("gateway.auth", &json!({ "mode": "token" })).unwrap();
+        assert!(doc.text().contains("    bind: \"loopback\",\n    auth: {\n      \"mode\": \"token\"\n    },\n  },"));
+
+        assert!(doc.unset("gateway.bind").unwrap());
+        assert!(!doc.unset("gateway.bind").unwrap());
+        assert!(!doc.text().contains("bind"));
+        assert!(doc.text().contains("port: 18800, // keep in sync with the proxy\n    auth"));
+
+        doc.insert("agents.list", 1, &json!({ "id": "new" })).unwrap();
+        doc.set("channels.discord", &json!({ "enabled": true })).unwrap();
+        let value = doc.value().unwrap();
+        assert_eq!(value["agents"]["list"][1]["id"], "new");
+        assert_eq!(value["channels"]["discord"]["enabled"], true);
+        assert!(doc.text().starts_with("// OpenClaw config, edited by hand\n{"));
+        assert!(doc.text().contains("/* agents */"));
+
+        assert!(doc.set("missing.key", &json!(1)).is_err());
+        assert!(doc.insert("agents.list", 9, &json!(1)).is_err());
+    }
+
+    #[test]
+    fn update_text_applies_a_whole_new_config_in_place() {
+        let mut next: Value = json5::from_str(CONFIG).unwrap();
+        next["gateway"]["port"] = json!(19000);
+        next["agents"]["list"].as_array_mut().unwrap().remove(0);
+        next["agents"]["list"][0]["model"] = json!("openai/gpt-5");
+        next["bindings"] = json!([{ "agentId": "ops" }]);
+
+        let text = update_text(CONFIG, &next);
+        assert_eq!(json5::from_str::<Value>(&text).unwrap(), next);
+        assert!(text.contains("port: 19000, // keep in sync with the proxy"));
+        assert!(text.contains("/* agents */"));
+        assert!(text.contains("      { id: \"ops\", model: \"openai/gpt-5\" },\n    ],"));
+        assert!(!text.contains("main"));
+        // Unchanged configs come back byte for byte.
+        assert_eq!(update_text(CONFIG, &json5::from_str::<Value>(CONFIG).unwrap()), CONFIG);
+    }
+
+    #[test]
+    fn empty_containers_and_single_line_styles() {
+        let mut doc = Json5Document::parse("{\"a\": [], \"b\": {\"x\": 1, \"y\": 2}}").unwrap();
+        doc.set("a[0]", &json!(1)).unwrap();
+        doc.unset("b.x").unwrap();
+        doc.set("b.z", &json!(3)).unwrap();
+        assert_eq!(doc.text(), "{\"a\": [\n  1\n], \"b\": {\"y\": 2, \"z\": 3}}");
+        doc.unset("b.y").unwrap();
+        doc.unset("b.z").unwrap();
+        assert_eq!(doc.value().unwrap(), json!({ "a": [1], "b": {} }));
+
+        // Unparseable text is replaced wholesale.
+        assert_eq!(update_text("{oops", &json!({ "a": 1 })), "{\n  \"a\": 1\n}");
+    }
+}
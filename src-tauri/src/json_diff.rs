@@ -0,0 +1,364 @@
+//! Structural JSON diff and unified text diff for previews.
+//!
+//! `diff_values` walks two documents and emits one `ChangeItem` per differing
+//! path. Objects are compared by key, so reordering alone is not a change.
+//! Arrays are aligned on equal elements first, so inserting one agent at the
+//! front of `agents.list` is a single `add` rather than a replace of every
+//! entry. Removed elements carry their index in the old array, added and
+//! replaced elements their index in the new one.
+//!
+//! [`value_at`] reads a value back from a path in the same notation.
+
+use serde_json::{Map, Value};
+
+use crate::recipe::ChangeItem;
+
+/// Above this many cells the quadratic alignment is skipped and the
+/// differing middle is treated as replaced wholesale.
+const MAX_ALIGN_CELLS: usize = 4_000_000;
+
+/// Matched `(left, right)` index pairs of a longest common subsequence.
+pub(crate) fn align<T: PartialEq>(a: &[T], b: &[T]) -> Vec<(usize, usize)> {
+    let prefix = a.iter().zip(b).take_while(|(x, y)| x == y).count();
+    let suffix = a[prefix..]
+        .iter()
+        .rev()
+        .zip(b[prefix..].iter().rev())
+        .take_while(|(x, y)| x == y)
+        .count();
+    let (ma, mb) = (&a[prefix..a.len() - suffix], &b[prefix..b.len() - suffix]);
+
+    let mut pairs: Vec<(usize, usize)> = (0..prefix).map(|i| (i, i)).collect();
+    if !ma.is_empty() && !mb.is_empty() && ma.len() * mb.len() <= MAX_ALIGN_CELLS {
+        let (n, m) = (ma.len(), mb.len());
+        // lengths[i][j] = LCS length of ma[i..] and mb[j..]
+        let mut lengths = vec![0u32; (n + 1) * (m + 1)];
+        for i in (0..n).rev() {
+            for j in (0..m).rev() {
+                lengths[i * (m + 1) + j] = if ma[i] == mb[j] {
+                    lengths[(i + 1) * (m + 1) + j + 1] + 1
+                } else {
+                    lengths[(i + 1) * (m + 1) + j].max(lengths[i * (m + 1) + j + 1])
+                };
+            }
+        }
+        let (mut i, mut j) = (0, 0);
+        while i < n && j < m {
+            if ma[i] == mb[j] {
+                pairs.push((prefix + i, prefix + j));
+                i += 1;
+                j += 1;
+            } else if lengths[(i + 1) * (m + 1) + j] >= lengths[i * (m + 1) + j + 1] {
+                i += 1;
+            } else {
+                j += 1;
+            }
+        }
+    }
+    pairs.extend((0..suffix).map(|k| (a.len() - suffix + k, b.len() - suffix + k)));
+    pairs
+}
+
+fn is_plain_key(key: &str) -> bool {
+    !key.is_empty() && key.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+}
+
+/// `parent.key`, or `parent["odd.key"]` when the key needs quoting.
+pub fn child_path(parent: &str, key: &str) -> String {
+    if is_plain_key(key) {
+        if parent.is_empty() {
+            key.to_string()
+        } else {
+            format!("{parent}.{key}")
+        }
+    } else {
+        format!("{parent}[{}]", Value::String(key.to_string()))
+    }
+}
+
+pub fn index_path(parent: &str, index: usize) -> String {
+    format!("{parent}[{index}]")
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum PathSegment {
+    Key(String),
+    Index(usize),
+}
+
+/// Parse a path as written by [`child_path`] and [`index_path`], e.g.
+/// `agents.list[0].model` or `channels["a.b"]`. `""` and `root` are the
+/// document itself; a path starting with `/` is read as a JSON pointer.
+pub fn parse_path(path: &str) -> Result<Vec<PathSegment>, String> {
+    if path.starts_with('/') {
+        return Ok(crate::json_patch::parse_pointer(path)?.into_iter().map(PathSegment::Key).collect());
+    }
+    let mut segments = Vec::new();
+    if path.is_empty() || path == "root" {
+        return Ok(segments);
+    }
+    let mut rest = path;
+    while !rest.is_empty() {
+        if let Some(inner) = rest.strip_prefix('[') {
+            let end = if let Some(quoted) = inner.strip_prefix('"') {
+                // Find the closing quote, skipping escaped characters.
+                let mut escaped = false;
+                quoted
+                    .find(|c: char| {
+                        let close = c == '"' && !escaped;
+                        escaped = c == '\\' && !escaped;
+                        close
+                    })
+                    .map(|i| i + 2)
+            } else {
+                inner.find(']')
+            }
+            .ok_or_else(|| format!("unclosed '[' in path {path:?}"))?;
+            let token = &inner[..end];
+            segments.push(if token.starts_with('"') {
+                PathSegment::Key(serde_json::from_str(token).map_err(|e| format!("invalid key in path {path:?}: {e}"))?)
+            } else {
+                PathSegment::Index(token.parse().map_err(|_| format!("invalid index {token:?} in path {path:?}"))?)
+            });
+            rest = inner[end..].strip_prefix(']').ok_or_else(|| format!("expected ']' in path {path:?}"))?;
+        } else {
+            let end = rest.find(['.', '[']).unwrap_or(rest.len());
+            if end == 0 {
+                return Err(format!("empty key in path {path:?}"));
+            }
+            segments.push(PathSegment::Key(rest[..end].to_string()));
+            rest = &rest[end..];
+        }
+        if let Some(next) = rest.strip_prefix('.') {
+            if next.is_empty() || next.starts_with('[') {
+                return Err(format!("empty key in path {path:?}"));
+            }
+            rest = next;
+        }
+    }
+    Ok(segments)
+}
+
+/// The value at `segments`, or `None` when the path doesn't exist.
+/// Keys that look like array indices (from JSON pointers) index arrays.
+pub fn value_at<'a>(root: &'a Value, segments: &[PathSegment]) -> Option<&'a Value> {
+    segments.iter().try_fold(root, |value, segment| match (value, segment) {
+        (Value::Object(map), PathSegment::Key(key)) => map.get(key),
+        (Value::Object(map), PathSegment::Index(index)) => map.get(&index.to_string()),
+        (Value::Array(items), PathSegment::Index(index)) => items.get(*index),
+        (Value::Array(items), PathSegment::Key(key)) => items.get(key.parse::<usize>().ok()?),
+        _ => None,
+    })
+}
+
+fn change(path: &str, op: &str) -> ChangeItem {
+    ChangeItem {
+        path: if path.is_empty() { "root".into() } else { path.to_string() },
+        op: op.into(),
+        risk: if op == "remove" { "medium" } else { "low" }.into(),
+        reason: None,
+    }
+}
+
+fn diff_objects(path: &str, before: &Map<String, Value>, after: &Map<String, Value>, out: &mut Vec<ChangeItem>) {
+    for (key, old) in before {
+        let child = child_path(path, key);
+        match after.get(key) {
+            Some(new) => diff_into(&child, old, new, out),
+            None => out.push(change(&child, "remove")),
+        }
+    }
+    for key in after.keys().filter(|k| !before.contains_key(*k)) {
+        out.push(change(&child_path(path, key), "add"));
+    }
+}
+
+/// Number of top-level fields two objects share with equal values.
+fn similarity(a: &Value, b: &Value) -> usize {
+    match (a, b) {
+        (Value::Object(a), Value::Object(b)) => a.iter().filter(|(k, v)| b.get(*k) == Some(*v)).count(),
+        _ => 0,
+    }
+}
+
+fn diff_arrays(path: &str, before: &[Value], after: &[Value], out: &mut Vec<ChangeItem>) {
+    let (mut i, mut j) = (0, 0);
+    let anchors = align(before, after).into_iter().chain([(before.len(), after.len())]);
+    for (next_i, next_j) in anchors {
+        // Between two anchors, decide which old elements were edited into which new ones.
+        let mut pairs = Vec::new();
+        if next_i - i == next_j - j {
+            pairs.extend((i..next_i).zip(j..next_j));
+        } else {
+            let mut from = i;
+            for (y, new) in after.iter().enumerate().take(next_j).skip(j) {
+                let mut best: Option<(usize, usize)> = None;
+                for (x, old) in before.iter().enumerate().take(next_i).skip(from) {
+                    let score = similarity(old, new);
+                    if score > 0 && best.is_none_or(|(_, s)| score > s) {
+                        best = Some((x, score));
+                    }
+                }
+                if let Some((x, _)) = best {
+                    pairs.push((x, y));
+                    from = x + 1;
+                }
+            }
+        }
+        for &(x, y) in &pairs {
+            diff_into(&index_path(path, y), &before[x], &after[y], out);
+        }
+        for x in (i..next_i).filter(|x| !pairs.iter().any(|p| p.0 == *x)) {
+            out.push(change(&index_path(path, x), "remove"));
+        }
+        for y in (j..next_j).filter(|y| !pairs.iter().any(|p| p.1 == *y)) {
+            out.push(change(&index_path(path, y), "add"));
+        }
+        i = next_i + 1;
+        j = next_j + 1;
+    }
+}
+
+fn diff_into(path: &str, before: &Value, after: &Value, out: &mut Vec<ChangeItem>) {
+    match (before, after) {
+        (a, b) if a == b => {}
+        (Value::Object(a), Value::Object(b)) => diff_objects(path, a, b, out),
+        (Value::Array(a), Value::Array(b)) => diff_arrays(path, a, b, out),
+        _ => out.push(change(path, "replace")),
+    }
+}
+
+/// One change per differing JSON path.
+pub fn diff_values(before: &Value, after: &Value) -> Vec<ChangeItem> {
+    let mut out = Vec::new();
+    diff_into("", before, after, &mut out);
+    out
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Line {
+    Keep,
+    Del,
+    Ins,
+}
+
+/// Unified diff (`--- before` / `+++ after`) with `context` lines around each hunk.
+/// Returns an empty string when the texts are equal.
+pub fn unified_diff(before: &str, after: &str, context: usize) -> String {
+    let a: Vec<&str> = before.lines().collect();
+    let b: Vec<&str> = after.lines().collect();
+
+    // (kind, line index in `a`, line index in `b`) — the index on the
+    // untouched side is where the line would sit.
+    let mut ops = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    for (next_i, next_j) in align(&a, &b).into_iter().chain([(a.len(), b.len())]) {
+        ops.extend((i..next_i).map(|x| (Line::Del, x, j)));
+        ops.extend((j..next_j).map(|y| (Line::Ins, next_i, y)));
+        if next_i < a.len() {
+            ops.push((Line::Keep, next_i, next_j));
+        }
+        i = next_i + 1;
+        j = next_j + 1;
+    }
+
+    let changed: Vec<usize> = (0..ops.len()).filter(|&k| ops[k].0 != Line::Keep).collect();
+    if changed.is_empty() {
+        return String::new();
+    }
+
+    let mut out = String::from("--- before\n+++ after\n");
+    let mut start = 0;
+    while start < changed.len() {
+        let mut end = start;
+        while end + 1 < changed.len() && changed[end + 1] - changed[end] <= 2 * context + 1 {
+            end += 1;
+        }
+        let lo = changed[start].saturating_sub(context);
+        let hi = (changed[end] + context + 1).min(ops.len());
+        let hunk = &ops[lo..hi];
+        let a_len = hunk.iter().filter(|op| op.0 != Line::Ins).count();
+        let b_len = hunk.iter().filter(|op| op.0 != Line::Del).count();
+        let a_start = hunk[0].1 + usize::from(a_len > 0);
+        let b_start = hunk[0].2 + usize::from(b_len > 0);
+        out.push_str(&format!("@@ -{a_start},{a_len} +{b_start},{b_len} @@\n"));
+        for &(kind, x, y) in hunk {
+            let (mark, text) = match kind {
+                Line::Keep => (' ', a[x]),
+                Line::Del => ('-', a[x]),
+                Line::Ins => ('+', b[y]),
+            };
+            out.push(mark);
+            out.push_str(text);
+            out.push('\n');
+        }
+        start = end + 1;
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn ops(changes: &[ChangeItem]) -> Vec<(String, String)> {
+        changes.iter().map(|c| (c.path.clone(), c.op.clone())).collect()
+    }
+
+    #[test]
+    fn emits_one_change_per_path() {
+        let before = json!({ "gateway": { "port": 1, "bind": "lan" }, "x.y": 1, "gone": true });
+        let after = json!({ "gateway": { "port": 2, "bind": "lan", "mode": "local" }, "x.y": 2 });
+        assert_eq!(
+            ops(&diff_values(&before, &after)),
+            vec![
+                ("gateway.port".into(), "replace".into()),
+                ("gateway.mode".into(), "add".into()),
+                ("gone".into(), "remove".into()),
+                ("[\"x.y\"]".into(), "replace".into()),
+            ]
+        );
+        assert!(diff_values(&before, &before).is_empty());
+    }
+
+    #[test]
+    fn arrays_are_aligned_by_content() {
+        let before = json!({ "list": [{ "id": "a" }, { "id": "b" }, { "id": "c" }] });
+        let after = json!({ "list": [{ "id": "new" }, { "id": "a" }, { "id": "c", "x": 1 }] });
+        assert_eq!(
+            ops(&diff_values(&before, &after)),
+            vec![
+                ("list[0]".into(), "add".into()),
+                ("list[2].x".into(), "add".into()),
+                ("list[1]".into(), "remove".into()),
+            ]
+        );
+    }
+
+    #[test]
+    fn paths_round_trip_through_value_at() {
+        let doc = json!({ "agents": { "list": [{ "id": "main", "model": "gpt" }] }, "a.b": { "c": 1 } });
+        let path = child_path(&index_path(&child_path("agents", "list"), 0), "model");
+        assert_eq!(path, "agents.list[0].model");
+        assert_eq!(value_at(&doc, &parse_path(&path).unwrap()), Some(&json!("gpt")));
+        let quoted = child_path(&child_path("", "a.b"), "c");
+        assert_eq!(quoted, "[\"a.b\"].c");
+        assert_eq!(value_at(&doc, &parse_path(&quoted).unwrap()), Some(&json!(1)));
+        assert_eq!(value_at(&doc, &parse_path("/agents/list/0/id").unwrap()), Some(&json!("main")));
+        assert_eq!(value_at(&doc, &parse_path("root").unwrap()), Some(&doc));
+        assert_eq!(value_at(&doc, &parse_path("agents.missing").unwrap()), None);
+        for bad in ["agents..list", "agents[", "agents[x]", "agents.", "[\"a\"x]"] {
+            assert!(parse_path(bad).is_err(), "{bad}");
+        }
+    }
+
+    #[test]
+    fn unified_diff_has_hunks_with_context() {
+        let before = "a\nb\nc\nd\ne\nf\ng\nh\n";
+        let after = "a\nb\nc\nD\ne\nf\ng\nh\ni\n";
+        let diff = unified_diff(before, after, 1);
+        assert_eq!(diff, "--- before\n+++ after\n@@ -3,3 +3,3 @@\n c\n-d\n+D\n e\n@@ -8,1 +8,2 @@\n h\n+i\n");
+        assert_eq!(unified_diff(before, before, 3), "");
+    }
+}
@@ -0,0 +1,322 @@
+//! JSON Merge Patch (RFC 7396) and JSON Patch (RFC 6902).
+//!
+//! `merge_patch` backs the `config_patch` recipe action. `apply_patch` backs
+//! the `json_patch` action, which can do what a merge patch cannot: append
+//! to or remove from arrays like `agents.list` and `bindings`, move and copy
+//! values, and guard a patch with `test`.
+
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+/// Apply an RFC 7396 merge patch to `target` in place.
+///
+/// `null` members delete, objects merge recursively and anything else
+/// (arrays included) replaces. Nulls inside a newly inserted subtree are
+/// dropped too, as the RFC requires.
+pub fn merge_patch(target: &mut Value, patch: &Value) {
+    let Value::Object(patch) = patch else {
+        *target = patch.clone();
+        return;
+    };
+    if !target.is_object() {
+        *target = Value::Object(Map::new());
+    }
+    let Value::Object(object) = target else { unreachable!() };
+    for (key, value) in patch {
+        if value.is_null() {
+            object.remove(key);
+        } else {
+            merge_patch(object.entry(key.clone()).or_insert(Value::Null), value);
+        }
+    }
+}
+
+/// One RFC 6902 operation.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "lowercase")]
+pub enum PatchOp {
+    Add { path: String, value: Value },
+    Remove { path: String },
+    Replace { path: String, value: Value },
+    Move { from: String, path: String },
+    Copy { from: String, path: String },
+    Test { path: String, value: Value },
+}
+
+/// Split a JSON Pointer (RFC 6901) into unescaped reference tokens.
+pub fn parse_pointer(pointer: &str) -> Result<Vec<String>, String> {
+    if pointer.is_empty() {
+        return Ok(Vec::new());
+    }
+    let Some(rest) = pointer.strip_prefix('/') else {
+        return Err(format!("JSON pointer {pointer:?} must start with '/'"));
+    };
+    rest.split('/')
+        .map(|token| {
+            let mut out = String::with_capacity(token.len());
+            let mut chars = token.chars();
+            while let Some(c) = chars.next() {
+                if c != '~' {
+                    out.push(c);
+                    continue;
+                }
+                match chars.next() {
+                    Some('0') => out.push('~'),
+                    Some('1') => out.push('/'),
+                    _ => return Err(format!("invalid escape in JSON pointer {pointer:?}")),
+                }
+            }
+            Ok(out)
+        })
+        .collect()
+}
+
+/// Index into an array of length `len`. `-` and `len` itself are only
+/// allowed when inserting.
+fn array_index(token: &str, len: usize, inserting: bool) -> Result<usize, String> {
+    if token == "-" && inserting {
+        return Ok(len);
+    }
+    let valid = !token.is_empty()
+        && token.chars().all(|c| c.is_ascii_digit())
+        && (token == "0" || !token.starts_with('0'));
+    let index: usize = if valid { token.parse().ok() } else { None }
+        .ok_or_else(|| format!("invalid array index {token:?}"))?;
+    let limit = if inserting { len } else { len.saturating_sub(1) };
+    if index > limit || (!inserting && len == 0) {
+        return Err(format!("array index {index} is out of bounds"));
+    }
+    Ok(index)
+}
+
+fn resolve<'a>(doc: &'a Value, tokens: &[String]) -> Option<&'a Value> {
+    tokens.iter().try_fold(doc, |node, token| match node {
+        Value::Object(map) => map.get(token),
+        Value::Array(items) => array_index(token, items.len(), false).ok().and_then(|i| items.get(i)),
+        _ => None,
+    })
+}
+
+fn resolve_mut<'a>(doc: &'a mut Value, tokens: &[String]) -> Option<&'a mut Value> {
+    tokens.iter().try_fold(doc, |node, token| match node {
+        Value::Object(map) => map.get_mut(token),
+        Value::Array(items) => {
+            let len = items.len();
+            array_index(token, len, false).ok().and_then(move |i| items.get_mut(i))
+        }
+        _ => None,
+    })
+}
+
+fn parent_of<'a>(doc: &'a mut Value, tokens: &'a [String], path: &str) -> Result<(&'a mut Value, &'a str), String> {
+    let (last, parents) = tokens.split_last().ok_or("the document root has no parent")?;
+    let parent = resolve_mut(doc, parents).ok_or_else(|| format!("path {path:?} does not exist"))?;
+    Ok((parent, last))
+}
+
+fn add(doc: &mut Value, path: &str, value: Value) -> Result<(), String> {
+    let tokens = parse_pointer(path)?;
+    if tokens.is_empty() {
+        *doc = value;
+        return Ok(());
+    }
+    match parent_of(doc, &tokens, path)? {
+        (Value::Object(map), key) => {
+            map.insert(key.to_string(), value);
+        }
+        (Value::Array(items), token) => {
+            let index = array_index(token, items.len(), true)?;
+            items.insert(index, value);
+        }
+        _ => return Err(format!("cannot add {path:?}: parent is not an object or array")),
+    }
+    Ok(())
+}
+
+fn remove(doc: &mut Value, path: &str) -> Result<Value, String> {
+    let tokens = parse_pointer(path)?;
+    match parent_of(doc, &tokens, path)? {
+        (Value::Object(map), key) => map.remove(key).ok_or_else(|| format!("path {path:?} does not exist")),
+        (Value::Array(items), token) => {
+            let index = array_index(token, items.len(), false)?;
+            Ok(items.remove(index))
+        }
+        _ => Err(format!("path {path:?} does not exist")),
+    }
+}
+
+/// Equality as RFC 6902 `test` defines it: numbers compare by value.
+fn json_equal(a: &Value, b: &Value) -> bool {
+    match (a, b) {
+        (Value::Number(x), Value::Number(y)) => match (x.as_i64(), y.as_i64()) {
+            (Some(x), Some(y)) => x == y,
+            _ => match (x.as_u64(), y.as_u64()) {
+                (Some(x), Some(y)) => x == y,
+                _ => x.as_f64() == y.as_f64(),
+            },
+        },
+        (Value::Array(x), Value::Array(y)) => x.len() == y.len() && x.iter().zip(y).all(|(x, y)| json_equal(x, y)),
+        (Value::Object(x), Value::Object(y)) => {
+            x.len() == y.len() && x.iter().all(|(k, v)| y.get(k).is_some_and(|w| json_equal(v, w)))
+        }
+        _ => a == b,
+    }
+}
+
+fn apply_op(doc: &mut Value, op: &PatchOp) -> Result<(), String> {
+    match op {
+        PatchOp::Add { path, value } => add(doc, path, value.clone()),
+        PatchOp::Remove { path } => remove(doc, path).map(drop),
+        PatchOp::Replace { path, value } => {
+            let tokens = parse_pointer(path)?;
+            let target = resolve_mut(doc, &tokens).ok_or_else(|| format!("path {path:?} does not exist"))?;
+            *target = value.clone();
+            Ok(())
+        }
+        PatchOp::Move { from, path } => {
+            if from == path {
+                return resolve(doc, &parse_pointer(from)?).map(drop).ok_or_else(|| format!("path {from:?} does not exist"));
+            }
+            if path.starts_with(&format!("{from}/")) {
+                return Err(format!("cannot move {from:?} into its own child {path:?}"));
+            }
+            let value = remove(doc, from)?;
+            add(doc, path, value)
+        }
+        PatchOp::Copy { from, path } => {
+            let value = resolve(doc, &parse_pointer(from)?)
+                .cloned()
+                .ok_or_else(|| format!("path {from:?} does not exist"))?;
+            add(doc, path, value)
+        }
+        PatchOp::Test { path, value } => {
+            let actual = resolve(doc, &parse_pointer(path)?).ok_or_else(|| format!("path {path:?} does not exist"))?;
+            if json_equal(actual, value) {
+                Ok(())
+            } else {
+                Err(format!("test failed: {path:?} is {actual}, expected {value}"))
+            }
+        }
+    }
+}
+
+/// Apply an RFC 6902 patch. Operations run in order and the patch is
+/// atomic: on any error `doc` is left untouched.
+pub fn apply_patch(doc: &mut Value, ops: &[PatchOp]) -> Result<(), String> {
+    let mut working = doc.clone();
+    for (i, op) in ops.iter().enumerate() {
+        apply_op(&mut working, op).map_err(|e| format!("operation {}: {e}", i + 1))?;
+    }
+    *doc = working;
+    Ok(())
+}
+
+/// Parse the operations of a patch document.
+pub fn parse_patch(value: &Value) -> Result<Vec<PatchOp>, String> {
+    serde_json::from_value(value.clone()).map_err(|e| format!("invalid JSON Patch: {e}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    /// RFC 7396 Appendix A.
+    #[test]
+    fn merge_patch_rfc7396_examples() {
+        let cases = [
+            (json!({"a":"b"}), json!({"a":"c"}), json!({"a":"c"})),
+            (json!({"a":"b"}), json!({"b":"c"}), json!({"a":"b","b":"c"})),
+            (json!({"a":"b"}), json!({"a":null}), json!({})),
+            (json!({"a":"b","b":"c"}), json!({"a":null}), json!({"b":"c"})),
+            (json!({"a":["b"]}), json!({"a":"c"}), json!({"a":"c"})),
+            (json!({"a":"c"}), json!({"a":["b"]}), json!({"a":["b"]})),
+            (json!({"a":{"b":"c"}}), json!({"a":{"b":"d","c":null}}), json!({"a":{"b":"d"}})),
+            (json!({"a":[{"b":"c"}]}), json!({"a":[1]}), json!({"a":[1]})),
+            (json!(["a","b"]), json!(["c","d"]), json!(["c","d"])),
+            (json!({"a":"b"}), json!(["c"]), json!(["c"])),
+            (json!({"a":"foo"}), json!(null), json!(null)),
+            (json!({"a":"foo"}), json!("bar"), json!("bar")),
+            (json!({"e":null}), json!({"a":1}), json!({"e":null,"a":1})),
+            (json!([1,2]), json!({"a":"b","c":null}), json!({"a":"b"})),
+            (json!({}), json!({"a":{"bb":{"ccc":null}}}), json!({"a":{"bb":{}}})),
+        ];
+        for (target, patch, expected) in cases {
+            let mut doc = target.clone();
+            merge_patch(&mut doc, &patch);
+            assert_eq!(doc, expected, "{target} + {patch}");
+        }
+    }
+
+    fn run(doc: Value, patch: Value) -> Result<Value, String> {
+        let mut doc = doc;
+        apply_patch(&mut doc, &parse_patch(&patch)?)?;
+        Ok(doc)
+    }
+
+    /// RFC 6902 Appendix A, the examples that succeed.
+    #[test]
+    fn json_patch_rfc6902_examples() {
+        let cases = [
+            // A.1 – A.7
+            (json!({"foo":"bar"}), json!([{"op":"add","path":"/baz","value":"qux"}]), json!({"baz":"qux","foo":"bar"})),
+            (json!({"foo":["bar","baz"]}), json!([{"op":"add","path":"/foo/1","value":"qux"}]), json!({"foo":["bar","qux","baz"]})),
+            (json!({"baz":"qux","foo":"bar"}), json!([{"op":"remove","path":"/baz"}]), json!({"foo":"bar"})),
+            (json!({"foo":["bar","qux","baz"]}), json!([{"op":"remove","path":"/foo/1"}]), json!({"foo":["bar","baz"]})),
+            (json!({"baz":"qux","foo":"bar"}), json!([{"op":"replace","path":"/baz","value":"boo"}]), json!({"baz":"boo","foo":"bar"})),
+            (
+                json!({"foo":{"bar":"baz","waldo":"fred"},"qux":{"corge":"grault"}}),
+                json!([{"op":"move","from":"/foo/waldo","path":"/qux/thud"}]),
+                json!({"foo":{"bar":"baz"},"qux":{"corge":"grault","thud":"fred"}}),
+            ),
+            (json!({"foo":["all","grass","cows","eat"]}), json!([{"op":"move","from":"/foo/1","path":"/foo/3"}]), json!({"foo":["all","cows","eat","grass"]})),
+            // A.8
+            (
+                json!({"baz":"qux","foo":["a",2,"c"]}),
+                json!([{"op":"test","path":"/baz","value":"qux"},{"op":"test","path":"/foo/1","value":2}]),
+                json!({"baz":"qux","foo":["a",2,"c"]}),
+            ),
+            // A.10, A.11, A.14, A.16
+            (json!({"foo":"bar"}), json!([{"op":"add","path":"/child","value":{"grandchild":{}}}]), json!({"foo":"bar","child":{"grandchild":{}}})),
+            (json!({"foo":"bar"}), json!([{"op":"add","path":"/baz","value":"qux","xyz":123}]), json!({"foo":"bar","baz":"qux"})),
+            (json!({"/":9,"~1":10}), json!([{"op":"test","path":"/~01","value":10}]), json!({"/":9,"~1":10})),
+            (json!({"foo":["bar"]}), json!([{"op":"add","path":"/foo/-","value":["abc","def"]}]), json!({"foo":["bar",["abc","def"]]})),
+            // copy, and adding at the root
+            (json!({"a":{"b":1}}), json!([{"op":"copy","from":"/a","path":"/c"}]), json!({"a":{"b":1},"c":{"b":1}})),
+            (json!({"a":1}), json!([{"op":"add","path":"","value":[1]}]), json!([1])),
+        ];
+        for (doc, patch, expected) in cases {
+            assert_eq!(run(doc.clone(), patch.clone()).unwrap(), expected, "{doc} + {patch}");
+        }
+        // `test` compares numbers by value.
+        assert!(run(json!({"n":1}), json!([{"op":"test","path":"/n","value":1.0}])).is_ok());
+    }
+
+    /// RFC 6902 Appendix A, the examples that must fail, plus atomicity.
+    #[test]
+    fn json_patch_rfc6902_errors() {
+        let cases = [
+            // A.9, A.12, A.15, then out-of-range, malformed and self-nesting paths
+            (json!({"baz":"qux"}), json!([{"op":"test","path":"/baz","value":"bar"}])),
+            (json!({"foo":"bar"}), json!([{"op":"add","path":"/baz/bat","value":"qux"}])),
+            (json!({"baz":"qux","foo":["a",2,"c"]}), json!([{"op":"test","path":"/foo/1","value":"2"}])),
+            (json!({"foo":["bar"]}), json!([{"op":"add","path":"/foo/2","value":1}])),
+            (json!({"foo":["bar"]}), json!([{"op":"remove","path":"/foo/-"}])),
+            (json!({"foo":["bar"]}), json!([{"op":"replace","path":"/foo/01","value":1}])),
+            (json!({"a":{"b":1}}), json!([{"op":"move","from":"/a","path":"/a/b/c"}])),
+            (json!({"a":1}), json!([{"op":"frobnicate","path":"/a"}])),
+        ];
+        for (doc, patch) in cases {
+            assert!(run(doc.clone(), patch.clone()).is_err(), "accepted {doc} + {patch}");
+        }
+
+        let mut doc = json!({"list":[1]});
+        let ops = parse_patch(&json!([
+            {"op":"add","path":"/list/-","value":2},
+            {"op":"remove","path":"/missing"},
+        ]))
+        .unwrap();
+        assert!(apply_patch(&mut doc, &ops).unwrap_err().starts_with("operation 2:"));
+        assert_eq!(doc, json!({"list":[1]}));
+    }
+}
@@ -1,5 +1,6 @@
 use crate::commands::{
-    apply_config_patch, fix_issues, get_system_status, get_status_light, list_history, list_recipes,
+    apply_config_patch, execute_recipe, prepare_recipe_install, list_recipe_installs, preview_recipe_uninstall, uninstall_recipe, validate_recipe_params, resolve_recipe_steps, apply_json_patch, get_json_schemas, fix_issues, get_system_status, get_status_light, list_history, update_snapshot, diff_snapshots, blame, get_history_retention, set_history_retention, gc_history, get_git_history_settings, set_git_history_settings, push_git_history, export_history_bundle, import_history_bundle, list_recipes, list_recipe_sources, upsert_recipe_source, remove_recipe_source,
+    get_recipe_trust, add_trusted_recipe_key, remove_trusted_recipe_key, set_recipe_trust_policy,
     list_model_profiles, upsert_model_profile, delete_model_profile, test_model_profile,
     get_cached_model_catalog, refresh_model_catalog, resolve_provider_auth,
     check_openclaw_update, extract_model_profiles_from_config,
@@ -18,16 +19,17 @@ use crate::commands::{
     set_global_model,
     set_agent_model,
     list_bindings,
+    list_local_instances, select_local_instance, add_local_instance, remove_local_instance,
     list_ssh_hosts, list_ssh_config_hosts, upsert_ssh_host, delete_ssh_host,
     ssh_connect, ssh_connect_with_passphrase, ssh_disconnect, ssh_status,
     ssh_exec, sftp_read_file, sftp_write_file, sftp_list_dir, sftp_remove_file,
     remote_read_raw_config, remote_get_system_status, remote_get_status_extra, get_status_extra,
     remote_list_agents_overview, remote_list_channels_minimal, remote_list_bindings,
-    remote_restart_gateway, remote_manage_rescue_bot, remote_apply_config_patch,
+    remote_restart_gateway, remote_manage_rescue_bot, remote_apply_config_patch, remote_validate_recipe_params, preview_recipe,
     remote_diagnose_primary_via_rescue,
     remote_repair_primary_via_rescue,
     remote_setup_agent_identity,
-    remote_run_doctor, remote_fix_issues, remote_list_history, remote_preview_rollback, remote_rollback,
+    remote_run_doctor, remote_fix_issues, remote_list_history, remote_update_snapshot, remote_diff_snapshots, remote_blame, remote_get_history_retention, remote_set_history_retention, remote_gc_history, remote_preview_rollback, remote_rollback,
     remote_list_discord_guild_channels, remote_write_raw_config,
     remote_analyze_sessions, remote_delete_sessions_by_ids,
     remote_list_session_files, remote_clear_all_sessions, remote_preview_session,
@@ -67,15 +69,34 @@ use crate::ssh::SshConnectionPool;
 pub mod bridge_client;
 pub mod cli_runner;
 pub mod commands;
+pub mod config_cas;
 pub mod config_io;
+pub mod config_watch;
+pub mod deep_link;
 pub mod doctor;
 pub mod doctor_commands;
 pub mod history;
+pub mod history_bundle;
+pub mod history_git;
+pub mod instances;
+pub mod json5_edit;
+pub mod json_diff;
+pub mod json_patch;
 pub mod logging;
 pub mod models;
 pub mod node_client;
+pub mod openclaw_config;
 pub mod recipe;
+pub mod recipe_bundle;
+pub mod recipe_expr;
+pub mod recipe_install;
+pub mod recipe_lint;
+pub mod recipe_registry;
+pub mod recipe_template;
 pub mod path_fix;
+pub mod risk;
+pub mod schemas;
+pub mod snapshot_store;
 pub mod ssh;
 
 pub fn run() {
@@ -93,6 +114,13 @@ pub fn run() {
             get_status_light,
             get_status_extra,
             list_recipes,
+            list_recipe_sources,
+            upsert_recipe_source,
+            remove_recipe_source,
+            get_recipe_trust,
+            add_trusted_recipe_key,
+            remove_trusted_recipe_key,
+            set_recipe_trust_policy,
             list_model_profiles,
             get_cached_model_catalog,
             refresh_model_catalog,
@@ -112,7 +140,27 @@ pub fn run() {
             check_openclaw_update,
             extract_model_profiles_from_config,
             apply_config_patch,
+            execute_recipe,
+            prepare_recipe_install,
+            list_recipe_installs,
+            preview_recipe_uninstall,
+            uninstall_recipe,
+            validate_recipe_params,
+            resolve_recipe_steps,
+            apply_json_patch,
+            get_json_schemas,
             list_history,
+            update_snapshot,
+            diff_snapshots,
+            blame,
+            get_history_retention,
+            set_history_retention,
+            gc_history,
+            get_git_history_settings,
+            set_git_history_settings,
+            push_git_history,
+            export_history_bundle,
+            import_history_bundle,
             preview_rollback,
             rollback,
             run_doctor_command,
@@ -135,6 +183,10 @@ pub fn run() {
             set_global_model,
             set_agent_model,
             list_bindings,
+            list_local_instances,
+            select_local_instance,
+            add_local_instance,
+            remove_local_instance,
             list_ssh_hosts,
             list_ssh_config_hosts,
             upsert_ssh_host,
@@ -159,10 +211,18 @@ pub fn run() {
             remote_diagnose_primary_via_rescue,
             remote_repair_primary_via_rescue,
             remote_apply_config_patch,
+            remote_validate_recipe_params,
+            preview_recipe,
             remote_setup_agent_identity,
             remote_run_doctor,
             remote_fix_issues,
             remote_list_history,
+            remote_update_snapshot,
+            remote_diff_snapshots,
+            remote_blame,
+            remote_get_history_retention,
+            remote_set_history_retention,
+            remote_gc_history,
             remote_preview_rollback,
             remote_rollback,
             remote_list_discord_guild_channels,
@@ -243,13 +303,24 @@ pub fn run() {
             doctor_bridge_disconnect,
             doctor_bridge_node_id,
         ])
-        .setup(|_app| {
+        .setup(|app| {
             // Run PATH fix in background so it doesn't block window creation.
             // openclaw commands won't fire until user interaction, giving this
             // plenty of time to complete.
             std::thread::spawn(|| {
                 crate::path_fix::ensure_tool_paths();
             });
+            std::thread::spawn(|| {
+                let paths = crate::models::resolve_paths();
+                if let Err(e) = crate::schemas::write_schemas(&paths.clawpal_dir) {
+                    eprintln!("Failed to write JSON schemas: {e}");
+                }
+            });
+            crate::config_watch::watch_local(
+                app.handle(),
+                &crate::instances::active_id(),
+                crate::models::resolve_paths(),
+            );
             Ok(())
         })
         .run(tauri::generate_context!())
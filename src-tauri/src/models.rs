@@ -15,7 +15,33 @@ pub struct OpenClawPaths {
     pub metadata_path: PathBuf,
 }
 
-fn expand_user_path(raw: &str) -> PathBuf {
+impl OpenClawPaths {
+    /// ClawPal's data for this instance alone: `clawpal_dir` for the
+    /// default instance, `clawpal_dir/instances/<slug>` for the others.
+    pub fn instance_dir(&self) -> &Path {
+        self.metadata_path.parent().unwrap_or(&self.clawpal_dir)
+    }
+}
+
+/// Default-instance paths under a fresh temp dir standing in for the home
+/// dir, which is `clawpal_dir.parent()`. Only `clawpal_dir` is created.
+#[cfg(test)]
+pub(crate) fn test_paths(name: &str) -> OpenClawPaths {
+    let home = env::temp_dir().join(format!("clawpal-{name}-{}", uuid::Uuid::new_v4()));
+    let openclaw_dir = home.join(".openclaw");
+    let clawpal_dir = home.join(".clawpal");
+    fs::create_dir_all(&clawpal_dir).unwrap();
+    OpenClawPaths {
+        config_path: openclaw_dir.join("openclaw.json"),
+        base_dir: openclaw_dir.clone(),
+        openclaw_dir,
+        history_dir: clawpal_dir.join("history"),
+        metadata_path: clawpal_dir.join("metadata.json"),
+        clawpal_dir,
+    }
+}
+
+pub(crate) fn expand_user_path(raw: &str) -> PathBuf {
     if let Some(rest) = raw.strip_prefix("~/") {
         if let Some(home) = home_dir() {
             return home.join(rest);
@@ -24,7 +50,7 @@ fn expand_user_path(raw: &str) -> PathBuf {
     PathBuf::from(raw)
 }
 
-fn env_path(name: &str) -> Option<PathBuf> {
+pub(crate) fn env_path(name: &str) -> Option<PathBuf> {
     env::var(name)
         .ok()
         .map(|value| value.trim().to_string())
@@ -49,7 +75,14 @@ fn scan_all_drives_for_openclaw() -> Option<PathBuf> {
     None
 }
 
+/// Paths for the local instance selected in ClawPal, see
+/// [`crate::instances`].
 pub fn resolve_paths() -> OpenClawPaths {
+    crate::instances::apply_active(resolve_default_paths())
+}
+
+/// Paths for the default local instance, whichever is selected.
+pub fn resolve_default_paths() -> OpenClawPaths {
     let home = home_dir().unwrap_or_else(|| Path::new(".").to_path_buf());
     
     // Priority: env var > scan all drives > default home directory
@@ -0,0 +1,536 @@
+//! Typed model of `openclaw.json`.
+//!
+//! Covers the sections ClawPal reads and edits: `agents`, `bindings`,
+//! `channels`, `gateway`, `models` and `auth`. Every struct keeps the keys it
+//! does not know in a flattened `extra` map, so a config survives
+//! [`OpenClawConfig::from_value`] and [`OpenClawConfig::to_value`] unchanged
+//! apart from what the caller edited. Deserialization errors carry the path
+//! of the offending value, e.g. `agents.list[2].id: missing field `id``.
+//!
+//! Nested channel nodes (guilds, accounts, per-channel overrides) stay as
+//! JSON inside their plugin's `extra`; [`OpenClawConfig::update_channel`]
+//! reads one as a [`ChannelConfig`] for editing.
+
+use std::collections::BTreeMap;
+
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+/// Gateway port when the config does not set one.
+pub const DEFAULT_GATEWAY_PORT: u16 = 18789;
+
+/// A config value that failed to deserialize, and where.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConfigError {
+    /// Dotted path from the config root; `.` for the root itself.
+    pub path: String,
+    pub message: String,
+}
+
+impl std::fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "invalid config at {}: {}", self.path, self.message)
+    }
+}
+
+impl From<ConfigError> for String {
+    fn from(error: ConfigError) -> Self {
+        error.to_string()
+    }
+}
+
+/// Deserialize `value`, reporting errors relative to `prefix`.
+fn parse_at<T: DeserializeOwned>(prefix: &str, value: &Value) -> Result<T, ConfigError> {
+    serde_path_to_error::deserialize(value).map_err(|e| {
+        let inner = e.path().to_string();
+        let path = match (prefix.is_empty(), inner.as_str()) {
+            (true, inner) => inner.to_string(),
+            (false, ".") => prefix.to_string(),
+            (false, inner) if inner.starts_with('[') => format!("{prefix}{inner}"),
+            (false, inner) => format!("{prefix}.{inner}"),
+        };
+        ConfigError { path, message: e.into_inner().to_string() }
+    })
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct OpenClawConfig {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub agents: Option<AgentsConfig>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bindings: Option<Vec<Binding>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub channels: Option<BTreeMap<String, ChannelConfig>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub gateway: Option<GatewayConfig>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub models: Option<ModelsConfig>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub auth: Option<AuthConfig>,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct AgentsConfig {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub defaults: Option<AgentDefaults>,
+    /// Spelling used by older configs; read when `defaults` lacks a value.
+    #[serde(rename = "default", default, skip_serializing_if = "Option::is_none")]
+    pub legacy_default: Option<AgentDefaults>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub list: Option<Vec<AgentEntry>>,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct AgentDefaults {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model: Option<ModelRef>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub workspace: Option<String>,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct AgentEntry {
+    pub id: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model: Option<ModelRef>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub workspace: Option<String>,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+/// A model setting: either a plain `provider/model` string or an object
+/// with a primary model and fallbacks.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum ModelRef {
+    Name(String),
+    Detailed(ModelSpec),
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ModelSpec {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub primary: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fallbacks: Option<Vec<String>>,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+impl ModelRef {
+    /// The model in use, from whichever key this config spells it with.
+    pub fn primary(&self) -> Option<String> {
+        let spec = match self {
+            ModelRef::Name(name) => return Some(name.clone()),
+            ModelRef::Detailed(spec) => spec,
+        };
+        if let Some(primary) = &spec.primary {
+            return Some(primary.clone());
+        }
+        let field = |key: &str| spec.extra.get(key).and_then(Value::as_str);
+        field("name")
+            .or_else(|| field("model"))
+            .or_else(|| field("default"))
+            .map(str::to_string)
+            .or_else(|| Some(format!("{}/{}", field("provider")?, field("id")?)))
+    }
+
+    /// Set the primary model, keeping fallbacks and other settings when the
+    /// slot holds an object.
+    pub fn set_primary(slot: &mut Option<ModelRef>, model: String) {
+        match slot {
+            Some(ModelRef::Detailed(spec)) => spec.primary = Some(model),
+            _ => *slot = Some(ModelRef::Name(model)),
+        }
+    }
+}
+
+/// Routes messages matching `match` to an agent.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Binding {
+    pub agent_id: String,
+    #[serde(rename = "match", default)]
+    pub match_rule: BindingMatch,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BindingMatch {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub channel: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub account_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub peer: Option<BindingPeer>,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct BindingPeer {
+    pub kind: String,
+    pub id: String,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+/// A channel plugin (`channels.discord`) or a node nested inside one. Only
+/// the fields ClawPal edits are typed; guilds, accounts and plugin-specific
+/// settings stay in `extra`.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ChannelConfig {
+    #[serde(rename = "type", default, skip_serializing_if = "Option::is_none")]
+    pub channel_type: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mode: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub allowlist: Option<Vec<String>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model: Option<ModelRef>,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct GatewayConfig {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub port: Option<u16>,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ModelsConfig {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub providers: Option<BTreeMap<String, ProviderConfig>>,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ProviderConfig {
+    #[serde(rename = "baseUrl", default, skip_serializing_if = "Option::is_none")]
+    pub base_url: Option<String>,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+impl ProviderConfig {
+    /// `baseUrl`, or one of the older spellings of it.
+    pub fn resolved_base_url(&self) -> Option<String> {
+        self.base_url.clone().or_else(|| {
+            ["base_url", "apiBase", "api_base"]
+                .iter()
+                .find_map(|key| self.extra.get(*key).and_then(Value::as_str))
+                .map(str::to_string)
+        })
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct AuthConfig {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub profiles: Option<BTreeMap<String, AuthProfile>>,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct AuthProfile {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub provider: Option<String>,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+impl OpenClawConfig {
+    pub fn from_value(value: &Value) -> Result<Self, ConfigError> {
+        parse_at("", value)
+    }
+
+    /// For reading only: a top-level section that doesn't fit the model is
+    /// kept untyped in `extra` and its error returned alongside, so schema
+    /// drift in one section doesn't hide the rest. Writes should use
+    /// [`OpenClawConfig::from_value`] and fail instead.
+    pub fn from_value_lenient(value: &Value) -> (Self, Vec<ConfigError>) {
+        let mut value = value.clone();
+        let mut untyped = Map::new();
+        let mut errors = Vec::new();
+        loop {
+            let error = match Self::from_value(&value) {
+                Ok(mut config) => {
+                    config.extra.extend(untyped);
+                    return (config, errors);
+                }
+                Err(error) => error,
+            };
+            let section = error.path.split(['.', '[']).next().unwrap_or_default().to_string();
+            let raw = value.as_object_mut().and_then(|map| map.remove(&section));
+            errors.push(error);
+            match raw {
+                Some(raw) => {
+                    untyped.insert(section, raw);
+                }
+                None => return (Self::default(), errors),
+            }
+        }
+    }
+
+    pub fn to_value(&self) -> Result<Value, String> {
+        serde_json::to_value(self).map_err(|e| e.to_string())
+    }
+
+    pub fn agent_list(&self) -> &[AgentEntry] {
+        self.agents.as_ref().and_then(|a| a.list.as_deref()).unwrap_or_default()
+    }
+
+    /// Configured agent ids; a config without `agents.list` has the
+    /// implicit `main` agent.
+    pub fn agent_ids(&self) -> Vec<String> {
+        let ids: Vec<String> = self.agent_list().iter().map(|a| a.id.clone()).collect();
+        if ids.is_empty() {
+            vec!["main".into()]
+        } else {
+            ids
+        }
+    }
+
+    pub fn agent_mut(&mut self, id: &str) -> Option<&mut AgentEntry> {
+        self.agents.as_mut()?.list.as_mut()?.iter_mut().find(|a| a.id == id)
+    }
+
+    /// `agents.list`, created if missing.
+    pub fn agent_list_mut(&mut self) -> &mut Vec<AgentEntry> {
+        self.agents.get_or_insert_with(Default::default).list.get_or_insert_with(Vec::new)
+    }
+
+    fn defaults_field<T>(&self, field: impl Fn(&AgentDefaults) -> Option<T>) -> Option<T> {
+        let agents = self.agents.as_ref()?;
+        agents.defaults.as_ref().and_then(&field).or_else(|| agents.legacy_default.as_ref().and_then(&field))
+    }
+
+    /// Model used by agents that do not set their own.
+    pub fn default_model(&self) -> Option<String> {
+        self.defaults_field(|d| d.model.as_ref().and_then(ModelRef::primary))
+    }
+
+    pub fn default_workspace(&self) -> Option<String> {
+        self.defaults_field(|d| d.workspace.clone())
+    }
+
+    /// `agents.defaults`, created if missing.
+    pub fn defaults_mut(&mut self) -> &mut AgentDefaults {
+        self.agents.get_or_insert_with(Default::default).defaults.get_or_insert_with(Default::default)
+    }
+
+    pub fn gateway_port(&self) -> u16 {
+        self.gateway.as_ref().and_then(|g| g.port).unwrap_or(DEFAULT_GATEWAY_PORT)
+    }
+
+    pub fn provider(&self, name: &str) -> Option<&ProviderConfig> {
+        self.models.as_ref()?.providers.as_ref()?.get(name.trim())
+    }
+
+    pub fn provider_base_url(&self, name: &str) -> Option<String> {
+        self.provider(name).and_then(ProviderConfig::resolved_base_url)
+    }
+
+    /// Auth profile id for `provider`, preferring `<provider>:default`.
+    pub fn auth_profile_for_provider(&self, provider: &str) -> Option<String> {
+        let provider = provider.trim();
+        if provider.is_empty() {
+            return None;
+        }
+        let profiles = self.auth.as_ref()?.profiles.as_ref()?;
+        let mut matching = profiles.iter().filter(|(_, profile)| {
+            profile
+                .provider
+                .as_deref()
+                .or_else(|| profile.extra.get("name").and_then(Value::as_str))
+                .is_some_and(|p| p.trim().eq_ignore_ascii_case(provider))
+        });
+        let first = matching.next()?.0;
+        let preferred = std::iter::once(first)
+            .chain(matching.map(|(id, _)| id))
+            .find(|id| id.ends_with(":default"));
+        Some(preferred.unwrap_or(first).clone())
+    }
+
+    /// Edit the channel node at a dotted `path` such as
+    /// `channels.discord.guilds.123`, creating it (and any missing parents)
+    /// as needed.
+    pub fn update_channel(&mut self, path: &str, edit: impl FnOnce(&mut ChannelConfig)) -> Result<(), String> {
+        let (plugin, rest) = split_channel_path(path)?;
+        let node = self.channels.get_or_insert_with(Default::default).entry(plugin.to_string()).or_default();
+        if rest.is_empty() {
+            edit(node);
+            return Ok(());
+        }
+        let mut slot = &mut node.extra;
+        let (last, parents) = rest.split_last().expect("rest is not empty");
+        for part in parents {
+            let child = slot.entry(part.to_string()).or_insert_with(|| Value::Object(Map::new()));
+            if !child.is_object() {
+                *child = Value::Object(Map::new());
+            }
+            slot = child.as_object_mut().expect("just made an object");
+        }
+        let current = slot.get(*last).cloned().filter(Value::is_object).unwrap_or_else(|| Value::Object(Map::new()));
+        let mut channel: ChannelConfig = parse_at(path, &current)?;
+        edit(&mut channel);
+        slot.insert(last.to_string(), serde_json::to_value(channel).map_err(|e| e.to_string())?);
+        Ok(())
+    }
+
+    /// Remove the channel node at `path`. Returns whether it existed.
+    pub fn remove_channel(&mut self, path: &str) -> Result<bool, String> {
+        let (plugin, rest) = split_channel_path(path)?;
+        let Some(channels) = self.channels.as_mut() else {
+            return Ok(false);
+        };
+        let Some((last, parents)) = rest.split_last() else {
+            return Ok(channels.remove(plugin).is_some());
+        };
+        let Some(node) = channels.get_mut(plugin) else {
+            return Ok(false);
+        };
+        let mut slot = &mut node.extra;
+        for part in parents {
+            match slot.get_mut(*part).and_then(Value::as_object_mut) {
+                Some(child) => slot = child,
+                None => return Ok(false),
+            }
+        }
+        Ok(slot.remove(*last).is_some())
+    }
+}
+
+/// `channels.<plugin>.<rest...>` split into the plugin and the rest.
+fn split_channel_path(path: &str) -> Result<(&str, Vec<&str>), String> {
+    let parts: Vec<&str> = path.trim().trim_matches('.').split('.').collect();
+    match parts.as_slice() {
+        ["channels", plugin, rest @ ..] if !plugin.is_empty() && rest.iter().all(|p| !p.is_empty()) => {
+            Ok((plugin, rest.to_vec()))
+        }
+        _ => Err(format!("not a channel path: {path}")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn sample() -> Value {
+        json!({
+            "meta": { "lastTouchedVersion": "2026.2.1" },
+            "agents": {
+                "defaults": { "model": { "primary": "anthropic/claude-sonnet-4-5", "fallbacks": ["openai/gpt-4o"] }, "maxConcurrent": 4 },
+                "list": [
+                    { "id": "main", "identity": { "name": "Claw" } },
+                    { "id": "ops", "model": "openai/gpt-4o", "workspace": "/ws/ops" }
+                ]
+            },
+            "bindings": [
+                { "agentId": "ops", "match": { "channel": "discord", "peer": { "kind": "channel", "id": "42" } } }
+            ],
+            "channels": {
+                "discord": {
+                    "enabled": true,
+                    "groupPolicy": "allowlist",
+                    "guilds": { "123": { "channels": { "42": { "allow": true } } } }
+                }
+            },
+            "gateway": { "port": 18790, "auth": { "mode": "token" } },
+            "models": { "providers": { "local": { "api_base": "http://localhost:11434", "models": [] } } },
+            "auth": { "profiles": {
+                "openai:work": { "provider": "openai", "mode": "api_key" },
+                "openai:default": { "provider": "openai", "mode": "api_key" }
+            } }
+        })
+    }
+
+    #[test]
+    fn round_trips_unknown_fields_and_reads_typed_sections() {
+        let value = sample();
+        let config = OpenClawConfig::from_value(&value).unwrap();
+        assert_eq!(config.to_value().unwrap(), value);
+
+        assert_eq!(config.agent_ids(), ["main", "ops"]);
+        assert_eq!(config.default_model().as_deref(), Some("anthropic/claude-sonnet-4-5"));
+        assert_eq!(config.gateway_port(), 18790);
+        assert_eq!(config.provider_base_url("local").as_deref(), Some("http://localhost:11434"));
+        assert_eq!(config.auth_profile_for_provider("OpenAI").as_deref(), Some("openai:default"));
+        assert_eq!(config.bindings.as_ref().unwrap()[0].match_rule.peer.as_ref().unwrap().id, "42");
+
+        let legacy = OpenClawConfig::from_value(&json!({ "agents": { "default": { "workspace": "/ws" } } })).unwrap();
+        assert_eq!(legacy.default_workspace().as_deref(), Some("/ws"));
+        assert_eq!(legacy.agent_ids(), ["main"]);
+        assert_eq!(legacy.gateway_port(), DEFAULT_GATEWAY_PORT);
+    }
+
+    #[test]
+    fn reports_the_path_of_invalid_values() {
+        let mut value = sample();
+        value["agents"]["list"][1]["workspace"] = json!(7);
+        let error = OpenClawConfig::from_value(&value).unwrap_err();
+        assert_eq!(error.path, "agents.list[1].workspace");
+
+        let error = OpenClawConfig::from_value(&json!({ "gateway": { "port": "x" } })).unwrap_err();
+        assert_eq!(error.path, "gateway.port");
+        assert!(error.to_string().starts_with("invalid config at gateway.port:"));
+
+        let mut config = OpenClawConfig::from_value(&sample()).unwrap();
+        config.channels.as_mut().unwrap().get_mut("discord").unwrap().extra["guilds"]["123"]["mode"] = json!(1);
+        let error = config.update_channel("channels.discord.guilds.123", |_| {}).unwrap_err();
+        assert_eq!(error, "invalid config at channels.discord.guilds.123.mode: invalid type: integer `1`, expected a string");
+    }
+
+    #[test]
+    fn lenient_reads_keep_the_sections_that_parse() {
+        let mut value = sample();
+        value["gateway"]["port"] = json!("x");
+        value["bindings"] = json!({ "not": "a list" });
+        let (config, errors) = OpenClawConfig::from_value_lenient(&value);
+        let paths: Vec<&str> = errors.iter().map(|e| e.path.as_str()).collect();
+        assert_eq!(paths.len(), 2);
+        assert!(paths.contains(&"gateway.port") && paths.contains(&"bindings"));
+        assert_eq!(config.agent_ids(), ["main", "ops"]);
+        assert_eq!(config.gateway_port(), DEFAULT_GATEWAY_PORT);
+        assert_eq!(config.extra["gateway"], value["gateway"]);
+
+        let (config, errors) = OpenClawConfig::from_value_lenient(&json!([]));
+        assert_eq!((config, errors.len()), (OpenClawConfig::default(), 1));
+    }
+
+    #[test]
+    fn edits_models_and_nested_channels_in_place() {
+        let mut config = OpenClawConfig::from_value(&sample()).unwrap();
+        ModelRef::set_primary(&mut config.defaults_mut().model, "openai/o3".into());
+        ModelRef::set_primary(&mut config.agent_mut("main").unwrap().model, "openai/gpt-4o-mini".into());
+        config
+            .update_channel("channels.discord.guilds.123.channels.42", |c| c.model = Some(ModelRef::Name("openai/o3".into())))
+            .unwrap();
+        config.update_channel("channels.telegram", |c| c.mode = Some("open".into())).unwrap();
+
+        let value = config.to_value().unwrap();
+        assert_eq!(value["agents"]["defaults"]["model"], json!({ "primary": "openai/o3", "fallbacks": ["openai/gpt-4o"] }));
+        assert_eq!(value["agents"]["list"][0]["model"], json!("openai/gpt-4o-mini"));
+        assert_eq!(value["channels"]["discord"]["guilds"]["123"]["channels"]["42"], json!({ "allow": true, "model": "openai/o3" }));
+        assert_eq!(value["channels"]["telegram"], json!({ "mode": "open" }));
+
+        assert!(config.remove_channel("channels.discord.guilds.123").unwrap());
+        assert!(!config.remove_channel("channels.discord.guilds.123").unwrap());
+        assert!(config.remove_channel("agents.list").is_err());
+    }
+}
@@ -1,18 +1,38 @@
-use std::{env, fs, path::{Path, PathBuf}};
+use std::{collections::HashMap, fs, path::{Path, PathBuf}};
 use regex::Regex;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use serde_json::{Map, Value};
 
+use crate::recipe_registry::{recipes_dir, RecipeSource};
+
 const BUILTIN_RECIPES_JSON: &str = include_str!("../recipes.json");
 
-#[derive(Debug, Serialize, Deserialize)]
+/// Step actions understood by [`apply_step`].
+pub const STEP_ACTIONS: &[&str] = &["create_agent", "setup_identity", "bind_channel", "config_patch", "json_patch"];
+
+/// Param types with dedicated validation or UI. Unknown types are treated as `string`.
+pub const PARAM_TYPES: &[&str] = &[
+    "string",
+    "textarea",
+    "boolean",
+    "number",
+    "enum",
+    "list",
+    "model_profile",
+    "discord_guild",
+    "discord_channel",
+    "agent",
+];
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
 #[serde(untagged)]
-enum RecipeDocument {
+pub(crate) enum RecipeDocument {
     List(Vec<Recipe>),
     Wrapped { recipes: Vec<Recipe> },
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, JsonSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct RecipeParam {
     pub id: String,
@@ -32,17 +52,51 @@ pub struct RecipeParam {
     pub depends_on: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub default_value: Option<String>,
+    /// Allowed values for `enum` params.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub options: Option<Vec<String>>,
+    /// Inclusive bounds for `number` params.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max: Option<f64>,
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+/// What happens to the remaining steps when a step fails. `continue` leaves
+/// the config as it was before the failing step and runs the next one.
+// No per-variant docs: schemars would turn the plain string enum into a oneOf.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum OnError {
+    #[default]
+    Abort,
+    Continue,
+}
+
+impl OnError {
+    fn is_abort(&self) -> bool {
+        *self == OnError::Abort
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, JsonSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct RecipeStep {
     pub action: String,
     pub label: String,
     pub args: Map<String, Value>,
+    /// Expression over params (see `recipe_expr`); the step is skipped when false.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub when: Option<String>,
+    /// Id of a `list` param. The step runs once per item, with `{{item}}`
+    /// and `{{index}}` available to its args.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub for_each: Option<String>,
+    #[serde(default, skip_serializing_if = "OnError::is_abort")]
+    pub on_error: OnError,
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, JsonSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct Recipe {
     pub id: String,
@@ -53,6 +107,10 @@ pub struct Recipe {
     pub difficulty: String,
     pub params: Vec<RecipeParam>,
     pub steps: Vec<RecipeStep>,
+    /// Set by the registry; never read from recipe documents.
+    #[serde(default, skip_deserializing, skip_serializing_if = "Option::is_none")]
+    #[schemars(skip)]
+    pub source: Option<RecipeSource>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -75,6 +133,9 @@ pub struct PreviewResult {
     pub can_rollback: bool,
     pub impact_level: String,
     pub warnings: Vec<String>,
+    /// Workspace files and directories the recipe would create or overwrite.
+    #[serde(default)]
+    pub files: Vec<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -88,11 +149,87 @@ pub struct ApplyResult {
     pub errors: Vec<String>,
 }
 
+/// Outcome of one recipe step inside an [`execute_recipe`] run.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RecipeStepResult {
+    pub index: usize,
+    pub action: String,
+    pub label: String,
+    /// "applied" | "skipped" | "failed" | "notRun"
+    pub status: String,
+    #[serde(flatten)]
+    pub result: ApplyResult,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RecipeExecution {
+    pub recipe_id: String,
+    pub ok: bool,
+    pub snapshot_id: Option<String>,
+    pub rolled_back: bool,
+    pub steps: Vec<RecipeStepResult>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub param_errors: Vec<ParamError>,
+    /// Install record that `uninstall_recipe` can revert.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub install_id: Option<String>,
+}
+
+/// A recipe step with its `{{param}}` placeholders rendered.
+///
+/// A `forEach` step resolves to one entry per item, all sharing `index`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ResolvedStep {
+    pub index: usize,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub item_index: Option<usize>,
+    pub action: String,
+    pub label: String,
+    pub args: Map<String, Value>,
+    #[serde(default)]
+    pub on_error: OnError,
+    /// Set when the step will not run, e.g. because its `when` is false.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub skip_reason: Option<String>,
+    /// Params the step is rendered with, including `item` and `index` in a `forEach`.
+    #[serde(default)]
+    pub scope: Map<String, Value>,
+}
+
+/// Everything the executor needs besides the config document itself.
+#[derive(Debug, Clone, Default)]
+pub struct RecipeEnv {
+    /// OpenClaw state dir; independent agents get `workspaces/<id>` below it.
+    pub base_dir: PathBuf,
+    /// Model profile id -> `provider/model` value written into the config.
+    pub model_values: HashMap<String, String>,
+    pub params: ParamContext,
+}
+
+/// Filesystem side effect of a step. These are deferred until every step
+/// has been applied to the in-memory config, so a failing step leaves no trace.
+#[derive(Debug, Clone, PartialEq)]
+pub enum FileEffect {
+    CreateDir(PathBuf),
+    WriteFile { path: PathBuf, content: String },
+}
+
+#[derive(Debug, Default)]
+pub struct StepOutcome {
+    pub skipped: bool,
+    pub changes: Vec<ChangeItem>,
+    pub files: Vec<FileEffect>,
+    pub warnings: Vec<String>,
+}
+
 pub fn builtin_recipes() -> Vec<Recipe> {
     parse_recipes_document(BUILTIN_RECIPES_JSON).unwrap_or_else(|_| Vec::new())
 }
 
-fn is_http_url(candidate: &str) -> bool {
+pub(crate) fn is_http_url(candidate: &str) -> bool {
     candidate.starts_with("http://") || candidate.starts_with("https://")
 }
 
@@ -105,15 +242,20 @@ fn expand_user_path(candidate: &str) -> PathBuf {
     PathBuf::from(candidate)
 }
 
-fn parse_recipes_document(text: &str) -> Result<Vec<Recipe>, String> {
-    let document: RecipeDocument = json5::from_str(text).map_err(|e| e.to_string())?;
+/// Parse a recipe document, checking it against the recipes schema first so
+/// that authors get a JSON pointer instead of a bare serde error.
+pub fn parse_recipes_document(text: &str) -> Result<Vec<Recipe>, String> {
+    let raw: Value = json5::from_str(text).map_err(|e| e.to_string())?;
+    crate::schemas::validate_recipes_document(&raw)?;
+    let document: RecipeDocument = serde_json::from_value(raw).map_err(|e| e.to_string())?;
     match document {
         RecipeDocument::List(recipes) => Ok(recipes),
         RecipeDocument::Wrapped { recipes } => Ok(recipes),
     }
 }
 
-pub fn load_recipes_from_source(source: &str) -> Result<Vec<Recipe>, String> {
+/// Read the raw text of a recipe source: an `http(s)` URL or a local path.
+pub fn read_source_text(source: &str) -> Result<String, String> {
     if source.trim().is_empty() {
         return Err("empty recipe source".into());
     }
@@ -123,43 +265,23 @@ pub fn load_recipes_from_source(source: &str) -> Result<Vec<Recipe>, String> {
         if !response.status().is_success() {
             return Err(format!("request failed: {}", response.status()));
         }
-        let text = response.text().map_err(|e| e.to_string())?;
-        parse_recipes_document(&text)
+        response.text().map_err(|e| e.to_string())
     } else {
         let path = expand_user_path(source);
         let path = Path::new(&path);
         if !path.exists() {
             return Err(format!("recipe file not found: {}", path.to_string_lossy()));
         }
-        let text = fs::read_to_string(path).map_err(|e| e.to_string())?;
-        parse_recipes_document(&text)
+        fs::read_to_string(path).map_err(|e| e.to_string())
     }
 }
 
-pub fn load_recipes_with_fallback(
-    explicit_source: Option<String>,
-    default_path: &Path,
-) -> Vec<Recipe> {
-    let builtin = builtin_recipes();
-
-    let candidates = [
-        explicit_source,
-        env::var("CLAWPAL_RECIPES_SOURCE").ok(),
-        Some(default_path.to_string_lossy().to_string()),
-    ];
-
-    for candidate in candidates.iter().flatten() {
-        if candidate.trim().is_empty() {
-            continue;
-        }
-        if let Ok(recipes) = load_recipes_from_source(candidate) {
-            if !recipes.is_empty() {
-                return recipes;
-            }
-        }
-    }
-
-    builtin
+/// Load recipes without consulting the trust store. Signed bundles are
+/// unwrapped but their signatures are not checked; use the registry for that.
+pub fn load_recipes_from_source(source: &str) -> Result<Vec<Recipe>, String> {
+    let text = read_source_text(source)?;
+    let (document, _) = crate::recipe_bundle::open(&text, &Default::default())?;
+    parse_recipes_document(&document)
 }
 
 pub fn find_recipe(id: &str) -> Option<Recipe> {
@@ -168,64 +290,282 @@ pub fn find_recipe(id: &str) -> Option<Recipe> {
 
 pub fn find_recipe_with_source(id: &str, source: Option<String>) -> Option<Recipe> {
     let paths = crate::models::resolve_paths();
-    let default_path = paths.clawpal_dir.join("recipes").join("recipes.json");
-    load_recipes_with_fallback(source, &default_path)
+    crate::recipe_registry::load_registry(source, &recipes_dir(&paths.clawpal_dir))
+        .recipes
         .into_iter()
         .find(|r| r.id == id)
 }
 
-pub fn validate(recipe: &Recipe, params: &Map<String, Value>) -> Vec<String> {
-    let mut errors = Vec::new();
-    for p in &recipe.params {
-        if p.required && !params.contains_key(&p.id) {
-            errors.push(format!("missing required param: {}", p.id));
-            continue;
+/// A validation failure for a single recipe param.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ParamError {
+    pub param_id: String,
+    /// Machine-readable reason, e.g. "required", "type", "pattern", "unknownModelProfile".
+    pub code: String,
+    pub message: String,
+}
+
+impl ParamError {
+    fn new(param_id: &str, code: &str, message: impl Into<String>) -> Self {
+        Self {
+            param_id: param_id.to_string(),
+            code: code.to_string(),
+            message: message.into(),
         }
+    }
+}
 
-        if let Some(v) = params.get(&p.id) {
-            let s = match v {
-                Value::String(s) => s.clone(),
-                _ => {
-                    errors.push(format!("param {} must be string", p.id));
-                    continue;
-                }
+/// Lookups for semantic param types. `None` means the data is unavailable
+/// (e.g. the guild cache was never refreshed) and the check is skipped.
+#[derive(Debug, Clone, Default)]
+pub struct ParamContext {
+    pub model_profile_ids: Option<Vec<String>>,
+    /// `(guild_id, channel_id)` pairs from the Discord guild channel cache.
+    pub discord_channels: Option<Vec<(String, String)>>,
+}
+
+fn is_blank_param(value: Option<&Value>) -> bool {
+    match value {
+        None | Some(Value::Null) => true,
+        Some(Value::String(s)) => s.trim().is_empty(),
+        _ => false,
+    }
+}
+
+/// A param only counts when every `dependsOn` parent is truthy.
+fn param_active(recipe: &Recipe, param: &RecipeParam, params: &Map<String, Value>) -> bool {
+    match &param.depends_on {
+        Some(parent) => {
+            crate::recipe_template::is_truthy(params.get(parent))
+                && recipe
+                    .params
+                    .iter()
+                    .find(|p| &p.id == parent)
+                    .is_none_or(|p| param_active(recipe, p, params))
+        }
+        None => true,
+    }
+}
+
+fn param_text(value: &Value) -> Option<String> {
+    match value {
+        Value::String(s) => Some(s.clone()),
+        Value::Number(n) => Some(n.to_string()),
+        _ => None,
+    }
+}
+
+fn check_text(param: &RecipeParam, text: &str, errors: &mut Vec<ParamError>) {
+    let len = text.chars().count();
+    if let Some(min) = param.min_length {
+        if len < min {
+            errors.push(ParamError::new(&param.id, "tooShort", format!("{} must be at least {min} characters", param.label)));
+        }
+    }
+    if let Some(max) = param.max_length {
+        if len > max {
+            errors.push(ParamError::new(&param.id, "tooLong", format!("{} must be at most {max} characters", param.label)));
+        }
+    }
+    if let Some(pattern) = &param.pattern {
+        match Regex::new(pattern) {
+            Ok(re) if !re.is_match(text) => {
+                errors.push(ParamError::new(&param.id, "pattern", format!("{} does not match the expected format", param.label)));
+            }
+            Ok(_) => {}
+            Err(e) => errors.push(ParamError::new(&param.id, "invalidPattern", format!("invalid validation pattern: {e}"))),
+        }
+    }
+}
+
+fn check_param(
+    recipe: &Recipe,
+    param: &RecipeParam,
+    value: &Value,
+    params: &Map<String, Value>,
+    ctx: &ParamContext,
+    errors: &mut Vec<ParamError>,
+) {
+    let type_error = |expected: &str| {
+        ParamError::new(&param.id, "type", format!("{} must be {expected}", param.label))
+    };
+    match param.kind.as_str() {
+        "boolean" => {
+            let ok = match value {
+                Value::Bool(_) => true,
+                Value::String(s) => matches!(s.trim(), "true" | "false"),
+                _ => false,
             };
-            if let Some(min) = p.min_length {
-                if s.len() < min {
-                    errors.push(format!("param {} too short", p.id));
+            if !ok {
+                errors.push(type_error("true or false"));
+            }
+        }
+        "number" => {
+            let number = match value {
+                Value::Number(n) => n.as_f64(),
+                Value::String(s) => s.trim().parse::<f64>().ok().filter(|n| n.is_finite()),
+                _ => None,
+            };
+            match number {
+                None => errors.push(type_error("a number")),
+                Some(n) => {
+                    if param.min.is_some_and(|min| n < min) || param.max.is_some_and(|max| n > max) {
+                        errors.push(ParamError::new(&param.id, "outOfRange", format!(
+                            "{} must be between {} and {}",
+                            param.label,
+                            param.min.map_or("-inf".into(), |v| v.to_string()),
+                            param.max.map_or("inf".into(), |v| v.to_string()),
+                        )));
+                    }
+                }
+            }
+        }
+        "enum" => match value.as_str() {
+            None => errors.push(type_error("one of the listed options")),
+            Some(choice) => {
+                let options = param.options.as_deref().unwrap_or(&[]);
+                if !options.iter().any(|o| o == choice) {
+                    errors.push(ParamError::new(&param.id, "notInOptions", format!(
+                        "{} must be one of: {}", param.label, options.join(", ")
+                    )));
+                }
+            }
+        },
+        "list" => match value {
+            Value::Array(items) => {
+                for item in items {
+                    match param_text(item) {
+                        Some(text) => check_text(param, &text, errors),
+                        None => {
+                            errors.push(type_error("a list of strings"));
+                            break;
+                        }
+                    }
                 }
             }
-            if let Some(max) = p.max_length {
-                if s.len() > max {
-                    errors.push(format!("param {} too long", p.id));
+            Value::String(text) => {
+                for item in split_list(text) {
+                    check_text(param, &item, errors);
                 }
             }
-            if let Some(pattern) = &p.pattern {
-                let re = Regex::new(pattern).map_err(|e| e.to_string()).ok();
-                if let Some(re) = re {
-                    if !re.is_match(&s) {
-                        errors.push(format!("param {} not match pattern", p.id));
+            _ => errors.push(type_error("a list of strings")),
+        },
+        kind => {
+            let Some(text) = param_text(value) else {
+                errors.push(type_error("a string"));
+                return;
+            };
+            check_text(param, &text, errors);
+            match kind {
+                "model_profile" => {
+                    if let Some(ids) = &ctx.model_profile_ids {
+                        if text != "__default__" && !ids.contains(&text) {
+                            errors.push(ParamError::new(&param.id, "unknownModelProfile", format!("model profile '{text}' does not exist")));
+                        }
+                    }
+                }
+                "discord_guild" => {
+                    if let Some(channels) = &ctx.discord_channels {
+                        if !channels.iter().any(|(guild, _)| guild == &text) {
+                            errors.push(ParamError::new(&param.id, "unknownGuild", format!("Discord guild '{text}' is not in the channel cache")));
+                        }
+                    }
+                }
+                "discord_channel" => {
+                    if let Some(channels) = &ctx.discord_channels {
+                        // Narrow to the guild picked in the same recipe, if any.
+                        let guild = recipe
+                            .params
+                            .iter()
+                            .find(|p| p.kind == "discord_guild")
+                            .and_then(|p| params.get(&p.id))
+                            .and_then(param_text)
+                            .filter(|g| !g.trim().is_empty());
+                        let found = channels.iter().any(|(g, c)| {
+                            c == &text && guild.as_ref().is_none_or(|guild| guild == g)
+                        });
+                        if !found {
+                            errors.push(ParamError::new(&param.id, "unknownChannel", format!("Discord channel '{text}' is not in the channel cache")));
+                        }
                     }
-                } else {
-                    errors.push("invalid validation pattern".into());
                 }
+                _ => {}
+            }
+        }
+    }
+}
+
+fn split_list(text: &str) -> Vec<String> {
+    text.split([',', '\n'])
+        .map(str::trim)
+        .filter(|item| !item.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+/// Validate `params` against the recipe's declared param types.
+///
+/// A param with `dependsOn` is only checked (and only required) while its
+/// parent param is truthy.
+pub fn validate_with(recipe: &Recipe, params: &Map<String, Value>, ctx: &ParamContext) -> Vec<ParamError> {
+    let mut errors = Vec::new();
+    for p in &recipe.params {
+        if !param_active(recipe, p, params) {
+            continue;
+        }
+        let value = params.get(&p.id);
+        if is_blank_param(value) {
+            if p.required {
+                errors.push(ParamError::new(&p.id, "required", format!("{} is required", p.label)));
             }
+            continue;
+        }
+        if let Some(value) = value {
+            check_param(recipe, p, value, params, ctx, &mut errors);
         }
     }
     errors
 }
 
-fn render_patch_template(template: &str, params: &Map<String, Value>) -> String {
-    let mut text = template.to_string();
-    for (k, v) in params {
-        let placeholder = format!("{{{{{}}}}}", k);
-        let replacement = match v {
-            Value::String(s) => s.clone(),
-            _ => v.to_string(),
+pub fn validate(recipe: &Recipe, params: &Map<String, Value>) -> Vec<ParamError> {
+    validate_with(recipe, params, &ParamContext::default())
+}
+
+/// Convert validated params to their declared JSON types: booleans and numbers
+/// become typed, lists become arrays. Inactive `dependsOn` params are blanked.
+pub fn coerce_params(recipe: &Recipe, params: &Map<String, Value>) -> Map<String, Value> {
+    let mut out = params.clone();
+    for p in &recipe.params {
+        let Some(value) = params.get(&p.id) else { continue };
+        if !param_active(recipe, p, params) {
+            out.insert(p.id.clone(), Value::String(String::new()));
+            continue;
+        }
+        let coerced = match (p.kind.as_str(), value) {
+            ("boolean", Value::String(s)) if matches!(s.trim(), "true" | "false") => {
+                Value::Bool(s.trim() == "true")
+            }
+            ("number", Value::String(s)) => s
+                .trim()
+                .parse::<f64>()
+                .ok()
+                .and_then(|n| {
+                    if n.fract() == 0.0 && n.abs() < i64::MAX as f64 {
+                        Some(Value::from(n as i64))
+                    } else {
+                        serde_json::Number::from_f64(n).map(Value::Number)
+                    }
+                })
+                .unwrap_or_else(|| value.clone()),
+            ("list", Value::String(s)) => {
+                Value::Array(split_list(s).into_iter().map(Value::String).collect())
+            }
+            _ => value.clone(),
         };
-        text = text.replace(&placeholder, &replacement);
+        out.insert(p.id.clone(), coerced);
     }
-    text
+    out
 }
 
 pub fn build_candidate_config_from_template(
@@ -233,85 +573,1001 @@ pub fn build_candidate_config_from_template(
     template: &str,
     params: &Map<String, Value>,
 ) -> Result<(Value, Vec<ChangeItem>), String> {
-    let rendered = render_patch_template(template, params);
-    let patch: Value = json5::from_str(&rendered).map_err(|e| e.to_string())?;
+    let patch = crate::recipe_template::render_template(template, params)?;
     let mut merged = current.clone();
-    let mut changes = Vec::new();
-    apply_merge_patch(&mut merged, &patch, "", &mut changes);
+    crate::json_patch::merge_patch(&mut merged, &patch);
+    let changes = collect_change_paths(current, &merged);
     Ok((merged, changes))
 }
 
-fn apply_merge_patch(target: &mut Value, patch: &Value, prefix: &str, changes: &mut Vec<ChangeItem>) {
-    if patch.is_object() && target.is_object() {
-        let t = target.as_object_mut().unwrap();
-        for (k, pv) in patch.as_object().unwrap() {
-            let path = if prefix.is_empty() {
-                k.clone()
+/// One `ChangeItem` per JSON path that differs between the two documents.
+pub fn collect_change_paths(current: &Value, patched: &Value) -> Vec<ChangeItem> {
+    crate::json_diff::diff_values(current, patched)
+}
+
+/// Unified diff of the pretty-printed documents.
+pub fn format_diff(before: &Value, after: &Value) -> String {
+    let before_text = serde_json::to_string_pretty(before).unwrap_or_else(|_| "{}".into());
+    let after_text = serde_json::to_string_pretty(after).unwrap_or_else(|_| "{}".into());
+    crate::json_diff::unified_diff(&before_text, &after_text, 3)
+}
+
+/// Render a step's args. `patchTemplate` is kept verbatim: it is parsed
+/// first and rendered at the value level by the `config_patch` action.
+fn render_step_args(args: &Map<String, Value>, params: &Map<String, Value>) -> Result<Map<String, Value>, String> {
+    args.iter()
+        .map(|(key, value)| {
+            let rendered = if key == "patchTemplate" {
+                value.clone()
             } else {
-                format!("{}.{}", prefix, k)
+                crate::recipe_template::render_value(value, params)?
             };
-            match pv {
-                Value::Null => {
-                    if t.remove(k).is_some() {
-                        changes.push(ChangeItem {
-                            path: path.clone(),
-                            op: "remove".into(),
-                            risk: "medium".into(),
-                            reason: None,
-                        });
+            Ok((key.clone(), rendered))
+        })
+        .collect()
+}
+
+/// Loop variables set for each item of a `forEach` step.
+pub const LOOP_VARS: &[&str] = &["item", "index"];
+
+/// Problems with a step's `when` and `forEach`, as `(field, message)` pairs.
+pub fn check_step(recipe: &Recipe, step: &RecipeStep) -> Vec<(&'static str, String)> {
+    let mut problems = Vec::new();
+    let declared = |id: &str| recipe.params.iter().find(|p| p.id == id);
+    if let Some(when) = &step.when {
+        match crate::recipe_expr::parse(when) {
+            Err(e) => problems.push(("when", format!("invalid when expression {when:?}: {e}"))),
+            Ok(expr) => {
+                for name in expr.params() {
+                    if declared(&name).is_none() {
+                        problems.push(("when", format!("when refers to undeclared param {name:?}")));
                     }
                 }
-                _ => {
-                    if let Some(tv) = t.get_mut(k) {
-                        if tv.is_object() && pv.is_object() {
-                            apply_merge_patch(tv, pv, &path, changes);
-                        } else {
-                            *tv = pv.clone();
-                            changes.push(ChangeItem {
-                                path,
-                                op: "replace".into(),
-                                risk: "low".into(),
-                                reason: None,
-                            });
-                        }
-                    } else {
-                        t.insert(k.clone(), pv.clone());
-                        changes.push(ChangeItem {
-                            path,
-                            op: "add".into(),
-                            risk: "low".into(),
-                            reason: None,
-                        });
-                    }
+            }
+        }
+    }
+    if let Some(list) = &step.for_each {
+        match declared(list) {
+            None => problems.push(("forEach", format!("forEach refers to undeclared param {list:?}"))),
+            Some(param) if param.kind != "list" => problems.push((
+                "forEach",
+                format!("forEach param {list:?} must be a list param, not {:?}", param.kind),
+            )),
+            Some(_) => {}
+        }
+        for var in LOOP_VARS.iter().filter(|v| declared(v).is_some()) {
+            problems.push(("forEach", format!("param {var:?} is shadowed by the forEach loop variable")));
+        }
+    }
+    problems
+}
+
+fn list_items(value: Option<&Value>) -> Vec<Value> {
+    match value {
+        Some(Value::Array(items)) => items.clone(),
+        Some(Value::String(text)) => split_list(text).into_iter().map(Value::String).collect(),
+        Some(Value::Null) | None => Vec::new(),
+        Some(other) => vec![other.clone()],
+    }
+}
+
+/// Render a step for each item it runs with. `when` is evaluated against
+/// the params before any loop variables are set.
+pub fn resolve_steps(recipe: &Recipe, params: &Map<String, Value>) -> Result<Vec<ResolvedStep>, String> {
+    let mut resolved = Vec::new();
+    for (index, step) in recipe.steps.iter().enumerate() {
+        let context = |e: String| format!("step {} ({}): {e}", index + 1, step.label);
+        if let Some((_, problem)) = check_step(recipe, step).into_iter().next() {
+            return Err(context(problem));
+        }
+        let skipped = |reason: String| ResolvedStep {
+            index,
+            item_index: None,
+            action: step.action.clone(),
+            label: step.label.clone(),
+            args: step.args.clone(),
+            on_error: step.on_error,
+            skip_reason: Some(reason),
+            scope: params.clone(),
+        };
+        if let Some(when) = &step.when {
+            if !crate::recipe_expr::parse(when).map_err(context)?.eval(params) {
+                resolved.push(skipped(format!("skipped: `{when}` is false")));
+                continue;
+            }
+        }
+        let scopes: Vec<(Option<usize>, Map<String, Value>)> = match &step.for_each {
+            None => vec![(None, params.clone())],
+            Some(list) => {
+                let items = list_items(params.get(list));
+                if items.is_empty() {
+                    resolved.push(skipped(format!("skipped: {list} is empty")));
+                    continue;
                 }
+                items
+                    .into_iter()
+                    .enumerate()
+                    .map(|(i, item)| {
+                        let mut scope = params.clone();
+                        scope.insert("item".into(), item);
+                        scope.insert("index".into(), Value::from(i));
+                        (Some(i), scope)
+                    })
+                    .collect()
             }
+        };
+        for (item_index, scope) in scopes {
+            let mut label = crate::recipe_template::render_string(&step.label, &scope).map_err(context)?;
+            if let (Some(_), Some(item)) = (item_index, scope.get("item")) {
+                if label == step.label {
+                    label = format!("{label} ({})", item.as_str().map_or_else(|| item.to_string(), str::to_string));
+                }
+            }
+            resolved.push(ResolvedStep {
+                index,
+                item_index,
+                action: step.action.clone(),
+                label,
+                args: render_step_args(&step.args, &scope).map_err(context)?,
+                on_error: step.on_error,
+                skip_reason: None,
+                scope,
+            });
         }
+    }
+    Ok(resolved)
+}
+
+fn arg_str<'a>(args: &'a Map<String, Value>, key: &str) -> &'a str {
+    args.get(key).and_then(Value::as_str).map(str::trim).unwrap_or("")
+}
+
+fn arg_bool(args: &Map<String, Value>, key: &str) -> bool {
+    match args.get(key) {
+        Some(Value::Bool(b)) => *b,
+        Some(Value::String(s)) => s.trim().eq_ignore_ascii_case("true"),
+        _ => false,
+    }
+}
+
+fn agent_ids(cfg: &Value) -> Vec<String> {
+    let mut ids: Vec<String> = cfg
+        .pointer("/agents/list")
+        .and_then(Value::as_array)
+        .map(|list| {
+            list.iter()
+                .filter_map(|a| a.get("id").and_then(Value::as_str))
+                .map(str::to_string)
+                .collect()
+        })
+        .unwrap_or_default();
+    // Implicit "main" agent when no agents.list
+    if ids.is_empty() {
+        ids.push("main".into());
+    }
+    ids
+}
+
+/// The existing agent `id` refers to. Agent ids are compared ignoring case,
+/// the same rule `create_agent` uses to reject duplicates.
+fn find_agent_id(cfg: &Value, id: &str) -> Option<String> {
+    agent_ids(cfg).into_iter().find(|existing| existing.eq_ignore_ascii_case(id))
+}
+
+fn default_workspace(cfg: &Value) -> Option<String> {
+    cfg.pointer("/agents/defaults/workspace")
+        .or_else(|| cfg.pointer("/agents/default/workspace"))
+        .and_then(Value::as_str)
+        .map(str::to_string)
+}
+
+fn step_create_agent(cfg: &mut Value, args: &Map<String, Value>, env: &RecipeEnv) -> Result<StepOutcome, String> {
+    let agent_id = arg_str(args, "agentId").to_string();
+    if agent_id.is_empty() {
+        return Err("Agent ID is required".into());
+    }
+    if !agent_id.chars().all(|c| c.is_alphanumeric() || c == '-' || c == '_') {
+        return Err("Agent ID may only contain letters, numbers, hyphens, and underscores".into());
+    }
+    if find_agent_id(cfg, &agent_id).is_some() {
+        return Err(format!("Agent '{}' already exists", agent_id));
+    }
+
+    let profile_id = arg_str(args, "modelProfileId");
+    let model = if profile_id.is_empty() || profile_id == "__default__" {
+        None
     } else {
-        *target = patch.clone();
-        changes.push(ChangeItem {
-            path: prefix.to_string(),
-            op: "replace".into(),
-            risk: "medium".into(),
-            reason: None,
+        // Unknown profile ids are passed through as raw model values, like the UI does.
+        Some(env.model_values.get(profile_id).cloned().unwrap_or_else(|| profile_id.to_string()))
+    };
+
+    let mut outcome = StepOutcome::default();
+    let workspace = if arg_bool(args, "independent") {
+        let ws_dir = env.base_dir.join("workspaces").join(&agent_id);
+        outcome.files.push(FileEffect::CreateDir(ws_dir.clone()));
+        Some(ws_dir.to_string_lossy().to_string())
+    } else {
+        default_workspace(cfg)
+    };
+
+    let mut agent_obj = Map::new();
+    agent_obj.insert("id".into(), Value::String(agent_id.clone()));
+    if let Some(model) = model {
+        agent_obj.insert("model".into(), Value::String(model));
+    }
+    if let Some(ws) = workspace {
+        agent_obj.insert("workspace".into(), Value::String(ws));
+    }
+
+    let list = cfg
+        .as_object_mut()
+        .ok_or("config is not an object")?
+        .entry("agents")
+        .or_insert_with(|| Value::Object(Map::new()))
+        .as_object_mut()
+        .ok_or("agents is not an object")?
+        .entry("list")
+        .or_insert_with(|| Value::Array(Vec::new()))
+        .as_array_mut()
+        .ok_or("agents.list is not an array")?;
+    list.push(Value::Object(agent_obj));
+
+    outcome.changes.push(ChangeItem {
+        path: format!("agents.list[{}]", list.len() - 1),
+        op: "add".into(),
+        risk: "low".into(),
+        reason: Some(format!("create agent {agent_id}")),
+    });
+    Ok(outcome)
+}
+
+fn step_setup_identity(cfg: &Value, args: &Map<String, Value>) -> Result<StepOutcome, String> {
+    let agent_id = arg_str(args, "agentId");
+    let name = arg_str(args, "name");
+    if agent_id.is_empty() {
+        return Err("Agent ID is required".into());
+    }
+    if name.is_empty() {
+        return Ok(StepOutcome {
+            skipped: true,
+            warnings: vec!["no display name given; identity left unchanged".into()],
+            ..StepOutcome::default()
         });
     }
+
+    let agent_id = find_agent_id(cfg, agent_id).ok_or_else(|| format!("Agent '{}' not found", agent_id))?;
+    let agent = cfg
+        .pointer("/agents/list")
+        .and_then(Value::as_array)
+        .and_then(|list| {
+            list.iter()
+                .find(|a| a.get("id").and_then(Value::as_str) == Some(agent_id.as_str()))
+        })
+        .ok_or_else(|| format!("Agent '{}' not found", agent_id))?;
+    let workspace = agent
+        .get("workspace")
+        .and_then(Value::as_str)
+        .map(str::to_string)
+        .or_else(|| default_workspace(cfg))
+        .ok_or_else(|| format!("Agent '{}' has no workspace configured", agent_id))?;
+
+    let mut content = format!("- Name: {}\n", name);
+    let emoji = arg_str(args, "emoji");
+    if !emoji.is_empty() {
+        content.push_str(&format!("- Emoji: {}\n", emoji));
+    }
+    let ws_path = expand_user_path(&workspace);
+    Ok(StepOutcome {
+        files: vec![
+            FileEffect::CreateDir(ws_path.clone()),
+            FileEffect::WriteFile { path: ws_path.join("IDENTITY.md"), content },
+        ],
+        ..StepOutcome::default()
+    })
 }
 
-pub fn collect_change_paths(current: &Value, patched: &Value) -> Vec<ChangeItem> {
-    if current == patched {
-        Vec::new()
-    } else {
-        vec![ChangeItem {
-            path: "root".to_string(),
-            op: "replace".to_string(),
-            risk: "medium".to_string(),
-            reason: None,
-        }]
+fn step_bind_channel(cfg: &mut Value, args: &Map<String, Value>) -> Result<StepOutcome, String> {
+    let channel_type = arg_str(args, "channelType").to_string();
+    let peer_id = arg_str(args, "peerId").to_string();
+    let agent_id = arg_str(args, "agentId").to_string();
+    if channel_type.is_empty() || peer_id.is_empty() || agent_id.is_empty() {
+        return Err("bind_channel requires channelType, peerId and agentId".into());
+    }
+    let agent_id = find_agent_id(cfg, &agent_id).ok_or_else(|| format!("Agent '{}' not found", agent_id))?;
+
+    let bindings = cfg
+        .as_object_mut()
+        .ok_or("config is not an object")?
+        .entry("bindings")
+        .or_insert_with(|| Value::Array(Vec::new()))
+        .as_array_mut()
+        .ok_or("bindings is not an array")?;
+    // Replace any existing binding for the same channel+peer
+    let before = bindings.len();
+    bindings.retain(|b| {
+        let m = match b.get("match") {
+            Some(m) => m,
+            None => return true,
+        };
+        !(m.get("channel").and_then(Value::as_str) == Some(channel_type.as_str())
+            && m.pointer("/peer/id").and_then(Value::as_str) == Some(peer_id.as_str()))
+    });
+    let replaced = bindings.len() != before;
+    bindings.push(serde_json::json!({
+        "agentId": agent_id,
+        "match": { "channel": channel_type, "peer": { "kind": "channel", "id": peer_id } },
+    }));
+
+    let mut outcome = StepOutcome::default();
+    if replaced {
+        outcome.warnings.push(format!("replaced existing binding for {channel_type}:{peer_id}"));
     }
+    outcome.changes.push(ChangeItem {
+        path: format!("bindings[{}]", bindings.len() - 1),
+        op: "add".into(),
+        risk: "low".into(),
+        reason: Some(format!("bind {channel_type}:{peer_id} to {agent_id}")),
+    });
+    Ok(outcome)
 }
 
-pub fn format_diff(before: &Value, after: &Value) -> String {
-    let before_text = serde_json::to_string_pretty(before).unwrap_or_else(|_| "{}".into());
-    let after_text = serde_json::to_string_pretty(after).unwrap_or_else(|_| "{}".into());
-    format!("before:\n{}\n\nafter:\n{}", before_text, after_text)
+/// Apply a single resolved step to `cfg` in memory. File effects are returned,
+/// not performed.
+pub fn apply_step(
+    cfg: &mut Value,
+    step: &ResolvedStep,
+    params: &Map<String, Value>,
+    env: &RecipeEnv,
+) -> Result<StepOutcome, String> {
+    match step.action.as_str() {
+        "create_agent" => step_create_agent(cfg, &step.args, env),
+        "setup_identity" => step_setup_identity(cfg, &step.args),
+        "bind_channel" => step_bind_channel(cfg, &step.args),
+        "config_patch" => {
+            // Patch templates are rendered with the raw params, not the step args.
+            let template = step
+                .args
+                .get("patchTemplate")
+                .and_then(Value::as_str)
+                .ok_or("config_patch requires patchTemplate")?;
+            let (next, changes) = build_candidate_config_from_template(cfg, template, params)?;
+            *cfg = next;
+            Ok(StepOutcome { changes, ..StepOutcome::default() })
+        }
+        "json_patch" => {
+            // Operations are rendered like any other arg, so values keep their type.
+            let operations = step.args.get("operations").ok_or("json_patch requires operations")?;
+            let ops = crate::json_patch::parse_patch(operations)?;
+            let before = cfg.clone();
+            crate::json_patch::apply_patch(cfg, &ops)?;
+            Ok(StepOutcome { changes: collect_change_paths(&before, cfg), ..StepOutcome::default() })
+        }
+        other => Err(format!("unknown recipe action: {other}")),
+    }
+}
+
+enum FileUndo {
+    RemoveDir(PathBuf),
+    RemoveFile(PathBuf),
+    Restore(PathBuf, String),
+}
+
+/// Perform file effects in order, logging how to revert each one.
+fn perform_file_effects(effects: &[FileEffect], undo: &mut Vec<FileUndo>) -> Result<(), String> {
+    for effect in effects {
+        match effect {
+            FileEffect::CreateDir(dir) => {
+                if !dir.exists() {
+                    // Undo removes every directory created here, not just the leaf.
+                    let mut top = dir.as_path();
+                    while let Some(parent) = top.parent().filter(|p| !p.as_os_str().is_empty() && !p.exists()) {
+                        top = parent;
+                    }
+                    fs::create_dir_all(dir)
+                        .map_err(|e| format!("Failed to create {}: {e}", dir.display()))?;
+                    undo.push(FileUndo::RemoveDir(top.to_path_buf()));
+                }
+            }
+            FileEffect::WriteFile { path, content } => {
+                let previous = fs::read_to_string(path).ok();
+                fs::write(path, content)
+                    .map_err(|e| format!("Failed to write {}: {e}", path.display()))?;
+                undo.push(match previous {
+                    Some(text) => FileUndo::Restore(path.clone(), text),
+                    None => FileUndo::RemoveFile(path.clone()),
+                });
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Best effort: the blob is left for `gc`.
+fn discard_snapshot(paths: &crate::models::OpenClawPaths, id: &str) {
+    use crate::snapshot_store::{run_local, LocalSnapshotStore, SnapshotStore};
+    let store = LocalSnapshotStore::for_paths(paths);
+    if let Ok(mut index) = run_local(store.read_index()) {
+        index.items.retain(|item| item.id != id);
+        let _ = run_local(store.write_index(&index));
+    }
+}
+
+fn undo_file_effects(undo: Vec<FileUndo>) {
+    for entry in undo.into_iter().rev() {
+        match entry {
+            FileUndo::RemoveDir(dir) => {
+                let _ = fs::remove_dir_all(dir);
+            }
+            FileUndo::RemoveFile(path) => {
+                let _ = fs::remove_file(path);
+            }
+            FileUndo::Restore(path, text) => {
+                let _ = fs::write(path, text);
+            }
+        }
+    }
+}
+
+fn step_result(step: &ResolvedStep, status: &str, config_path: &str, warnings: Vec<String>, errors: Vec<String>) -> RecipeStepResult {
+    RecipeStepResult {
+        index: step.index,
+        action: step.action.clone(),
+        label: step.label.clone(),
+        status: status.into(),
+        result: ApplyResult {
+            ok: errors.is_empty(),
+            snapshot_id: None,
+            config_path: config_path.to_string(),
+            backup_path: None,
+            warnings,
+            errors,
+        },
+    }
+}
+
+/// Every step of a recipe applied to an in-memory copy of the config.
+pub struct RecipePlan {
+    pub config: Value,
+    pub steps: Vec<RecipeStepResult>,
+    pub files: Vec<FileEffect>,
+    pub changes: Vec<ChangeItem>,
+    pub warnings: Vec<String>,
+    pub failed: bool,
+}
+
+/// Apply the steps of `recipe` to a copy of `current` without touching disk.
+///
+/// `params` must already be validated and coerced. A failing step with
+/// `onError: continue` is reported and skipped; after any other failure the
+/// remaining steps are reported as "notRun".
+pub fn plan_recipe(
+    current: &Value,
+    recipe: &Recipe,
+    params: &Map<String, Value>,
+    env: &RecipeEnv,
+    config_path: &str,
+) -> Result<RecipePlan, String> {
+    let steps = resolve_steps(recipe, params)?;
+    let mut plan = RecipePlan {
+        config: current.clone(),
+        steps: Vec::new(),
+        files: Vec::new(),
+        changes: Vec::new(),
+        warnings: Vec::new(),
+        failed: false,
+    };
+    for step in &steps {
+        if plan.failed {
+            plan.steps.push(step_result(step, "notRun", config_path, Vec::new(), Vec::new()));
+            continue;
+        }
+        if let Some(reason) = &step.skip_reason {
+            plan.steps.push(step_result(step, "skipped", config_path, vec![reason.clone()], Vec::new()));
+            continue;
+        }
+        // Work on a copy so a failing `onError: continue` step leaves no partial edit.
+        let mut next = plan.config.clone();
+        match apply_step(&mut next, step, &step.scope, env) {
+            Ok(outcome) => {
+                plan.config = next;
+                let status = if outcome.skipped { "skipped" } else { "applied" };
+                plan.files.extend(outcome.files);
+                plan.changes.extend(outcome.changes);
+                plan.warnings.extend(outcome.warnings.iter().cloned());
+                plan.steps.push(step_result(step, status, config_path, outcome.warnings, Vec::new()));
+            }
+            Err(e) => {
+                plan.failed = step.on_error == OnError::Abort;
+                plan.steps.push(step_result(step, "failed", config_path, Vec::new(), vec![e]));
+            }
+        }
+    }
+    Ok(plan)
+}
+
+/// Summarise a plan for the confirm screen.
+pub fn plan_preview(recipe_id: &str, current: &Value, plan: &RecipePlan) -> Result<PreviewResult, String> {
+    let mut changes = plan.changes.clone();
+    let assessment = crate::risk::assess(&mut changes);
+    let mut warnings = assessment.warnings;
+    warnings.extend(plan.warnings.iter().cloned());
+    for step in plan.steps.iter().filter(|s| s.status == "failed") {
+        warnings.extend(step.result.errors.iter().map(|e| format!("{}: {e}", step.label)));
+    }
+    Ok(PreviewResult {
+        recipe_id: recipe_id.to_string(),
+        diff: format_diff(current, &plan.config),
+        config_before: serde_json::to_string_pretty(current).map_err(|e| e.to_string())?,
+        config_after: serde_json::to_string_pretty(&plan.config).map_err(|e| e.to_string())?,
+        overwrites_existing: changes.iter().any(|c| c.op != "add"),
+        can_rollback: true,
+        impact_level: assessment.impact_level,
+        changes,
+        warnings,
+        files: plan.files.iter().map(describe_file_effect).collect(),
+    })
+}
+
+fn describe_file_effect(effect: &FileEffect) -> String {
+    match effect {
+        FileEffect::CreateDir(dir) => format!("create directory {}", dir.display()),
+        FileEffect::WriteFile { path, .. } => format!("write {}", path.display()),
+    }
+}
+
+/// Dry run of a whole recipe against `current`. Nothing is written.
+pub fn preview_recipe(
+    current: &Value,
+    recipe: &Recipe,
+    params: &Map<String, Value>,
+    env: &RecipeEnv,
+    config_path: &str,
+) -> Result<PreviewResult, String> {
+    let errors = validate_with(recipe, params, &env.params);
+    if !errors.is_empty() {
+        let messages: Vec<String> = errors.iter().map(|e| format!("{}: {}", e.param_id, e.message)).collect();
+        return Err(format!("invalid params: {}", messages.join("; ")));
+    }
+    let params = coerce_params(recipe, params);
+    let plan = plan_recipe(current, recipe, &params, env, config_path)?;
+    plan_preview(&recipe.id, current, &plan)
+}
+
+/// Preview of replacing `current` with `target` wholesale.
+pub fn replacement_preview(label: &str, current: &Value, target: &Value, mut warnings: Vec<String>) -> PreviewResult {
+    let mut changes = collect_change_paths(current, target);
+    let assessment = crate::risk::assess(&mut changes);
+    warnings.extend(assessment.warnings);
+    PreviewResult {
+        recipe_id: label.to_string(),
+        diff: format_diff(current, target),
+        config_before: serde_json::to_string_pretty(current).unwrap_or_else(|_| "{}".into()),
+        config_after: serde_json::to_string_pretty(target).unwrap_or_else(|_| "{}".into()),
+        overwrites_existing: changes.iter().any(|c| c.op != "add"),
+        changes,
+        can_rollback: true,
+        impact_level: assessment.impact_level,
+        warnings,
+        files: Vec::new(),
+    }
+}
+
+/// Preview of replacing `current` with a snapshot's contents.
+pub fn rollback_preview(current: &Value, target: &Value) -> PreviewResult {
+    let mut preview = replacement_preview(
+        "rollback",
+        current,
+        target,
+        vec!["Rollback will replace current configuration".to_string()],
+    );
+    preview.overwrites_existing = true;
+    preview
+}
+
+/// Recipes from an untrusted source only run once the user has allowed it
+/// for that run; nothing else stops one from rewriting `gateway.auth`.
+pub fn ensure_runnable(recipe: &Recipe, allow_untrusted: bool) -> Result<(), String> {
+    match &recipe.source {
+        Some(source) if crate::recipe_registry::is_untrusted(recipe) && !allow_untrusted => Err(format!(
+            "recipe {} comes from an untrusted source ({}); allow it explicitly to run it",
+            recipe.id, source.location
+        )),
+        _ => Ok(()),
+    }
+}
+
+/// Run every step of `recipe` against the local config as one transaction.
+///
+/// Steps are applied in order to an in-memory copy of the config. Only when all
+/// of them succeed are the file effects performed, a single snapshot taken and
+/// the config written. A failure anywhere leaves config, filesystem and history
+/// as they were.
+pub fn execute_recipe(
+    paths: &crate::models::OpenClawPaths,
+    recipe: &Recipe,
+    params: &Map<String, Value>,
+    env: &RecipeEnv,
+) -> Result<RecipeExecution, String> {
+    let param_errors = validate_with(recipe, params, &env.params);
+    if !param_errors.is_empty() {
+        return Ok(RecipeExecution {
+            recipe_id: recipe.id.clone(),
+            ok: false,
+            snapshot_id: None,
+            rolled_back: false,
+            steps: Vec::new(),
+            param_errors,
+            install_id: None,
+        });
+    }
+    let params = &coerce_params(recipe, params);
+
+    crate::config_io::ensure_dirs(paths)?;
+    let base = crate::config_cas::read_base(paths)?;
+    let current = base.value.clone();
+    let config_path = paths.config_path.to_string_lossy().to_string();
+    let RecipePlan { config: working, steps: mut results, files: effects, failed, .. } =
+        plan_recipe(&current, recipe, params, env, &config_path)?;
+    if failed {
+        // Nothing has touched disk yet, so there is nothing to undo.
+        return Ok(RecipeExecution {
+            recipe_id: recipe.id.clone(),
+            ok: false,
+            snapshot_id: None,
+            rolled_back: true,
+            steps: results,
+            param_errors: Vec::new(),
+            install_id: None,
+        });
+    }
+
+    let mut started = false;
+    let mut snapshot = None;
+    let mut undo = Vec::new();
+    let committed = crate::config_cas::commit(paths, &base, &working, |current_text| {
+        started = true;
+        perform_file_effects(&effects, &mut undo)?;
+        snapshot = Some(crate::history::add_snapshot(
+            &paths.history_dir,
+            &paths.metadata_path,
+            Some(recipe.id.clone()),
+            "recipe",
+            true,
+            current_text,
+            None,
+        )?);
+        Ok(())
+    });
+    if let Err(e) = committed {
+        if !started {
+            // Stopped before anything was written, e.g. on a conflicting outside edit.
+            return Err(e);
+        }
+        // The config write is the last, atomic operation, so only files need
+        // undoing, and a snapshot of a run that changed nothing is dropped.
+        undo_file_effects(undo);
+        if let Some(snapshot) = &snapshot {
+            discard_snapshot(paths, &snapshot.id);
+        }
+        for result in &mut results {
+            result.status = "failed".into();
+            result.result.ok = false;
+            result.result.errors.push(format!("rolled back: {e}"));
+        }
+        return Ok(RecipeExecution {
+            recipe_id: recipe.id.clone(),
+            ok: false,
+            snapshot_id: None,
+            rolled_back: true,
+            steps: results,
+            param_errors: Vec::new(),
+            install_id: None,
+        });
+    }
+    let snapshot = snapshot.ok_or_else(|| "snapshot was not recorded".to_string())?;
+
+    for result in &mut results {
+        result.result.snapshot_id = Some(snapshot.id.clone());
+        result.result.backup_path = Some(snapshot.config_path.clone());
+    }
+
+    // The config is already written; a failure to record the install only
+    // means it cannot be uninstalled selectively later.
+    let install = crate::recipe_install::new_install(
+        &recipe.id,
+        &recipe.version,
+        Some(snapshot.id.clone()),
+        &current,
+        &working,
+    );
+    let installs_path = crate::recipe_install::installs_path(paths);
+    let mut index = crate::recipe_install::read_installs(&installs_path);
+    let install_id = install.id.clone();
+    index.installs.push(install);
+    let install_id = crate::recipe_install::write_installs(&installs_path, &index)
+        .ok()
+        .map(|_| install_id);
+
+    Ok(RecipeExecution {
+        recipe_id: recipe.id.clone(),
+        ok: true,
+        snapshot_id: Some(snapshot.id),
+        rolled_back: false,
+        steps: results,
+        param_errors: Vec::new(),
+        install_id,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn params(value: Value) -> Map<String, Value> {
+        value.as_object().cloned().unwrap()
+    }
+
+    fn dedicated_agent() -> Recipe {
+        builtin_recipes()
+            .into_iter()
+            .find(|r| r.id == "dedicated-channel-agent")
+            .unwrap()
+    }
+
+    #[test]
+    fn dedicated_agent_steps_apply_in_memory() {
+        let recipe = dedicated_agent();
+        let params = params(json!({
+            "agent_id": "helper", "model": "p1", "guild_id": "g1", "channel_id": "c1",
+            "independent": "true", "name": "Helper", "emoji": "", "persona": "Be nice",
+        }));
+        let env = RecipeEnv {
+            base_dir: PathBuf::from("/oc"),
+            model_values: HashMap::from([("p1".to_string(), "openai/gpt-4o".to_string())]),
+            ..RecipeEnv::default()
+        };
+        let mut cfg = json!({});
+        let mut files = Vec::new();
+        for step in resolve_steps(&recipe, &params).unwrap() {
+            files.extend(apply_step(&mut cfg, &step, &params, &env).unwrap().files);
+        }
+        assert_eq!(cfg.pointer("/agents/list/0/model"), Some(&json!("openai/gpt-4o")));
+        assert_eq!(cfg.pointer("/bindings/0/agentId"), Some(&json!("helper")));
+        assert_eq!(
+            cfg.pointer("/channels/discord/guilds/g1/channels/c1/systemPrompt"),
+            Some(&json!("Be nice"))
+        );
+        assert!(files.contains(&FileEffect::WriteFile {
+            path: PathBuf::from("/oc/workspaces/helper/IDENTITY.md"),
+            content: "- Name: Helper\n".into(),
+        }));
+    }
+
+    #[test]
+    fn preview_simulates_every_step_without_touching_disk() {
+        let paths = crate::models::test_paths("recipe");
+        let env = RecipeEnv { base_dir: paths.base_dir.clone(), ..RecipeEnv::default() };
+        let current = json!({ "agents": { "list": [{ "id": "main" }] } });
+        let input = params(json!({
+            "agent_id": "helper", "model": "__default__", "guild_id": "g1", "channel_id": "c1",
+            "independent": "true", "name": "Helper",
+        }));
+        let preview = preview_recipe(&current, &dedicated_agent(), &input, &env, "openclaw.json").unwrap();
+        let paths_changed: Vec<&str> = preview.changes.iter().map(|c| c.path.as_str()).collect();
+        assert!(paths_changed.contains(&"agents.list[1]"));
+        assert!(paths_changed.contains(&"bindings[0]"));
+        assert!(preview.diff.lines().any(|l| l.starts_with('+') && l.contains("\"helper\"")));
+        assert!(!preview.files.is_empty());
+        assert!(!paths.base_dir.exists());
+
+        let missing = params(json!({ "agent_id": "" }));
+        assert!(preview_recipe(&current, &dedicated_agent(), &missing, &env, "openclaw.json").is_err());
+    }
+
+    #[test]
+    fn executed_recipe_can_be_uninstalled_keeping_later_edits() {
+        let paths = crate::models::test_paths("recipe");
+        crate::config_io::write_text(&paths.config_path, r#"{"agents":{"list":[{"id":"main"}]}}"#).unwrap();
+        let input = params(json!({
+            "agent_id": "helper", "model": "__default__", "guild_id": "g1", "channel_id": "c1",
+        }));
+        let env = RecipeEnv { base_dir: paths.base_dir.clone(), ..RecipeEnv::default() };
+        let execution = execute_recipe(&paths, &dedicated_agent(), &input, &env).unwrap();
+        let install_id = execution.install_id.expect("install recorded");
+
+        let mut cfg = crate::config_io::read_openclaw_config(&paths).unwrap();
+        cfg["gateway"] = json!({ "port": 18789 });
+        crate::config_io::write_json(&paths.config_path, &cfg).unwrap();
+
+        let result = crate::recipe_install::uninstall(&paths, &install_id, false).unwrap();
+        assert!(result.ok && result.conflicts.is_empty());
+        let cfg = crate::config_io::read_openclaw_config(&paths).unwrap();
+        assert_eq!(cfg.pointer("/agents/list"), Some(&json!([{ "id": "main" }])));
+        assert_eq!(cfg.pointer("/gateway/port"), Some(&json!(18789)));
+        let _ = fs::remove_dir_all(paths.clawpal_dir.parent().unwrap());
+    }
+
+    #[test]
+    fn failing_step_leaves_config_and_workspace_untouched() {
+        let paths = crate::models::test_paths("recipe");
+        let original = r#"{"agents":{"list":[{"id":"main"}]}}"#;
+        crate::config_io::write_text(&paths.config_path, original).unwrap();
+        let mut recipe = dedicated_agent();
+        // bind to an agent that never gets created
+        recipe.steps[2].args.insert("agentId".into(), json!("ghost"));
+        let params = params(json!({
+            "agent_id": "helper", "model": "__default__", "guild_id": "g1", "channel_id": "c1",
+            "independent": "true", "name": "Helper", "persona": "",
+        }));
+        let env = RecipeEnv { base_dir: paths.base_dir.clone(), ..RecipeEnv::default() };
+
+        let execution = execute_recipe(&paths, &recipe, &params, &env).unwrap();
+        assert!(!execution.ok);
+        assert!(execution.rolled_back);
+        let statuses: Vec<_> = execution.steps.iter().map(|s| s.status.as_str()).collect();
+        assert_eq!(statuses, vec!["applied", "applied", "failed", "notRun"]);
+        assert_eq!(fs::read_to_string(&paths.config_path).unwrap(), original);
+        assert!(!paths.base_dir.join("workspaces").join("helper").exists());
+        assert!(crate::history::list_snapshots(&paths.metadata_path).unwrap().items.is_empty());
+        let _ = fs::remove_dir_all(paths.clawpal_dir.parent().unwrap());
+    }
+
+    #[test]
+    fn failing_file_effect_leaves_no_snapshot_or_directories() {
+        let paths = crate::models::test_paths("recipe");
+        let original = r#"{"agents":{"list":[{"id":"main"}]}}"#;
+        crate::config_io::write_text(&paths.config_path, original).unwrap();
+        // A directory where the identity file goes makes writing it fail.
+        fs::create_dir_all(paths.base_dir.join("workspaces/helper/IDENTITY.md")).unwrap();
+        let input = params(json!({
+            "agent_id": "helper", "model": "__default__", "guild_id": "g1", "channel_id": "c1",
+            "independent": "true", "name": "Helper",
+        }));
+        let env = RecipeEnv { base_dir: paths.base_dir.clone(), ..RecipeEnv::default() };
+
+        let execution = execute_recipe(&paths, &dedicated_agent(), &input, &env).unwrap();
+        assert!(!execution.ok && execution.rolled_back);
+        assert!(execution.steps.iter().all(|s| s.status == "failed"));
+        assert!(execution.snapshot_id.is_none());
+        assert_eq!(fs::read_to_string(&paths.config_path).unwrap(), original);
+        assert!(crate::history::list_snapshots(&paths.metadata_path).unwrap().items.is_empty());
+
+        let root = paths.clawpal_dir.parent().unwrap();
+        let effects = [
+            FileEffect::CreateDir(root.join("a/b/c")),
+            FileEffect::WriteFile { path: root.join("a/b/c/missing/x.md"), content: String::new() },
+        ];
+        let mut undo = Vec::new();
+        assert!(perform_file_effects(&effects, &mut undo).is_err());
+        undo_file_effects(undo);
+        assert!(!root.join("a").exists() && root.exists());
+        let _ = fs::remove_dir_all(root);
+    }
+
+    #[test]
+    fn agent_ids_match_ignoring_case() {
+        let mut cfg = json!({ "agents": { "list": [{ "id": "Helper", "workspace": "/ws/helper" }] } });
+        let env = RecipeEnv::default();
+        let create = params(json!({ "agentId": "HELPER" }));
+        assert!(step_create_agent(&mut cfg, &create, &env).is_err());
+        let bind = params(json!({ "channelType": "discord", "peerId": "c1", "agentId": "helper" }));
+        step_bind_channel(&mut cfg, &bind).unwrap();
+        assert_eq!(cfg.pointer("/bindings/0/agentId"), Some(&json!("Helper")));
+        let identity = params(json!({ "agentId": "hELPER", "name": "Helper" }));
+        let outcome = step_setup_identity(&cfg, &identity).unwrap();
+        assert!(outcome.files.contains(&FileEffect::WriteFile {
+            path: PathBuf::from("/ws/helper/IDENTITY.md"),
+            content: "- Name: Helper\n".into(),
+        }));
+    }
+
+    #[test]
+    fn steps_honour_when_for_each_and_on_error() {
+        let env = RecipeEnv::default();
+        let shared = params(json!({
+            "agent_id": "helper", "model": "__default__", "guild_id": "g1", "channel_id": "c1",
+            "independent": "false", "persona": "",
+        }));
+        let plan = plan_recipe(&json!({}), &dedicated_agent(), &shared, &env, "openclaw.json").unwrap();
+        let statuses: Vec<_> = plan.steps.iter().map(|s| s.status.as_str()).collect();
+        assert_eq!(statuses, vec!["applied", "skipped", "applied", "skipped"]);
+        assert!(plan.config.pointer("/channels").is_none());
+
+        let mut recipe = dedicated_agent();
+        recipe.params.push(serde_json::from_value(json!({ "id": "ids", "label": "Ids", "type": "list", "required": true })).unwrap());
+        recipe.steps = vec![serde_json::from_value(json!({
+            "action": "create_agent", "label": "Create", "forEach": "ids", "onError": "continue",
+            "args": { "agentId": "{{item}}" },
+        }))
+        .unwrap()];
+        let input = coerce_params(&recipe, &params(json!({ "ids": "a, main, b" })));
+        let plan = plan_recipe(&json!({ "agents": { "list": [{ "id": "main" }] } }), &recipe, &input, &env, "openclaw.json").unwrap();
+        let results: Vec<_> = plan.steps.iter().map(|s| (s.label.as_str(), s.status.as_str())).collect();
+        assert_eq!(results, vec![("Create (a)", "applied"), ("Create (main)", "failed"), ("Create (b)", "applied")]);
+        assert!(!plan.failed);
+        assert_eq!(agent_ids(&plan.config), vec!["main", "a", "b"]);
+
+        recipe.steps[0].when = Some("missing".into());
+        assert!(resolve_steps(&recipe, &input).unwrap_err().contains("undeclared param \"missing\""));
+        recipe.steps[0].when = None;
+        recipe.steps[0].for_each = Some("agent_id".into());
+        assert!(resolve_steps(&recipe, &input).unwrap_err().contains("must be a list param"));
+    }
+
+    #[test]
+    fn patch_steps_follow_the_rfcs() {
+        let recipe: Recipe = serde_json::from_value(json!({
+            "id": "p", "name": "P", "description": "", "version": "1", "tags": [], "difficulty": "easy",
+            "params": [{ "id": "peer", "label": "Peer", "type": "string", "required": true }],
+            "steps": [
+                { "action": "config_patch", "label": "Merge", "args": {
+                    "patchTemplate": "{ tools: { web: { enabled: true, proxy: null } }, legacy: null }" } },
+                { "action": "json_patch", "label": "Append", "args": { "operations": [
+                    { "op": "test", "path": "/agents/list/0/id", "value": "main" },
+                    { "op": "add", "path": "/bindings/-", "value": { "agentId": "main", "match": { "peer": { "id": "{{peer}}" } } } },
+                    { "op": "remove", "path": "/agents/list/1" },
+                ] } },
+            ],
+        }))
+        .unwrap();
+        let current = json!({ "agents": { "list": [{ "id": "main" }, { "id": "old" }] }, "bindings": [], "legacy": 1 });
+        let plan = plan_recipe(&current, &recipe, &params(json!({ "peer": "c1" })), &RecipeEnv::default(), "openclaw.json").unwrap();
+        assert!(!plan.failed, "{:?}", plan.steps.iter().map(|s| &s.result.errors).collect::<Vec<_>>());
+        assert_eq!(plan.config, json!({
+            "agents": { "list": [{ "id": "main" }] },
+            "bindings": [{ "agentId": "main", "match": { "peer": { "id": "c1" } } }],
+            "tools": { "web": { "enabled": true } },
+        }));
+        let ops: Vec<_> = plan.changes.iter().map(|c| (c.path.as_str(), c.op.as_str())).collect();
+        assert!(ops.contains(&("bindings[0]", "add")) && ops.contains(&("agents.list[1]", "remove")), "{ops:?}");
+    }
+
+    #[test]
+    fn typed_params_are_validated_by_kind() {
+        let recipe = dedicated_agent();
+        let ctx = ParamContext {
+            model_profile_ids: Some(vec!["p1".into()]),
+            discord_channels: Some(vec![("g1".into(), "c1".into()), ("g2".into(), "c2".into())]),
+        };
+        let errors = validate_with(&recipe, &params(json!({
+            "agent_id": "a", "model": "nope", "guild_id": "g1", "channel_id": "c2",
+            "independent": "yes",
+        })), &ctx);
+        let codes: Vec<_> = errors.iter().map(|e| (e.param_id.as_str(), e.code.as_str())).collect();
+        assert_eq!(codes, vec![
+            ("model", "unknownModelProfile"),
+            ("channel_id", "unknownChannel"),
+            ("independent", "type"),
+        ]);
+    }
+
+    #[test]
+    fn depends_on_only_requires_when_parent_truthy() {
+        let mut recipe = dedicated_agent();
+        recipe.params.iter_mut().find(|p| p.id == "name").unwrap().required = true;
+        let base = json!({ "agent_id": "a", "model": "__default__", "guild_id": "g", "channel_id": "c" });
+        let mut off = params(base.clone());
+        off.insert("independent".into(), json!("false"));
+        assert!(validate(&recipe, &off).is_empty());
+        let mut on = params(base);
+        on.insert("independent".into(), json!(true));
+        assert_eq!(validate(&recipe, &on), vec![ParamError::new("name", "required", "Display Name is required")]);
+        assert_eq!(coerce_params(&recipe, &off).get("independent"), Some(&json!(false)));
+    }
+
+    #[test]
+    fn untrusted_recipes_need_explicit_permission_to_run() {
+        let mut recipe = dedicated_agent();
+        assert!(ensure_runnable(&recipe, false).is_ok());
+        recipe.source = Some(crate::recipe_registry::RecipeSource {
+            kind: "explicit".into(),
+            location: "https://example.com/recipes.json".into(),
+            precedence: crate::recipe_registry::EXPLICIT_PRECEDENCE,
+            trust: crate::recipe_registry::RecipeTrust::Untrusted,
+            signed_by: None,
+        });
+        assert!(ensure_runnable(&recipe, false).unwrap_err().contains("untrusted"));
+        assert!(ensure_runnable(&recipe, true).is_ok());
+    }
 }
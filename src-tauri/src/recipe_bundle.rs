@@ -0,0 +1,212 @@
+//! Signed recipe bundles.
+//!
+//! A bundle wraps a recipes document so its author can be verified:
+//!
+//! ```json
+//! {
+//!   "format": "clawpal-recipe-bundle/1",
+//!   "keyId": "<16 hex chars>",
+//!   "payload": "<base64url of the recipes document>",
+//!   "signature": "<base64url ed25519 signature over the decoded payload>"
+//! }
+//! ```
+//!
+//! Trusted public keys and the policy for unsigned remote recipes live in
+//! `~/.clawpal/recipes/trust.json`.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use base64::Engine;
+use ed25519_dalek::{Signature, Signer, SigningKey, Verifier, VerifyingKey};
+use serde::{Deserialize, Serialize};
+
+pub const BUNDLE_FORMAT: &str = "clawpal-recipe-bundle/1";
+
+/// What happens to remote recipes that are not signed by a trusted key.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum TrustPolicy {
+    /// Load them, marked as untrusted.
+    #[default]
+    Warn,
+    /// Refuse to load them.
+    Require,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TrustedKey {
+    pub key_id: String,
+    pub name: String,
+    /// Raw 32-byte ed25519 public key, base64url.
+    pub public_key: String,
+    pub added_at: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TrustStore {
+    #[serde(default)]
+    pub policy: TrustPolicy,
+    #[serde(default)]
+    pub keys: Vec<TrustedKey>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RecipeBundle {
+    pub format: String,
+    pub key_id: String,
+    pub payload: String,
+    pub signature: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Verification {
+    /// Plain recipes document.
+    Unsigned,
+    /// Well-formed bundle whose key is not in the trust store.
+    UnknownKey(String),
+    /// Signature checked against a trusted key.
+    Verified { key_id: String, name: String },
+}
+
+fn trust_path(recipes_dir: &Path) -> PathBuf {
+    recipes_dir.join("trust.json")
+}
+
+pub fn read_trust_store(recipes_dir: &Path) -> TrustStore {
+    fs::read_to_string(trust_path(recipes_dir))
+        .ok()
+        .and_then(|text| serde_json::from_str(&text).ok())
+        .unwrap_or_default()
+}
+
+pub fn write_trust_store(recipes_dir: &Path, store: &TrustStore) -> Result<(), String> {
+    let text = serde_json::to_string_pretty(store).map_err(|e| e.to_string())?;
+    crate::config_io::write_text(&trust_path(recipes_dir), &text)
+}
+
+fn b64() -> base64::engine::GeneralPurpose {
+    base64::engine::general_purpose::URL_SAFE_NO_PAD
+}
+
+fn decode_b64(text: &str) -> Result<Vec<u8>, String> {
+    let trimmed = text.trim().trim_end_matches('=');
+    b64()
+        .decode(trimmed)
+        .or_else(|_| base64::engine::general_purpose::STANDARD_NO_PAD.decode(trimmed))
+        .map_err(|e| format!("invalid base64: {e}"))
+}
+
+/// Short, stable identifier for a public key: the first 16 hex chars of its SHA-256.
+pub fn key_id_for(public_key: &VerifyingKey) -> String {
+    crate::recipe_registry::sha256_hex(public_key.as_bytes())[..16].to_string()
+}
+
+pub fn parse_public_key(text: &str) -> Result<VerifyingKey, String> {
+    let bytes: [u8; 32] = decode_b64(text)?
+        .try_into()
+        .map_err(|_| "ed25519 public key must be 32 bytes".to_string())?;
+    VerifyingKey::from_bytes(&bytes).map_err(|e| format!("invalid ed25519 public key: {e}"))
+}
+
+/// Build a trust store entry for a base64 public key.
+pub fn trusted_key(name: &str, public_key: &str) -> Result<TrustedKey, String> {
+    let key = parse_public_key(public_key)?;
+    Ok(TrustedKey {
+        key_id: key_id_for(&key),
+        name: name.trim().to_string(),
+        public_key: b64().encode(key.as_bytes()),
+        added_at: chrono::Utc::now().to_rfc3339(),
+    })
+}
+
+pub fn sign_bundle(document: &str, signing_key: &SigningKey) -> RecipeBundle {
+    let signature = signing_key.sign(document.as_bytes());
+    RecipeBundle {
+        format: BUNDLE_FORMAT.into(),
+        key_id: key_id_for(&signing_key.verifying_key()),
+        payload: b64().encode(document.as_bytes()),
+        signature: b64().encode(signature.to_bytes()),
+    }
+}
+
+fn as_bundle(text: &str) -> Option<RecipeBundle> {
+    let value: serde_json::Value = json5::from_str(text).ok()?;
+    let format = value.get("format")?.as_str()?;
+    if !format.starts_with("clawpal-recipe-bundle/") {
+        return None;
+    }
+    serde_json::from_value(value).ok()
+}
+
+/// Unwrap a bundle (or pass a plain document through) and check its signature.
+///
+/// A bundle with a bad signature is an error regardless of policy.
+pub fn open(text: &str, store: &TrustStore) -> Result<(String, Verification), String> {
+    let Some(bundle) = as_bundle(text) else {
+        return Ok((text.to_string(), Verification::Unsigned));
+    };
+    if bundle.format != BUNDLE_FORMAT {
+        return Err(format!("unsupported recipe bundle format: {}", bundle.format));
+    }
+    let payload = decode_b64(&bundle.payload)?;
+    let document = String::from_utf8(payload).map_err(|_| "bundle payload is not UTF-8".to_string())?;
+    let Some(trusted) = store.keys.iter().find(|k| k.key_id == bundle.key_id) else {
+        return Ok((document, Verification::UnknownKey(bundle.key_id)));
+    };
+    let key = parse_public_key(&trusted.public_key)?;
+    let signature_bytes: [u8; 64] = decode_b64(&bundle.signature)?
+        .try_into()
+        .map_err(|_| "ed25519 signature must be 64 bytes".to_string())?;
+    key.verify(document.as_bytes(), &Signature::from_bytes(&signature_bytes))
+        .map_err(|_| format!("recipe bundle signature does not match key {}", trusted.key_id))?;
+    Ok((
+        document,
+        Verification::Verified { key_id: trusted.key_id.clone(), name: trusted.name.clone() },
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DOC: &str = r#"{"recipes":[]}"#;
+
+    fn fixture_key(seed: u8) -> SigningKey {
+        SigningKey::from_bytes(&[seed; 32])
+    }
+
+    fn store_with(key: &SigningKey) -> TrustStore {
+        let public = b64().encode(key.verifying_key().as_bytes());
+        TrustStore { policy: TrustPolicy::Require, keys: vec![trusted_key("fixture", &public).unwrap()] }
+    }
+
+    #[test]
+    fn signed_bundle_verifies_against_trusted_key() {
+        let key = fixture_key(7);
+        let text = serde_json::to_string(&sign_bundle(DOC, &key)).unwrap();
+        let (document, verification) = open(&text, &store_with(&key)).unwrap();
+        assert_eq!(document, DOC);
+        assert!(matches!(verification, Verification::Verified { ref name, .. } if name == "fixture"));
+    }
+
+    #[test]
+    fn unknown_key_and_plain_documents_are_not_verified() {
+        let text = serde_json::to_string(&sign_bundle(DOC, &fixture_key(7))).unwrap();
+        let (_, verification) = open(&text, &store_with(&fixture_key(8))).unwrap();
+        assert!(matches!(verification, Verification::UnknownKey(_)));
+        assert_eq!(open(DOC, &TrustStore::default()).unwrap().1, Verification::Unsigned);
+    }
+
+    #[test]
+    fn tampered_payload_is_rejected() {
+        let key = fixture_key(7);
+        let mut bundle = sign_bundle(DOC, &key);
+        bundle.payload = b64().encode(r#"{"recipes":[{"id":"evil"}]}"#);
+        let text = serde_json::to_string(&bundle).unwrap();
+        assert!(open(&text, &store_with(&key)).is_err());
+    }
+}
@@ -0,0 +1,287 @@
+//! `when` expressions on recipe steps.
+//!
+//! ```text
+//! independent
+//! independent && persona
+//! !independent || mode == "shared"
+//! (channel_type == 'discord') && count != 0
+//! ```
+//!
+//! A bare param name is tested for truthiness with the same rules as
+//! `{{#if}}` in templates. `==` and `!=` compare the text form of both
+//! sides, so `independent == true` matches the string `"true"` as well as
+//! the boolean. `!` binds tightest, then `==`/`!=`, then `&&`, then `||`.
+
+use serde_json::{Map, Value};
+
+use crate::recipe_template::is_truthy;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expr {
+    Param(String),
+    Literal(Value),
+    Not(Box<Expr>),
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+    Eq { left: Box<Expr>, right: Box<Expr>, negate: bool },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Ident(String),
+    Literal(Value),
+    Not,
+    And,
+    Or,
+    Eq,
+    Ne,
+    Open,
+    Close,
+}
+
+fn tokenize(text: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = Vec::new();
+    let mut chars = text.chars().peekable();
+    while let Some(&c) = chars.peek() {
+        match c {
+            c if c.is_whitespace() => {
+                chars.next();
+            }
+            '(' | ')' => {
+                chars.next();
+                tokens.push(if c == '(' { Token::Open } else { Token::Close });
+            }
+            '!' | '=' | '&' | '|' => {
+                chars.next();
+                let next = chars.peek().copied();
+                let token = match (c, next) {
+                    ('!', Some('=')) => Token::Ne,
+                    ('!', _) => Token::Not,
+                    ('=', Some('=')) => Token::Eq,
+                    ('&', Some('&')) => Token::And,
+                    ('|', Some('|')) => Token::Or,
+                    _ => return Err(format!("unexpected {c:?}; use ==, !=, && or ||")),
+                };
+                if token != Token::Not {
+                    chars.next();
+                }
+                tokens.push(token);
+            }
+            '"' | '\'' => {
+                chars.next();
+                let mut literal = String::new();
+                loop {
+                    match chars.next() {
+                        None => return Err("unterminated string".into()),
+                        Some('\\') => literal.extend(chars.next()),
+                        Some(q) if q == c => break,
+                        Some(other) => literal.push(other),
+                    }
+                }
+                tokens.push(Token::Literal(Value::String(literal)));
+            }
+            c if c.is_ascii_digit() || c == '-' => {
+                let mut number = String::new();
+                while let Some(&d) = chars.peek() {
+                    if d.is_ascii_digit() || d == '.' || d == '-' {
+                        number.push(d);
+                        chars.next();
+                    } else {
+                        break;
+                    }
+                }
+                let value: serde_json::Number = number.parse().map_err(|_| format!("invalid number {number:?}"))?;
+                tokens.push(Token::Literal(Value::Number(value)));
+            }
+            c if c.is_alphanumeric() || c == '_' => {
+                let mut ident = String::new();
+                while let Some(&d) = chars.peek() {
+                    if d.is_alphanumeric() || d == '_' || d == '-' || d == '.' {
+                        ident.push(d);
+                        chars.next();
+                    } else {
+                        break;
+                    }
+                }
+                tokens.push(match ident.as_str() {
+                    "true" => Token::Literal(Value::Bool(true)),
+                    "false" => Token::Literal(Value::Bool(false)),
+                    _ => Token::Ident(ident),
+                });
+            }
+            other => return Err(format!("unexpected {other:?}")),
+        }
+    }
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn eat(&mut self, token: &Token) -> bool {
+        if self.peek() == Some(token) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn or(&mut self) -> Result<Expr, String> {
+        let mut left = self.and()?;
+        while self.eat(&Token::Or) {
+            left = Expr::Or(Box::new(left), Box::new(self.and()?));
+        }
+        Ok(left)
+    }
+
+    fn and(&mut self) -> Result<Expr, String> {
+        let mut left = self.comparison()?;
+        while self.eat(&Token::And) {
+            left = Expr::And(Box::new(left), Box::new(self.comparison()?));
+        }
+        Ok(left)
+    }
+
+    fn comparison(&mut self) -> Result<Expr, String> {
+        let left = self.unary()?;
+        let negate = match self.peek() {
+            Some(Token::Eq) => false,
+            Some(Token::Ne) => true,
+            _ => return Ok(left),
+        };
+        self.pos += 1;
+        let right = self.unary()?;
+        Ok(Expr::Eq { left: Box::new(left), right: Box::new(right), negate })
+    }
+
+    fn unary(&mut self) -> Result<Expr, String> {
+        if self.eat(&Token::Not) {
+            return Ok(Expr::Not(Box::new(self.unary()?)));
+        }
+        match self.tokens.get(self.pos).cloned() {
+            Some(Token::Ident(name)) => {
+                self.pos += 1;
+                Ok(Expr::Param(name))
+            }
+            Some(Token::Literal(value)) => {
+                self.pos += 1;
+                Ok(Expr::Literal(value))
+            }
+            Some(Token::Open) => {
+                self.pos += 1;
+                let inner = self.or()?;
+                if !self.eat(&Token::Close) {
+                    return Err("missing closing parenthesis".into());
+                }
+                Ok(inner)
+            }
+            Some(_) => Err("expected a param name, a literal or '('".into()),
+            None => Err("expression ends too early".into()),
+        }
+    }
+}
+
+pub fn parse(text: &str) -> Result<Expr, String> {
+    let mut parser = Parser { tokens: tokenize(text)?, pos: 0 };
+    if parser.tokens.is_empty() {
+        return Err("empty expression".into());
+    }
+    let expr = parser.or()?;
+    if parser.pos < parser.tokens.len() {
+        return Err("unexpected input after the end of the expression".into());
+    }
+    Ok(expr)
+}
+
+fn text_of(value: Option<&Value>) -> String {
+    match value {
+        None | Some(Value::Null) => String::new(),
+        Some(Value::String(s)) => s.trim().to_string(),
+        Some(other) => other.to_string(),
+    }
+}
+
+impl Expr {
+    /// Param names referenced by the expression, in order of appearance.
+    pub fn params(&self) -> Vec<String> {
+        fn walk(expr: &Expr, out: &mut Vec<String>) {
+            match expr {
+                Expr::Param(name) => {
+                    if !out.contains(name) {
+                        out.push(name.clone());
+                    }
+                }
+                Expr::Literal(_) => {}
+                Expr::Not(inner) => walk(inner, out),
+                Expr::And(a, b) | Expr::Or(a, b) | Expr::Eq { left: a, right: b, .. } => {
+                    walk(a, out);
+                    walk(b, out);
+                }
+            }
+        }
+        let mut out = Vec::new();
+        walk(self, &mut out);
+        out
+    }
+
+    fn value<'a>(&'a self, params: &'a Map<String, Value>) -> Option<&'a Value> {
+        match self {
+            Expr::Param(name) => params.get(name),
+            Expr::Literal(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    pub fn eval(&self, params: &Map<String, Value>) -> bool {
+        match self {
+            Expr::Param(_) | Expr::Literal(_) => is_truthy(self.value(params)),
+            Expr::Not(inner) => !inner.eval(params),
+            Expr::And(a, b) => a.eval(params) && b.eval(params),
+            Expr::Or(a, b) => a.eval(params) || b.eval(params),
+            Expr::Eq { left, right, negate } => {
+                let side = |expr: &Expr| match expr {
+                    Expr::Param(_) | Expr::Literal(_) => text_of(expr.value(params)),
+                    nested => nested.eval(params).to_string(),
+                };
+                (side(left) == side(right)) != *negate
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn eval(text: &str, params: Value) -> bool {
+        parse(text).unwrap().eval(params.as_object().unwrap())
+    }
+
+    #[test]
+    fn evaluates_truthiness_comparisons_and_precedence() {
+        let params = json!({ "independent": "true", "persona": "", "mode": "shared", "count": 2 });
+        assert!(eval("independent", params.clone()));
+        assert!(!eval("independent && persona", params.clone()));
+        assert!(eval("!persona || missing", params.clone()));
+        assert!(eval("independent == true && mode == 'shared'", params.clone()));
+        assert!(eval("count != 0 && !(mode == \"other\")", params.clone()));
+        assert!(!eval("persona || missing && independent", params));
+        assert_eq!(parse("a && (b || !c) == d").unwrap().params(), vec!["a", "b", "c", "d"]);
+    }
+
+    #[test]
+    fn rejects_malformed_expressions() {
+        for text in ["", "a &", "a = b", "(a", "a b", "'open", "&& a", "a $ b"] {
+            assert!(parse(text).is_err(), "accepted {text:?}");
+        }
+    }
+}
@@ -0,0 +1,431 @@
+//! What each recipe application contributed, and how to take it back out.
+//!
+//! When a recipe is executed the difference between the config before and
+//! after it is stored as a list of contributions in
+//! `~/.clawpal/recipe-installs.json`. Uninstalling reverses only those
+//! contributions against the *current* config (a three-way merge between
+//! the pre-install config, the post-install config and today's config), so
+//! edits made since are kept. A contribution whose value has changed since
+//! the install is reported as a conflict and left alone.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+use crate::models::OpenClawPaths;
+use crate::recipe::PreviewResult;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+pub enum Contribution {
+    /// An object key the recipe added (`before` is `None`), changed or removed
+    /// (`after` is `None`).
+    #[serde(rename_all = "camelCase")]
+    Set {
+        path: Vec<String>,
+        before: Option<Value>,
+        after: Option<Value>,
+    },
+    /// An element the recipe inserted into the array at `path`.
+    #[serde(rename_all = "camelCase")]
+    Insert { path: Vec<String>, value: Value },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RecipeInstall {
+    pub id: String,
+    pub recipe_id: String,
+    pub recipe_version: String,
+    pub installed_at: String,
+    pub snapshot_id: Option<String>,
+    /// Display paths of every contribution.
+    pub paths: Vec<String>,
+    /// Agent ids the recipe created.
+    pub agents: Vec<String>,
+    /// Binding entries the recipe added.
+    pub bindings: Vec<Value>,
+    pub contributions: Vec<Contribution>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct InstallIndex {
+    #[serde(default)]
+    pub installs: Vec<RecipeInstall>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UninstallConflict {
+    pub path: String,
+    pub reason: String,
+    /// What the recipe left there.
+    pub expected: Option<Value>,
+    /// What is there now.
+    pub actual: Option<Value>,
+}
+
+#[derive(Debug, Clone)]
+pub struct UninstallPlan {
+    pub config: Value,
+    pub reverted: Vec<String>,
+    pub conflicts: Vec<UninstallConflict>,
+}
+
+/// Installs are tracked per instance, next to the instance's history.
+pub fn installs_path(paths: &OpenClawPaths) -> PathBuf {
+    paths.instance_dir().join("recipe-installs.json")
+}
+
+pub fn read_installs(path: &Path) -> InstallIndex {
+    fs::read_to_string(path)
+        .ok()
+        .and_then(|text| serde_json::from_str(&text).ok())
+        .unwrap_or_default()
+}
+
+pub fn write_installs(path: &Path, index: &InstallIndex) -> Result<(), String> {
+    let text = serde_json::to_string_pretty(index).map_err(|e| e.to_string())?;
+    crate::config_io::write_text(path, &text)
+}
+
+pub fn display_path(path: &[String]) -> String {
+    if path.is_empty() {
+        return "root".into();
+    }
+    path.iter().fold(String::new(), |acc, key| crate::json_diff::child_path(&acc, key))
+}
+
+fn push(path: &[String], key: &str) -> Vec<String> {
+    let mut next = path.to_vec();
+    next.push(key.to_string());
+    next
+}
+
+/// Positions in `after` of the elements of `before`, if `before` is an
+/// in-order subsequence of `after`.
+fn subsequence_positions(before: &[Value], after: &[Value]) -> Option<Vec<usize>> {
+    let mut positions = Vec::with_capacity(before.len());
+    let mut next = 0;
+    for item in before {
+        let offset = after[next..].iter().position(|candidate| candidate == item)?;
+        positions.push(next + offset);
+        next += offset + 1;
+    }
+    Some(positions)
+}
+
+fn collect(path: &[String], before: Option<&Value>, after: Option<&Value>, out: &mut Vec<Contribution>) {
+    match (before, after) {
+        (a, b) if a == b => {}
+        (Some(Value::Object(a)), Some(Value::Object(b))) => {
+            for (key, old) in a {
+                collect(&push(path, key), Some(old), b.get(key), out);
+            }
+            for (key, new) in b.iter().filter(|(k, _)| !a.contains_key(*k)) {
+                collect(&push(path, key), None, Some(new), out);
+            }
+        }
+        (Some(Value::Array(a)), Some(Value::Array(b))) => match subsequence_positions(a, b) {
+            Some(kept) => {
+                for (_, value) in b.iter().enumerate().filter(|(i, _)| !kept.contains(i)) {
+                    out.push(Contribution::Insert { path: path.to_vec(), value: value.clone() });
+                }
+            }
+            None => out.push(Contribution::Set {
+                path: path.to_vec(),
+                before: before.cloned(),
+                after: after.cloned(),
+            }),
+        },
+        // A new array is recorded element by element; uninstall leaves it empty
+        // so entries added later by someone else survive.
+        (None, Some(Value::Array(b))) => {
+            for value in b {
+                out.push(Contribution::Insert { path: path.to_vec(), value: value.clone() });
+            }
+        }
+        _ => out.push(Contribution::Set {
+            path: path.to_vec(),
+            before: before.cloned(),
+            after: after.cloned(),
+        }),
+    }
+}
+
+/// Everything `after` adds to or changes in `before`.
+pub fn contributions(before: &Value, after: &Value) -> Vec<Contribution> {
+    let mut out = Vec::new();
+    collect(&[], Some(before), Some(after), &mut out);
+    out
+}
+
+pub fn new_install(
+    recipe_id: &str,
+    recipe_version: &str,
+    snapshot_id: Option<String>,
+    before: &Value,
+    after: &Value,
+) -> RecipeInstall {
+    let contributions = contributions(before, after);
+    let mut install = RecipeInstall {
+        id: uuid::Uuid::new_v4().to_string(),
+        recipe_id: recipe_id.to_string(),
+        recipe_version: recipe_version.to_string(),
+        installed_at: chrono::Utc::now().to_rfc3339(),
+        snapshot_id,
+        paths: Vec::new(),
+        agents: Vec::new(),
+        bindings: Vec::new(),
+        contributions,
+    };
+    for contribution in &install.contributions {
+        match contribution {
+            Contribution::Set { path, .. } => install.paths.push(display_path(path)),
+            Contribution::Insert { path, value } => {
+                install.paths.push(display_path(path));
+                if path == &["agents", "list"] {
+                    if let Some(id) = value.get("id").and_then(Value::as_str) {
+                        install.agents.push(id.to_string());
+                    }
+                } else if path == &["bindings"] {
+                    install.bindings.push(value.clone());
+                }
+            }
+        }
+    }
+    install.paths.dedup();
+    install
+}
+
+fn get<'a>(root: &'a Value, path: &[String]) -> Option<&'a Value> {
+    path.iter().try_fold(root, |value, key| value.get(key))
+}
+
+/// Set or remove `path`, creating intermediate objects as needed.
+fn put(root: &mut Value, path: &[String], value: Option<Value>) {
+    let Some((last, parents)) = path.split_last() else {
+        *root = value.unwrap_or_else(|| Value::Object(Map::new()));
+        return;
+    };
+    let mut node = root;
+    for key in parents {
+        if !node.is_object() {
+            *node = Value::Object(Map::new());
+        }
+        node = node
+            .as_object_mut()
+            .unwrap()
+            .entry(key.clone())
+            .or_insert_with(|| Value::Object(Map::new()));
+    }
+    let Some(map) = node.as_object_mut() else { return };
+    match value {
+        Some(value) => {
+            map.insert(last.clone(), value);
+        }
+        None => {
+            map.remove(last);
+        }
+    }
+}
+
+fn same_entry(candidate: &Value, value: &Value) -> bool {
+    match (candidate.get("id"), value.get("id")) {
+        (Some(a), Some(b)) => a == b,
+        _ => candidate == value,
+    }
+}
+
+/// Reverse the contributions of `install` against `current`.
+pub fn plan_uninstall(current: &Value, install: &RecipeInstall) -> UninstallPlan {
+    let mut plan = UninstallPlan { config: current.clone(), reverted: Vec::new(), conflicts: Vec::new() };
+    for contribution in install.contributions.iter().rev() {
+        match contribution {
+            Contribution::Set { path, before, after } => {
+                let actual = get(&plan.config, path).cloned();
+                if actual == *after {
+                    put(&mut plan.config, path, before.clone());
+                    plan.reverted.push(display_path(path));
+                } else if actual != *before {
+                    plan.conflicts.push(UninstallConflict {
+                        path: display_path(path),
+                        reason: "changed since the recipe was installed".into(),
+                        expected: after.clone(),
+                        actual,
+                    });
+                }
+            }
+            Contribution::Insert { path, value } => {
+                let label = display_path(path);
+                let Some(items) = get(&plan.config, path).and_then(Value::as_array) else {
+                    continue;
+                };
+                let Some(index) = items.iter().position(|item| same_entry(item, value)) else {
+                    continue;
+                };
+                if &items[index] != value {
+                    plan.conflicts.push(UninstallConflict {
+                        path: crate::json_diff::index_path(&label, index),
+                        reason: "entry was edited since the recipe was installed".into(),
+                        expected: Some(value.clone()),
+                        actual: Some(items[index].clone()),
+                    });
+                    continue;
+                }
+                let mut items = items.clone();
+                items.remove(index);
+                put(&mut plan.config, path, Some(Value::Array(items)));
+                plan.reverted.push(crate::json_diff::index_path(&label, index));
+            }
+        }
+    }
+    plan
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UninstallPreview {
+    pub install: RecipeInstall,
+    pub preview: PreviewResult,
+    pub reverted: Vec<String>,
+    pub conflicts: Vec<UninstallConflict>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UninstallResult {
+    pub ok: bool,
+    pub snapshot_id: Option<String>,
+    pub reverted: Vec<String>,
+    pub conflicts: Vec<UninstallConflict>,
+}
+
+fn find_install(index: &InstallIndex, install_id: &str) -> Result<usize, String> {
+    index
+        .installs
+        .iter()
+        .position(|i| i.id == install_id)
+        .ok_or_else(|| format!("recipe install not found: {install_id}"))
+}
+
+pub fn preview_uninstall(paths: &OpenClawPaths, install_id: &str) -> Result<UninstallPreview, String> {
+    let index = read_installs(&installs_path(paths));
+    let install = index.installs[find_install(&index, install_id)?].clone();
+    let current = crate::config_io::read_openclaw_config(paths)?;
+    let plan = plan_uninstall(&current, &install);
+    let warnings = if plan.conflicts.is_empty() {
+        Vec::new()
+    } else {
+        vec![format!("{} recipe-owned entries were edited since install and will be kept", plan.conflicts.len())]
+    };
+    let preview = crate::recipe::replacement_preview(&install.recipe_id, &current, &plan.config, warnings);
+    Ok(UninstallPreview { install, preview, reverted: plan.reverted, conflicts: plan.conflicts })
+}
+
+/// Revert an install. With conflicts and `keep_conflicts` unset nothing is
+/// written; otherwise the conflicting entries are left as they are.
+pub fn uninstall(paths: &OpenClawPaths, install_id: &str, keep_conflicts: bool) -> Result<UninstallResult, String> {
+    let index_path = installs_path(paths);
+    let mut index = read_installs(&index_path);
+    let position = find_install(&index, install_id)?;
+    let base = crate::config_cas::read_base(paths)?;
+    let plan = plan_uninstall(&base.value, &index.installs[position]);
+    if !plan.conflicts.is_empty() && !keep_conflicts {
+        return Ok(UninstallResult {
+            ok: false,
+            snapshot_id: None,
+            reverted: Vec::new(),
+            conflicts: plan.conflicts,
+        });
+    }
+
+    let snapshot = crate::config_cas::commit(paths, &base, &plan.config, |current_text| {
+        crate::history::add_snapshot(
+            &paths.history_dir,
+            &paths.metadata_path,
+            Some(index.installs[position].recipe_id.clone()),
+            "recipe-uninstall",
+            true,
+            current_text,
+            None,
+        )
+    })?;
+    index.installs.remove(position);
+    write_installs(&index_path, &index)?;
+    Ok(UninstallResult {
+        ok: true,
+        snapshot_id: Some(snapshot.id),
+        reverted: plan.reverted,
+        conflicts: plan.conflicts,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn installed() -> (Value, Value) {
+        let before = json!({
+            "agents": { "list": [{ "id": "main" }] },
+            "channels": { "discord": { "guilds": {} } },
+        });
+        let after = json!({
+            "agents": { "list": [{ "id": "main" }, { "id": "helper", "model": "a/b" }] },
+            "bindings": [{ "agentId": "helper", "match": { "channel": "discord" } }],
+            "channels": { "discord": { "guilds": { "g1": { "channels": { "c1": { "systemPrompt": "Hi" } } } } } },
+        });
+        (before, after)
+    }
+
+    #[test]
+    fn records_agents_bindings_and_paths() {
+        let (before, after) = installed();
+        let install = new_install("r", "1.0.0", None, &before, &after);
+        assert_eq!(install.agents, vec!["helper"]);
+        assert_eq!(install.bindings.len(), 1);
+        assert!(install.paths.contains(&"channels.discord.guilds.g1".to_string()));
+    }
+
+    #[test]
+    fn uninstall_keeps_later_edits() {
+        let (before, after) = installed();
+        let install = new_install("r", "1.0.0", None, &before, &after);
+        let mut current = after.clone();
+        current["agents"]["list"].as_array_mut().unwrap().push(json!({ "id": "later" }));
+        current["gateway"] = json!({ "port": 1234 });
+
+        let plan = plan_uninstall(&current, &install);
+        assert!(plan.conflicts.is_empty(), "{:?}", plan.conflicts);
+        assert_eq!(
+            plan.config,
+            json!({
+                "agents": { "list": [{ "id": "main" }, { "id": "later" }] },
+                "bindings": [],
+                "channels": { "discord": { "guilds": {} } },
+                "gateway": { "port": 1234 },
+            })
+        );
+    }
+
+    #[test]
+    fn edited_recipe_keys_are_reported_not_overwritten() {
+        let (before, after) = installed();
+        let install = new_install("r", "1.0.0", None, &before, &after);
+        let mut current = after.clone();
+        current["channels"]["discord"]["guilds"]["g1"]["channels"]["c1"]["systemPrompt"] = json!("Edited");
+        current["agents"]["list"][1]["model"] = json!("c/d");
+
+        let plan = plan_uninstall(&current, &install);
+        assert_eq!(plan.conflicts.len(), 2);
+        assert_eq!(get(&plan.config, &["agents".into(), "list".into()]).unwrap().as_array().unwrap().len(), 2);
+        assert_eq!(
+            plan.config.pointer("/channels/discord/guilds/g1/channels/c1/systemPrompt"),
+            Some(&json!("Edited"))
+        );
+        assert_eq!(plan.config["bindings"], json!([]));
+    }
+}
@@ -0,0 +1,677 @@
+//! Static checks and fixture runs for recipe documents.
+//!
+//! Used by the `clawpal-recipes` binary so recipe authors find mistakes
+//! before anyone clicks "apply". Everything here works on plain text and
+//! directories; nothing touches the user's OpenClaw config.
+//!
+//! A fixture case is a directory holding:
+//! - `case.json`: `{ "recipe": "<id>", "params": { ... }, "modelValues": { ... } }`
+//! - `openclaw.json`: the config the recipe is dry-run against
+//! - `expected.json`: the golden result, written with `--update`
+
+use std::{collections::HashSet, fs, path::Path};
+
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+use crate::json_diff::{child_path, index_path};
+use crate::json_patch::PatchOp;
+use crate::recipe::{
+    check_step, coerce_params, parse_recipes_document, plan_recipe, validate, Recipe, RecipeEnv, RecipeParam,
+    RecipeStep, LOOP_VARS, PARAM_TYPES, STEP_ACTIONS,
+};
+use crate::recipe_template::referenced_params;
+
+const RECIPE_FIELDS: &[&str] = &["id", "name", "description", "version", "tags", "difficulty", "params", "steps"];
+const PARAM_FIELDS: &[&str] = &[
+    "id", "label", "type", "required", "pattern", "minLength", "maxLength", "placeholder", "dependsOn",
+    "defaultValue", "options", "min", "max",
+];
+const STEP_FIELDS: &[&str] = &["action", "label", "args", "when", "forEach", "onError"];
+
+/// Args each action cannot run without.
+const REQUIRED_ARGS: &[(&str, &[&str])] = &[
+    ("create_agent", &["agentId"]),
+    ("setup_identity", &["agentId"]),
+    ("bind_channel", &["channelType", "peerId", "agentId"]),
+    ("config_patch", &["patchTemplate"]),
+    ("json_patch", &["operations"]),
+];
+
+/// Fixed state dir for fixture runs so golden files do not depend on the machine.
+pub const FIXTURE_BASE_DIR: &str = "/fixture/.openclaw";
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Diagnostic {
+    /// "error" | "warning"
+    pub severity: String,
+    pub code: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub recipe_id: Option<String>,
+    /// Location inside the document, e.g. `recipes[0].params[2].pattern`.
+    pub path: String,
+    pub message: String,
+    /// Only known for syntax errors.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub line: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub column: Option<usize>,
+}
+
+impl Diagnostic {
+    pub fn new(severity: &str, code: &str, recipe_id: Option<&str>, path: &str, message: impl Into<String>) -> Self {
+        Self {
+            severity: severity.into(),
+            code: code.into(),
+            recipe_id: recipe_id.map(str::to_string),
+            path: if path.is_empty() { "root".into() } else { path.to_string() },
+            message: message.into(),
+            line: None,
+            column: None,
+        }
+    }
+
+    pub fn is_error(&self) -> bool {
+        self.severity == "error"
+    }
+}
+
+struct Linter {
+    out: Vec<Diagnostic>,
+}
+
+impl Linter {
+    fn error(&mut self, code: &str, recipe_id: Option<&str>, path: &str, message: impl Into<String>) {
+        self.out.push(Diagnostic::new("error", code, recipe_id, path, message));
+    }
+
+    fn warning(&mut self, code: &str, recipe_id: Option<&str>, path: &str, message: impl Into<String>) {
+        self.out.push(Diagnostic::new("warning", code, recipe_id, path, message));
+    }
+
+    fn unknown_fields(&mut self, value: &Value, known: &[&str], recipe_id: Option<&str>, path: &str) {
+        let Some(object) = value.as_object() else { return };
+        for key in object.keys().filter(|k| !known.contains(&k.as_str())) {
+            self.warning("unknownField", recipe_id, &child_path(path, key), format!("unknown field {key:?} is ignored"));
+        }
+    }
+
+    /// Deserialize one recipe, pointing at the param or step that breaks the schema.
+    fn schema(&mut self, raw: &Value, path: &str) -> Option<Recipe> {
+        let recipe_id = raw.get("id").and_then(Value::as_str);
+        self.unknown_fields(raw, RECIPE_FIELDS, recipe_id, path);
+        for (key, known) in [("params", PARAM_FIELDS), ("steps", STEP_FIELDS)] {
+            for (i, item) in raw.get(key).and_then(Value::as_array).into_iter().flatten().enumerate() {
+                self.unknown_fields(item, known, recipe_id, &index_path(&child_path(path, key), i));
+            }
+        }
+        match serde_json::from_value::<Recipe>(raw.clone()) {
+            Ok(recipe) => Some(recipe),
+            Err(e) => {
+                let mut located = false;
+                for (i, item) in raw.get("params").and_then(Value::as_array).into_iter().flatten().enumerate() {
+                    if let Err(e) = serde_json::from_value::<RecipeParam>(item.clone()) {
+                        self.error("schema", recipe_id, &index_path(&child_path(path, "params"), i), e.to_string());
+                        located = true;
+                    }
+                }
+                for (i, item) in raw.get("steps").and_then(Value::as_array).into_iter().flatten().enumerate() {
+                    if let Err(e) = serde_json::from_value::<RecipeStep>(item.clone()) {
+                        self.error("schema", recipe_id, &index_path(&child_path(path, "steps"), i), e.to_string());
+                        located = true;
+                    }
+                }
+                if !located {
+                    self.error("schema", recipe_id, path, e.to_string());
+                }
+                None
+            }
+        }
+    }
+
+    fn params(&mut self, recipe: &Recipe, path: &str) {
+        let id = Some(recipe.id.as_str());
+        let mut seen = HashSet::new();
+        for (i, param) in recipe.params.iter().enumerate() {
+            let path = index_path(&child_path(path, "params"), i);
+            if !seen.insert(param.id.as_str()) {
+                self.error("duplicateParam", id, &child_path(&path, "id"), format!("param {:?} is declared twice", param.id));
+            }
+            if !PARAM_TYPES.contains(&param.kind.as_str()) {
+                self.error("unknownParamType", id, &child_path(&path, "type"), format!(
+                    "unknown param type {:?}; expected one of: {}", param.kind, PARAM_TYPES.join(", ")
+                ));
+            }
+            if let Some(pattern) = &param.pattern {
+                if let Err(e) = Regex::new(pattern) {
+                    self.error("invalidPattern", id, &child_path(&path, "pattern"), format!("pattern does not compile: {e}"));
+                }
+            }
+            match (&param.options, param.kind.as_str()) {
+                (None, "enum") => self.error("missingOptions", id, &path, "enum params need options"),
+                (Some(options), "enum") => {
+                    if let Some(default) = param.default_value.as_ref().filter(|d| !options.contains(d)) {
+                        self.error("invalidDefault", id, &child_path(&path, "defaultValue"), format!(
+                            "default {default:?} is not one of the options"
+                        ));
+                    }
+                }
+                (Some(_), _) => self.warning("unusedOptions", id, &child_path(&path, "options"), "options only apply to enum params"),
+                (None, _) => {}
+            }
+            if let (Some(min), Some(max)) = (param.min_length, param.max_length) {
+                if min > max {
+                    self.error("invalidRange", id, &path, format!("minLength {min} is greater than maxLength {max}"));
+                }
+            }
+            if let (Some(min), Some(max)) = (param.min, param.max) {
+                if min > max {
+                    self.error("invalidRange", id, &path, format!("min {min} is greater than max {max}"));
+                }
+            }
+            if let Some(parent) = &param.depends_on {
+                let path = child_path(&path, "dependsOn");
+                if parent == &param.id {
+                    self.error("dependsOnSelf", id, &path, "param depends on itself");
+                } else if !recipe.params.iter().any(|p| &p.id == parent) {
+                    self.error("unknownParam", id, &path, format!("dependsOn refers to undeclared param {parent:?}"));
+                } else if depends_on_cycle(recipe, param) {
+                    self.error("dependsOnCycle", id, &path, "dependsOn chain loops back to this param");
+                }
+            }
+        }
+    }
+
+    /// Check every string in a step's args (keys included) for placeholders.
+    /// Returns the params that were referenced.
+    fn steps(&mut self, recipe: &Recipe, path: &str) -> HashSet<String> {
+        let id = Some(recipe.id.as_str());
+        let declared: HashSet<&str> = recipe.params.iter().map(|p| p.id.as_str()).collect();
+        let mut used = HashSet::new();
+        if recipe.steps.is_empty() {
+            self.warning("noSteps", id, &child_path(path, "steps"), "recipe has no steps");
+        }
+        for (i, step) in recipe.steps.iter().enumerate() {
+            let path = index_path(&child_path(path, "steps"), i);
+            if !STEP_ACTIONS.contains(&step.action.as_str()) {
+                self.error("unknownAction", id, &child_path(&path, "action"), format!(
+                    "unknown action {:?}; expected one of: {}", step.action, STEP_ACTIONS.join(", ")
+                ));
+            }
+            let required = REQUIRED_ARGS.iter().find(|(action, _)| *action == step.action).map_or(&[][..], |(_, args)| args);
+            for arg in required.iter().filter(|a| !step.args.contains_key(**a)) {
+                self.error("missingArg", id, &child_path(&path, "args"), format!("{} requires {arg:?}", step.action));
+            }
+            for (field, problem) in check_step(recipe, step) {
+                let code = if field == "when" { "invalidWhen" } else { "invalidForEach" };
+                self.error(code, id, &child_path(&path, field), problem);
+            }
+            if let Some(expr) = step.when.as_deref().and_then(|w| crate::recipe_expr::parse(w).ok()) {
+                used.extend(expr.params());
+            }
+            used.extend(step.for_each.iter().cloned());
+            let mut in_scope = declared.clone();
+            if step.for_each.is_some() {
+                in_scope.extend(LOOP_VARS);
+            }
+
+            let args_path = child_path(&path, "args");
+            if let Some(operations) = step.args.get("operations").filter(|_| step.action == "json_patch") {
+                self.patch(id, &child_path(&args_path, "operations"), operations);
+            }
+            let mut strings = vec![(child_path(&path, "label"), step.label.clone())];
+            for (key, value) in &step.args {
+                let at = child_path(&args_path, key);
+                if key != "patchTemplate" {
+                    collect_strings(value, &at, &mut strings);
+                    continue;
+                }
+                // Placeholders in a patch template live inside the parsed document.
+                match value.as_str().map(json5::from_str::<Value>) {
+                    None => self.error("invalidTemplate", id, &at, "patchTemplate must be a string"),
+                    Some(Err(e)) => self.error("invalidTemplate", id, &at, format!("patchTemplate is not valid JSON5: {e}")),
+                    Some(Ok(parsed)) => {
+                        let mut inner = Vec::new();
+                        collect_strings(&parsed, "", &mut inner);
+                        strings.extend(inner.into_iter().map(|(_, text)| (at.clone(), text)));
+                    }
+                }
+            }
+            for (at, text) in strings {
+                match referenced_params(&text) {
+                    Ok(names) => {
+                        for name in names {
+                            if !in_scope.contains(name.as_str()) {
+                                self.error("unknownParam", id, &at, format!("{{{{{name}}}}} does not refer to a declared param"));
+                            }
+                            used.insert(name);
+                        }
+                    }
+                    Err(e) => self.error("invalidTemplate", id, &at, e),
+                }
+            }
+        }
+        used
+    }
+
+    /// Check JSON Patch operations. Pointers that contain placeholders are
+    /// only known after rendering and are skipped.
+    fn patch(&mut self, recipe_id: Option<&str>, at: &str, operations: &Value) {
+        let ops = match crate::json_patch::parse_patch(operations) {
+            Ok(ops) => ops,
+            Err(e) => return self.error("invalidPatch", recipe_id, at, e),
+        };
+        for (i, op) in ops.iter().enumerate() {
+            let pointers: Vec<&String> = match op {
+                PatchOp::Move { from, path } | PatchOp::Copy { from, path } => vec![from, path],
+                PatchOp::Add { path, .. }
+                | PatchOp::Remove { path }
+                | PatchOp::Replace { path, .. }
+                | PatchOp::Test { path, .. } => vec![path],
+            };
+            for pointer in pointers.into_iter().filter(|p| !p.contains("{{")) {
+                if let Err(e) = crate::json_patch::parse_pointer(pointer) {
+                    self.error("invalidPatch", recipe_id, &index_path(at, i), e);
+                }
+            }
+        }
+    }
+
+    fn recipe(&mut self, recipe: &Recipe, path: &str) {
+        let id = Some(recipe.id.as_str());
+        if recipe.id.trim().is_empty() {
+            self.error("emptyId", None, &child_path(path, "id"), "recipe id is empty");
+        }
+        if crate::recipe_registry::parse_version(&recipe.version).is_none() {
+            self.warning("invalidVersion", id, &child_path(path, "version"), format!(
+                "version {:?} is not semver; it will sort below every other version", recipe.version
+            ));
+        }
+        self.params(recipe, path);
+        let used = self.steps(recipe, path);
+        for (i, param) in recipe.params.iter().enumerate() {
+            let is_parent = recipe.params.iter().any(|p| p.depends_on.as_ref() == Some(&param.id));
+            if !used.contains(&param.id) && !is_parent {
+                self.warning("unusedParam", id, &index_path(&child_path(path, "params"), i), format!(
+                    "param {:?} is never used by a step", param.id
+                ));
+            }
+        }
+    }
+}
+
+fn depends_on_cycle(recipe: &Recipe, start: &RecipeParam) -> bool {
+    let mut current = start.depends_on.as_deref();
+    let mut hops = 0;
+    while let Some(id) = current {
+        if id == start.id || hops > recipe.params.len() {
+            return true;
+        }
+        hops += 1;
+        current = recipe.params.iter().find(|p| p.id == id).and_then(|p| p.depends_on.as_deref());
+    }
+    false
+}
+
+fn collect_strings(value: &Value, path: &str, out: &mut Vec<(String, String)>) {
+    match value {
+        Value::String(text) => out.push((path.to_string(), text.clone())),
+        Value::Array(items) => {
+            for (i, item) in items.iter().enumerate() {
+                collect_strings(item, &index_path(path, i), out);
+            }
+        }
+        Value::Object(map) => {
+            for (key, item) in map {
+                let at = child_path(path, key);
+                if key.contains("{{") {
+                    out.push((at.clone(), key.clone()));
+                }
+                collect_strings(item, &at, out);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Lint the text of a recipes document or signed bundle.
+///
+/// Bundles are unwrapped without checking their signature against a trust
+/// store; a signature that does not match its payload is still an error.
+pub fn lint_document(text: &str) -> Vec<Diagnostic> {
+    let mut linter = Linter { out: Vec::new() };
+    let document = match crate::recipe_bundle::open(text, &Default::default()) {
+        Ok((document, _)) => document,
+        Err(e) => {
+            linter.error("bundle", None, "", e);
+            return linter.out;
+        }
+    };
+    let root: Value = match json5::from_str(&document) {
+        Ok(root) => root,
+        Err(json5::Error::Message { msg, location }) => {
+            let mut diagnostic = Diagnostic::new("error", "syntax", None, "", msg);
+            diagnostic.line = location.as_ref().map(|l| l.line);
+            diagnostic.column = location.as_ref().map(|l| l.column);
+            linter.out.push(diagnostic);
+            return linter.out;
+        }
+    };
+    let (items, root_path) = match &root {
+        Value::Array(items) => (items, ""),
+        Value::Object(map) => match map.get("recipes") {
+            Some(Value::Array(items)) => (items, "recipes"),
+            _ => {
+                linter.error("schema", None, "", "expected an array of recipes or an object with a \"recipes\" array");
+                return linter.out;
+            }
+        },
+        _ => {
+            linter.error("schema", None, "", "expected an array of recipes or an object with a \"recipes\" array");
+            return linter.out;
+        }
+    };
+
+    let mut seen = HashSet::new();
+    for (i, raw) in items.iter().enumerate() {
+        let path = index_path(root_path, i);
+        let Some(recipe) = linter.schema(raw, &path) else { continue };
+        if !seen.insert(recipe.id.clone()) {
+            linter.error("duplicateRecipe", Some(&recipe.id), &child_path(&path, "id"), format!(
+                "recipe {:?} is declared twice; only the first is used", recipe.id
+            ));
+        }
+        linter.recipe(&recipe, &path);
+    }
+    linter.out
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct FixtureCase {
+    recipe: String,
+    #[serde(default)]
+    params: Map<String, Value>,
+    /// Model profile id -> `provider/model`, as resolved by the app.
+    #[serde(default)]
+    model_values: std::collections::HashMap<String, String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FixtureResult {
+    pub case: String,
+    pub recipe_id: String,
+    /// "pass" | "fail" | "error" | "updated"
+    pub status: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+    /// Unified diff from `expected.json` to the actual result.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub diff: Option<String>,
+}
+
+/// Dry-run a recipe as described by one fixture case. The result is what
+/// gets compared to `expected.json`: the final config, the workspace files
+/// the recipe would touch, step warnings and step errors.
+pub fn dry_run_case(recipes: &[Recipe], case_dir: &Path) -> Result<(String, Value), String> {
+    let read = |name: &str| {
+        fs::read_to_string(case_dir.join(name)).map_err(|e| format!("cannot read {name}: {e}"))
+    };
+    let case: FixtureCase = json5::from_str(&read("case.json")?).map_err(|e| format!("invalid case.json: {e}"))?;
+    let current: Value = json5::from_str(&read("openclaw.json")?).map_err(|e| format!("invalid openclaw.json: {e}"))?;
+    let recipe = recipes
+        .iter()
+        .find(|r| r.id == case.recipe)
+        .ok_or_else(|| format!("recipe not found: {}", case.recipe))?;
+
+    let errors = validate(recipe, &case.params);
+    if !errors.is_empty() {
+        let messages: Vec<String> = errors.iter().map(|e| format!("{}: {}", e.param_id, e.message)).collect();
+        return Err(format!("invalid params: {}", messages.join("; ")));
+    }
+    let env = RecipeEnv {
+        base_dir: FIXTURE_BASE_DIR.into(),
+        model_values: case.model_values,
+        ..RecipeEnv::default()
+    };
+    let params = coerce_params(recipe, &case.params);
+    let plan = plan_recipe(&current, recipe, &params, &env, "openclaw.json")?;
+    let files: Vec<String> = plan
+        .files
+        .iter()
+        .map(|effect| match effect {
+            crate::recipe::FileEffect::CreateDir(dir) => format!("mkdir {}", dir.display()),
+            crate::recipe::FileEffect::WriteFile { path, .. } => format!("write {}", path.display()),
+        })
+        .collect();
+    let errors: Vec<String> = plan
+        .steps
+        .iter()
+        .filter(|s| s.status == "failed")
+        .flat_map(|s| s.result.errors.iter().map(move |e| format!("{}: {e}", s.label)))
+        .collect();
+    Ok((
+        recipe.id.clone(),
+        serde_json::json!({
+            "config": plan.config,
+            "files": files,
+            "warnings": plan.warnings,
+            "errors": errors,
+        }),
+    ))
+}
+
+/// Run every fixture case below `dir` (one sub-directory per case, sorted
+/// by name). With `update`, missing or outdated golden files are rewritten.
+pub fn run_fixtures(recipes: &[Recipe], dir: &Path, update: bool) -> Result<Vec<FixtureResult>, String> {
+    let mut cases: Vec<_> = fs::read_dir(dir)
+        .map_err(|e| format!("cannot read {}: {e}", dir.display()))?
+        .filter_map(Result::ok)
+        .map(|entry| entry.path())
+        .filter(|path| path.join("case.json").is_file())
+        .collect();
+    cases.sort();
+
+    let mut results = Vec::new();
+    for case_dir in cases {
+        let case = case_dir.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+        let mut result = FixtureResult {
+            case,
+            recipe_id: String::new(),
+            status: "pass".into(),
+            message: None,
+            diff: None,
+        };
+        let (recipe_id, actual) = match dry_run_case(recipes, &case_dir) {
+            Ok(outcome) => outcome,
+            Err(e) => {
+                result.status = "error".into();
+                result.message = Some(e);
+                results.push(result);
+                continue;
+            }
+        };
+        result.recipe_id = recipe_id;
+
+        let golden_path = case_dir.join("expected.json");
+        let expected: Option<Value> = match fs::read_to_string(&golden_path).ok().map(|text| json5::from_str(&text)) {
+            None => None,
+            Some(Ok(value)) => Some(value),
+            Some(Err(e)) => {
+                result.status = "error".into();
+                result.message = Some(format!("invalid expected.json: {e}"));
+                results.push(result);
+                continue;
+            }
+        };
+        if expected.as_ref() != Some(&actual) {
+            if update {
+                let text = serde_json::to_string_pretty(&actual).map_err(|e| e.to_string())?;
+                fs::write(&golden_path, text + "\n").map_err(|e| format!("cannot write {}: {e}", golden_path.display()))?;
+                result.status = "updated".into();
+            } else {
+                result.status = "fail".into();
+                let pretty = |v: &Value| serde_json::to_string_pretty(v).unwrap_or_default();
+                match &expected {
+                    None => result.message = Some("expected.json is missing; run with --update to create it".into()),
+                    Some(expected) => {
+                        result.message = Some("dry run does not match expected.json".into());
+                        result.diff = Some(crate::json_diff::unified_diff(&pretty(expected), &pretty(&actual), 3));
+                    }
+                }
+            }
+        }
+        results.push(result);
+    }
+    Ok(results)
+}
+
+/// Parse a recipes document for fixture runs, failing on the first lint error.
+pub fn load_checked(text: &str) -> Result<Vec<Recipe>, String> {
+    if let Some(first) = lint_document(text).into_iter().find(Diagnostic::is_error) {
+        return Err(format!("{}: {}", first.path, first.message));
+    }
+    let (document, _) = crate::recipe_bundle::open(text, &Default::default())?;
+    parse_recipes_document(&document)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn codes(text: &str) -> Vec<(String, String)> {
+        lint_document(text).into_iter().map(|d| (d.code, d.path)).collect()
+    }
+
+    fn recipe_with(params: Value, steps: Value) -> String {
+        json!({ "recipes": [{
+            "id": "r", "name": "R", "description": "", "version": "1.0.0", "tags": [], "difficulty": "easy",
+            "params": params, "steps": steps,
+        }] })
+        .to_string()
+    }
+
+    #[test]
+    fn reports_broken_recipes_with_paths() {
+        let text = recipe_with(
+            json!([
+                { "id": "a", "label": "A", "type": "string", "required": true, "pattern": "([a-z" },
+                { "id": "b", "label": "B", "type": "colour", "required": false, "dependsOn": "nope" },
+            ]),
+            json!([
+                { "action": "create_agent", "label": "x", "args": { "agentId": "{{a}}-{{missing}}" } },
+                { "action": "launch", "label": "y", "args": {} },
+                { "action": "config_patch", "label": "z", "args": { "patchTemplate": "{\"{{#if b}}\": {}}" } },
+            ]),
+        );
+        let found = codes(&text);
+        for expected in [
+            ("invalidPattern", "recipes[0].params[0].pattern"),
+            ("unknownParamType", "recipes[0].params[1].type"),
+            ("unknownParam", "recipes[0].params[1].dependsOn"),
+            ("unknownParam", "recipes[0].steps[0].args.agentId"),
+            ("unknownAction", "recipes[0].steps[1].action"),
+        ] {
+            assert!(found.contains(&(expected.0.into(), expected.1.into())), "missing {expected:?} in {found:?}");
+        }
+        assert_eq!(found.len(), 5, "{found:?}");
+
+        let schema = codes(&recipe_with(json!([{ "id": "a", "type": "string", "required": true }]), json!([])));
+        assert!(schema.contains(&("schema".into(), "recipes[0].params[0]".into())), "{schema:?}");
+
+        let syntax = lint_document("{ recipes: [ }");
+        assert_eq!(syntax[0].code, "syntax");
+        assert!(syntax[0].line.is_some());
+    }
+
+    #[test]
+    fn checks_when_and_for_each() {
+        let text = recipe_with(
+            json!([
+                { "id": "ids", "label": "Ids", "type": "list", "required": true },
+                { "id": "name", "label": "Name", "type": "string", "required": false },
+            ]),
+            json!([
+                { "action": "bind_channel", "label": "Bind {{item}}", "forEach": "ids",
+                  "args": { "channelType": "discord", "peerId": "{{item}}", "agentId": "{{name}}" } },
+                { "action": "setup_identity", "label": "x", "when": "name &&", "forEach": "name", "args": { "agentId": "{{item}}" } },
+                { "action": "setup_identity", "label": "y", "when": "other", "args": { "agentId": "{{index}}" } },
+            ]),
+        );
+        assert_eq!(
+            codes(&text),
+            vec![
+                ("invalidWhen".to_string(), "recipes[0].steps[1].when".to_string()),
+                ("invalidForEach".into(), "recipes[0].steps[1].forEach".into()),
+                ("invalidWhen".into(), "recipes[0].steps[2].when".into()),
+                ("unknownParam".into(), "recipes[0].steps[2].args.agentId".into()),
+            ]
+        );
+    }
+
+    #[test]
+    fn checks_json_patch_operations() {
+        let text = recipe_with(
+            json!([{ "id": "peer", "label": "Peer", "type": "string", "required": true }]),
+            json!([
+                { "action": "json_patch", "label": "ok", "args": { "operations": [
+                    { "op": "add", "path": "/bindings/-", "value": "{{peer}}" },
+                    { "op": "remove", "path": "/channels/{{peer}}" },
+                ] } },
+                { "action": "json_patch", "label": "bad pointer", "args": { "operations": [{ "op": "remove", "path": "bindings" }] } },
+                { "action": "json_patch", "label": "bad op", "args": { "operations": [{ "op": "merge", "path": "/a" }] } },
+                { "action": "json_patch", "label": "missing", "args": {} },
+            ]),
+        );
+        assert_eq!(
+            codes(&text),
+            vec![
+                ("invalidPatch".to_string(), "recipes[0].steps[1].args.operations[0]".to_string()),
+                ("invalidPatch".into(), "recipes[0].steps[2].args.operations".into()),
+                ("missingArg".into(), "recipes[0].steps[3].args".into()),
+            ]
+        );
+    }
+
+    #[test]
+    fn fixtures_compare_against_golden_files() {
+        let dir = std::env::temp_dir().join(format!("clawpal-fixtures-{}", uuid::Uuid::new_v4()));
+        let case = dir.join("persona");
+        fs::create_dir_all(&case).unwrap();
+        fs::write(
+            case.join("case.json"),
+            json!({ "recipe": "discord-channel-persona", "params": { "guild_id": "g", "channel_id": "c", "persona": "Be brief" } }).to_string(),
+        )
+        .unwrap();
+        fs::write(case.join("openclaw.json"), "{ gateway: { port: 18789 } }").unwrap();
+        let recipes = crate::recipe::builtin_recipes();
+
+        let first = run_fixtures(&recipes, &dir, false).unwrap();
+        assert_eq!(first[0].status, "fail");
+        assert_eq!(run_fixtures(&recipes, &dir, true).unwrap()[0].status, "updated");
+        let passed = run_fixtures(&recipes, &dir, false).unwrap();
+        assert_eq!((passed[0].status.as_str(), passed[0].recipe_id.as_str()), ("pass", "discord-channel-persona"));
+
+        fs::write(case.join("openclaw.json"), "{ gateway: { port: 1 } }").unwrap();
+        let failed = run_fixtures(&recipes, &dir, false).unwrap();
+        assert_eq!(failed[0].status, "fail");
+        assert!(failed[0].diff.as_deref().is_some_and(|d| d.contains("+      \"port\": 1")), "{:?}", failed[0].diff);
+
+        // A broken golden file is reported on its case without stopping the others.
+        let other = dir.join("z-other");
+        fs::create_dir_all(&other).unwrap();
+        for name in ["case.json", "openclaw.json", "expected.json"] {
+            fs::copy(case.join(name), other.join(name)).unwrap();
+        }
+        fs::write(case.join("expected.json"), "{ not json").unwrap();
+        let results = run_fixtures(&recipes, &dir, false).unwrap();
+        assert_eq!(results[0].status, "error");
+        assert!(results[0].message.as_deref().is_some_and(|m| m.starts_with("invalid expected.json")));
+        assert_eq!(results[1].status, "fail");
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,543 @@
+//! Layered recipe registry.
+//!
+//! Recipes are merged by id from every configured source: the builtin set,
+//! the user's `~/.clawpal/recipes/recipes.json`, remote sources listed in
+//! `~/.clawpal/recipes/sources.json`, `CLAWPAL_RECIPES_SOURCE` and an explicit
+//! source. When an id appears more than once the higher semver `version` wins;
+//! on equal versions the source with the higher precedence wins.
+//!
+//! HTTP sources are cached on disk and revalidated with `ETag` /
+//! `Last-Modified`, so recipes keep listing when the network is down.
+//! Remote recipes that are not signed by a trusted key are marked untrusted,
+//! or refused when the trust policy is `require` (see `recipe_bundle`).
+
+use std::cmp::Ordering;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::recipe::{builtin_recipes, is_http_url, parse_recipes_document, read_source_text, Recipe};
+use crate::recipe_bundle::{self, TrustPolicy, TrustStore, Verification};
+
+pub const BUILTIN_PRECEDENCE: i32 = 0;
+pub const USER_PRECEDENCE: i32 = 10;
+pub const REMOTE_PRECEDENCE: i32 = 20;
+pub const ENV_PRECEDENCE: i32 = 30;
+pub const EXPLICIT_PRECEDENCE: i32 = 40;
+
+const FETCH_TIMEOUT: Duration = Duration::from_secs(15);
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum RecipeTrust {
+    /// Shipped with ClawPal.
+    Builtin,
+    /// An unsigned file on this machine.
+    Local,
+    /// Signed by a key in the trust store.
+    Trusted,
+    /// Fetched over HTTP without a trusted signature.
+    #[default]
+    Untrusted,
+}
+
+/// Where a recipe was loaded from.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RecipeSource {
+    /// "builtin" | "user" | "remote" | "env" | "explicit"
+    pub kind: String,
+    pub location: String,
+    pub precedence: i32,
+    #[serde(default)]
+    pub trust: RecipeTrust,
+    /// Name of the trusted key that signed the bundle.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signed_by: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RemoteSourceConfig {
+    pub url: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub precedence: Option<i32>,
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct SourcesFile {
+    #[serde(default)]
+    pub sources: Vec<RemoteSourceConfig>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SourceStatus {
+    pub source: RecipeSource,
+    pub recipe_count: usize,
+    /// True when an HTTP source was served from the on-disk cache.
+    pub from_cache: bool,
+    pub fetched_at: Option<String>,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Default)]
+pub struct Registry {
+    pub recipes: Vec<Recipe>,
+    pub sources: Vec<SourceStatus>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CacheEntry {
+    url: String,
+    etag: Option<String>,
+    last_modified: Option<String>,
+    fetched_at: String,
+    body: String,
+}
+
+struct Fetched {
+    body: String,
+    from_cache: bool,
+    fetched_at: String,
+    /// Set when the network failed and a cached copy was used instead.
+    error: Option<String>,
+}
+
+pub fn recipes_dir(clawpal_dir: &Path) -> PathBuf {
+    clawpal_dir.join("recipes")
+}
+
+fn sources_path(recipes_dir: &Path) -> PathBuf {
+    recipes_dir.join("sources.json")
+}
+
+pub fn read_sources(recipes_dir: &Path) -> SourcesFile {
+    fs::read_to_string(sources_path(recipes_dir))
+        .ok()
+        .and_then(|text| serde_json::from_str(&text).ok())
+        .unwrap_or_default()
+}
+
+pub fn write_sources(recipes_dir: &Path, sources: &SourcesFile) -> Result<(), String> {
+    let text = serde_json::to_string_pretty(sources).map_err(|e| e.to_string())?;
+    crate::config_io::write_text(&sources_path(recipes_dir), &text)
+}
+
+pub(crate) fn sha256_hex(bytes: &[u8]) -> String {
+    Sha256::digest(bytes).iter().map(|b| format!("{b:02x}")).collect()
+}
+
+fn cache_path(recipes_dir: &Path, url: &str) -> PathBuf {
+    recipes_dir.join("cache").join(format!("{}.json", sha256_hex(url.as_bytes())))
+}
+
+fn read_cache(path: &Path) -> Option<CacheEntry> {
+    serde_json::from_str(&fs::read_to_string(path).ok()?).ok()
+}
+
+/// GET `url`, revalidating against the cached copy and falling back to it
+/// when the request fails.
+fn fetch_cached(url: &str, recipes_dir: &Path) -> Result<Fetched, String> {
+    let path = cache_path(recipes_dir, url);
+    let cached = read_cache(&path);
+
+    let network = (|| -> Result<Option<CacheEntry>, String> {
+        let client = reqwest::blocking::Client::builder()
+            .timeout(FETCH_TIMEOUT)
+            .build()
+            .map_err(|e| e.to_string())?;
+        let mut request = client.get(url);
+        if let Some(entry) = &cached {
+            if let Some(etag) = &entry.etag {
+                request = request.header(reqwest::header::IF_NONE_MATCH, etag);
+            }
+            if let Some(modified) = &entry.last_modified {
+                request = request.header(reqwest::header::IF_MODIFIED_SINCE, modified);
+            }
+        }
+        let response = request.send().map_err(|e| e.to_string())?;
+        if response.status() == reqwest::StatusCode::NOT_MODIFIED && cached.is_some() {
+            return Ok(None);
+        }
+        if !response.status().is_success() {
+            return Err(format!("request failed: {}", response.status()));
+        }
+        let header = |name| {
+            response
+                .headers()
+                .get(name)
+                .and_then(|v: &reqwest::header::HeaderValue| v.to_str().ok())
+                .map(str::to_string)
+        };
+        let etag = header(reqwest::header::ETAG);
+        let last_modified = header(reqwest::header::LAST_MODIFIED);
+        let body = response.text().map_err(|e| e.to_string())?;
+        Ok(Some(CacheEntry {
+            url: url.to_string(),
+            etag,
+            last_modified,
+            fetched_at: chrono::Utc::now().to_rfc3339(),
+            body,
+        }))
+    })();
+
+    match (network, cached) {
+        (Ok(Some(fresh)), _) => {
+            // Only cache documents that parse; a broken upload must not evict a good copy.
+            if json5::from_str::<serde_json::Value>(&fresh.body).is_ok() {
+                if let Ok(text) = serde_json::to_string(&fresh) {
+                    let _ = crate::config_io::write_text(&path, &text);
+                }
+            }
+            Ok(Fetched { body: fresh.body, from_cache: false, fetched_at: fresh.fetched_at, error: None })
+        }
+        (Ok(None), Some(entry)) => Ok(Fetched {
+            body: entry.body,
+            from_cache: true,
+            fetched_at: entry.fetched_at,
+            error: None,
+        }),
+        (Err(e), Some(entry)) => Ok(Fetched {
+            body: entry.body,
+            from_cache: true,
+            fetched_at: entry.fetched_at,
+            error: Some(e),
+        }),
+        (Err(e), None) => Err(e),
+        (Ok(None), None) => Err("server answered 304 without a cached copy".into()),
+    }
+}
+
+/// Unwrap a signed bundle and decide how far the source can be trusted.
+fn check_trust(text: &str, source: &mut RecipeSource, store: &TrustStore) -> Result<String, String> {
+    let (document, verification) = recipe_bundle::open(text, store)?;
+    let remote = is_http_url(&source.location);
+    source.trust = match verification {
+        Verification::Verified { name, .. } => {
+            source.signed_by = Some(name);
+            RecipeTrust::Trusted
+        }
+        Verification::Unsigned | Verification::UnknownKey(_) if !remote => RecipeTrust::Local,
+        Verification::Unsigned if store.policy == TrustPolicy::Require => {
+            return Err("refused by trust policy: remote recipes must be signed".into());
+        }
+        Verification::UnknownKey(key_id) if store.policy == TrustPolicy::Require => {
+            return Err(format!("refused by trust policy: signing key {key_id} is not trusted"));
+        }
+        Verification::Unsigned | Verification::UnknownKey(_) => RecipeTrust::Untrusted,
+    };
+    Ok(document)
+}
+
+fn load_layer(mut source: RecipeSource, recipes_dir: &Path, store: &TrustStore) -> (Vec<Recipe>, SourceStatus) {
+    let mut status = SourceStatus {
+        source: source.clone(),
+        recipe_count: 0,
+        from_cache: false,
+        fetched_at: None,
+        error: None,
+    };
+    let loaded = if source.kind == "builtin" {
+        source.trust = RecipeTrust::Builtin;
+        Ok(builtin_recipes())
+    } else {
+        let text = if is_http_url(&source.location) {
+            fetch_cached(&source.location, recipes_dir).map(|fetched| {
+                status.from_cache = fetched.from_cache;
+                status.fetched_at = Some(fetched.fetched_at);
+                status.error = fetched.error;
+                fetched.body
+            })
+        } else {
+            read_source_text(&source.location)
+        };
+        text.and_then(|text| check_trust(&text, &mut source, store))
+            .and_then(|document| parse_recipes_document(&document))
+    };
+    status.source = source.clone();
+    match loaded {
+        Ok(mut recipes) => {
+            for recipe in &mut recipes {
+                recipe.source = Some(source.clone());
+            }
+            status.recipe_count = recipes.len();
+            (recipes, status)
+        }
+        Err(e) => {
+            status.error = Some(e);
+            (Vec::new(), status)
+        }
+    }
+}
+
+/// Recipes from one source alone, as an `explicit` layer with the usual
+/// trust checks. Deep links use this so a link's source can't shadow
+/// recipes from the other layers.
+pub fn load_source(location: &str, recipes_dir: &Path) -> Result<Vec<Recipe>, String> {
+    let source = RecipeSource {
+        kind: "explicit".into(),
+        location: location.to_string(),
+        precedence: EXPLICIT_PRECEDENCE,
+        trust: RecipeTrust::default(),
+        signed_by: None,
+    };
+    let store = recipe_bundle::read_trust_store(recipes_dir);
+    match load_layer(source, recipes_dir, &store) {
+        (recipes, status) if recipes.is_empty() => match status.error {
+            Some(error) => Err(error),
+            None => Ok(recipes),
+        },
+        (recipes, _) => Ok(recipes),
+    }
+}
+
+pub(crate) fn parse_version(raw: &str) -> Option<semver::Version> {
+    let trimmed = raw.trim().trim_start_matches(['v', 'V']);
+    if let Ok(version) = semver::Version::parse(trimmed) {
+        return Some(version);
+    }
+    // Accept short forms like "1" or "1.2"
+    let parts: Vec<&str> = trimmed.split('.').collect();
+    if parts.len() < 3 && parts.iter().all(|p| !p.is_empty() && p.chars().all(|c| c.is_ascii_digit())) {
+        let mut padded = parts.join(".");
+        for _ in parts.len()..3 {
+            padded.push_str(".0");
+        }
+        return semver::Version::parse(&padded).ok();
+    }
+    None
+}
+
+/// Semver ordering; unparseable versions sort below any valid one.
+pub fn compare_versions(a: &str, b: &str) -> Ordering {
+    match (parse_version(a), parse_version(b)) {
+        (Some(a), Some(b)) => a.cmp(&b),
+        (Some(_), None) => Ordering::Greater,
+        (None, Some(_)) => Ordering::Less,
+        (None, None) => Ordering::Equal,
+    }
+}
+
+fn precedence_of(recipe: &Recipe) -> i32 {
+    recipe.source.as_ref().map_or(i32::MIN, |s| s.precedence)
+}
+
+pub fn is_untrusted(recipe: &Recipe) -> bool {
+    recipe.source.as_ref().is_some_and(|s| s.trust == RecipeTrust::Untrusted)
+}
+
+/// Merge layers by recipe id. The result keeps first-seen order.
+///
+/// Newer versions win, then higher precedence, except that an untrusted
+/// recipe never replaces a builtin, local or trusted one with the same id;
+/// those are returned separately so their source can report them. A
+/// trusted recipe always replaces an untrusted one.
+pub fn merge_layers(layers: Vec<Vec<Recipe>>) -> (Vec<Recipe>, Vec<Recipe>) {
+    let mut merged: Vec<Recipe> = Vec::new();
+    let mut shadowed = Vec::new();
+    for recipe in layers.into_iter().flatten() {
+        match merged.iter_mut().find(|r| r.id == recipe.id) {
+            Some(existing) => {
+                let wins = match (is_untrusted(&recipe), is_untrusted(existing)) {
+                    (true, false) => {
+                        shadowed.push(recipe);
+                        continue;
+                    }
+                    (false, true) => true,
+                    _ => match compare_versions(&recipe.version, &existing.version) {
+                        Ordering::Greater => true,
+                        Ordering::Less => false,
+                        Ordering::Equal => precedence_of(&recipe) >= precedence_of(existing),
+                    },
+                };
+                if wins {
+                    *existing = recipe;
+                }
+            }
+            None => merged.push(recipe),
+        }
+    }
+    (merged, shadowed)
+}
+
+/// Every source in ascending precedence.
+pub fn configured_sources(explicit: Option<String>, recipes_dir: &Path) -> Vec<RecipeSource> {
+    let mut sources = vec![
+        RecipeSource {
+            kind: "builtin".into(),
+            location: "builtin".into(),
+            precedence: BUILTIN_PRECEDENCE,
+            trust: RecipeTrust::Builtin,
+            signed_by: None,
+        },
+    ];
+    let user_file = recipes_dir.join("recipes.json");
+    if user_file.exists() {
+        sources.push(RecipeSource {
+            kind: "user".into(),
+            location: user_file.to_string_lossy().to_string(),
+            precedence: USER_PRECEDENCE,
+            trust: RecipeTrust::default(),
+            signed_by: None,
+        });
+    }
+    for remote in read_sources(recipes_dir).sources.into_iter().filter(|s| s.enabled) {
+        sources.push(RecipeSource {
+            kind: "remote".into(),
+            location: remote.url,
+            precedence: remote.precedence.unwrap_or(REMOTE_PRECEDENCE),
+            trust: RecipeTrust::default(),
+            signed_by: None,
+        });
+    }
+    let extra = [
+        ("env", std::env::var("CLAWPAL_RECIPES_SOURCE").ok(), ENV_PRECEDENCE),
+        ("explicit", explicit, EXPLICIT_PRECEDENCE),
+    ];
+    for (kind, location, precedence) in extra {
+        if let Some(location) = location.map(|l| l.trim().to_string()).filter(|l| !l.is_empty()) {
+            sources.push(RecipeSource {
+                kind: kind.into(),
+                location,
+                precedence,
+                trust: RecipeTrust::default(),
+                signed_by: None,
+            });
+        }
+    }
+    sources.sort_by_key(|s| s.precedence);
+    sources
+}
+
+pub fn load_registry(explicit: Option<String>, recipes_dir: &Path) -> Registry {
+    let mut registry = Registry::default();
+    let mut layers = Vec::new();
+    let store = recipe_bundle::read_trust_store(recipes_dir);
+    for source in configured_sources(explicit, recipes_dir) {
+        let (recipes, status) = load_layer(source, recipes_dir, &store);
+        layers.push(recipes);
+        registry.sources.push(status);
+    }
+    let (recipes, shadowed) = merge_layers(layers);
+    for recipe in shadowed {
+        let Some(status) = registry.sources.iter_mut().find(|s| recipe.source.as_ref() == Some(&s.source)) else {
+            continue;
+        };
+        let message = format!("recipe {} ignored: an untrusted source can't replace a trusted recipe", recipe.id);
+        status.error = Some(match status.error.take() {
+            Some(error) => format!("{error}; {message}"),
+            None => message,
+        });
+    }
+    registry.recipes = recipes;
+    registry
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn recipe(id: &str, version: &str, precedence: i32) -> Recipe {
+        let mut recipe = builtin_recipes().remove(0);
+        recipe.id = id.into();
+        recipe.version = version.into();
+        recipe.source = Some(RecipeSource {
+            kind: "test".into(),
+            location: format!("p{precedence}"),
+            precedence,
+            trust: RecipeTrust::Local,
+            signed_by: None,
+        });
+        recipe
+    }
+
+    fn untrusted(mut recipe: Recipe) -> Recipe {
+        recipe.source.as_mut().unwrap().trust = RecipeTrust::Untrusted;
+        recipe
+    }
+
+    #[test]
+    fn versions_compare_semantically() {
+        assert_eq!(compare_versions("1.10.0", "1.9.3"), Ordering::Greater);
+        assert_eq!(compare_versions("2", "1.9"), Ordering::Greater);
+        assert_eq!(compare_versions("1.0.0-beta", "1.0.0"), Ordering::Less);
+        assert_eq!(compare_versions("garbage", "0.0.1"), Ordering::Less);
+    }
+
+    #[test]
+    fn merge_prefers_newer_version_then_precedence() {
+        let (merged, shadowed) = merge_layers(vec![
+            vec![recipe("a", "1.2.0", 0), recipe("b", "1.0.0", 0)],
+            vec![recipe("a", "1.1.0", 10), recipe("b", "1.0.0", 10), recipe("c", "1.0.0", 10)],
+        ]);
+        assert!(shadowed.is_empty());
+        let picked: Vec<_> = merged
+            .iter()
+            .map(|r| (r.id.as_str(), r.source.as_ref().unwrap().precedence))
+            .collect();
+        assert_eq!(picked, vec![("a", 0), ("b", 10), ("c", 10)]);
+    }
+
+    #[test]
+    fn untrusted_recipes_never_replace_trusted_ones() {
+        let (merged, shadowed) = merge_layers(vec![
+            vec![recipe("a", "1.0.0", 0)],
+            vec![untrusted(recipe("b", "1.0.0", 20))],
+            vec![untrusted(recipe("a", "9.0.0", 40)), recipe("b", "0.1.0", 30)],
+        ]);
+        let picked: Vec<_> = merged
+            .iter()
+            .map(|r| (r.id.as_str(), r.version.as_str(), is_untrusted(r)))
+            .collect();
+        assert_eq!(picked, vec![("a", "1.0.0", false), ("b", "0.1.0", false)]);
+        assert_eq!(shadowed.len(), 1);
+        assert_eq!(shadowed[0].version, "9.0.0");
+    }
+
+    #[test]
+    fn http_source_falls_back_to_cache_when_offline() {
+        let dir = std::env::temp_dir().join(format!("clawpal-registry-{}", uuid::Uuid::new_v4()));
+        // Nothing listens on the discard port, so the request fails fast.
+        let url = "http://127.0.0.1:9/recipes.json";
+        let body = serde_json::json!({ "recipes": [recipe("cached", "1.0.0", 0)] }).to_string();
+        let entry = CacheEntry {
+            url: url.into(),
+            etag: Some("\"v1\"".into()),
+            last_modified: None,
+            fetched_at: "2026-01-01T00:00:00Z".into(),
+            body,
+        };
+        crate::config_io::write_text(&cache_path(&dir, url), &serde_json::to_string(&entry).unwrap()).unwrap();
+
+        let source = RecipeSource {
+            kind: "remote".into(),
+            location: url.into(),
+            precedence: REMOTE_PRECEDENCE,
+            trust: RecipeTrust::default(),
+            signed_by: None,
+        };
+        let (recipes, status) = load_layer(source.clone(), &dir, &TrustStore::default());
+        assert_eq!(recipes.len(), 1);
+        assert_eq!(recipes[0].source.as_ref(), Some(&source));
+        assert!(status.from_cache);
+        assert!(status.error.is_some());
+
+        // The same unsigned source is refused once signatures are required.
+        let strict = TrustStore { policy: TrustPolicy::Require, keys: Vec::new() };
+        let (recipes, status) = load_layer(source, &dir, &strict);
+        assert!(recipes.is_empty());
+        assert!(status.error.unwrap().contains("trust policy"));
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,336 @@
+//! Value-level templating for recipe patches and step args.
+//!
+//! Templates are parsed as JSON5 *before* any substitution happens, and params
+//! are then inserted into the parsed `serde_json::Value`. A param can therefore
+//! never change the shape of the document, no matter which quotes, backslashes
+//! or newlines it contains.
+//!
+//! Supported syntax inside any string (keys included):
+//! - `{{name}}` inserts a param. When a string consists of a single
+//!   placeholder the param keeps its JSON type; typing params by their
+//!   declared kind is up to [`crate::recipe::coerce_params`].
+//! - `{{name | fallback}}` uses `fallback` when the param is missing or empty.
+//!   The fallback is read as a JSON literal when possible (`8080`, `true`,
+//!   `"text"`), otherwise as plain text.
+//! - `{{#if name}}...{{else}}...{{/if}}` and `{{#unless name}}...{{/unless}}`.
+//!
+//! An object key of the form `"{{#if name}}"` (or `"{{#unless name}}"`) whose
+//! value is an object merges that object into its parent when the condition holds.
+
+use std::collections::HashSet;
+
+use serde_json::{Map, Value};
+
+#[derive(Debug, Clone, PartialEq)]
+enum Node {
+    Text(String),
+    Var { name: String, fallback: Option<String> },
+    Cond { negate: bool, name: String, then: Vec<Node>, otherwise: Vec<Node> },
+}
+
+enum Tag {
+    Var { name: String, fallback: Option<String> },
+    Open { negate: bool, name: String },
+    Else,
+    Close { negate: bool },
+}
+
+fn parse_tag(raw: &str) -> Result<Tag, String> {
+    let tag = raw.trim();
+    if let Some(name) = tag.strip_prefix("#if ") {
+        return Ok(Tag::Open { negate: false, name: checked_name(name)? });
+    }
+    if let Some(name) = tag.strip_prefix("#unless ") {
+        return Ok(Tag::Open { negate: true, name: checked_name(name)? });
+    }
+    match tag {
+        "else" => return Ok(Tag::Else),
+        "/if" => return Ok(Tag::Close { negate: false }),
+        "/unless" => return Ok(Tag::Close { negate: true }),
+        _ => {}
+    }
+    let (name, fallback) = match tag.split_once('|') {
+        Some((name, fallback)) => (name, Some(fallback.trim().to_string())),
+        None => (tag, None),
+    };
+    Ok(Tag::Var { name: checked_name(name)?, fallback })
+}
+
+fn checked_name(raw: &str) -> Result<String, String> {
+    let name = raw.trim();
+    if name.is_empty() || !name.chars().all(|c| c.is_alphanumeric() || c == '_' || c == '-' || c == '.') {
+        return Err(format!("invalid template placeholder: {{{{{raw}}}}}"));
+    }
+    Ok(name.to_string())
+}
+
+/// An open `{{#if}}`/`{{#unless}}` block while parsing.
+struct Frame {
+    negate: bool,
+    name: String,
+    then: Vec<Node>,
+    otherwise: Vec<Node>,
+    in_else: bool,
+}
+
+fn parse(text: &str) -> Result<Vec<Node>, String> {
+    let mut stack: Vec<Frame> = Vec::new();
+    let mut root = Vec::new();
+    let mut rest = text;
+
+    fn current<'a>(root: &'a mut Vec<Node>, stack: &'a mut [Frame]) -> &'a mut Vec<Node> {
+        match stack.last_mut() {
+            Some(frame) if frame.in_else => &mut frame.otherwise,
+            Some(frame) => &mut frame.then,
+            None => root,
+        }
+    }
+
+    while let Some(start) = rest.find("{{") {
+        if start > 0 {
+            current(&mut root, &mut stack).push(Node::Text(rest[..start].to_string()));
+        }
+        let after = &rest[start + 2..];
+        let end = after
+            .find("}}")
+            .ok_or_else(|| format!("unclosed template placeholder in {text:?}"))?;
+        match parse_tag(&after[..end])? {
+            Tag::Var { name, fallback } => {
+                current(&mut root, &mut stack).push(Node::Var { name, fallback });
+            }
+            Tag::Open { negate, name } => stack.push(Frame {
+                negate,
+                name,
+                then: Vec::new(),
+                otherwise: Vec::new(),
+                in_else: false,
+            }),
+            Tag::Else => match stack.last_mut() {
+                Some(frame) if !frame.in_else => frame.in_else = true,
+                _ => return Err(format!("unexpected {{{{else}}}} in {text:?}")),
+            },
+            Tag::Close { negate } => {
+                let frame = stack
+                    .pop()
+                    .filter(|frame| frame.negate == negate)
+                    .ok_or_else(|| format!("mismatched block close in {text:?}"))?;
+                current(&mut root, &mut stack).push(Node::Cond {
+                    negate: frame.negate,
+                    name: frame.name,
+                    then: frame.then,
+                    otherwise: frame.otherwise,
+                });
+            }
+        }
+        rest = &after[end + 2..];
+    }
+    if !stack.is_empty() {
+        return Err(format!("unclosed conditional block in {text:?}"));
+    }
+    if !rest.is_empty() {
+        root.push(Node::Text(rest.to_string()));
+    }
+    Ok(root)
+}
+
+/// Truthiness used by conditionals: missing, null, false, "", "false", 0
+/// and empty arrays/objects are falsy.
+pub fn is_truthy(value: Option<&Value>) -> bool {
+    match value {
+        None | Some(Value::Null) => false,
+        Some(Value::Bool(b)) => *b,
+        Some(Value::String(s)) => {
+            let s = s.trim();
+            !s.is_empty() && !s.eq_ignore_ascii_case("false")
+        }
+        Some(Value::Number(n)) => n.as_f64().is_some_and(|n| n != 0.0),
+        Some(Value::Array(items)) => !items.is_empty(),
+        Some(Value::Object(map)) => !map.is_empty(),
+    }
+}
+
+fn is_blank(value: Option<&Value>) -> bool {
+    match value {
+        None | Some(Value::Null) => true,
+        Some(Value::String(s)) => s.is_empty(),
+        _ => false,
+    }
+}
+
+fn lookup(name: &str, fallback: Option<&str>, params: &Map<String, Value>) -> Value {
+    let value = params.get(name);
+    if is_blank(value) {
+        if let Some(fallback) = fallback {
+            return serde_json::from_str(fallback).unwrap_or_else(|_| Value::String(fallback.to_string()));
+        }
+        return Value::String(String::new());
+    }
+    value.cloned().unwrap_or(Value::Null)
+}
+
+fn write_nodes(nodes: &[Node], params: &Map<String, Value>, out: &mut String) {
+    for node in nodes {
+        match node {
+            Node::Text(text) => out.push_str(text),
+            Node::Var { name, fallback } => match lookup(name, fallback.as_deref(), params) {
+                Value::String(s) => out.push_str(&s),
+                other => out.push_str(&other.to_string()),
+            },
+            Node::Cond { negate, name, then, otherwise } => {
+                let branch = if is_truthy(params.get(name)) != *negate { then } else { otherwise };
+                write_nodes(branch, params, out);
+            }
+        }
+    }
+}
+
+/// Render placeholders in a plain string. Values are inserted verbatim.
+pub fn render_string(text: &str, params: &Map<String, Value>) -> Result<String, String> {
+    let mut out = String::new();
+    write_nodes(&parse(text)?, params, &mut out);
+    Ok(out)
+}
+
+fn render_str_value(text: &str, params: &Map<String, Value>) -> Result<Value, String> {
+    let nodes = parse(text)?;
+    if let [Node::Var { name, fallback }] = nodes.as_slice() {
+        return Ok(lookup(name, fallback.as_deref(), params));
+    }
+    let mut out = String::new();
+    write_nodes(&nodes, params, &mut out);
+    Ok(Value::String(out))
+}
+
+/// Parse a conditional key such as `{{#if name}}`, returning `(negate, name)`.
+fn conditional_key(key: &str) -> Option<(bool, String)> {
+    let inner = key.trim().strip_prefix("{{")?.strip_suffix("}}")?;
+    match parse_tag(inner).ok()? {
+        Tag::Open { negate, name } => Some((negate, name)),
+        _ => None,
+    }
+}
+
+fn render_object(map: &Map<String, Value>, params: &Map<String, Value>, out: &mut Map<String, Value>) -> Result<(), String> {
+    for (key, value) in map {
+        if let Some((negate, name)) = conditional_key(key) {
+            let body = value
+                .as_object()
+                .ok_or_else(|| format!("conditional key {key:?} must map to an object"))?;
+            if is_truthy(params.get(&name)) != negate {
+                render_object(body, params, out)?;
+            }
+            continue;
+        }
+        let rendered_key = render_string(key, params)?;
+        if rendered_key.is_empty() {
+            return Err(format!("template key {key:?} rendered to an empty string"));
+        }
+        out.insert(rendered_key, render_value(value, params)?);
+    }
+    Ok(())
+}
+
+/// Render every string (keys included) of a parsed template.
+pub fn render_value(template: &Value, params: &Map<String, Value>) -> Result<Value, String> {
+    Ok(match template {
+        Value::String(text) => render_str_value(text, params)?,
+        Value::Array(items) => Value::Array(
+            items
+                .iter()
+                .map(|item| render_value(item, params))
+                .collect::<Result<_, _>>()?,
+        ),
+        Value::Object(map) => {
+            let mut out = Map::new();
+            render_object(map, params, &mut out)?;
+            Value::Object(out)
+        }
+        other => other.clone(),
+    })
+}
+
+/// Parse a JSON5 template and render it.
+pub fn render_template(template: &str, params: &Map<String, Value>) -> Result<Value, String> {
+    let parsed: Value = json5::from_str(template).map_err(|e| format!("invalid patch template: {e}"))?;
+    render_value(&parsed, params)
+}
+
+/// Names of every param referenced by a template string, each once, in order
+/// of first appearance.
+/// A conditional object key such as `{{#if name}}` counts as a reference.
+pub fn referenced_params(text: &str) -> Result<Vec<String>, String> {
+    if let Some((_, name)) = conditional_key(text) {
+        return Ok(vec![name]);
+    }
+    fn walk(nodes: &[Node], out: &mut Vec<String>) {
+        for node in nodes {
+            match node {
+                Node::Text(_) => {}
+                Node::Var { name, .. } => out.push(name.clone()),
+                Node::Cond { name, then, otherwise, .. } => {
+                    out.push(name.clone());
+                    walk(then, out);
+                    walk(otherwise, out);
+                }
+            }
+        }
+    }
+    let mut out = Vec::new();
+    walk(&parse(text)?, &mut out);
+    let mut seen = HashSet::new();
+    out.retain(|name| seen.insert(name.clone()));
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn params(value: Value) -> Map<String, Value> {
+        value.as_object().cloned().unwrap()
+    }
+
+    #[test]
+    fn hostile_values_cannot_change_document_shape() {
+        let template = r#"{"channels":{"{{id}}":{"systemPrompt":"{{persona}}"}}}"#;
+        let persona = "He said \"hi\",\n\\ \"}, \"gateway\": {\"auth\": null";
+        let out = render_template(template, &params(json!({ "id": "c1", "persona": persona }))).unwrap();
+        assert_eq!(out, json!({ "channels": { "c1": { "systemPrompt": persona } } }));
+    }
+
+    #[test]
+    fn whole_placeholders_keep_their_type() {
+        let template = r#"{"independent":"{{independent}}","port":"{{port | 8080}}","id":"{{guild}}","name":"{{name}}"}"#;
+        let input = params(json!({ "independent": true, "port": "", "guild": "123", "name": "true" }));
+        let out = render_template(template, &input).unwrap();
+        // A string stays a string, whatever it spells.
+        assert_eq!(out, json!({ "independent": true, "port": 8080, "id": "123", "name": "true" }));
+    }
+
+    #[test]
+    fn conditionals_in_strings_and_keys() {
+        let template = r#"{
+            "greeting": "Hi{{#if name}} {{name}}{{else}} there{{/if}}!",
+            "{{#if persona}}": { "systemPrompt": "{{persona}}" },
+            "{{#unless persona}}": { "note": "none" }
+        }"#;
+        let out = render_template(template, &params(json!({ "name": "", "persona": "" }))).unwrap();
+        assert_eq!(out, json!({ "greeting": "Hi there!", "note": "none" }));
+        let out = render_template(template, &params(json!({ "name": "Ann", "persona": "Be kind" }))).unwrap();
+        assert_eq!(out, json!({ "greeting": "Hi Ann!", "systemPrompt": "Be kind" }));
+    }
+
+    #[test]
+    fn malformed_templates_are_rejected() {
+        assert!(render_string("{{#if a}}open", &Map::new()).is_err());
+        assert!(render_string("{{name", &Map::new()).is_err());
+        assert!(render_string("{{bad name}}", &Map::new()).is_err());
+    }
+
+    #[test]
+    fn referenced_params_are_listed_once_in_order() {
+        assert_eq!(referenced_params("{{a}}{{b}}{{a}}").unwrap(), vec!["a", "b"]);
+        assert_eq!(referenced_params("{{#if b}}{{a}}{{else}}{{b}}{{/if}}").unwrap(), vec!["b", "a"]);
+    }
+}
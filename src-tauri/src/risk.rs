@@ -0,0 +1,249 @@
+//! Risk scoring for config changes.
+//!
+//! `RULES` maps JSON path patterns and operations to a risk level, a reason
+//! shown next to the change and an optional warning shown once per preview.
+//! Patterns are dot-separated; `*` matches one segment (array indices
+//! included) and `**` matches any number of segments. The first matching
+//! rule wins, so specific rules go before general ones. The pattern `root`
+//! matches the document itself.
+
+use crate::json_diff::{parse_path, PathSegment};
+use crate::recipe::ChangeItem;
+
+pub struct RiskRule {
+    pub pattern: &'static str,
+    /// Operations the rule applies to; empty means any.
+    pub ops: &'static [&'static str],
+    pub risk: &'static str,
+    pub reason: &'static str,
+    pub warning: Option<&'static str>,
+}
+
+const DISCONNECT_WARNING: &str = "This will disconnect all paired devices until they reconnect with the new gateway settings.";
+
+pub const RULES: &[RiskRule] = &[
+    RiskRule {
+        pattern: "root",
+        ops: &[],
+        risk: "high",
+        reason: "Replaces the whole configuration",
+        warning: Some("The entire configuration will be replaced."),
+    },
+    RiskRule {
+        pattern: "gateway.auth.**",
+        ops: &[],
+        risk: "high",
+        reason: "Gateway credentials change",
+        warning: Some(DISCONNECT_WARNING),
+    },
+    RiskRule {
+        pattern: "gateway.port",
+        ops: &[],
+        risk: "high",
+        reason: "Gateway port change",
+        warning: Some(DISCONNECT_WARNING),
+    },
+    RiskRule {
+        pattern: "gateway.bind",
+        ops: &[],
+        risk: "high",
+        reason: "Gateway bind address change",
+        warning: Some("Changing the bind address can expose the gateway to the network or make it unreachable."),
+    },
+    RiskRule {
+        pattern: "gateway",
+        ops: &["remove", "replace"],
+        risk: "high",
+        reason: "Gateway section replaced",
+        warning: Some(DISCONNECT_WARNING),
+    },
+    RiskRule {
+        pattern: "gateway.**",
+        ops: &[],
+        risk: "medium",
+        reason: "Gateway setting",
+        warning: None,
+    },
+    RiskRule {
+        pattern: "**.apiKey",
+        ops: &[],
+        risk: "high",
+        reason: "Credential change",
+        warning: Some("A stored API key will be changed or removed."),
+    },
+    RiskRule {
+        pattern: "agents.list.*",
+        ops: &["remove"],
+        risk: "high",
+        reason: "Deletes an agent",
+        warning: Some("Removing an agent stops it from answering on every channel bound to it."),
+    },
+    RiskRule {
+        pattern: "agents.list",
+        ops: &["remove", "replace"],
+        risk: "high",
+        reason: "Agent list replaced",
+        warning: Some("Removing an agent stops it from answering on every channel bound to it."),
+    },
+    RiskRule {
+        pattern: "agents.defaults.**",
+        ops: &[],
+        risk: "medium",
+        reason: "Changes defaults for every agent",
+        warning: None,
+    },
+    RiskRule {
+        pattern: "agents.list.*.model.**",
+        ops: &[],
+        risk: "medium",
+        reason: "Agent model change",
+        warning: None,
+    },
+    RiskRule {
+        pattern: "agents.list.*.identity.**",
+        ops: &[],
+        risk: "low",
+        reason: "Agent identity edit",
+        warning: None,
+    },
+    RiskRule {
+        pattern: "bindings.*",
+        ops: &["remove"],
+        risk: "medium",
+        reason: "Removes a channel binding",
+        warning: None,
+    },
+    RiskRule {
+        pattern: "bindings",
+        ops: &["remove", "replace"],
+        risk: "medium",
+        reason: "Binding list replaced",
+        warning: None,
+    },
+    RiskRule {
+        pattern: "channels.**",
+        ops: &["remove"],
+        risk: "medium",
+        reason: "Channel config removed",
+        warning: None,
+    },
+    RiskRule {
+        pattern: "**.systemPrompt",
+        ops: &[],
+        risk: "low",
+        reason: "Persona edit",
+        warning: None,
+    },
+];
+
+fn matches(pattern: &[&str], path: &[PathSegment]) -> bool {
+    match pattern.split_first() {
+        None => path.is_empty(),
+        Some((&"**", rest)) => (0..=path.len()).any(|skip| matches(rest, &path[skip..])),
+        Some((head, rest)) => match path.split_first() {
+            Some((segment, tail)) => {
+                let hit = match segment {
+                    PathSegment::Key(key) => head == key,
+                    PathSegment::Index(index) => *head == index.to_string(),
+                };
+                (*head == "*" || hit) && matches(rest, tail)
+            }
+            None => false,
+        },
+    }
+}
+
+pub fn rule_for(change: &ChangeItem) -> Option<&'static RiskRule> {
+    // A path the diff could not have produced matches no rule.
+    let path = parse_path(&change.path).ok()?;
+    RULES.iter().find(|rule| {
+        let pattern: Vec<&str> = match rule.pattern {
+            "root" => Vec::new(),
+            pattern => pattern.split('.').collect(),
+        };
+        (rule.ops.is_empty() || rule.ops.contains(&change.op.as_str())) && matches(&pattern, &path)
+    })
+}
+
+fn rank(risk: &str) -> u8 {
+    match risk {
+        "high" => 2,
+        "medium" => 1,
+        _ => 0,
+    }
+}
+
+/// Overall impact level and the warnings to show above a preview.
+pub struct Assessment {
+    pub impact_level: String,
+    pub warnings: Vec<String>,
+}
+
+/// Score every change in place and summarise them.
+///
+/// Changes without a matching rule are "medium" for removals and "low"
+/// otherwise. A reason already set by the caller is kept.
+pub fn assess(changes: &mut [ChangeItem]) -> Assessment {
+    let mut warnings: Vec<String> = Vec::new();
+    let mut level = 0;
+    for change in changes.iter_mut() {
+        match rule_for(change) {
+            Some(rule) => {
+                change.risk = rule.risk.into();
+                if change.reason.is_none() {
+                    change.reason = Some(rule.reason.into());
+                }
+                if let Some(warning) = rule.warning {
+                    if !warnings.iter().any(|w| w == warning) {
+                        warnings.push(warning.into());
+                    }
+                }
+            }
+            None => change.risk = if change.op == "remove" { "medium" } else { "low" }.into(),
+        }
+        level = level.max(rank(&change.risk));
+    }
+    let impact_level = ["low", "medium", "high"][level as usize].to_string();
+    Assessment { impact_level, warnings }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn change(path: &str, op: &str) -> ChangeItem {
+        ChangeItem { path: path.into(), op: op.into(), risk: "low".into(), reason: None }
+    }
+
+    #[test]
+    fn rules_match_indices_quoted_keys_and_root() {
+        let rule = |path: &str, op: &str| rule_for(&change(path, op)).map(|r| r.pattern);
+        assert_eq!(rule("agents.list[2].model", "replace"), Some("agents.list.*.model.**"));
+        assert_eq!(rule("channels[\"a.b\"][0]", "remove"), Some("channels.**"));
+        assert_eq!(rule("root", "replace"), Some("root"));
+    }
+
+    #[test]
+    fn gateway_and_agent_removal_are_high_risk() {
+        let mut changes = vec![
+            change("gateway.auth.token", "replace"),
+            change("agents.list[1]", "remove"),
+            change("agents.list[0].identity.name", "replace"),
+        ];
+        let assessment = assess(&mut changes);
+        assert_eq!(assessment.impact_level, "high");
+        assert_eq!(changes[0].risk, "high");
+        assert_eq!(changes[1].reason.as_deref(), Some("Deletes an agent"));
+        assert_eq!(changes[2].risk, "low");
+        assert!(assessment.warnings.iter().any(|w| w.contains("disconnect all paired devices")));
+    }
+
+    #[test]
+    fn unmatched_changes_fall_back_by_operation() {
+        let mut changes = vec![change("tools.web.enabled", "replace"), change("tools.extra", "remove")];
+        let assessment = assess(&mut changes);
+        assert_eq!((changes[0].risk.as_str(), changes[1].risk.as_str()), ("low", "medium"));
+        assert_eq!(assessment.impact_level, "medium");
+        assert!(assessment.warnings.is_empty());
+    }
+}
@@ -0,0 +1,435 @@
+//! JSON Schemas for the files ClawPal reads and writes.
+//!
+//! The schemas are generated from the Rust types, so they can't drift from
+//! what the app actually parses. Each one carries a version in its `$id`
+//! and file name; bump [`SCHEMA_VERSION`] when a change would reject files
+//! that used to load.
+//!
+//! ```text
+//! ~/.clawpal/schemas/recipes.v1.schema.json         recipes.json, recipe sources
+//! ~/.clawpal/schemas/model-profiles.v1.schema.json  model-profiles.json
+//! ~/.clawpal/schemas/snapshot-index.v1.schema.json  metadata.json
+//! ~/.clawpal/schemas/ssh-hosts.v1.schema.json       remote-instances.json
+//! ```
+
+use std::{fs, path::{Path, PathBuf}, sync::OnceLock};
+
+use schemars::{gen::SchemaGenerator, JsonSchema};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+use crate::commands::ModelProfile;
+use crate::history::SnapshotIndex;
+use crate::recipe::RecipeDocument;
+use crate::ssh::SshHostConfig;
+
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// Schema kinds and the data file each one describes.
+pub const SCHEMA_KINDS: &[(&str, &str)] = &[
+    ("recipes", "recipes.json"),
+    ("model-profiles", "model-profiles.json"),
+    ("snapshot-index", "metadata.json"),
+    ("ssh-hosts", "remote-instances.json"),
+];
+
+/// On-disk shape of `model-profiles.json`.
+#[derive(Deserialize, JsonSchema)]
+#[allow(dead_code)]
+struct ModelProfilesFile {
+    #[serde(default)]
+    profiles: Vec<ModelProfile>,
+    #[serde(default)]
+    version: u8,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SchemaDocument {
+    pub kind: String,
+    pub version: u32,
+    pub file_name: String,
+    /// Data file the schema describes, relative to the ClawPal directory.
+    pub describes: String,
+    pub schema: Value,
+}
+
+fn generate<T: JsonSchema>() -> Value {
+    let root = SchemaGenerator::default().into_root_schema_for::<T>();
+    serde_json::to_value(root).unwrap_or(Value::Null)
+}
+
+pub fn schema_file_name(kind: &str) -> String {
+    format!("{kind}.v{SCHEMA_VERSION}.schema.json")
+}
+
+pub fn schema_for(kind: &str) -> Result<Value, String> {
+    let mut schema = match kind {
+        "recipes" => generate::<RecipeDocument>(),
+        "model-profiles" => generate::<ModelProfilesFile>(),
+        "snapshot-index" => generate::<SnapshotIndex>(),
+        "ssh-hosts" => generate::<Vec<SshHostConfig>>(),
+        other => return Err(format!("unknown schema kind: {other}")),
+    };
+    if let Value::Object(map) = &mut schema {
+        map.insert("$id".into(), Value::String(format!("urn:clawpal:schema:{kind}:v{SCHEMA_VERSION}")));
+        map.insert("title".into(), Value::String(format!("ClawPal {kind} (v{SCHEMA_VERSION})")));
+    }
+    Ok(schema)
+}
+
+pub fn all_schemas() -> Vec<SchemaDocument> {
+    SCHEMA_KINDS
+        .iter()
+        .filter_map(|(kind, describes)| {
+            Some(SchemaDocument {
+                kind: kind.to_string(),
+                version: SCHEMA_VERSION,
+                file_name: schema_file_name(kind),
+                describes: describes.to_string(),
+                schema: schema_for(kind).ok()?,
+            })
+        })
+        .collect()
+}
+
+/// Write every schema into `<clawpal_dir>/schemas`, leaving files that are
+/// already up to date untouched. Returns the paths that were (re)written.
+pub fn write_schemas(clawpal_dir: &Path) -> Result<Vec<PathBuf>, String> {
+    let dir = clawpal_dir.join("schemas");
+    fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+    let mut written = Vec::new();
+    for doc in all_schemas() {
+        let path = dir.join(&doc.file_name);
+        let text = serde_json::to_string_pretty(&doc.schema).map_err(|e| e.to_string())? + "\n";
+        if fs::read_to_string(&path).ok().as_deref() == Some(text.as_str()) {
+            continue;
+        }
+        crate::config_io::write_text(&path, &text)?;
+        written.push(path);
+    }
+    Ok(written)
+}
+
+fn recipes_schema() -> &'static Value {
+    static SCHEMA: OnceLock<Value> = OnceLock::new();
+    SCHEMA.get_or_init(|| schema_for("recipes").unwrap_or(Value::Null))
+}
+
+/// Check a parsed recipe document against the recipes schema. The error
+/// lists up to five violations, each prefixed with its JSON pointer.
+pub fn validate_recipes_document(document: &Value) -> Result<(), String> {
+    let errors = validate(recipes_schema(), document);
+    if errors.is_empty() {
+        return Ok(());
+    }
+    let mut message = errors.iter().take(5).cloned().collect::<Vec<_>>().join("; ");
+    if errors.len() > 5 {
+        message.push_str(&format!("; and {} more", errors.len() - 5));
+    }
+    Err(format!("recipe document does not match the schema: {message}"))
+}
+
+/// Keywords [`check`] enforces.
+const KEYWORDS: &[&str] = &[
+    "$ref", "definitions", "type", "enum", "const", "properties", "required", "additionalProperties",
+    "items", "anyOf", "oneOf", "allOf", "minimum", "maximum", "format",
+];
+
+/// Keywords that only annotate a schema and never reject an instance.
+const ANNOTATIONS: &[&str] = &["$schema", "$id", "title", "description", "default", "examples"];
+
+/// `format` values [`check`] understands; the `uint*` ones bound the value.
+const FORMATS: &[&str] = &["uint8", "uint16", "uint32", "uint", "uint64", "int64", "double", "float"];
+
+/// Validate `instance` against `schema`.
+///
+/// Covers the draft-07 subset that schemars emits: `$ref` into
+/// `definitions`, `type`, `enum`, `const`, `properties`, `required`,
+/// `additionalProperties`, single-schema `items`, `anyOf`/`oneOf`/`allOf`,
+/// numeric `minimum`/`maximum` and the bounds implied by the `uint*`
+/// formats. A schema using anything else is reported as an error rather
+/// than half-checked.
+pub fn validate(schema: &Value, instance: &Value) -> Vec<String> {
+    let mut errors = Vec::new();
+    unsupported_keywords(schema, "#", &mut errors);
+    if !errors.is_empty() {
+        return errors;
+    }
+    let definitions = schema.get("definitions").and_then(Value::as_object);
+    check(schema, instance, "", definitions, &mut errors);
+    errors
+}
+
+/// Report every keyword in `schema` that [`check`] would not enforce.
+fn unsupported_keywords(schema: &Value, pointer: &str, errors: &mut Vec<String>) {
+    let Value::Object(map) = schema else {
+        return;
+    };
+    for (keyword, value) in map {
+        let child = format!("{pointer}/{keyword}");
+        if ANNOTATIONS.contains(&keyword.as_str()) {
+            continue;
+        }
+        if !KEYWORDS.contains(&keyword.as_str()) {
+            errors.push(format!("{pointer}: unsupported schema keyword {keyword:?}"));
+            continue;
+        }
+        match (keyword.as_str(), value) {
+            ("properties" | "definitions", Value::Object(subs)) => {
+                for (name, sub) in subs {
+                    unsupported_keywords(sub, &format!("{child}/{name}"), errors);
+                }
+            }
+            ("allOf" | "anyOf" | "oneOf", Value::Array(subs)) => {
+                for (i, sub) in subs.iter().enumerate() {
+                    unsupported_keywords(sub, &format!("{child}/{i}"), errors);
+                }
+            }
+            ("items", Value::Array(_)) => errors.push(format!("{pointer}: unsupported tuple form of \"items\"")),
+            ("items" | "additionalProperties", sub) => unsupported_keywords(sub, &child, errors),
+            ("format", Value::String(format)) if !FORMATS.contains(&format.as_str()) => {
+                errors.push(format!("{pointer}: unsupported format {format:?}"));
+            }
+            _ => {}
+        }
+    }
+}
+
+fn type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(n) if n.is_i64() || n.is_u64() => "integer",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+fn type_matches(expected: &str, value: &Value) -> bool {
+    let actual = type_name(value);
+    actual == expected
+        || (expected == "number" && actual == "integer")
+        || (expected == "integer" && value.as_f64().is_some_and(|f| f.fract() == 0.0))
+}
+
+/// Whether the top-level `type` of `schema` (following one `$ref`) admits
+/// `instance`. Schemas without a `type` admit anything.
+fn accepts_type(schema: &Value, instance: &Value, definitions: Option<&Map<String, Value>>) -> bool {
+    let resolved = schema
+        .get("$ref")
+        .and_then(Value::as_str)
+        .and_then(|reference| reference.strip_prefix("#/definitions/"))
+        .and_then(|name| definitions.and_then(|defs| defs.get(name)))
+        .unwrap_or(schema);
+    match resolved.get("type") {
+        Some(Value::String(name)) => type_matches(name, instance),
+        Some(Value::Array(names)) => names.iter().filter_map(Value::as_str).any(|name| type_matches(name, instance)),
+        _ => true,
+    }
+}
+
+fn at(pointer: &str) -> &str {
+    if pointer.is_empty() { "/" } else { pointer }
+}
+
+fn check(
+    schema: &Value,
+    instance: &Value,
+    pointer: &str,
+    definitions: Option<&Map<String, Value>>,
+    errors: &mut Vec<String>,
+) {
+    let Value::Object(schema) = schema else {
+        if schema == &Value::Bool(false) {
+            errors.push(format!("{}: not allowed", at(pointer)));
+        }
+        return;
+    };
+
+    if let Some(reference) = schema.get("$ref").and_then(Value::as_str) {
+        match reference
+            .strip_prefix("#/definitions/")
+            .and_then(|name| definitions.and_then(|defs| defs.get(name)))
+        {
+            Some(target) => check(target, instance, pointer, definitions, errors),
+            None => errors.push(format!("{}: unresolved $ref {reference}", at(pointer))),
+        }
+    }
+
+    if let Some(expected) = schema.get("type") {
+        let names: Vec<&str> = match expected {
+            Value::String(name) => vec![name.as_str()],
+            Value::Array(names) => names.iter().filter_map(Value::as_str).collect(),
+            _ => Vec::new(),
+        };
+        if !names.is_empty() && !names.iter().any(|name| type_matches(name, instance)) {
+            errors.push(format!("{}: expected {}, found {}", at(pointer), names.join(" or "), type_name(instance)));
+            return;
+        }
+    }
+
+    if let Some(Value::Array(allowed)) = schema.get("enum") {
+        if !allowed.contains(instance) {
+            let list: Vec<String> = allowed.iter().map(Value::to_string).collect();
+            errors.push(format!("{}: expected one of {}", at(pointer), list.join(", ")));
+        }
+    }
+    if let Some(expected) = schema.get("const") {
+        if expected != instance {
+            errors.push(format!("{}: expected {expected}", at(pointer)));
+        }
+    }
+
+    if let Some(number) = instance.as_f64() {
+        if let Some(min) = schema.get("minimum").and_then(Value::as_f64) {
+            if number < min {
+                errors.push(format!("{}: must be at least {min}", at(pointer)));
+            }
+        }
+        let format_max = match schema.get("format").and_then(Value::as_str) {
+            Some("uint8") => Some(u8::MAX as f64),
+            Some("uint16") => Some(u16::MAX as f64),
+            Some("uint32") => Some(u32::MAX as f64),
+            _ => None,
+        };
+        if let Some(max) = schema.get("maximum").and_then(Value::as_f64).or(format_max) {
+            if number > max {
+                errors.push(format!("{}: must be at most {max}", at(pointer)));
+            }
+        }
+    }
+
+    if let Value::Object(map) = instance {
+        for name in schema.get("required").and_then(Value::as_array).into_iter().flatten() {
+            if let Some(name) = name.as_str() {
+                if !map.contains_key(name) {
+                    errors.push(format!("{}: missing required property {name:?}", at(pointer)));
+                }
+            }
+        }
+        let properties = schema.get("properties").and_then(Value::as_object);
+        for (key, value) in map {
+            let child = format!("{pointer}/{}", key.replace('~', "~0").replace('/', "~1"));
+            match properties.and_then(|props| props.get(key)) {
+                Some(sub) => check(sub, value, &child, definitions, errors),
+                None => match schema.get("additionalProperties") {
+                    Some(Value::Bool(false)) => errors.push(format!("{}: unknown property", child)),
+                    Some(sub @ Value::Object(_)) => check(sub, value, &child, definitions, errors),
+                    _ => {}
+                },
+            }
+        }
+    }
+
+    if let (Value::Array(items), Some(item_schema)) = (instance, schema.get("items")) {
+        for (i, item) in items.iter().enumerate() {
+            check(item_schema, item, &format!("{pointer}/{i}"), definitions, errors);
+        }
+    }
+
+    if let Some(Value::Array(all)) = schema.get("allOf") {
+        for sub in all {
+            check(sub, instance, pointer, definitions, errors);
+        }
+    }
+    for keyword in ["anyOf", "oneOf"] {
+        let Some(Value::Array(branches)) = schema.get(keyword) else {
+            continue;
+        };
+        let results: Vec<Vec<String>> = branches
+            .iter()
+            .map(|sub| {
+                let mut branch = Vec::new();
+                check(sub, instance, pointer, definitions, &mut branch);
+                branch
+            })
+            .collect();
+        let passing = results.iter().filter(|branch| branch.is_empty()).count();
+        if keyword == "oneOf" && passing > 1 {
+            errors.push(format!("{}: matches more than one alternative", at(pointer)));
+        } else if passing == 0 {
+            // Report the alternative of the right JSON type with the fewest
+            // violations; that is almost always the one the author meant.
+            let closest = branches
+                .iter()
+                .zip(results)
+                .min_by_key(|(sub, branch)| (!accepts_type(sub, instance, definitions), branch.len()));
+            if let Some((_, branch)) = closest {
+                errors.extend(branch);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn schemas_are_versioned_and_accept_the_builtin_recipes() {
+        let docs = all_schemas();
+        assert_eq!(docs.len(), SCHEMA_KINDS.len());
+        for doc in &docs {
+            assert_eq!(doc.schema["$id"], json!(format!("urn:clawpal:schema:{}:v1", doc.kind)));
+            assert!(doc.file_name.ends_with(".v1.schema.json"));
+        }
+        let builtin: Value = json5::from_str(include_str!("../recipes.json")).unwrap();
+        assert_eq!(validate_recipes_document(&builtin), Ok(()));
+
+        let profiles = schema_for("model-profiles").unwrap();
+        let file = json!({ "profiles": [{ "id": "p", "name": "P", "provider": "openai", "model": "gpt", "enabled": true }], "version": 1 });
+        assert!(validate(&profiles, &file).is_empty());
+        let hosts = schema_for("ssh-hosts").unwrap();
+        let bad = json!([{ "id": "h", "label": "H", "host": "x", "port": 70000, "username": "u", "authMethod": "key" }]);
+        assert_eq!(validate(&hosts, &bad), vec!["/0/port: must be at most 65535"]);
+    }
+
+    #[test]
+    fn reports_violations_with_pointers() {
+        let document = json!({ "recipes": [{
+            "id": "r", "name": "R", "description": "", "version": "1.0.0", "tags": [], "difficulty": "easy",
+            "params": [{ "id": "p", "label": 3, "type": "string", "required": true }],
+            "steps": [{ "action": "config_patch", "label": "x", "args": {}, "onError": "retry" }],
+        }] });
+        let errors = validate(recipes_schema(), &document);
+        assert_eq!(
+            errors,
+            vec![
+                "/recipes/0/params/0/label: expected string, found integer",
+                "/recipes/0/steps/0/onError: expected one of \"abort\", \"continue\"",
+            ]
+        );
+        let missing = validate(recipes_schema(), &json!([{ "id": "r" }]));
+        assert!(missing.contains(&"/0: missing required property \"steps\"".to_string()));
+    }
+
+    #[test]
+    fn rejects_schemas_with_unsupported_keywords() {
+        for doc in all_schemas() {
+            let mut errors = Vec::new();
+            unsupported_keywords(&doc.schema, "#", &mut errors);
+            assert!(errors.is_empty(), "{}: {errors:?}", doc.kind);
+        }
+        let schema = json!({
+            "type": "object",
+            "properties": { "name": { "type": "string", "pattern": "^[a-z]+$" } },
+            "definitions": { "Pair": { "type": "array", "items": [{ "type": "string" }] } },
+        });
+        assert_eq!(
+            validate(&schema, &json!({ "name": "x" })),
+            vec![
+                "#/definitions/Pair: unsupported tuple form of \"items\"",
+                "#/properties/name: unsupported schema keyword \"pattern\"",
+            ]
+        );
+        assert_eq!(
+            validate(&json!({ "type": "string", "format": "date-time" }), &json!("now")),
+            vec!["#: unsupported format \"date-time\""]
+        );
+    }
+
+}
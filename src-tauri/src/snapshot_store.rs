@@ -0,0 +1,677 @@
+//! One snapshot history implementation over two kinds of storage.
+//!
+//! [`SnapshotStore`] needs a handful of file operations relative to a
+//! history directory plus access to the index; everything else — hashing,
+//! compression, retention, rollback chains, GC — is provided on top, so
+//! local and remote instances share the index format and its semantics.
+//!
+//! * [`LocalSnapshotStore`]: `~/.clawpal/history` and `~/.clawpal/metadata.json`.
+//! * [`SshSnapshotStore`]: the same paths on a remote host, over the SSH pool.
+//!
+//! Remote hosts used to keep whole configs in `~/.clawpal/snapshots/`, with
+//! their metadata encoded in the file name. [`SshSnapshotStore::import_legacy`]
+//! moves those into the index.
+
+use std::collections::{HashMap, HashSet};
+use std::future::Future;
+use std::path::{Path, PathBuf};
+
+use base64::Engine;
+use chrono::{NaiveDateTime, Utc};
+use futures_util::FutureExt;
+use serde_json::Value;
+
+use crate::history::{
+    apply_retention, blame, blob_key, config_fingerprint, content_hash, decode_blob, encode_blob, list_snapshots,
+    write_snapshots, BlameEntry, ConfigHead, GcReport, RetentionPolicy, SnapshotIndex, SnapshotMeta, SnapshotUpdate,
+    EXTERNAL_SOURCE, HEAD_FILE, RETENTION_FILE, TIMESTAMP_FORMAT,
+};
+use crate::json_diff::{parse_path, value_at};
+use crate::ssh::SshConnectionPool;
+
+/// A snapshot to record, taken just before a config write.
+#[derive(Debug, Clone)]
+pub struct NewSnapshot {
+    pub recipe_id: Option<String>,
+    pub source: String,
+    pub can_rollback: bool,
+    pub config: String,
+    /// Id of the snapshot being rolled back to, for rollback chains.
+    pub rollback_of: Option<String>,
+}
+
+/// Run a [`LocalSnapshotStore`] operation from sync code. Local file I/O
+/// never yields, so the future is always complete after one poll.
+pub fn run_local<T>(future: impl Future<Output = Result<T, String>>) -> Result<T, String> {
+    future
+        .now_or_never()
+        .unwrap_or_else(|| Err("local snapshot store operation did not complete".into()))
+}
+
+/// Keys name files relative to the history directory, with `/` separators.
+// Only used through concrete stores, so the futures' Send-ness is known.
+#[allow(async_fn_in_trait)]
+pub trait SnapshotStore {
+    async fn read(&self, key: &str) -> Result<Option<Vec<u8>>, String>;
+    async fn write(&self, key: &str, bytes: &[u8]) -> Result<(), String>;
+    async fn exists(&self, key: &str) -> Result<bool, String>;
+    async fn remove(&self, key: &str) -> Result<(), String>;
+    /// Every file under the history directory, with its size in bytes.
+    async fn list(&self) -> Result<Vec<(String, u64)>, String>;
+    async fn read_index(&self) -> Result<SnapshotIndex, String>;
+    async fn write_index(&self, index: &SnapshotIndex) -> Result<(), String>;
+    /// The path recorded in [`SnapshotMeta::config_path`] for `key`.
+    fn location(&self, key: &str) -> String;
+
+    /// Key of the file behind `meta`: its blob, or the legacy file it names.
+    fn key_of(&self, meta: &SnapshotMeta) -> Option<String> {
+        if let Some(hash) = &meta.hash {
+            return Some(blob_key(hash));
+        }
+        let key = meta.config_path.strip_prefix(&self.location(""))?;
+        Some(key.trim_start_matches(['/', '\\']).replace('\\', "/"))
+    }
+
+    async fn retention(&self) -> RetentionPolicy {
+        match self.read(RETENTION_FILE).await {
+            Ok(Some(bytes)) => serde_json::from_slice(&bytes).unwrap_or_default(),
+            _ => RetentionPolicy::default(),
+        }
+    }
+
+    async fn set_retention(&self, policy: &RetentionPolicy) -> Result<(), String> {
+        let text = serde_json::to_string_pretty(policy).map_err(|e| e.to_string())?;
+        self.write(RETENTION_FILE, text.as_bytes()).await
+    }
+
+    /// Index entries, newest first.
+    async fn snapshots(&self) -> Result<Vec<SnapshotMeta>, String> {
+        let mut items = self.read_index().await?.items;
+        items.sort_by(|a, b| b.created_at.cmp(&a.created_at));
+        Ok(items)
+    }
+
+    async fn find(&self, id: &str) -> Result<SnapshotMeta, String> {
+        self.snapshots()
+            .await?
+            .into_iter()
+            .find(|s| s.id == id)
+            .ok_or_else(|| "snapshot not found".to_string())
+    }
+
+    /// Pin, label or annotate a snapshot and return it as updated.
+    async fn update(&self, id: &str, update: SnapshotUpdate) -> Result<SnapshotMeta, String> {
+        let mut index = self.read_index().await?;
+        let meta = index.items.iter_mut().find(|s| s.id == id).ok_or_else(|| "snapshot not found".to_string())?;
+        meta.apply_update(update);
+        let meta = meta.clone();
+        self.write_index(&index).await?;
+        Ok(meta)
+    }
+
+    async fn content(&self, meta: &SnapshotMeta) -> Result<String, String> {
+        let key = self.key_of(meta).ok_or_else(|| format!("snapshot {} is outside the history directory", meta.id))?;
+        let bytes = self.read(&key).await?.ok_or_else(|| format!("snapshot {} is missing its content", meta.id))?;
+        decode_blob(&key, &bytes)
+    }
+
+    /// The snapshot's config, parsed.
+    async fn config(&self, meta: &SnapshotMeta) -> Result<Value, String> {
+        let text = self.content(meta).await?;
+        json5::from_str(&text).map_err(|e| format!("Failed to parse snapshot {}: {e}", meta.id))
+    }
+
+    /// Every snapshot whose write changed the value at `path` (see
+    /// [`crate::json_diff::parse_path`]), newest first. `current` is the live
+    /// config. Snapshots whose content can't be read are skipped.
+    async fn blame(&self, path: &str, current: &Value) -> Result<Vec<BlameEntry>, String> {
+        let segments = parse_path(path)?;
+        let mut states = Vec::new();
+        // Identical configs share a blob; read and parse each one once.
+        let mut seen: HashMap<String, Option<Value>> = HashMap::new();
+        for meta in self.snapshots().await?.into_iter().rev() {
+            let cached = meta.hash.as_ref().and_then(|hash| seen.get(hash)).cloned();
+            let value = match cached {
+                Some(value) => value,
+                None => match self.config(&meta).await {
+                    Ok(config) => value_at(&config, &segments).cloned(),
+                    Err(_) => continue,
+                },
+            };
+            if let Some(hash) = &meta.hash {
+                seen.insert(hash.clone(), value.clone());
+            }
+            states.push((meta, value));
+        }
+        Ok(blame(&states, value_at(current, &segments)))
+    }
+
+    /// Store `text` compressed under its hash, unless an identical blob
+    /// exists. Returns the hash and the blob's key.
+    async fn put_blob(&self, text: &str) -> Result<(String, String), String> {
+        let hash = content_hash(text);
+        let key = blob_key(&hash);
+        if !self.exists(&key).await? {
+            self.write(&key, &encode_blob(text)?).await?;
+        }
+        Ok((hash, key))
+    }
+
+    async fn head(&self) -> Option<ConfigHead> {
+        let bytes = self.read(HEAD_FILE).await.ok()??;
+        serde_json::from_slice(&bytes).ok()
+    }
+
+    /// Remember `config` as the state ClawPal last wrote or saw.
+    async fn set_head(&self, config: &str) -> Result<(), String> {
+        let (hash, _) = self.put_blob(config).await?;
+        let head = ConfigHead { fingerprint: config_fingerprint(config), hash };
+        let text = serde_json::to_string_pretty(&head).map_err(|e| e.to_string())?;
+        self.write(HEAD_FILE, text.as_bytes()).await
+    }
+
+    /// Compare the live `config` with the head. If something outside
+    /// ClawPal changed it, record the state it replaced as an `external`
+    /// snapshot, like any other write. The first observation only sets the head.
+    async fn observe(&self, config: &str) -> Result<Option<SnapshotMeta>, String> {
+        let head = self.head().await;
+        if head.as_ref().is_some_and(|h| h.fingerprint == config_fingerprint(config)) {
+            return Ok(None);
+        }
+        let mut recorded = None;
+        if let Some(head) = head {
+            let key = blob_key(&head.hash);
+            if let Some(bytes) = self.read(&key).await? {
+                let snapshot = NewSnapshot {
+                    recipe_id: Some(EXTERNAL_SOURCE.into()),
+                    source: EXTERNAL_SOURCE.into(),
+                    can_rollback: true,
+                    config: decode_blob(&key, &bytes)?,
+                    rollback_of: None,
+                };
+                recorded = self.insert(vec![(snapshot, Utc::now().naive_utc())]).await?.pop();
+            }
+        }
+        self.set_head(config).await?;
+        Ok(recorded)
+    }
+
+    /// Record a snapshot of the config about to be overwritten. An outside
+    /// edit nobody has observed yet is recorded first.
+    async fn add(&self, snapshot: NewSnapshot) -> Result<SnapshotMeta, String> {
+        self.observe(&snapshot.config).await?;
+        let mut added = self.insert(vec![(snapshot, Utc::now().naive_utc())]).await?;
+        added.pop().ok_or_else(|| "snapshot was not recorded".to_string())
+    }
+
+    /// Record snapshots taken at the given times, then apply retention and
+    /// delete the files of entries it dropped.
+    async fn insert(&self, snapshots: Vec<(NewSnapshot, NaiveDateTime)>) -> Result<Vec<SnapshotMeta>, String> {
+        let mut index = self.read_index().await?;
+        let mut added = Vec::new();
+        for (snapshot, taken_at) in snapshots {
+            let created_at = taken_at.format(TIMESTAMP_FORMAT).to_string();
+            let label = snapshot.recipe_id.clone().unwrap_or_else(|| "manual".into());
+            let (hash, key) = self.put_blob(&snapshot.config).await?;
+            let meta = SnapshotMeta {
+                id: format!("{created_at}-{label}"),
+                recipe_id: snapshot.recipe_id,
+                created_at,
+                config_path: self.location(&key),
+                source: snapshot.source,
+                can_rollback: snapshot.can_rollback,
+                rollback_of: snapshot.rollback_of,
+                hash: Some(hash),
+                pinned: false,
+                labels: Vec::new(),
+                note: None,
+            };
+            // Newest first, and after anything recorded in the same second.
+            index.items.insert(0, meta.clone());
+            added.push(meta);
+        }
+        index.items.sort_by(|a, b| b.created_at.cmp(&a.created_at));
+        let dropped = apply_retention(&mut index.items, &self.retention().await, Utc::now().naive_utc());
+        self.write_index(&index).await?;
+
+        // Best effort: `gc` sweeps anything left behind here.
+        let mut in_use: HashSet<String> = index.items.iter().filter_map(|item| self.key_of(item)).collect();
+        in_use.extend(self.head().await.map(|head| blob_key(&head.hash)));
+        for key in dropped.iter().filter_map(|item| self.key_of(item)) {
+            if !in_use.contains(&key) {
+                let _ = self.remove(&key).await;
+            }
+        }
+        Ok(added)
+    }
+
+    /// Apply the retention policy, migrate legacy snapshots into the object
+    /// store and delete every file the index no longer references.
+    async fn gc(&self) -> Result<GcReport, String> {
+        let mut report = GcReport::default();
+        let mut index = SnapshotIndex { items: self.snapshots().await? };
+        let dropped = apply_retention(&mut index.items, &self.retention().await, Utc::now().naive_utc());
+        report.removed_snapshots = dropped.len();
+
+        for i in 0..index.items.len() {
+            if index.items[i].hash.is_some() {
+                continue;
+            }
+            let Ok(text) = self.content(&index.items[i]).await else {
+                continue;
+            };
+            let (hash, key) = self.put_blob(&text).await?;
+            index.items[i].config_path = self.location(&key);
+            index.items[i].hash = Some(hash);
+            report.migrated_snapshots += 1;
+        }
+        self.write_index(&index).await?;
+
+        let mut referenced: HashSet<String> = index.items.iter().filter_map(|item| self.key_of(item)).collect();
+        referenced.extend(self.head().await.map(|head| blob_key(&head.hash)));
+        for (key, size) in self.list().await? {
+            // Legacy snapshots sit at the top level and are named `<timestamp>-….json`.
+            let legacy = !key.contains('/') && key.ends_with(".json") && key.starts_with(|c: char| c.is_ascii_digit());
+            if !key.starts_with("objects/") && !legacy {
+                continue;
+            }
+            if referenced.contains(&key) {
+                report.stored_bytes += size;
+            } else {
+                self.remove(&key).await?;
+                report.removed_files += 1;
+                report.reclaimed_bytes += size;
+            }
+        }
+        report.kept_snapshots = index.items.len();
+        Ok(report)
+    }
+}
+
+pub struct LocalSnapshotStore {
+    history_dir: PathBuf,
+    metadata_path: PathBuf,
+}
+
+impl LocalSnapshotStore {
+    pub fn new(history_dir: &Path, metadata_path: &Path) -> Self {
+        Self { history_dir: history_dir.to_path_buf(), metadata_path: metadata_path.to_path_buf() }
+    }
+
+    pub fn for_paths(paths: &crate::models::OpenClawPaths) -> Self {
+        Self::new(&paths.history_dir, &paths.metadata_path)
+    }
+
+    fn path(&self, key: &str) -> PathBuf {
+        key.split('/').fold(self.history_dir.clone(), |path, part| path.join(part))
+    }
+}
+
+fn walk(dir: &Path, prefix: &str, out: &mut Vec<(String, u64)>) {
+    for entry in std::fs::read_dir(dir).into_iter().flatten().flatten() {
+        let name = entry.file_name().to_string_lossy().to_string();
+        let key = if prefix.is_empty() { name } else { format!("{prefix}/{name}") };
+        let path = entry.path();
+        if path.is_dir() {
+            walk(&path, &key, out);
+        } else {
+            out.push((key, entry.metadata().map(|m| m.len()).unwrap_or(0)));
+        }
+    }
+}
+
+impl SnapshotStore for LocalSnapshotStore {
+    async fn read(&self, key: &str) -> Result<Option<Vec<u8>>, String> {
+        match std::fs::read(self.path(key)) {
+            Ok(bytes) => Ok(Some(bytes)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.to_string()),
+        }
+    }
+
+    async fn write(&self, key: &str, bytes: &[u8]) -> Result<(), String> {
+        let path = self.path(key);
+        let parent = path.parent().ok_or_else(|| "invalid snapshot path".to_string())?;
+        std::fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+        let tmp = path.with_extension("tmp");
+        std::fs::write(&tmp, bytes).map_err(|e| e.to_string())?;
+        std::fs::rename(&tmp, &path).map_err(|e| e.to_string())
+    }
+
+    async fn exists(&self, key: &str) -> Result<bool, String> {
+        Ok(self.path(key).exists())
+    }
+
+    async fn remove(&self, key: &str) -> Result<(), String> {
+        std::fs::remove_file(self.path(key)).map_err(|e| e.to_string())
+    }
+
+    async fn list(&self) -> Result<Vec<(String, u64)>, String> {
+        let mut out = Vec::new();
+        walk(&self.history_dir, "", &mut out);
+        Ok(out)
+    }
+
+    async fn read_index(&self) -> Result<SnapshotIndex, String> {
+        list_snapshots(&self.metadata_path)
+    }
+
+    async fn write_index(&self, index: &SnapshotIndex) -> Result<(), String> {
+        write_snapshots(&self.metadata_path, index)
+    }
+
+    fn location(&self, key: &str) -> String {
+        if key.is_empty() {
+            return self.history_dir.to_string_lossy().to_string();
+        }
+        self.path(key).to_string_lossy().to_string()
+    }
+}
+
+const REMOTE_HISTORY_DIR: &str = "~/.clawpal/history";
+const REMOTE_METADATA_PATH: &str = "~/.clawpal/metadata.json";
+const REMOTE_LEGACY_DIR: &str = "~/.clawpal/snapshots";
+
+pub struct SshSnapshotStore<'a> {
+    pool: &'a SshConnectionPool,
+    host_id: &'a str,
+}
+
+impl<'a> SshSnapshotStore<'a> {
+    pub fn new(pool: &'a SshConnectionPool, host_id: &'a str) -> Self {
+        Self { pool, host_id }
+    }
+
+    async fn read_path(&self, path: &str) -> Result<Option<Vec<u8>>, String> {
+        let resolved = self.pool.resolve_path(self.host_id, path).await?;
+        let quoted = crate::ssh::shell_quote(&resolved);
+        let cmd = format!("if [ -f {quoted} ]; then base64 < {quoted}; else exit 3; fi");
+        let result = self.pool.exec(self.host_id, &cmd).await?;
+        match result.exit_code {
+            0 => {
+                let encoded: String = result.stdout.split_whitespace().collect();
+                base64::engine::general_purpose::STANDARD
+                    .decode(encoded)
+                    .map(Some)
+                    .map_err(|e| format!("Failed to decode {resolved}: {e}"))
+            }
+            3 => Ok(None),
+            _ => Err(format!("Failed to read {resolved}: {}", result.stderr.trim())),
+        }
+    }
+
+    async fn write_path(&self, path: &str, bytes: &[u8]) -> Result<(), String> {
+        let resolved = self.pool.resolve_path(self.host_id, path).await?;
+        let b64 = base64::engine::general_purpose::STANDARD.encode(bytes);
+        let result = self.pool.exec(self.host_id, &crate::ssh::build_sftp_write_command(&resolved, &b64)).await?;
+        if result.exit_code != 0 {
+            return Err(format!("Failed to write {resolved}: {}", result.stderr.trim()));
+        }
+        Ok(())
+    }
+
+    /// Move snapshots from the old `~/.clawpal/snapshots/{ts}-{source}[-{summary}].json`
+    /// layout into the index, keeping their timestamps. Returns how many were imported.
+    pub async fn import_legacy(&self) -> Result<usize, String> {
+        let entries = self.pool.sftp_list(self.host_id, REMOTE_LEGACY_DIR).await.unwrap_or_default();
+        let mut imported = Vec::new();
+        let mut names = Vec::new();
+        for entry in entries {
+            if entry.is_dir || entry.name.starts_with('.') || !entry.name.ends_with(".json") {
+                continue;
+            }
+            let Some(snapshot) = parse_legacy_name(&entry.name) else {
+                continue;
+            };
+            let path = format!("{REMOTE_LEGACY_DIR}/{}", entry.name);
+            let Some(bytes) = self.read_path(&path).await? else {
+                continue;
+            };
+            let config = String::from_utf8_lossy(&bytes).to_string();
+            imported.push((NewSnapshot { config, ..snapshot.0 }, snapshot.1));
+            names.push(path);
+        }
+        if imported.is_empty() {
+            return Ok(0);
+        }
+        let count = imported.len();
+        self.insert(imported).await?;
+        for path in names {
+            let _ = self.pool.sftp_remove(self.host_id, &path).await;
+        }
+        Ok(count)
+    }
+}
+
+/// `1718000000-config-patch.json` was written by a command with that source;
+/// `1718000000-clawpal-Set model.json` by the command queue, whose sources
+/// (`clawpal`, `rollback`) are followed by a summary of the queued commands.
+fn parse_legacy_name(name: &str) -> Option<(NewSnapshot, NaiveDateTime)> {
+    let (ts, rest) = name.strip_suffix(".json")?.split_once('-')?;
+    let taken_at = chrono::DateTime::from_timestamp(ts.parse().ok()?, 0)?.naive_utc();
+    let (source, summary) = match rest.split_once('-') {
+        Some((queue @ ("clawpal" | "rollback"), summary)) => (queue, Some(summary)),
+        _ => (rest, None),
+    };
+    let source = source.to_string();
+    let recipe_id = Some(summary.map(str::to_string).unwrap_or_else(|| source.clone()));
+    let snapshot = NewSnapshot {
+        recipe_id,
+        can_rollback: source != "rollback",
+        source,
+        config: String::new(),
+        rollback_of: None,
+    };
+    Some((snapshot, taken_at))
+}
+
+impl SnapshotStore for SshSnapshotStore<'_> {
+    async fn read(&self, key: &str) -> Result<Option<Vec<u8>>, String> {
+        self.read_path(&self.location(key)).await
+    }
+
+    async fn write(&self, key: &str, bytes: &[u8]) -> Result<(), String> {
+        self.write_path(&self.location(key), bytes).await
+    }
+
+    async fn exists(&self, key: &str) -> Result<bool, String> {
+        let resolved = self.pool.resolve_path(self.host_id, &self.location(key)).await?;
+        let result = self.pool.exec(self.host_id, &format!("test -f {}", crate::ssh::shell_quote(&resolved))).await?;
+        Ok(result.exit_code == 0)
+    }
+
+    async fn remove(&self, key: &str) -> Result<(), String> {
+        self.pool.sftp_remove(self.host_id, &self.location(key)).await
+    }
+
+    async fn list(&self) -> Result<Vec<(String, u64)>, String> {
+        let resolved = self.pool.resolve_path(self.host_id, REMOTE_HISTORY_DIR).await?;
+        let cmd = format!(
+            "cd {} 2>/dev/null && find . -type f -exec wc -c {{}} + || true",
+            crate::ssh::shell_quote(&resolved)
+        );
+        let result = self.pool.exec(self.host_id, &cmd).await?;
+        Ok(result
+            .stdout
+            .lines()
+            .filter_map(|line| {
+                let (size, name) = line.trim().split_once(' ')?;
+                let key = name.trim().strip_prefix("./")?;
+                Some((key.to_string(), size.parse().ok()?))
+            })
+            .collect())
+    }
+
+    async fn read_index(&self) -> Result<SnapshotIndex, String> {
+        match self.read_path(REMOTE_METADATA_PATH).await? {
+            Some(bytes) if !bytes.iter().all(u8::is_ascii_whitespace) => {
+                serde_json::from_slice(&bytes).map_err(|e| e.to_string())
+            }
+            _ => Ok(SnapshotIndex::default()),
+        }
+    }
+
+    async fn write_index(&self, index: &SnapshotIndex) -> Result<(), String> {
+        let text = serde_json::to_string_pretty(index).map_err(|e| e.to_string())?;
+        self.write_path(REMOTE_METADATA_PATH, text.as_bytes()).await
+    }
+
+    fn location(&self, key: &str) -> String {
+        if key.is_empty() {
+            return REMOTE_HISTORY_DIR.to_string();
+        }
+        format!("{REMOTE_HISTORY_DIR}/{key}")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+    use std::sync::Mutex;
+
+    /// Keeps files in memory and never yields, like a remote host that
+    /// answers instantly.
+    #[derive(Default)]
+    struct MemoryStore {
+        files: Mutex<BTreeMap<String, Vec<u8>>>,
+        index: Mutex<Vec<SnapshotMeta>>,
+    }
+
+    impl SnapshotStore for MemoryStore {
+        async fn read(&self, key: &str) -> Result<Option<Vec<u8>>, String> {
+            Ok(self.files.lock().unwrap().get(key).cloned())
+        }
+        async fn write(&self, key: &str, bytes: &[u8]) -> Result<(), String> {
+            self.files.lock().unwrap().insert(key.to_string(), bytes.to_vec());
+            Ok(())
+        }
+        async fn exists(&self, key: &str) -> Result<bool, String> {
+            Ok(self.files.lock().unwrap().contains_key(key))
+        }
+        async fn remove(&self, key: &str) -> Result<(), String> {
+            self.files.lock().unwrap().remove(key).map(|_| ()).ok_or_else(|| format!("no such file: {key}"))
+        }
+        async fn list(&self) -> Result<Vec<(String, u64)>, String> {
+            Ok(self.files.lock().unwrap().iter().map(|(k, v)| (k.clone(), v.len() as u64)).collect())
+        }
+        async fn read_index(&self) -> Result<SnapshotIndex, String> {
+            Ok(SnapshotIndex { items: self.index.lock().unwrap().clone() })
+        }
+        async fn write_index(&self, index: &SnapshotIndex) -> Result<(), String> {
+            *self.index.lock().unwrap() = index.items.clone();
+            Ok(())
+        }
+        fn location(&self, key: &str) -> String {
+            format!("mem://history/{key}")
+        }
+    }
+
+    fn snapshot(config: &str, source: &str, rollback_of: Option<String>) -> NewSnapshot {
+        NewSnapshot {
+            recipe_id: Some("r".into()),
+            source: source.into(),
+            can_rollback: true,
+            config: config.into(),
+            rollback_of,
+        }
+    }
+
+    fn at(text: &str) -> NaiveDateTime {
+        NaiveDateTime::parse_from_str(text, TIMESTAMP_FORMAT).unwrap()
+    }
+
+    #[test]
+    fn stores_share_rollback_chains_retention_and_gc() {
+        let store = MemoryStore::default();
+        let first = run_local(store.add(snapshot("{\"v\":1}", "recipe", None))).unwrap();
+        assert_eq!(first.config_path, format!("mem://history/{}", blob_key(first.hash.as_deref().unwrap())));
+        let back = run_local(store.insert(vec![(snapshot("{\"v\":2}", "rollback", Some(first.id.clone())), at("2099-01-01T00-00-00"))]))
+            .unwrap()
+            .remove(0);
+        let found = run_local(store.find(&back.id)).unwrap();
+        assert_eq!(found.rollback_of.as_deref(), Some(first.id.as_str()));
+        assert_eq!(run_local(store.content(&found)).unwrap(), "{\"v\":2}");
+
+        store.files.lock().unwrap().insert("1999-01-01T00-00-00-manual.json".into(), b"{}".to_vec());
+        run_local(store.set_retention(&RetentionPolicy { keep_last: 1, keep_daily_days: 0 })).unwrap();
+        let report = run_local(store.gc()).unwrap();
+        assert_eq!((report.kept_snapshots, report.removed_snapshots, report.removed_files), (1, 1, 1));
+        // The first snapshot's content is still the head, so its blob stays.
+        let keys: HashSet<String> = store.files.lock().unwrap().keys().cloned().collect();
+        let expected = [&back, &first].map(|m| blob_key(m.hash.as_deref().unwrap()));
+        assert_eq!(keys, expected.into_iter().chain([HEAD_FILE.into(), RETENTION_FILE.into()]).collect());
+    }
+
+    #[test]
+    fn records_outside_edits_as_external_snapshots() {
+        let store = MemoryStore::default();
+        assert!(run_local(store.observe("{\"v\": 1}")).unwrap().is_none());
+        // Reformatting isn't a change.
+        assert!(run_local(store.observe("{\n  \"v\": 1\n}")).unwrap().is_none());
+        let external = run_local(store.observe("{\"v\":2}")).unwrap().unwrap();
+        assert_eq!((external.source.as_str(), external.can_rollback), (EXTERNAL_SOURCE, true));
+        assert_eq!(run_local(store.content(&external)).unwrap(), "{\"v\": 1}");
+
+        // A write whose "before" differs from the head first records the edit it missed.
+        run_local(store.set_head("{\"v\":3}")).unwrap();
+        let own = run_local(store.add(snapshot("{\"v\":4}", "recipe", None))).unwrap();
+        let items = run_local(store.snapshots()).unwrap();
+        let sources: Vec<_> = items.iter().map(|s| s.source.as_str()).collect();
+        assert_eq!(sources, vec!["recipe", EXTERNAL_SOURCE, EXTERNAL_SOURCE]);
+        assert_eq!(items[0].id, own.id);
+        assert_eq!(run_local(store.content(&items[1])).unwrap(), "{\"v\":3}");
+        let head = run_local(async { Ok(store.head().await) }).unwrap().unwrap();
+        assert_eq!(head.hash, content_hash("{\"v\":4}"));
+    }
+
+    #[test]
+    fn pinned_snapshots_survive_retention() {
+        let store = MemoryStore::default();
+        run_local(store.set_retention(&RetentionPolicy { keep_last: 1, keep_daily_days: 0 })).unwrap();
+        let old = run_local(store.insert(vec![(snapshot("{\"v\":1}", "recipe", None), at("2020-01-01T00-00-00"))]))
+            .unwrap()
+            .remove(0);
+        let update = SnapshotUpdate {
+            pinned: Some(true),
+            labels: Some(vec!["known-good".into()]),
+            note: Some("before the proxy change".into()),
+        };
+        run_local(store.update(&old.id, update)).unwrap();
+        run_local(store.add(snapshot("{\"v\":2}", "recipe", None))).unwrap();
+        run_local(store.add(snapshot("{\"v\":3}", "recipe", None))).unwrap();
+        let kept = run_local(store.snapshots()).unwrap();
+        assert_eq!(kept.len(), 2);
+        let pinned = run_local(store.find(&old.id)).unwrap();
+        assert_eq!(pinned.labels, vec!["known-good"]);
+        assert_eq!(run_local(store.content(&pinned)).unwrap(), "{\"v\":1}");
+        assert!(run_local(store.update("missing", SnapshotUpdate::default())).is_err());
+    }
+
+    #[test]
+    fn blames_a_path_across_snapshots() {
+        let store = MemoryStore::default();
+        let configs = ["{}", "{\"gateway\":{\"port\":1}}", "{\"gateway\":{\"port\":1},\"x\":1}"];
+        for (i, config) in configs.into_iter().enumerate() {
+            let taken_at = at(&format!("2025-01-0{}T00-00-00", i + 1));
+            run_local(store.insert(vec![(snapshot(config, &format!("step-{i}"), None), taken_at)])).unwrap();
+        }
+        let current = serde_json::json!({ "gateway": { "port": 2 } });
+        let entries = run_local(store.blame("gateway.port", &current)).unwrap();
+        let sources: Vec<_> = entries.iter().map(|e| (e.source.as_str(), e.after.clone())).collect();
+        assert_eq!(sources, vec![("step-2", Some(2.into())), ("step-0", Some(1.into()))]);
+        assert!(run_local(store.blame("gateway..port", &current)).is_err());
+    }
+
+    #[test]
+    fn parses_legacy_remote_snapshot_names() {
+        let (snapshot, taken_at) = parse_legacy_name("1718000000-rollback-Set model-x.json").unwrap();
+        assert_eq!(taken_at.format(TIMESTAMP_FORMAT).to_string(), "2024-06-10T06-13-20");
+        assert_eq!((snapshot.source.as_str(), snapshot.recipe_id.as_deref()), ("rollback", Some("Set model-x")));
+        assert!(!snapshot.can_rollback);
+        let (snapshot, _) = parse_legacy_name("1718000000-config-patch.json").unwrap();
+        assert_eq!((snapshot.source.as_str(), snapshot.recipe_id.as_deref()), ("config-patch", Some("config-patch")));
+        assert!(snapshot.can_rollback);
+        assert!(parse_legacy_name("notes.json").is_none());
+    }
+}
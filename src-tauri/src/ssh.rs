@@ -1,6 +1,7 @@
 use std::collections::HashMap;
 
 use base64::Engine;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use tokio::sync::Mutex;
 
@@ -8,7 +9,7 @@ use tokio::sync::Mutex;
 // Data types (unchanged — frontend compatibility)
 // ---------------------------------------------------------------------------
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct SshHostConfig {
     pub id: String,
@@ -39,7 +40,7 @@ pub struct SftpEntry {
 }
 
 /// Shell-quote a string using single quotes with proper escaping.
-fn shell_quote(s: &str) -> String {
+pub(crate) fn shell_quote(s: &str) -> String {
     format!("'{}'", s.replace('\'', "'\\''"))
 }
 
@@ -49,7 +50,7 @@ fn base64_decode_pipeline() -> &'static str {
 }
 
 /// Build a safe remote write command using base64 transport.
-fn build_sftp_write_command(path: &str, b64: &str) -> String {
+pub(crate) fn build_sftp_write_command(path: &str, b64: &str) -> String {
     let quoted = shell_quote(path);
     format!(
         "mkdir -p \"$(dirname {quoted})\" && printf '%s' '{b64}' | ({decode}) > {quoted}",
@@ -0,0 +1,29 @@
+use std::path::Path;
+
+use clawpal::recipe_lint::{lint_document, load_checked, run_fixtures};
+
+const RECIPES: &str = include_str!("../recipes.json");
+
+#[test]
+fn bundled_recipes_have_no_lint_errors() {
+    let diagnostics = lint_document(RECIPES);
+    assert!(diagnostics.is_empty(), "{diagnostics:#?}");
+}
+
+#[test]
+fn bundled_recipes_match_golden_fixtures() {
+    let recipes = load_checked(RECIPES).expect("bundled recipes load");
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/recipes");
+    let results = run_fixtures(&recipes, &dir, false).expect("fixtures run");
+    assert!(!results.is_empty(), "no fixture cases found in {}", dir.display());
+    for result in &results {
+        assert_eq!(
+            result.status, "pass",
+            "{} ({}): {:?}\n{}",
+            result.case,
+            result.recipe_id,
+            result.message,
+            result.diff.as_deref().unwrap_or("")
+        );
+    }
+}
@@ -27,7 +27,7 @@ import { Chat } from "./components/Chat";
 import logoUrl from "./assets/logo.png";
 import { PendingChangesBar } from "./components/PendingChangesBar";
 import { InstanceTabBar } from "./components/InstanceTabBar";
-import { InstanceContext } from "./lib/instance-context";
+import { InstanceContext, isLocalInstanceId } from "./lib/instance-context";
 import { api } from "./lib/api";
 import { Button } from "@/components/ui/button";
 import { Dialog, DialogContent, DialogFooter, DialogHeader, DialogTitle } from "@/components/ui/dialog";
@@ -35,7 +35,7 @@ import { Input } from "@/components/ui/input";
 import { Label } from "@/components/ui/label";
 import { cn } from "@/lib/utils";
 import { Toaster } from "sonner";
-import type { DiscordGuildChannel, SshHost } from "./lib/types";
+import type { DiscordGuildChannel, LocalInstance, SshHost } from "./lib/types";
 
 const PING_URL = "https://api.clawpal.zhixian.io/ping";
 
@@ -75,12 +75,14 @@ export function App() {
   const [route, setRoute] = useState<Route>("home");
   const [recipeId, setRecipeId] = useState<string | null>(null);
   const [recipeSource, setRecipeSource] = useState<string | undefined>(undefined);
+  const [recipeParams, setRecipeParams] = useState<Record<string, string> | undefined>(undefined);
   const [discordGuildChannels, setDiscordGuildChannels] = useState<DiscordGuildChannel[]>([]);
   const [chatOpen, setChatOpen] = useState(false);
 
   // SSH remote instance state
   const [activeInstance, setActiveInstance] = useState("local");
   const [sshHosts, setSshHosts] = useState<SshHost[]>([]);
+  const [localInstances, setLocalInstances] = useState<LocalInstance[]>([]);
   const [connectionStatus, setConnectionStatus] = useState<Record<string, "connected" | "disconnected" | "error">>({});
 
   const refreshHosts = useCallback(() => {
@@ -91,6 +93,28 @@ export function App() {
     refreshHosts();
   }, [refreshHosts]);
 
+  const refreshLocalInstances = useCallback(() => {
+    return api.listLocalInstances()
+      .then((instances) => {
+        setLocalInstances(instances);
+        return instances;
+      })
+      .catch((e) => {
+        console.error("Failed to load local instances:", e);
+        return [] as LocalInstance[];
+      });
+  }, []);
+
+  // Start on whichever local instance the backend has selected
+  useEffect(() => {
+    refreshLocalInstances().then((instances) => {
+      const active = instances.find((instance) => instance.active);
+      if (active) {
+        setActiveInstance((current) => (current === "local" ? active.id : current));
+      }
+    });
+  }, [refreshLocalInstances]);
+
   const [appUpdateAvailable, setAppUpdateAvailable] = useState(false);
   const [hasEscalatedCron, setHasEscalatedCron] = useState(false);
 
@@ -183,37 +207,45 @@ export function App() {
 
 
   const handleInstanceSelect = useCallback((id: string) => {
-    setActiveInstance(id);
-    if (id !== "local") {
-      // Check if backend still has a live connection before reconnecting.
-      // Do not pre-mark as disconnected — transient status failures would
-      // otherwise gray out the whole remote UI.
-      api.sshStatus(id)
-        .then((status) => {
-          if (status === "connected") {
-            setConnectionStatus((prev) => ({ ...prev, [id]: "connected" }));
-          } else {
-            return connectWithPassphraseFallback(id)
-              .then(() => setConnectionStatus((prev) => ({ ...prev, [id]: "connected" })));
-          }
+    if (isLocalInstanceId(id)) {
+      // Local commands follow the backend's selection, so switch it first
+      api.selectLocalInstance(id)
+        .then(() => {
+          setActiveInstance(id);
+          refreshLocalInstances();
         })
-        .catch(() => {
-          // sshStatus failed or reconnect failed — try fresh connect
-          connectWithPassphraseFallback(id)
-            .then(() => setConnectionStatus((prev) => ({ ...prev, [id]: "connected" })))
-            .catch((e2) => {
-              setConnectionStatus((prev) => ({ ...prev, [id]: "error" }));
-              const raw = String(e2);
-              const friendly = friendlySshError(raw, t);
-              showToast(friendly, "error");
-            });
-        });
+        .catch((e) => showToast(String(e), "error"));
+      return;
     }
-  }, [connectWithPassphraseFallback, showToast, t]);
+    setActiveInstance(id);
+    // Check if backend still has a live connection before reconnecting.
+    // Do not pre-mark as disconnected — transient status failures would
+    // otherwise gray out the whole remote UI.
+    api.sshStatus(id)
+      .then((status) => {
+        if (status === "connected") {
+          setConnectionStatus((prev) => ({ ...prev, [id]: "connected" }));
+        } else {
+          return connectWithPassphraseFallback(id)
+            .then(() => setConnectionStatus((prev) => ({ ...prev, [id]: "connected" })));
+        }
+      })
+      .catch(() => {
+        // sshStatus failed or reconnect failed — try fresh connect
+        connectWithPassphraseFallback(id)
+          .then(() => setConnectionStatus((prev) => ({ ...prev, [id]: "connected" })))
+          .catch((e2) => {
+            setConnectionStatus((prev) => ({ ...prev, [id]: "error" }));
+            const raw = String(e2);
+            const friendly = friendlySshError(raw, t);
+            showToast(friendly, "error");
+          });
+      });
+  }, [connectWithPassphraseFallback, refreshLocalInstances, showToast, t]);
 
   const [configVersion, setConfigVersion] = useState(0);
 
-  const isRemote = activeInstance !== "local";
+  const isRemote = !isLocalInstanceId(activeInstance);
   const isConnected = !isRemote || connectionStatus[activeInstance] === "connected";
 
   // Keep active remote instance self-healed: detect dropped SSH and reconnect.
@@ -280,19 +312,23 @@ export function App() {
 
   // Load Discord data + extract profiles on startup or connection ready
   useEffect(() => {
-    if (activeInstance === "local") {
+    if (!isRemote) {
       if (!localStorage.getItem("clawpal_profiles_extracted")) {
         api.extractModelProfilesFromConfig()
-          .then(() => localStorage.setItem("clawpal_profiles_extracted", "1"))
+          .then((result) => {
+            result.warnings.forEach((w) => console.warn("Model profile extraction:", w));
+            localStorage.setItem("clawpal_profiles_extracted", "1");
+          })
           .catch((e) => console.error("Failed to extract model profiles:", e));
       }
       api.listDiscordGuildChannels().then(setDiscordGuildChannels).catch((e) => console.error("Failed to load Discord channels:", e));
     } else if (isConnected) {
       api.remoteExtractModelProfilesFromConfig(activeInstance)
+        .then((result) => result.warnings.forEach((w) => console.warn("Model profile extraction:", w)))
         .catch((e) => console.error("Failed to extract remote model profiles:", e));
       api.remoteListDiscordGuildChannels(activeInstance).then(setDiscordGuildChannels).catch((e) => console.error("Failed to load remote Discord channels:", e));
     }
-  }, [activeInstance, isConnected]);
+  }, [activeInstance, isRemote, isConnected]);
 
   // Poll watchdog status for escalated cron jobs (red dot badge)
   useEffect(() => {
@@ -344,10 +380,12 @@ export function App() {
     <div className="flex flex-col h-screen bg-background text-foreground">
       <InstanceTabBar
         hosts={sshHosts}
+        localInstances={localInstances}
         activeId={activeInstance}
         connectionStatus={connectionStatus}
         onSelect={handleInstanceSelect}
         onHostsChange={refreshHosts}
+        onLocalInstancesChange={refreshLocalInstances}
       />
       <InstanceContext.Provider value={{ instanceId: activeInstance, isRemote, isConnected, discordGuildChannels }}>
       <div className="flex flex-1 overflow-hidden">
@@ -446,6 +484,7 @@ export function App() {
               onCook={(id, source) => {
                 setRecipeId(id);
                 setRecipeSource(source);
+                setRecipeParams(undefined);
                 setRoute("cook");
               }}
               showToast={showToast}
@@ -454,9 +493,10 @@ export function App() {
           )}
           {route === "recipes" && (
             <Recipes
-              onCook={(id, source) => {
+              onCook={(id, source, params) => {
                 setRecipeId(id);
                 setRecipeSource(source);
+                setRecipeParams(params);
                 setRoute("cook");
               }}
             />
@@ -465,6 +505,7 @@ export function App() {
             <Cook
               recipeId={recipeId}
               recipeSource={recipeSource}
+              initialParams={recipeParams}
               onDone={() => {
                 setRoute("recipes");
               }}
@@ -30,14 +30,16 @@ import {
 } from "@/components/ui/alert-dialog";
 import { cn } from "@/lib/utils";
 import { api } from "@/lib/api";
-import type { SshConfigHostSuggestion, SshHost } from "@/lib/types";
+import type { LocalInstance, SshConfigHostSuggestion, SshHost } from "@/lib/types";
 
 interface InstanceTabBarProps {
   hosts: SshHost[];
-  activeId: string; // "local" or host.id
+  localInstances: LocalInstance[];
+  activeId: string; // local instance id or host.id
   connectionStatus: Record<string, "connected" | "disconnected" | "error">;
   onSelect: (id: string) => void;
   onHostsChange: () => void;
+  onLocalInstancesChange: () => void;
 }
 
 const emptyHost: Omit<SshHost, "id"> = {
@@ -52,10 +54,12 @@ const emptyHost: Omit<SshHost, "id"> = {
 
 export function InstanceTabBar({
   hosts,
+  localInstances,
   activeId,
   connectionStatus,
   onSelect,
   onHostsChange,
+  onLocalInstancesChange,
 }: InstanceTabBarProps) {
   const { t } = useTranslation();
   const [dialogOpen, setDialogOpen] = useState(false);
@@ -66,6 +70,9 @@ export function InstanceTabBar({
   const [keyGuideOpen, setKeyGuideOpen] = useState(false);
   const [sshConfigHosts, setSshConfigHosts] = useState<SshConfigHostSuggestion[]>([]);
   const [selectedConfigAlias, setSelectedConfigAlias] = useState<string | undefined>(undefined);
+  const [localDialogOpen, setLocalDialogOpen] = useState(false);
+  const [localForm, setLocalForm] = useState({ path: "", label: "" });
+  const [localFormError, setLocalFormError] = useState("");
   const duplicateDisplayNames = useMemo(() => {
     const counts = new Map<string, number>();
     for (const host of hosts) {
@@ -159,6 +166,41 @@ export function InstanceTabBar({
       .catch((e) => console.error("Failed to delete SSH host:", e));
   };
 
+  const openAddLocalDialog = () => {
+    setLocalForm({ path: "", label: "" });
+    setLocalFormError("");
+    setLocalDialogOpen(true);
+  };
+
+  const handleAddLocal = () => {
+    setLocalFormError("");
+    setSaving(true);
+    api
+      .addLocalInstance(localForm.path, localForm.label || undefined)
+      .then((instance) => {
+        onLocalInstancesChange();
+        setLocalDialogOpen(false);
+        onSelect(instance.id);
+      })
+      .catch((e) => setLocalFormError(String(e)))
+      .finally(() => setSaving(false));
+  };
+
+  const handleRemoveLocal = (instanceId: string) => {
+    api
+      .removeLocalInstance(instanceId)
+      .then(() => {
+        onLocalInstancesChange();
+        if (activeId === instanceId) onSelect("local");
+      })
+      .catch((e) => console.error("Failed to remove local instance:", e));
+  };
+
+  const localDisplayName = (instance: LocalInstance) =>
+    instance.kind === "default"
+      ? t('instance.local')
+      : t('instance.localNamed', { label: instance.label });
+
   const statusDot = (status: "connected" | "disconnected" | "error" | undefined) => {
     const color =
       status === "connected"
@@ -180,19 +222,42 @@ export function InstanceTabBar({
   return (
     <>
       <div className="flex items-center gap-1 px-3 py-2 bg-sidebar border-b border-sidebar-border overflow-x-auto shrink-0">
-        {/* Local tab */}
-        <button
-          className={cn(
-            "flex items-center gap-1.5 px-3 py-1.5 rounded-lg text-sm whitespace-nowrap transition-all duration-200 cursor-pointer",
-            activeId === "local"
-              ? "bg-card shadow-sm font-semibold text-primary border-b-2 border-b-primary"
-              : "text-muted-foreground hover:text-foreground"
-          )}
-          onClick={() => onSelect("local")}
-        >
-          {statusDot("connected")}
-          {t('instance.local')}
-        </button>
+        {/* Local tabs: the default instance even before the list loads */}
+        {(localInstances.length > 0
+          ? localInstances
+          : [{ id: "local", kind: "default", hasConfig: true } as LocalInstance]
+        ).map((instance) => (
+          <div
+            key={instance.id}
+            className="relative group flex items-center"
+          >
+            <button
+              className={cn(
+                "flex items-center gap-1.5 px-3 py-1.5 rounded-lg text-sm whitespace-nowrap transition-all duration-200 cursor-pointer",
+                activeId === instance.id
+                  ? "bg-card shadow-sm font-semibold text-primary border-b-2 border-b-primary"
+                  : "text-muted-foreground hover:text-foreground"
+              )}
+              onClick={() => onSelect(instance.id)}
+              title={instance.openclawDir}
+            >
+              {statusDot(instance.hasConfig ? "connected" : "disconnected")}
+              {localDisplayName(instance)}
+            </button>
+            {instance.removable && (
+              <button
+                className="absolute -top-0.5 -right-0.5 hidden group-hover:flex items-center justify-center w-4 h-4 rounded-full bg-muted-foreground/20 hover:bg-destructive hover:text-white text-[10px] leading-none"
+                title={t('instance.removeLocal')}
+                onClick={(e) => {
+                  e.stopPropagation();
+                  handleRemoveLocal(instance.id);
+                }}
+              >
+                &times;
+              </button>
+            )}
+          </div>
+        ))}
 
         {/* Remote tabs */}
         {hosts.map((host) => (
@@ -248,7 +313,15 @@ export function InstanceTabBar({
           </div>
         ))}
 
-        {/* Add button */}
+        {/* Add buttons */}
+        <Button
+          variant="ghost"
+          size="sm"
+          className="h-7 px-2 shrink-0 text-xs"
+          onClick={openAddLocalDialog}
+        >
+          {t('instance.addLocal')}
+        </Button>
         <Button
           variant="ghost"
           size="sm"
@@ -259,6 +332,51 @@ export function InstanceTabBar({
         </Button>
       </div>
 
+      {/* Add Local Instance Dialog */}
+      <Dialog open={localDialogOpen} onOpenChange={setLocalDialogOpen}>
+        <DialogContent>
+          <DialogHeader>
+            <DialogTitle>{t('instance.addLocalTitle')}</DialogTitle>
+          </DialogHeader>
+          <div className="space-y-4">
+            <div className="space-y-1.5">
+              <Label htmlFor="local-path">{t('instance.localPath')}</Label>
+              <Input
+                id="local-path"
+                value={localForm.path}
+                onChange={(e) => setLocalForm((f) => ({ ...f, path: e.target.value }))}
+                placeholder="~/.openclaw-work"
+                autoCapitalize="off"
+                autoCorrect="off"
+                spellCheck={false}
+              />
+              <p className="text-xs text-muted-foreground">{t('instance.localPathHint')}</p>
+            </div>
+            <div className="space-y-1.5">
+              <Label htmlFor="local-label">{t('instance.label')}</Label>
+              <Input
+                id="local-label"
+                value={localForm.label}
+                onChange={(e) => setLocalForm((f) => ({ ...f, label: e.target.value }))}
+              />
+            </div>
+          </div>
+          <DialogFooter>
+            {localFormError && (
+              <p className="text-xs text-destructive mr-auto">
+                {localFormError}
+              </p>
+            )}
+            <Button variant="outline" onClick={() => setLocalDialogOpen(false)} disabled={saving}>
+              {t('instance.cancel')}
+            </Button>
+            <Button onClick={handleAddLocal} disabled={saving || !localForm.path.trim()}>
+              {saving ? t('instance.saving') : t('instance.add')}
+            </Button>
+          </DialogFooter>
+        </DialogContent>
+      </Dialog>
+
       {/* Add/Edit Dialog */}
       <Dialog open={dialogOpen} onOpenChange={setDialogOpen}>
         <DialogContent>
@@ -22,7 +22,7 @@ import {
   AlertDialogHeader,
   AlertDialogTitle,
 } from "@/components/ui/alert-dialog";
-import { cn } from "@/lib/utils";
+import { cn, describeConfigError } from "@/lib/utils";
 import type { PendingCommand, PreviewQueueResult } from "@/lib/types";
 
 interface PendingChangesBarProps {
@@ -113,14 +113,16 @@ export function PendingChangesBar({ onApplied, showToast }: PendingChangesBarPro
           onApplied?.();
         } else {
           setApplyError(
-            result.error || t("queue.applyFailed", { error: "unknown" }),
+            result.error
+              ? describeConfigError(result.error, t)
+              : t("queue.applyFailed", { error: "unknown" }),
           );
           if (result.rolledBack) {
             setApplyError((prev) => prev + " " + t("queue.rolledBack"));
           }
         }
       })
-      .catch((e) => setApplyError(String(e)))
+      .catch((e) => setApplyError(describeConfigError(e, t)))
       .finally(() => setApplying(false));
   }, [api, refreshCount, showToast, onApplied, t]);
 
@@ -1,5 +1,5 @@
 import { api } from "./api";
-import type { ModelProfile } from "./types";
+import type { ModelProfile, RecipeResolvedStep } from "./types";
 import { profileToModelValue } from "./model-value";
 
 export interface ActionContext {
@@ -27,33 +27,6 @@ export interface ActionDef {
   describe: (args: Record<string, unknown>) => string;
 }
 
-function renderArgs(
-  args: Record<string, unknown>,
-  params: Record<string, string>,
-): Record<string, unknown> {
-  const result: Record<string, unknown> = {};
-  for (const [key, value] of Object.entries(args)) {
-    if (typeof value === "string") {
-      const singleMatch = value.match(/^\{\{(\w+)\}\}$/);
-      if (singleMatch) {
-        const paramValue = params[singleMatch[1]] ?? "";
-        if (paramValue === "true") result[key] = true;
-        else if (paramValue === "false") result[key] = false;
-        else result[key] = paramValue;
-      } else {
-        let rendered = value;
-        for (const [paramId, paramValue] of Object.entries(params)) {
-          rendered = rendered.split(`{{${paramId}}}`).join(paramValue);
-        }
-        result[key] = rendered;
-      }
-    } else {
-      result[key] = value;
-    }
-  }
-  return result;
-}
-
 const registry: Record<string, ActionDef> = {
   create_agent: {
     toCommands: async (args, ctx) => {
@@ -176,6 +149,29 @@ const registry: Record<string, ActionDef> = {
     },
     describe: () => "",
   },
+  json_patch: {
+    toCommands: async (args, ctx) => {
+      const rawConfig = ctx?.isRemote
+        ? await api.remoteReadRawConfig(ctx.instanceId)
+        : await api.readRawConfig();
+      const before = JSON.parse(rawConfig) as Record<string, unknown>;
+      const after = (await api.applyJsonPatch(before, args.operations)) as Record<string, unknown>;
+      // Rewrite each top-level section the patch touched.
+      const commands: [string, string[]][] = [];
+      for (const key of new Set([...Object.keys(before), ...Object.keys(after)])) {
+        if (!(key in after)) {
+          commands.push([`Unset ${key}`, ["openclaw", "config", "unset", key]]);
+        } else if (JSON.stringify(before[key]) !== JSON.stringify(after[key])) {
+          commands.push([`Set ${key}`, ["openclaw", "config", "set", key, JSON.stringify(after[key]), "--json"]]);
+        }
+      }
+      return commands;
+    },
+    describe: (args) => {
+      const count = Array.isArray(args.operations) ? args.operations.length : 0;
+      return `Apply ${count} JSON Patch operation${count === 1 ? "" : "s"}`;
+    },
+  },
   set_global_model: {
     toCommands: async (args, ctx) => {
       const modelValue = await resolveProfileToModelValue(
@@ -208,36 +204,31 @@ export interface ResolvedStep {
   args: Record<string, unknown>;
   description: string;
   skippable: boolean;
+  skipReason?: string;
+  onError: "abort" | "continue";
 }
 
-export function resolveSteps(
-  steps: { action: string; label: string; args: Record<string, unknown> }[],
-  params: Record<string, string>,
-): ResolvedStep[] {
-  return steps.map((step, index) => {
-    const resolved = renderArgs(step.args, params);
+/** Attach UI descriptions to steps resolved by the recipe engine. */
+export function describeSteps(steps: RecipeResolvedStep[]): ResolvedStep[] {
+  return steps.map((step) => {
+    const args = { ...step.args };
     if (step.action === "config_patch") {
-      resolved.params = params;
+      // The patch template is rendered with the step's params, loop variables included.
+      args.params = Object.fromEntries(
+        Object.entries(step.scope).map(([k, v]) => [k, typeof v === "string" ? v : JSON.stringify(v)]),
+      );
     }
-    const skippable = Object.values(step.args).some((origValue) => {
-      if (typeof origValue !== "string") return false;
-      const matches = origValue.match(/\{\{(\w+)\}\}/g);
-      if (!matches) return false;
-      return matches.some((m) => {
-        const paramId = m.slice(2, -2);
-        const val = params[paramId];
-        return val !== undefined && val.trim() === "";
-      });
-    });
     const actionDef = getAction(step.action);
-    const description = actionDef?.describe(resolved) || step.label;
+    const description = actionDef?.describe(args) || step.label;
     return {
-      index,
+      index: step.index,
       action: step.action,
       label: step.label,
-      args: resolved,
-      description: description || step.label,
-      skippable,
+      args,
+      description,
+      skippable: step.skipReason !== undefined,
+      skipReason: step.skipReason,
+      onError: step.onError,
     };
   });
 }
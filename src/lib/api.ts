@@ -1,5 +1,5 @@
 import { invoke } from "@tauri-apps/api/core";
-import type { AgentOverview, AgentSessionAnalysis, ApplyQueueResult, ApplyResult, BackupInfo, Binding, ChannelNode, CronJob, CronRun, DiscordGuildChannel, GatewayCredentials, HistoryItem, InstanceStatus, StatusExtra, ModelCatalogProvider, ModelProfile, PendingCommand, PreviewQueueResult, PreviewResult, ProviderAuthSuggestion, Recipe, RescueBotAction, RescueBotManageResult, RescuePrimaryDiagnosisResult, RescuePrimaryRepairResult, ResolvedApiKey, SshConfigHostSuggestion, SystemStatus, DoctorReport, SessionFile, SshHost, WatchdogStatus } from "./types";
+import type { AgentOverview, AgentSessionAnalysis, ApplyQueueResult, ApplyResult, BackupInfo, BlameEntry, Binding, ChannelNode, CronJob, CronRun, DiscordGuildChannel, GatewayCredentials, GitHistorySettings, HistoryExportResult, HistoryGcReport, HistoryImportReport, HistoryItem, HistoryPage, HistoryRetentionPolicy, InstallPreview, InstanceStatus, JsonSchemaDocument, LocalInstance, StatusExtra, ModelCatalogProvider, ModelProfile, ParamError, PendingCommand, PreviewQueueResult, PreviewResult, ProviderAuthSuggestion, Recipe, RecipeExecution, RecipeInstall, RecipeResolvedStep, UninstallPreview, UninstallResult, RecipeSourceStatus, RecipeTrustPolicy, RecipeTrustStore, RescueBotAction, RescueBotManageResult, RescuePrimaryDiagnosisResult, RescuePrimaryRepairResult, ResolvedApiKey, SnapshotDiff, SnapshotUpdate, SshConfigHostSuggestion, SystemStatus, DoctorReport, SessionFile, SshHost, WatchdogStatus } from "./types";
 
 export const api = {
   getSystemStatus: (): Promise<SystemStatus> =>
@@ -14,17 +14,73 @@ export const api = {
     invoke("refresh_model_catalog", {}),
   listRecipes: (source?: string): Promise<Recipe[]> =>
     invoke("list_recipes", source ? { source } : {}),
+  listRecipeSources: (source?: string): Promise<RecipeSourceStatus[]> =>
+    invoke("list_recipe_sources", source ? { source } : {}),
+  upsertRecipeSource: (url: string, precedence?: number, enabled?: boolean): Promise<boolean> =>
+    invoke("upsert_recipe_source", { url, precedence: precedence ?? null, enabled: enabled ?? null }),
+  removeRecipeSource: (url: string): Promise<boolean> =>
+    invoke("remove_recipe_source", { url }),
+  getRecipeTrust: (): Promise<RecipeTrustStore> =>
+    invoke("get_recipe_trust", {}),
+  addTrustedRecipeKey: (name: string, publicKey: string): Promise<RecipeTrustStore> =>
+    invoke("add_trusted_recipe_key", { name, publicKey }),
+  removeTrustedRecipeKey: (keyId: string): Promise<RecipeTrustStore> =>
+    invoke("remove_trusted_recipe_key", { keyId }),
+  setRecipeTrustPolicy: (policy: RecipeTrustPolicy): Promise<RecipeTrustStore> =>
+    invoke("set_recipe_trust_policy", { policy }),
   applyConfigPatch: (patchTemplate: string, params: Record<string, string>): Promise<ApplyResult> =>
     invoke("apply_config_patch", { patchTemplate, params }),
-  listHistory: (limit = 20, offset = 0): Promise<{ items: HistoryItem[] }> =>
-    invoke("list_history", { limit, offset }),
+  executeRecipe: (recipeId: string, params: Record<string, unknown>, source?: string, allowUntrusted = false): Promise<RecipeExecution> =>
+    invoke("execute_recipe", { recipeId, params, source: source ?? null, allowUntrusted }),
+  listRecipeInstalls: (): Promise<RecipeInstall[]> =>
+    invoke("list_recipe_installs", {}),
+  previewRecipeUninstall: (installId: string): Promise<UninstallPreview> =>
+    invoke("preview_recipe_uninstall", { installId }),
+  uninstallRecipe: (installId: string, keepConflicts = false): Promise<UninstallResult> =>
+    invoke("uninstall_recipe", { installId, keepConflicts }),
+  prepareRecipeInstall: (link: string): Promise<InstallPreview> =>
+    invoke("prepare_recipe_install", { link }),
+  previewRecipe: (recipeId: string, params: Record<string, unknown>, target?: string, source?: string): Promise<PreviewResult> =>
+    invoke("preview_recipe", { recipeId, params, target: target ?? null, source: source ?? null }),
+  validateRecipeParams: (recipeId: string, params: Record<string, unknown>, source?: string): Promise<ParamError[]> =>
+    invoke("validate_recipe_params", { recipeId, params, source: source ?? null }),
+  resolveRecipeSteps: (recipeId: string, params: Record<string, unknown>, source?: string): Promise<RecipeResolvedStep[]> =>
+    invoke("resolve_recipe_steps", { recipeId, params, source: source ?? null }),
+  applyJsonPatch: (document: unknown, operations: unknown): Promise<unknown> =>
+    invoke("apply_json_patch", { document, operations }),
+  getJsonSchemas: (): Promise<JsonSchemaDocument[]> =>
+    invoke("get_json_schemas", {}),
+  listHistory: (limit = 20, offset = 0, query?: string): Promise<HistoryPage> =>
+    invoke("list_history", { limit, offset, query }),
+  updateSnapshot: (snapshotId: string, update: SnapshotUpdate): Promise<HistoryItem> =>
+    invoke("update_snapshot", { snapshotId, update }),
+  diffSnapshots: (fromId: string, toId: string): Promise<SnapshotDiff> =>
+    invoke("diff_snapshots", { fromId, toId }),
+  blame: (jsonPath: string): Promise<BlameEntry[]> =>
+    invoke("blame", { jsonPath }),
+  getHistoryRetention: (): Promise<HistoryRetentionPolicy> =>
+    invoke("get_history_retention", {}),
+  setHistoryRetention: (policy: HistoryRetentionPolicy): Promise<HistoryRetentionPolicy> =>
+    invoke("set_history_retention", { policy }),
+  gcHistory: (): Promise<HistoryGcReport> =>
+    invoke("gc_history", {}),
+  getGitHistorySettings: (): Promise<GitHistorySettings> =>
+    invoke("get_git_history_settings", {}),
+  setGitHistorySettings: (settings: GitHistorySettings): Promise<GitHistorySettings> =>
+    invoke("set_git_history_settings", { settings }),
+  pushGitHistory: (): Promise<boolean> =>
+    invoke("push_git_history", {}),
+  exportHistoryBundle: (path: string | undefined, stripSecrets: boolean): Promise<HistoryExportResult> =>
+    invoke("export_history_bundle", { path, stripSecrets }),
+  importHistoryBundle: (path: string): Promise<HistoryImportReport> =>
+    invoke("import_history_bundle", { path }),
   previewRollback: (snapshotId: string): Promise<PreviewResult> =>
     invoke("preview_rollback", { snapshotId }),
   rollback: (snapshotId: string): Promise<ApplyResult> =>
     invoke("rollback", { snapshotId }),
   listModelProfiles: (): Promise<ModelProfile[]> =>
     invoke("list_model_profiles", {}),
-  extractModelProfilesFromConfig: (): Promise<{ created: number; reused: number; skippedInvalid: number }> =>
+  extractModelProfilesFromConfig: (): Promise<{ created: number; reused: number; skippedInvalid: number; warnings: string[] }> =>
     invoke("extract_model_profiles_from_config", {}),
   upsertModelProfile: (profile: ModelProfile): Promise<ModelProfile> =>
     invoke("upsert_model_profile", { profile }),
@@ -92,6 +148,16 @@ export const api = {
     invoke("set_agent_model", { agentId, modelValue }),
   listBindings: (): Promise<Binding[]> =>
     invoke("list_bindings", {}),
+  // Local instances
+  listLocalInstances: (): Promise<LocalInstance[]> =>
+    invoke("list_local_instances", {}),
+  selectLocalInstance: (instanceId: string): Promise<LocalInstance> =>
+    invoke("select_local_instance", { instanceId }),
+  addLocalInstance: (path: string, label?: string): Promise<LocalInstance> =>
+    invoke("add_local_instance", { path, label: label ?? null }),
+  removeLocalInstance: (instanceId: string): Promise<boolean> =>
+    invoke("remove_local_instance", { instanceId }),
+
   // SSH host management
   listSshHosts: (): Promise<SshHost[]> =>
     invoke("list_ssh_hosts", {}),
@@ -135,6 +201,8 @@ export const api = {
     invoke("remote_repair_primary_via_rescue", { hostId, targetProfile: targetProfile ?? null, rescueProfile: rescueProfile ?? null, issueIds: issueIds ?? null }),
   remoteApplyConfigPatch: (hostId: string, patchTemplate: string, params: Record<string, string>): Promise<ApplyResult> =>
     invoke("remote_apply_config_patch", { hostId, patchTemplate, params }),
+  remoteValidateRecipeParams: (hostId: string, recipeId: string, params: Record<string, unknown>, source?: string): Promise<ParamError[]> =>
+    invoke("remote_validate_recipe_params", { hostId, recipeId, params, source: source ?? null }),
   remoteListDiscordGuildChannels: (hostId: string): Promise<DiscordGuildChannel[]> =>
     invoke("remote_list_discord_guild_channels", { hostId }),
   remoteRunDoctor: (hostId: string): Promise<DoctorReport> =>
@@ -143,8 +211,20 @@ export const api = {
     invoke("remote_fix_issues", { hostId, ids }),
   remoteSetupAgentIdentity: (hostId: string, agentId: string, name: string, emoji?: string): Promise<boolean> =>
     invoke("remote_setup_agent_identity", { hostId, agentId, name, emoji }),
-  remoteListHistory: (hostId: string): Promise<{ items: HistoryItem[] }> =>
-    invoke("remote_list_history", { hostId }),
+  remoteListHistory: (hostId: string, limit?: number, offset?: number, query?: string): Promise<HistoryPage> =>
+    invoke("remote_list_history", { hostId, limit, offset, query }),
+  remoteUpdateSnapshot: (hostId: string, snapshotId: string, update: SnapshotUpdate): Promise<HistoryItem> =>
+    invoke("remote_update_snapshot", { hostId, snapshotId, update }),
+  remoteDiffSnapshots: (hostId: string, fromId: string, toId: string): Promise<SnapshotDiff> =>
+    invoke("remote_diff_snapshots", { hostId, fromId, toId }),
+  remoteBlame: (hostId: string, jsonPath: string): Promise<BlameEntry[]> =>
+    invoke("remote_blame", { hostId, jsonPath }),
+  remoteGetHistoryRetention: (hostId: string): Promise<HistoryRetentionPolicy> =>
+    invoke("remote_get_history_retention", { hostId }),
+  remoteSetHistoryRetention: (hostId: string, policy: HistoryRetentionPolicy): Promise<HistoryRetentionPolicy> =>
+    invoke("remote_set_history_retention", { hostId, policy }),
+  remoteGcHistory: (hostId: string): Promise<HistoryGcReport> =>
+    invoke("remote_gc_history", { hostId }),
   remotePreviewRollback: (hostId: string, snapshotId: string): Promise<PreviewResult> =>
     invoke("remote_preview_rollback", { hostId, snapshotId }),
   remoteRollback: (hostId: string, snapshotId: string): Promise<ApplyResult> =>
@@ -171,7 +251,7 @@ export const api = {
     invoke("remote_test_model_profile", { hostId, profileId }),
   remoteResolveApiKeys: (hostId: string): Promise<ResolvedApiKey[]> =>
     invoke("remote_resolve_api_keys", { hostId }),
-  remoteExtractModelProfilesFromConfig: (hostId: string): Promise<{ created: number; reused: number; skippedInvalid: number }> =>
+  remoteExtractModelProfilesFromConfig: (hostId: string): Promise<{ created: number; reused: number; skippedInvalid: number; warnings: string[] }> =>
     invoke("remote_extract_model_profiles_from_config", { hostId }),
   remoteRefreshModelCatalog: (hostId: string): Promise<ModelCatalogProvider[]> =>
     invoke("remote_refresh_model_catalog", { hostId }),
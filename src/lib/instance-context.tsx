@@ -15,6 +15,11 @@ export const InstanceContext = createContext<InstanceContextValue>({
   discordGuildChannels: [],
 });
 
+/** Local instance ids are `"local"` and `"local:<name>"`; anything else is an SSH host id. */
+export function isLocalInstanceId(id: string) {
+  return id === "local" || id.startsWith("local:");
+}
+
 export function useInstance() {
   return useContext(InstanceContext);
 }
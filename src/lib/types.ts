@@ -21,7 +21,7 @@ export interface DiscordGuildChannel {
 export interface RecipeParam {
   id: string;
   label: string;
-  type: "string" | "number" | "boolean" | "textarea" | "discord_guild" | "discord_channel" | "model_profile" | "agent";
+  type: "string" | "number" | "boolean" | "textarea" | "enum" | "list" | "discord_guild" | "discord_channel" | "model_profile" | "agent";
   required: boolean;
   pattern?: string;
   minLength?: number;
@@ -29,12 +29,47 @@ export interface RecipeParam {
   placeholder?: string;
   dependsOn?: string;
   defaultValue?: string;
+  options?: string[];
+  min?: number;
+  max?: number;
+}
+
+export interface ParamError {
+  paramId: string;
+  code: string;
+  message: string;
 }
 
 export interface RecipeStep {
   action: string;
   label: string;
   args: Record<string, unknown>;
+  /** Expression over params; the step is skipped when it is false. */
+  when?: string;
+  /** Id of a list param; the step runs once per item with {{item}} and {{index}}. */
+  forEach?: string;
+  onError?: "abort" | "continue";
+}
+
+/** A recipe step as resolved by the engine. */
+export interface RecipeResolvedStep {
+  index: number;
+  itemIndex?: number;
+  action: string;
+  label: string;
+  args: Record<string, unknown>;
+  onError: "abort" | "continue";
+  skipReason?: string;
+  scope: Record<string, unknown>;
+}
+
+/** A generated JSON Schema, also written to `~/.clawpal/schemas/<fileName>`. */
+export interface JsonSchemaDocument {
+  kind: "recipes" | "model-profiles" | "snapshot-index" | "ssh-hosts";
+  version: number;
+  fileName: string;
+  describes: string;
+  schema: Record<string, unknown>;
 }
 
 export interface Recipe {
@@ -46,6 +81,54 @@ export interface Recipe {
   difficulty: "easy" | "normal" | "advanced";
   params: RecipeParam[];
   steps: RecipeStep[];
+  source?: RecipeSource;
+}
+
+export interface RecipeSource {
+  kind: "builtin" | "user" | "remote" | "env" | "explicit";
+  location: string;
+  precedence: number;
+  trust: "builtin" | "local" | "trusted" | "untrusted";
+  signedBy?: string;
+}
+
+export type RecipeTrustPolicy = "warn" | "require";
+
+export interface TrustedRecipeKey {
+  keyId: string;
+  name: string;
+  publicKey: string;
+  addedAt: string;
+}
+
+export interface RecipeTrustStore {
+  policy: RecipeTrustPolicy;
+  keys: TrustedRecipeKey[];
+}
+
+export interface InstallLink {
+  recipeId: string;
+  source: string | null;
+  origin: string | null;
+  version: string | null;
+  params: Record<string, string>;
+}
+
+export interface InstallPreview {
+  link: InstallLink;
+  recipe: Recipe;
+  params: Record<string, unknown>;
+  paramErrors: ParamError[];
+  preview: PreviewResult | null;
+  warnings: string[];
+}
+
+export interface RecipeSourceStatus {
+  source: RecipeSource;
+  recipeCount: number;
+  fromCache: boolean;
+  fetchedAt: string | null;
+  error: string | null;
 }
 
 export interface ChangeItem {
@@ -65,6 +148,7 @@ export interface PreviewResult {
   canRollback: boolean;
   impactLevel: string;
   warnings: string[];
+  files: string[];
 }
 
 export interface ApplyResult {
@@ -76,6 +160,56 @@ export interface ApplyResult {
   errors: string[];
 }
 
+export interface RecipeStepResult extends ApplyResult {
+  index: number;
+  action: string;
+  label: string;
+  status: "applied" | "skipped" | "failed" | "notRun";
+}
+
+export interface RecipeExecution {
+  recipeId: string;
+  ok: boolean;
+  snapshotId?: string;
+  rolledBack: boolean;
+  steps: RecipeStepResult[];
+  paramErrors?: ParamError[];
+  installId?: string;
+}
+
+export interface RecipeInstall {
+  id: string;
+  recipeId: string;
+  recipeVersion: string;
+  installedAt: string;
+  snapshotId: string | null;
+  paths: string[];
+  agents: string[];
+  bindings: unknown[];
+  contributions: unknown[];
+}
+
+export interface UninstallConflict {
+  path: string;
+  reason: string;
+  expected: unknown;
+  actual: unknown;
+}
+
+export interface UninstallPreview {
+  install: RecipeInstall;
+  preview: PreviewResult;
+  reverted: string[];
+  conflicts: UninstallConflict[];
+}
+
+export interface UninstallResult {
+  ok: boolean;
+  snapshotId: string | null;
+  reverted: string[];
+  conflicts: UninstallConflict[];
+}
+
 export interface SystemStatus {
   healthy: boolean;
   configPath: string;
@@ -191,6 +325,125 @@ export interface HistoryItem {
   source: string;
   canRollback: boolean;
   rollbackOf?: string;
+  pinned: boolean;
+  labels: string[];
+  note?: string;
+}
+
+export interface HistoryPage {
+  items: HistoryItem[];
+  /** Every pinned snapshot matching the query, whatever the page. */
+  pinned: HistoryItem[];
+}
+
+/** Payload of the `history:external-change` event. */
+export interface ExternalConfigChange {
+  /** `null` for local instances. */
+  hostId: string | null;
+  /** The local instance id, or the host id. */
+  instanceId: string;
+  snapshotId: string;
+  createdAt: string;
+}
+
+export interface ConfigLockOwner {
+  pid: number;
+  tool: string;
+  acquiredAt: string;
+}
+
+/** Typed error returned, as JSON, by config writes that could not go ahead. */
+export type ConfigWriteError =
+  | {
+      kind: "conflict";
+      expectedHash: string;
+      actualHash: string;
+      /** Paths edited both here and elsewhere; empty if the file no longer parses. */
+      paths: string[];
+    }
+  | {
+      kind: "locked";
+      lockPath: string;
+      holder: ConfigLockOwner | null;
+    };
+
+export interface SnapshotDiff {
+  from: HistoryItem;
+  to: HistoryItem;
+  changes: ChangeItem[];
+  diff: string;
+  configBefore: string;
+  configAfter: string;
+}
+
+export interface BlameEntry {
+  snapshotId: string;
+  createdAt: string;
+  source: string;
+  recipeId?: string;
+  rollbackOf?: string;
+  /** Value at the path before the snapshot's write; null when absent. */
+  before: unknown;
+  after: unknown;
+}
+
+export interface SnapshotUpdate {
+  pinned?: boolean;
+  labels?: string[];
+  /** An empty note clears it. */
+  note?: string;
+}
+
+export interface HistoryRetentionPolicy {
+  keepLast: number;
+  keepDailyDays: number;
+}
+
+export interface HistoryGcReport {
+  keptSnapshots: number;
+  removedSnapshots: number;
+  migratedSnapshots: number;
+  removedFiles: number;
+  reclaimedBytes: number;
+  storedBytes: number;
+}
+
+export interface BundleFile {
+  path: string;
+  size: number;
+  sha256: string;
+}
+
+export interface BundleManifest {
+  format: string;
+  createdAt: string;
+  clawpalVersion: string;
+  secretsStripped: boolean;
+  snapshots: number;
+  files: BundleFile[];
+}
+
+export interface HistoryExportResult {
+  path: string;
+  manifest: BundleManifest;
+}
+
+export interface HistoryImportReport {
+  importedSnapshots: number;
+  /** Snapshots already in the local history. */
+  skippedSnapshots: number;
+  /** Imported snapshots whose id was taken, with their new id. */
+  renamedSnapshots: Record<string, string>;
+  modelProfiles: number;
+  sshHosts: number;
+  secretsStripped: boolean;
+}
+
+/** Local config history mirrored into a git repository. */
+export interface GitHistorySettings {
+  enabled: boolean;
+  /** Remote name or URL that new commits are pushed to. */
+  remote?: string;
 }
 
 export interface DoctorIssue {
@@ -242,6 +495,21 @@ export interface BackupInfo {
   sizeBytes: number;
 }
 
+/** An OpenClaw state dir on this machine. */
+export interface LocalInstance {
+  /** `"local"` for the default instance, `"local:<name>"` for the others. */
+  id: string;
+  label: string;
+  kind: "default" | "profile" | "custom";
+  /** Set for `~/.openclaw-<name>` dirs, run with `openclaw --profile <name>`. */
+  profile: string | null;
+  openclawDir: string;
+  hasConfig: boolean;
+  /** Added by hand, so it can be removed again. */
+  removable: boolean;
+  active: boolean;
+}
+
 export interface SshHost {
   id: string;
   label: string;
@@ -414,6 +682,10 @@ export interface PreviewQueueResult {
   commands: PendingCommand[];
   configBefore: string;
   configAfter: string;
+  changes: ChangeItem[];
+  diff: string;
+  impactLevel: string;
+  warnings: string[];
   errors: string[];
 }
 
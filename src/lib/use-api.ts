@@ -33,7 +33,7 @@ function trimReadCacheIfNeeded() {
   }
 }
 
-function invalidateReadCacheForInstance(instanceId: string, methods?: string[]) {
+export function invalidateReadCacheForInstance(instanceId: string, methods?: string[]) {
   const methodSet = methods ? new Set(methods) : null;
   for (const key of API_READ_CACHE.keys()) {
     if (!key.startsWith(`${instanceId}:`)) continue;
@@ -341,6 +341,24 @@ export function useApi() {
         api.remotePreviewRollback,
       ),
       rollback: withInvalidation(dispatch(api.rollback, api.remoteRollback)),
+      diffSnapshots: dispatch(api.diffSnapshots, api.remoteDiffSnapshots),
+      blame: dispatch(api.blame, api.remoteBlame),
+      updateSnapshot: withInvalidation(
+        dispatch(api.updateSnapshot, api.remoteUpdateSnapshot),
+        ["listHistory"],
+      ),
+      getHistoryRetention: dispatch(
+        api.getHistoryRetention,
+        api.remoteGetHistoryRetention,
+      ),
+      setHistoryRetention: dispatch(
+        api.setHistoryRetention,
+        api.remoteSetHistoryRetention,
+      ),
+      gcHistory: withInvalidation(
+        dispatch(api.gcHistory, api.remoteGcHistory),
+        ["listHistory"],
+      ),
 
       // Sessions
       analyzeSessions: dispatch(
@@ -506,6 +524,12 @@ export function useApi() {
       ),
       getSystemStatus: api.getSystemStatus,
       listRecipes: localCached("listRecipes", 20_000, api.listRecipes),
+      getGitHistorySettings: api.getGitHistorySettings,
+      setGitHistorySettings: withInvalidation(api.setGitHistorySettings, ["listHistory"]),
+      pushGitHistory: api.pushGitHistory,
+      exportHistoryBundle: api.exportHistoryBundle,
+      // Adds snapshots, model profiles and SSH hosts
+      importHistoryBundle: withInvalidation(api.importHistoryBundle),
 
       // SSH management (infrastructure, not abstracted)
       listSshHosts: api.listSshHosts,
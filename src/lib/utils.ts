@@ -1,5 +1,7 @@
 import { type ClassValue, clsx } from "clsx";
 import { twMerge } from "tailwind-merge";
+import type { TFunction } from "i18next";
+import type { ConfigWriteError } from "./types";
 
 export function cn(...inputs: ClassValue[]) {
   return twMerge(clsx(inputs));
@@ -41,3 +43,29 @@ export function formatBytes(bytes: number): string {
   }
   return `${value.toFixed(1)} ${units[index]}`;
 }
+
+/** Find a typed config write error in an invoke error, which may carry it
+ *  as is or embedded in a longer message (e.g. a failed queue step). */
+export function parseConfigWriteError(error: unknown): ConfigWriteError | null {
+  const raw = String(error);
+  const start = raw.indexOf('{"kind":');
+  if (start < 0) return null;
+  try {
+    const parsed = JSON.parse(raw.slice(start)) as ConfigWriteError;
+    return parsed.kind === "conflict" || parsed.kind === "locked" ? parsed : null;
+  } catch {
+    return null;
+  }
+}
+
+/** Human-readable message for an error from a config write. */
+export function describeConfigError(error: unknown, t: TFunction): string {
+  const parsed = parseConfigWriteError(error);
+  if (!parsed) return String(error);
+  if (parsed.kind === "locked") {
+    const holder = parsed.holder ? `${parsed.holder.tool} (pid ${parsed.holder.pid})` : parsed.lockPath;
+    return t("config.writeLocked", { holder });
+  }
+  if (parsed.paths.length === 0) return t("config.writeConflictUnparsed");
+  return t("config.writeConflict", { paths: parsed.paths.join(", ") });
+}
@@ -1,12 +1,13 @@
 import { useEffect, useState } from "react";
 import { useTranslation } from "react-i18next";
 import { ParamForm } from "../components/ParamForm";
-import { resolveSteps, stepToCommands, type ResolvedStep } from "../lib/actions";
-import { useApi } from "@/lib/use-api";
+import { describeSteps, stepToCommands, type ResolvedStep } from "../lib/actions";
+import { api } from "@/lib/api";
+import { invalidateReadCacheForInstance, useApi } from "@/lib/use-api";
 import { Card, CardContent } from "@/components/ui/card";
 import { Button } from "@/components/ui/button";
 import { cn } from "@/lib/utils";
-import type { Recipe } from "../lib/types";
+import type { PreviewResult, Recipe, RecipeExecution, RecipeStepResult } from "../lib/types";
 import { useInstance } from "@/lib/instance-context";
 
 
@@ -17,10 +18,12 @@ export function Cook({
   recipeId,
   onDone,
   recipeSource,
+  initialParams,
 }: {
   recipeId: string;
   onDone?: () => void;
   recipeSource?: string;
+  initialParams?: Record<string, string>;
 }) {
   const { t } = useTranslation();
   const ua = useApi();
@@ -33,6 +36,11 @@ export function Cook({
   const [stepStatuses, setStepStatuses] = useState<StepStatus[]>([]);
   const [stepErrors, setStepErrors] = useState<Record<number, string>>({});
   const [needsRestart, setNeedsRestart] = useState(false);
+  const [resolveError, setResolveError] = useState<string | null>(null);
+  const [preview, setPreview] = useState<PreviewResult | null>(null);
+  const [previewError, setPreviewError] = useState<string | null>(null);
+  const [execution, setExecution] = useState<RecipeExecution | null>(null);
+  const [executeError, setExecuteError] = useState<string | null>(null);
 
   useEffect(() => {
     setLoading(true);
@@ -44,7 +52,7 @@ export function Cook({
         for (const p of found.params) {
           defaults[p.id] = p.defaultValue ?? (p.type === "boolean" ? "false" : "");
         }
-        setParams(defaults);
+        setParams({ ...defaults, ...initialParams });
       }
     }).finally(() => setLoading(false));
   }, [recipeId, recipeSource]);
@@ -109,14 +117,28 @@ export function Cook({
   if (loading) return <div className="flex items-center justify-center py-12"><div className="h-6 w-6 animate-spin rounded-full border-2 border-primary border-t-transparent" /></div>;
   if (!recipe) return <div>{t('cook.recipeNotFound')}</div>;
 
-  const handleNext = () => {
-    const steps = resolveSteps(recipe.steps, params);
+  const handleNext = async () => {
+    let steps: ResolvedStep[];
+    try {
+      // The engine evaluates `when`, expands `forEach` and renders placeholders.
+      steps = describeSteps(await api.resolveRecipeSteps(recipe.id, params, recipeSource));
+    } catch (err) {
+      setResolveError(String(err));
+      return;
+    }
+    setResolveError(null);
     setResolvedStepList(steps);
-    // Auto-skip steps whose template args resolved to empty
     setStepStatuses(steps.map((s) => (s.skippable ? "skipped" : "pending")));
     setStepErrors({});
-    setNeedsRestart(steps.some((s) => !s.skippable));
+    setNeedsRestart(isRemote && steps.some((s) => !s.skippable));
+    setExecution(null);
+    setExecuteError(null);
+    setPreview(null);
+    setPreviewError(null);
     setPhase("confirm");
+    api.previewRecipe(recipe.id, params, isRemote ? instanceId : undefined, recipeSource)
+      .then(setPreview)
+      .catch((err) => setPreviewError(String(err)));
   };
 
   const runFrom = async (startIndex: number, statuses: StepStatus[]) => {
@@ -134,6 +156,7 @@ export function Cook({
         statuses[i] = "failed";
         setStepErrors((prev) => ({ ...prev, [i]: String(err) }));
         setStepStatuses([...statuses]);
+        if (resolvedStepList[i].onError === "continue") continue;
         return;
       }
       setStepStatuses([...statuses]);
@@ -141,8 +164,27 @@ export function Cook({
     setPhase("done");
   };
 
+  // The engine runs every step against the local config under one snapshot
+  // and rolls the whole recipe back on failure; remote hosts still go
+  // through the command queue one step at a time.
+  const executeLocal = async () => {
+    setStepStatuses(stepStatuses.map((s) => (s === "skipped" ? s : "running")));
+    try {
+      const result = await api.executeRecipe(recipe.id, params, recipeSource);
+      invalidateReadCacheForInstance(instanceId);
+      setExecution(result);
+    } catch (err) {
+      setExecuteError(String(err));
+    }
+    setPhase("done");
+  };
+
   const handleExecute = () => {
     setPhase("execute");
+    if (!isRemote) {
+      executeLocal();
+      return;
+    }
     const statuses = [...stepStatuses];
     runFrom(0, statuses);
   };
@@ -193,8 +235,22 @@ export function Cook({
     }
   };
 
-  const doneCount = stepStatuses.filter((s) => s === "done").length;
-  const skippedCount = stepStatuses.filter((s) => s === "skipped").length;
+  const resultStatus = (r: RecipeStepResult): StepStatus => {
+    switch (r.status) {
+      case "applied": return "done";
+      case "failed": return "failed";
+      case "skipped": return "skipped";
+      case "notRun": return "pending";
+    }
+  };
+
+  const doneCount = execution
+    ? execution.steps.filter((r) => r.status === "applied").length
+    : stepStatuses.filter((s) => s === "done").length;
+  const skippedCount = execution
+    ? execution.steps.filter((r) => r.status === "skipped").length
+    : stepStatuses.filter((s) => s === "skipped").length;
+  const failed = executeError !== null || (execution !== null && !execution.ok);
 
   return (
     <section>
@@ -206,13 +262,18 @@ export function Cook({
       </div>
 
       {phase === "params" && (
-        <ParamForm
-          recipe={recipe}
-          values={params}
-          onChange={(id, value) => setParams((prev) => ({ ...prev, [id]: value }))}
-          onSubmit={handleNext}
-          submitLabel={t('cook.next')}
-        />
+        <>
+          <ParamForm
+            recipe={recipe}
+            values={params}
+            onChange={(id, value) => setParams((prev) => ({ ...prev, [id]: value }))}
+            onSubmit={handleNext}
+            submitLabel={t('cook.next')}
+          />
+          {resolveError && (
+            <div className="text-sm text-destructive mt-2">{resolveError}</div>
+          )}
+        </>
       )}
 
       {(phase === "confirm" || phase === "execute") && (
@@ -228,7 +289,7 @@ export function Cook({
                     <div className="text-sm font-medium">
                       {step.label}
                       {stepStatuses[i] === "skipped" && phase === "confirm" && (
-                        <span className="text-xs text-muted-foreground ml-2">{t('cook.skippedEmpty')}</span>
+                        <span className="text-xs text-muted-foreground ml-2" title={step.skipReason}>{t('cook.skippedByRecipe')}</span>
                       )}
                     </div>
                     {step.description !== step.label && stepStatuses[i] !== "skipped" && (
@@ -251,6 +312,25 @@ export function Cook({
                 </div>
               ))}
             </div>
+            {phase === "confirm" && preview && (
+              <div className="mt-4 border-t pt-3 space-y-1">
+                <div className="text-sm font-medium">
+                  {t('cook.previewChanges', { count: preview.changes.length, impact: preview.impactLevel })}
+                </div>
+                {preview.changes.map((change) => (
+                  <div key={`${change.op}:${change.path}`} className="text-xs font-mono text-muted-foreground">
+                    {change.op} {change.path}
+                    {change.risk !== "low" && <span className="text-destructive ml-2">{change.risk}</span>}
+                  </div>
+                ))}
+                {preview.warnings.map((w, i) => (
+                  <div key={i} className="text-xs text-yellow-600">{w}</div>
+                ))}
+              </div>
+            )}
+            {phase === "confirm" && previewError && (
+              <div className="text-xs text-destructive mt-4">{previewError}</div>
+            )}
             {phase === "confirm" && (
               <div className="flex gap-2 mt-4">
                 <Button onClick={handleExecute}>{t('cook.execute')}</Button>
@@ -264,19 +344,64 @@ export function Cook({
       {phase === "done" && (
         <Card>
           <CardContent className="py-8 text-center">
-            <div className="text-2xl mb-2">&#10003;</div>
-            <p className="text-lg font-medium">
-              {t('cook.stepsCompleted', { done: doneCount })}
-              {skippedCount > 0 && t('cook.stepsSkipped', { skipped: skippedCount })}
-            </p>
+            <div className={cn("text-2xl mb-2", failed && "text-destructive")}>
+              {failed ? "\u2717" : "\u2713"}
+            </div>
+            {executeError ? (
+              <p className="text-sm text-destructive">{executeError}</p>
+            ) : failed && execution ? (
+              <p className="text-lg font-medium">
+                {execution.paramErrors?.length
+                  ? t('cook.paramErrors')
+                  : execution.rolledBack ? t('cook.rolledBack') : t('cook.notRolledBack')}
+              </p>
+            ) : (
+              <p className="text-lg font-medium">
+                {t('cook.stepsCompleted', { done: doneCount })}
+                {skippedCount > 0 && t('cook.stepsSkipped', { skipped: skippedCount })}
+              </p>
+            )}
+            {execution?.paramErrors?.map((e, i) => (
+              <div key={i} className="text-xs text-destructive mt-1">{e.paramId}: {e.message}</div>
+            ))}
+            {execution && execution.steps.length > 0 && (
+              <div className="space-y-2 mt-4 text-left">
+                {execution.steps.map((r, i) => (
+                  <div key={i} className={cn("flex items-start gap-3", r.status === "skipped" && "opacity-50")}>
+                    <span className={cn("text-lg font-mono w-5 text-center", statusColor(resultStatus(r)))}>
+                      {statusIcon(resultStatus(r))}
+                    </span>
+                    <div className="flex-1">
+                      <div className="text-sm font-medium">{r.label}</div>
+                      {r.errors.map((e, j) => (
+                        <div key={j} className="text-xs text-destructive mt-1">{e}</div>
+                      ))}
+                      {r.warnings.map((w, j) => (
+                        <div key={j} className="text-xs text-yellow-600 mt-1">{w}</div>
+                      ))}
+                    </div>
+                  </div>
+                ))}
+              </div>
+            )}
+            {execution?.snapshotId && (
+              <p className="text-xs text-muted-foreground mt-3">
+                {t('cook.snapshotRecorded', { id: execution.snapshotId })}
+              </p>
+            )}
             {needsRestart && (
               <p className="text-sm text-muted-foreground mt-1">
                 {t('cook.applyHint')}
               </p>
             )}
-            <Button className="mt-4" onClick={onDone}>
-              {t('cook.done')}
-            </Button>
+            <div className="flex justify-center gap-2 mt-4">
+              {failed && (
+                <Button variant="outline" onClick={() => setPhase("params")}>{t('cook.back')}</Button>
+              )}
+              <Button onClick={onDone}>
+                {t('cook.done')}
+              </Button>
+            </div>
           </CardContent>
         </Card>
       )}
@@ -1,13 +1,20 @@
 import { useEffect, useState } from "react";
 import { useTranslation } from "react-i18next";
-import { useApi } from "@/lib/use-api";
+import { listen } from "@tauri-apps/api/event";
+import { invalidateReadCacheForInstance, useApi } from "@/lib/use-api";
+import { useInstance } from "@/lib/instance-context";
 import { DiffViewer } from "../components/DiffViewer";
 import { Card, CardContent } from "@/components/ui/card";
 import { Badge } from "@/components/ui/badge";
 import { Button } from "@/components/ui/button";
+import { Input } from "@/components/ui/input";
+import { Textarea } from "@/components/ui/textarea";
+import { Checkbox } from "@/components/ui/checkbox";
+import { Label } from "@/components/ui/label";
 import {
   Dialog,
   DialogContent,
+  DialogFooter,
   DialogHeader,
   DialogTitle,
 } from "@/components/ui/dialog";
@@ -22,135 +29,505 @@ import {
   AlertDialogTitle,
   AlertDialogTrigger,
 } from "@/components/ui/alert-dialog";
-import type { HistoryItem, PreviewResult } from "../lib/types";
+import type { BlameEntry, ExternalConfigChange, GitHistorySettings, HistoryItem, PreviewResult, SnapshotDiff, SnapshotUpdate } from "../lib/types";
 import { formatTime } from "@/lib/utils";
 
 export function History() {
   const { t } = useTranslation();
   const ua = useApi();
+  const { instanceId, isRemote } = useInstance();
   const [history, setHistory] = useState<HistoryItem[]>([]);
+  const [pinned, setPinned] = useState<HistoryItem[]>([]);
+  const [query, setQuery] = useState("");
   const [preview, setPreview] = useState<PreviewResult | null>(null);
+  const [editing, setEditing] = useState<HistoryItem | null>(null);
+  const [labelsDraft, setLabelsDraft] = useState("");
+  const [noteDraft, setNoteDraft] = useState("");
+  const [compareFrom, setCompareFrom] = useState<HistoryItem | null>(null);
+  const [comparison, setComparison] = useState<SnapshotDiff | null>(null);
+  const [blamePath, setBlamePath] = useState("");
+  const [blameEntries, setBlameEntries] = useState<BlameEntry[] | null>(null);
+  const [gitSettings, setGitSettings] = useState<GitHistorySettings | null>(null);
+  const [gitRemoteDraft, setGitRemoteDraft] = useState("");
+  const [stripSecrets, setStripSecrets] = useState(true);
+  const [importPath, setImportPath] = useState("");
   const [message, setMessage] = useState("");
 
   const refreshHistory = () => {
-    return ua.listHistory()
-      .then((resp) => setHistory(resp.items))
+    return ua.listHistory(20, 0, query.trim() || undefined)
+      .then((resp) => {
+        setHistory(resp.items);
+        setPinned(resp.pinned);
+      })
       .catch(() => setMessage(t('history.failedLoad')));
   };
 
   useEffect(() => {
     refreshHistory();
-  }, [ua]);
+  }, [ua, query]);
+
+  useEffect(() => {
+    // Git history is only kept for the local instance
+    if (isRemote) {
+      setGitSettings(null);
+      return;
+    }
+    ua.getGitHistorySettings()
+      .then((settings) => {
+        setGitSettings(settings);
+        setGitRemoteDraft(settings.remote ?? "");
+      })
+      .catch(() => setGitSettings(null));
+  }, [ua, isRemote]);
+
+  const saveGitSettings = async (settings: GitHistorySettings) => {
+    try {
+      const saved = await ua.setGitHistorySettings(settings);
+      setGitSettings(saved);
+      setGitRemoteDraft(saved.remote ?? "");
+      setMessage(t('history.gitSaved'));
+      await refreshHistory();
+    } catch (err) {
+      setMessage(String(err));
+    }
+  };
+
+  useEffect(() => {
+    const unlisten = listen<ExternalConfigChange>("history:external-change", (event) => {
+      if (event.payload.instanceId !== instanceId) return;
+      // The config itself changed, so every cached read is stale
+      invalidateReadCacheForInstance(instanceId);
+      setMessage(t('history.externalChange'));
+      refreshHistory();
+    });
+    return () => {
+      unlisten.then((f) => f());
+    };
+  }, [ua, query, instanceId, isRemote]);
+
+  const updateSnapshot = async (item: HistoryItem, update: SnapshotUpdate) => {
+    try {
+      await ua.updateSnapshot(item.id, update);
+      await refreshHistory();
+    } catch (err) {
+      setMessage(String(err));
+    }
+  };
+
+  const compareWith = async (item: HistoryItem) => {
+    if (!compareFrom) {
+      setCompareFrom(item);
+      setMessage(t('history.compareSelectSecond'));
+      return;
+    }
+    setCompareFrom(null);
+    setMessage("");
+    if (compareFrom.id === item.id) return;
+    // Always diff older → newer
+    const [from, to] = compareFrom.createdAt <= item.createdAt ? [compareFrom, item] : [item, compareFrom];
+    try {
+      setComparison(await ua.diffSnapshots(from.id, to.id));
+    } catch (err) {
+      setMessage(String(err));
+    }
+  };
+
+  const runBlame = async () => {
+    if (!blamePath.trim()) return;
+    try {
+      setBlameEntries(await ua.blame(blamePath.trim()));
+    } catch (err) {
+      setMessage(String(err));
+    }
+  };
+
+  const formatValue = (value: unknown) =>
+    value === null || value === undefined ? t('history.blameAbsent') : JSON.stringify(value);
+
+  const openEditor = (item: HistoryItem) => {
+    setLabelsDraft(item.labels.join(", "));
+    setNoteDraft(item.note ?? "");
+    setEditing(item);
+  };
 
   // Build a map from snapshot ID to its display info for rollback references
   const historyMap = new Map(
-    history.map((h) => [h.id, h])
+    [...pinned, ...history].map((h) => [h.id, h])
   );
+  // Pinned snapshots outside the current page are listed above it
+  const pinnedElsewhere = pinned.filter((p) => !history.some((h) => h.id === p.id));
 
-  return (
-    <section>
-      <h2 className="text-2xl font-bold mb-4">{t('history.title')}</h2>
-      <div className="space-y-3">
-        {history.map((item) => {
-          const isRollback = item.source === "rollback";
-          const rollbackTarget = item.rollbackOf ? historyMap.get(item.rollbackOf) : undefined;
-          return (
-            <Card key={item.id} className={isRollback ? "border-dashed opacity-75" : ""}>
-              <CardContent>
-                <div className="flex items-center gap-2 text-sm flex-wrap">
-                  <span className="text-muted-foreground">{formatTime(item.createdAt)}</span>
-                  {isRollback ? (
-                    <>
-                      <Badge variant="outline">{t('history.rollback')}</Badge>
-                      <span className="text-muted-foreground">
-                        {t('history.reverted', {
-                          details: rollbackTarget
-                            ? t('history.revertedRecipe', {
-                                recipeId: rollbackTarget.recipeId || t('history.manual'),
-                                time: formatTime(rollbackTarget.createdAt),
-                              })
-                            : item.recipeId || t('history.unknown'),
-                        })}
-                      </span>
-                    </>
-                  ) : (
-                    <>
-                      <Badge variant="secondary">{item.recipeId || t('history.manual')}</Badge>
-                      <span className="text-muted-foreground">{item.source}</span>
-                    </>
-                  )}
-                  {!item.canRollback && !isRollback && (
-                    <Badge variant="outline" className="text-muted-foreground">{t('history.notRollbackable')}</Badge>
-                  )}
-                </div>
-                {!isRollback && (
-                  <div className="flex gap-2 mt-2">
+  const renderItem = (item: HistoryItem) => {
+    const isRollback = item.source === "rollback";
+    const rollbackTarget = item.rollbackOf ? historyMap.get(item.rollbackOf) : undefined;
+    return (
+      <Card key={item.id} className={isRollback ? "border-dashed opacity-75" : ""}>
+        <CardContent>
+          <div className="flex items-center gap-2 text-sm flex-wrap">
+            <span className="text-muted-foreground">{formatTime(item.createdAt)}</span>
+            {isRollback ? (
+              <>
+                <Badge variant="outline">{t('history.rollback')}</Badge>
+                <span className="text-muted-foreground">
+                  {t('history.reverted', {
+                    details: rollbackTarget
+                      ? t('history.revertedRecipe', {
+                          recipeId: rollbackTarget.recipeId || t('history.manual'),
+                          time: formatTime(rollbackTarget.createdAt),
+                        })
+                      : item.recipeId || t('history.unknown'),
+                  })}
+                </span>
+              </>
+            ) : (
+              <>
+                <Badge variant="secondary">{item.recipeId || t('history.manual')}</Badge>
+                <span className="text-muted-foreground">{item.source}</span>
+              </>
+            )}
+            {!item.canRollback && !isRollback && (
+              <Badge variant="outline" className="text-muted-foreground">{t('history.notRollbackable')}</Badge>
+            )}
+            {item.pinned && <Badge>{t('history.pinned')}</Badge>}
+            {item.labels.map((label) => (
+              <Badge
+                key={label}
+                variant="outline"
+                className="cursor-pointer"
+                onClick={() => setQuery(`label:${label}`)}
+              >
+                {label}
+              </Badge>
+            ))}
+          </div>
+          {item.note && (
+            <p className="text-sm mt-1 whitespace-pre-wrap">{item.note}</p>
+          )}
+          <div className="flex gap-2 mt-2">
+            <Button
+              variant="outline"
+              size="sm"
+              onClick={() => updateSnapshot(item, { pinned: !item.pinned })}
+            >
+              {item.pinned ? t('history.unpin') : t('history.pin')}
+            </Button>
+            <Button variant="outline" size="sm" onClick={() => openEditor(item)}>
+              {t('history.editNotes')}
+            </Button>
+            <Button
+              variant={compareFrom?.id === item.id ? "secondary" : "outline"}
+              size="sm"
+              onClick={() => compareWith(item)}
+            >
+              {t('history.compare')}
+            </Button>
+            {!isRollback && (
+              <>
+                <Button
+                  variant="outline"
+                  size="sm"
+                  onClick={async () => {
+                    try {
+                      const p = await ua.previewRollback(item.id);
+                      setPreview(p);
+                    } catch (err) {
+                      setMessage(String(err));
+                    }
+                  }}
+                  disabled={!item.canRollback}
+                >
+                  {t('history.preview')}
+                </Button>
+                <AlertDialog>
+                  <AlertDialogTrigger asChild>
                     <Button
-                      variant="outline"
+                      variant="destructive"
                       size="sm"
-                      onClick={async () => {
-                        try {
-                          const p = await ua.previewRollback(item.id);
-                          setPreview(p);
-                        } catch (err) {
-                          setMessage(String(err));
-                        }
-                      }}
                       disabled={!item.canRollback}
                     >
-                      {t('history.preview')}
+                      {t('history.rollbackBtn')}
                     </Button>
-                    <AlertDialog>
-                      <AlertDialogTrigger asChild>
-                        <Button
-                          variant="destructive"
-                          size="sm"
-                          disabled={!item.canRollback}
-                        >
-                          {t('history.rollbackBtn')}
-                        </Button>
-                      </AlertDialogTrigger>
-                      <AlertDialogContent>
-                        <AlertDialogHeader>
-                          <AlertDialogTitle>{t('history.rollbackConfirmTitle')}</AlertDialogTitle>
-                          <AlertDialogDescription>
-                            {t('history.rollbackConfirmDescription')}
-                          </AlertDialogDescription>
-                        </AlertDialogHeader>
-                        <AlertDialogFooter>
-                          <AlertDialogCancel>{t('config.cancel')}</AlertDialogCancel>
-                          <AlertDialogAction
-                            className="bg-destructive text-destructive-foreground hover:bg-destructive/90"
-                            onClick={async () => {
-                              try {
-                                const p = await ua.previewRollback(item.id);
-                                const label = `Rollback to ${item.recipeId || formatTime(item.createdAt)}`;
-                                await ua.queueCommand(label, ["__rollback__", p.configAfter]);
-                                setMessage(t('history.rollbackQueued'));
-                              } catch (err) {
-                                setMessage(String(err));
-                              }
-                            }}
-                          >
-                            {t('history.rollbackBtn')}
-                          </AlertDialogAction>
-                        </AlertDialogFooter>
-                      </AlertDialogContent>
-                    </AlertDialog>
+                  </AlertDialogTrigger>
+                  <AlertDialogContent>
+                    <AlertDialogHeader>
+                      <AlertDialogTitle>{t('history.rollbackConfirmTitle')}</AlertDialogTitle>
+                      <AlertDialogDescription>
+                        {t('history.rollbackConfirmDescription')}
+                      </AlertDialogDescription>
+                    </AlertDialogHeader>
+                    <AlertDialogFooter>
+                      <AlertDialogCancel>{t('config.cancel')}</AlertDialogCancel>
+                      <AlertDialogAction
+                        className="bg-destructive text-destructive-foreground hover:bg-destructive/90"
+                        onClick={async () => {
+                          try {
+                            const p = await ua.previewRollback(item.id);
+                            const label = `Rollback to ${item.recipeId || formatTime(item.createdAt)}`;
+                            await ua.queueCommand(label, ["__rollback__", p.configAfter]);
+                            setMessage(t('history.rollbackQueued'));
+                          } catch (err) {
+                            setMessage(String(err));
+                          }
+                        }}
+                      >
+                        {t('history.rollbackBtn')}
+                      </AlertDialogAction>
+                    </AlertDialogFooter>
+                  </AlertDialogContent>
+                </AlertDialog>
+              </>
+            )}
+          </div>
+        </CardContent>
+      </Card>
+    );
+  };
+
+  return (
+    <section>
+      <h2 className="text-2xl font-bold mb-4">{t('history.title')}</h2>
+      <Input
+        className="mb-3"
+        value={query}
+        onChange={(e) => setQuery(e.target.value)}
+        placeholder={t('history.searchPlaceholder')}
+      />
+      <div className="flex gap-2 mb-3">
+        <Input
+          value={blamePath}
+          onChange={(e) => setBlamePath(e.target.value)}
+          onKeyDown={(e) => { if (e.key === "Enter") runBlame(); }}
+          placeholder={t('history.blamePlaceholder')}
+        />
+        <Button variant="outline" onClick={runBlame} disabled={!blamePath.trim()}>
+          {t('history.blame')}
+        </Button>
+      </div>
+      {blameEntries && (
+        <Card className="mb-4">
+          <CardContent>
+            {blameEntries.length === 0 ? (
+              <p className="text-sm text-muted-foreground">{t('history.blameEmpty')}</p>
+            ) : (
+              <div className="space-y-2">
+                {blameEntries.map((entry) => (
+                  <div key={entry.snapshotId} className="flex items-center gap-2 text-sm flex-wrap">
+                    <span className="text-muted-foreground">{formatTime(entry.createdAt)}</span>
+                    <Badge variant="secondary">{entry.recipeId || t('history.manual')}</Badge>
+                    <span className="text-muted-foreground">{entry.source}</span>
+                    <code className="text-xs">{formatValue(entry.before)} → {formatValue(entry.after)}</code>
                   </div>
-                )}
-              </CardContent>
-            </Card>
-          );
-        })}
+                ))}
+              </div>
+            )}
+          </CardContent>
+        </Card>
+      )}
+      {pinnedElsewhere.length > 0 && (
+        <>
+          <h3 className="text-sm font-medium text-muted-foreground mb-2">{t('history.pinnedSection')}</h3>
+          <div className="space-y-3 mb-4">{pinnedElsewhere.map(renderItem)}</div>
+        </>
+      )}
+      <div className="space-y-3">
+        {history.map(renderItem)}
+      </div>
+      <div className="flex gap-2 mt-3">
+        <Button variant="outline" onClick={refreshHistory}>
+          {t('history.refresh')}
+        </Button>
+        <Button
+          variant="outline"
+          onClick={async () => {
+            try {
+              const report = await ua.gcHistory();
+              setMessage(t('history.cleanedUp', {
+                removed: report.removedSnapshots,
+                kb: Math.round(report.reclaimedBytes / 1024),
+              }));
+              refreshHistory();
+            } catch (err) {
+              setMessage(String(err));
+            }
+          }}
+        >
+          {t('history.cleanUp')}
+        </Button>
       </div>
-      <Button variant="outline" onClick={refreshHistory} className="mt-3">
-        {t('history.refresh')}
-      </Button>
+      {!isRemote && (
+        <Card className="mt-4">
+          <CardContent className="space-y-3">
+            <h3 className="text-sm font-medium">{t('history.bundleTitle')}</h3>
+            <div className="flex items-center gap-2">
+              <Checkbox
+                id="bundle-strip-secrets"
+                checked={stripSecrets}
+                onCheckedChange={(checked) => setStripSecrets(checked === true)}
+              />
+              <Label htmlFor="bundle-strip-secrets" className="font-normal">
+                {t('history.bundleStripSecrets')}
+              </Label>
+              <Button
+                variant="outline"
+                size="sm"
+                className="ml-auto"
+                onClick={async () => {
+                  try {
+                    const result = await ua.exportHistoryBundle(undefined, stripSecrets);
+                    setMessage(t('history.bundleExported', {
+                      snapshots: result.manifest.snapshots,
+                      path: result.path,
+                    }));
+                  } catch (err) {
+                    setMessage(String(err));
+                  }
+                }}
+              >
+                {t('history.bundleExport')}
+              </Button>
+            </div>
+            <div className="flex gap-2">
+              <Input
+                value={importPath}
+                onChange={(e) => setImportPath(e.target.value)}
+                placeholder={t('history.bundleImportPlaceholder')}
+              />
+              <Button
+                variant="outline"
+                disabled={!importPath.trim()}
+                onClick={async () => {
+                  try {
+                    const report = await ua.importHistoryBundle(importPath.trim());
+                    setMessage(t('history.bundleImported', {
+                      imported: report.importedSnapshots,
+                      skipped: report.skippedSnapshots,
+                      profiles: report.modelProfiles,
+                      hosts: report.sshHosts,
+                    }));
+                    setImportPath("");
+                    refreshHistory();
+                  } catch (err) {
+                    setMessage(String(err));
+                  }
+                }}
+              >
+                {t('history.bundleImport')}
+              </Button>
+            </div>
+          </CardContent>
+        </Card>
+      )}
+      {gitSettings && (
+        <Card className="mt-4">
+          <CardContent className="space-y-3">
+            <h3 className="text-sm font-medium">{t('history.gitTitle')}</h3>
+            <div className="flex items-center gap-2">
+              <Checkbox
+                id="git-history-enabled"
+                checked={gitSettings.enabled}
+                onCheckedChange={(checked) =>
+                  saveGitSettings({ ...gitSettings, enabled: checked === true })
+                }
+              />
+              <Label htmlFor="git-history-enabled" className="font-normal">
+                {t('history.gitEnabled')}
+              </Label>
+            </div>
+            {gitSettings.enabled && (
+              <div className="flex gap-2">
+                <Input
+                  value={gitRemoteDraft}
+                  onChange={(e) => setGitRemoteDraft(e.target.value)}
+                  placeholder={t('history.gitRemotePlaceholder')}
+                />
+                <Button
+                  variant="outline"
+                  disabled={gitRemoteDraft.trim() === (gitSettings.remote ?? "")}
+                  onClick={() => saveGitSettings({ ...gitSettings, remote: gitRemoteDraft.trim() || undefined })}
+                >
+                  {t('history.save')}
+                </Button>
+                <Button
+                  variant="outline"
+                  disabled={!gitSettings.remote}
+                  onClick={async () => {
+                    try {
+                      await ua.pushGitHistory();
+                      setMessage(t('history.gitPushed'));
+                    } catch (err) {
+                      setMessage(String(err));
+                    }
+                  }}
+                >
+                  {t('history.gitPush')}
+                </Button>
+              </div>
+            )}
+          </CardContent>
+        </Card>
+      )}
       {message && (
         <p className="text-sm text-muted-foreground mt-2">{message}</p>
       )}
 
+      {/* Labels & Note Dialog */}
+      <Dialog open={!!editing} onOpenChange={(open) => { if (!open) setEditing(null); }}>
+        <DialogContent>
+          <DialogHeader>
+            <DialogTitle>{t('history.editNotes')}</DialogTitle>
+          </DialogHeader>
+          <div className="space-y-3">
+            <Input
+              value={labelsDraft}
+              onChange={(e) => setLabelsDraft(e.target.value)}
+              placeholder={t('history.labelsPlaceholder')}
+            />
+            <Textarea
+              value={noteDraft}
+              onChange={(e) => setNoteDraft(e.target.value)}
+              placeholder={t('history.notePlaceholder')}
+            />
+          </div>
+          <DialogFooter>
+            <Button variant="outline" onClick={() => setEditing(null)}>
+              {t('config.cancel')}
+            </Button>
+            <Button
+              onClick={async () => {
+                if (!editing) return;
+                await updateSnapshot(editing, {
+                  labels: labelsDraft.split(","),
+                  note: noteDraft,
+                });
+                setEditing(null);
+              }}
+            >
+              {t('history.save')}
+            </Button>
+          </DialogFooter>
+        </DialogContent>
+      </Dialog>
+
+      {/* Compare Dialog */}
+      <Dialog open={!!comparison} onOpenChange={(open) => { if (!open) setComparison(null); }}>
+        <DialogContent className="max-w-3xl">
+          <DialogHeader>
+            <DialogTitle>
+              {comparison && t('history.compareTitle', {
+                from: formatTime(comparison.from.createdAt),
+                to: formatTime(comparison.to.createdAt),
+              })}
+            </DialogTitle>
+          </DialogHeader>
+          {comparison && (
+            <DiffViewer
+              oldValue={comparison.configBefore}
+              newValue={comparison.configAfter}
+            />
+          )}
+        </DialogContent>
+      </Dialog>
+
       {/* Preview Dialog */}
       <Dialog open={!!preview} onOpenChange={(open) => { if (!open) setPreview(null); }}>
         <DialogContent className="max-w-3xl">
@@ -11,13 +11,15 @@ import { Label } from "@/components/ui/label";
 export function Recipes({
   onCook,
 }: {
-  onCook: (id: string, source?: string) => void;
+  onCook: (id: string, source?: string, params?: Record<string, string>) => void;
 }) {
   const { t } = useTranslation();
   const [recipes, setRecipes] = useState<Recipe[]>([]);
   const [source, setSource] = useState("");
   const [loadedSource, setLoadedSource] = useState<string | undefined>(undefined);
   const [isLoading, setIsLoading] = useState(false);
+  const [link, setLink] = useState("");
+  const [linkError, setLinkError] = useState<string | null>(null);
 
   const load = (nextSource: string) => {
     setIsLoading(true);
@@ -41,6 +43,16 @@ export function Recipes({
     load(source);
   };
 
+  // Resolves a clawpal://install link and opens it in Cook with its params filled in.
+  const onOpenLink = (event: FormEvent) => {
+    event.preventDefault();
+    setLinkError(null);
+    api
+      .prepareRecipeInstall(link.trim())
+      .then((prepared) => onCook(prepared.recipe.id, prepared.link.source ?? undefined, prepared.link.params))
+      .catch((e) => setLinkError(String(e)));
+  };
+
   return (
     <section>
       <h2 className="text-2xl font-bold mb-4">{t('recipes.title')}</h2>
@@ -59,6 +71,19 @@ export function Recipes({
       <p className="text-sm text-muted-foreground mt-0">
         {t('recipes.loadedFrom', { source: loadedSource || t('recipes.builtinSource') })}
       </p>
+      <form onSubmit={onOpenLink} className="mb-4 flex items-center gap-2">
+        <Label>{t('recipes.installLinkLabel')}</Label>
+        <Input
+          value={link}
+          onChange={(event) => setLink(event.target.value)}
+          placeholder="clawpal://install/recipe-id"
+          className="w-[380px]"
+        />
+        <Button type="submit" variant="outline" className="ml-2" disabled={!link.trim()}>
+          {t('recipes.openLink')}
+        </Button>
+      </form>
+      {linkError && <p className="text-sm text-destructive -mt-2 mb-4">{linkError}</p>}
       <div className="grid grid-cols-[repeat(auto-fit,minmax(220px,1fr))] gap-3">
         {recipes.map((recipe) => (
           <RecipeCard